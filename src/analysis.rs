@@ -0,0 +1,328 @@
+use ndarray::{s, Array2};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{
+    distance::distance_field,
+    map::{BlockType, Map},
+    position::Position,
+};
+
+/// average clearance (in blocks) at which a segment counts as fully tight
+const TIGHT_CLEARANCE: f32 = 1.0;
+
+/// average clearance (in blocks) at which a segment doesnt count as tight at all
+const WIDE_CLEARANCE: f32 = 2.5;
+
+/// average distance (in blocks) to the next hookable block that counts as a full hook gap
+const MAX_HOOK_GAP: f32 = 6.0;
+
+/// amount of skip blocks in a segment that counts as a full skip tunnel
+const MAX_SKIP_BLOCKS: f32 = 50.0;
+
+/// feature that contributes the most to the difficulty of a segment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+pub enum SegmentFeature {
+    TightCorridor,
+    LongHookGap,
+    SkipTunnel,
+}
+
+/// difficulty estimation of the path between two waypoints of the map config
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct SegmentReport {
+    /// index of the waypoint at the end of the segment
+    pub index: usize,
+
+    /// walker steps covered by this segment (exclusive end)
+    pub start_step: usize,
+    pub end_step: usize,
+
+    /// average distance from the walker path to the next non-empty block
+    pub clearance: f32,
+
+    /// average distance from the walker path to the next hookable block
+    pub hook_distance: f32,
+
+    /// amount of skip blocks within the bounding box of the segment
+    pub skip_blocks: usize,
+
+    /// difficulty score between 0 (easy) and 1 (hard)
+    pub difficulty: f32,
+
+    pub feature: SegmentFeature,
+}
+
+/// splits the walker path into waypoint segments and estimates the difficulty of each segment.
+/// skips are the positions of generated skips, e.g. the skips debug layer.
+pub fn analyze_segments(
+    map: &Map,
+    position_history: &[Position],
+    waypoints: &[Position],
+    waypoint_reached_dist: usize,
+    skips: Option<&Array2<bool>>,
+) -> Vec<SegmentReport> {
+    if waypoints.len() < 2 || position_history.is_empty() {
+        return Vec::new();
+    }
+
+    let clearance = distance_to(map, |block_type| *block_type != BlockType::Empty);
+    let hook_distance = distance_to(map, |block_type| *block_type == BlockType::Hookable);
+
+    // determine at which step each segment ends
+    let mut segment_ends = Vec::new();
+    let mut segment_index = 1;
+    for (step, pos) in position_history.iter().enumerate() {
+        if segment_index + 1 < waypoints.len()
+            && waypoints[segment_index].distance_squared(pos) <= waypoint_reached_dist
+        {
+            segment_ends.push(step + 1);
+            segment_index += 1;
+        }
+    }
+    segment_ends.push(position_history.len());
+
+    let mut reports = Vec::new();
+    let mut start_step = 0;
+    for (index, end_step) in segment_ends.into_iter().enumerate() {
+        let positions = &position_history[start_step..end_step];
+        if positions.is_empty() {
+            start_step = end_step;
+            continue;
+        }
+
+        let num_positions = positions.len() as f32;
+        let avg_clearance = positions
+            .iter()
+            .map(|pos| clearance[pos.as_index()])
+            .sum::<f32>()
+            / num_positions;
+        let avg_hook_distance = positions
+            .iter()
+            .map(|pos| hook_distance[pos.as_index()])
+            .sum::<f32>()
+            / num_positions;
+        let skip_blocks = skips.map_or(0, |skips| count_in_bounds(skips, positions));
+
+        // normalize all features to [0, 1] and let the strongest one dominate
+        let tightness =
+            ((WIDE_CLEARANCE - avg_clearance) / (WIDE_CLEARANCE - TIGHT_CLEARANCE)).clamp(0.0, 1.0);
+        let hook_gap = (avg_hook_distance / MAX_HOOK_GAP).min(1.0);
+        let skip_tunnel = (skip_blocks as f32 / MAX_SKIP_BLOCKS).min(1.0);
+
+        let feature = if tightness >= hook_gap && tightness >= skip_tunnel {
+            SegmentFeature::TightCorridor
+        } else if hook_gap >= skip_tunnel {
+            SegmentFeature::LongHookGap
+        } else {
+            SegmentFeature::SkipTunnel
+        };
+
+        reports.push(SegmentReport {
+            index: index + 1,
+            start_step,
+            end_step,
+            clearance: avg_clearance,
+            hook_distance: avg_hook_distance,
+            skip_blocks,
+            difficulty: (tightness + hook_gap + skip_tunnel) / 3.0,
+            feature,
+        });
+
+        start_step = end_step;
+    }
+
+    reports
+}
+
+/// comparable summary of a whole map along a path from spawn to finish
+#[derive(Debug, Clone, Serialize)]
+pub struct MapAnalysis {
+    pub width: usize,
+    pub height: usize,
+
+    /// amount of blocks of the path
+    pub path_length: usize,
+
+    /// share of the non-solid blocks that are freeze
+    pub freeze_coverage: f32,
+
+    /// corridor widths at all path blocks that are not freeze, see corridor_width
+    pub avg_corridor_width: f32,
+    pub min_corridor_width: usize,
+    pub max_corridor_width: usize,
+
+    /// average and max. difficulty of all segments
+    pub avg_difficulty: f32,
+    pub max_difficulty: f32,
+
+    pub segments: Vec<SegmentReport>,
+}
+
+/// runs the corridor width, freeze coverage and difficulty passes over a map. The path is split
+/// into segments at the waypoints, which have to be positions of the path.
+pub fn analyze_map(map: &Map, path: &[Position], waypoints: &[Position]) -> MapAnalysis {
+    let stats = MapStats::new(map);
+    let non_solid = stats.empty + stats.freeze;
+    let freeze_coverage = match non_solid {
+        0 => 0.0,
+        _ => stats.freeze as f32 / non_solid as f32,
+    };
+
+    let widths: Vec<usize> = path
+        .iter()
+        .map(|pos| corridor_width(map, pos))
+        .filter(|width| *width > 0)
+        .collect();
+    let avg_corridor_width = match widths.len() {
+        0 => 0.0,
+        len => widths.iter().sum::<usize>() as f32 / len as f32,
+    };
+
+    // waypoints are part of the path, so they are reached exactly
+    let segments = analyze_segments(map, path, waypoints, 0, None);
+    let avg_difficulty = match segments.len() {
+        0 => 0.0,
+        len => {
+            segments
+                .iter()
+                .map(|segment| segment.difficulty)
+                .sum::<f32>()
+                / len as f32
+        }
+    };
+
+    MapAnalysis {
+        width: map.width,
+        height: map.height,
+        path_length: path.len(),
+        freeze_coverage,
+        avg_corridor_width,
+        min_corridor_width: widths.iter().copied().min().unwrap_or(0),
+        max_corridor_width: widths.iter().copied().max().unwrap_or(0),
+        avg_difficulty,
+        max_difficulty: segments
+            .iter()
+            .map(|segment| segment.difficulty)
+            .fold(0.0, f32::max),
+        segments,
+    }
+}
+
+/// euclidean distance of each block to the next block that fulfills the criterion
+fn distance_to<F>(map: &Map, criterion: F) -> Array2<f32>
+where
+    F: Fn(&BlockType) -> bool,
+{
+    distance_field(&map.grid.map(criterion))
+}
+
+/// counts all set blocks within the bounding box of the given positions
+fn count_in_bounds(grid: &Array2<bool>, positions: &[Position]) -> usize {
+    let x_min = positions.iter().map(|pos| pos.x).min().unwrap();
+    let x_max = positions.iter().map(|pos| pos.x).max().unwrap();
+    let y_min = positions.iter().map(|pos| pos.y).min().unwrap();
+    let y_max = positions.iter().map(|pos| pos.y).max().unwrap();
+
+    grid.slice(s![x_min..=x_max, y_min..=y_max])
+        .iter()
+        .filter(|value| **value)
+        .count()
+}
+
+/// block counts of a map, updated incrementally based on the changed chunks
+#[derive(Debug, Clone)]
+pub struct MapStats {
+    pub empty: usize,
+    pub freeze: usize,
+    pub hookable: usize,
+    pub platform: usize,
+
+    /// (empty, freeze, hookable, platform) counts of each chunk
+    chunk_counts: Array2<[usize; 4]>,
+}
+
+impl MapStats {
+    pub fn new(map: &Map) -> MapStats {
+        let mut stats = MapStats {
+            empty: 0,
+            freeze: 0,
+            hookable: 0,
+            platform: 0,
+            chunk_counts: Array2::from_elem(map.chunk_dirty.dim(), [0; 4]),
+        };
+
+        for chunk_pos in ndarray::indices(map.chunk_dirty.dim()) {
+            stats.update_chunk_counts(&map.grid, map.chunk_size, chunk_pos);
+        }
+
+        stats
+    }
+
+    /// re-counts all chunks that changed since the last update. Only changes done via
+    /// apply_kernel or set_area are tracked, so direct grid edits require a new MapStats.
+    pub fn update(&mut self, map: &mut Map) {
+        if map.chunk_dirty.dim() != self.chunk_counts.dim() {
+            *self = MapStats::new(map);
+        }
+
+        for (chunk_pos, dirty) in map.chunk_dirty.indexed_iter_mut() {
+            if *dirty {
+                *dirty = false;
+                self.update_chunk_counts(&map.grid, map.chunk_size, chunk_pos);
+            }
+        }
+    }
+
+    fn update_chunk_counts(
+        &mut self,
+        grid: &Array2<BlockType>,
+        chunk_size: usize,
+        (chunk_x, chunk_y): (usize, usize),
+    ) {
+        let x_start = chunk_x * chunk_size;
+        let y_start = chunk_y * chunk_size;
+        let x_end = usize::min(x_start + chunk_size, grid.dim().0);
+        let y_end = usize::min(y_start + chunk_size, grid.dim().1);
+
+        let mut counts = [0; 4];
+        for block_type in grid.slice(s![x_start..x_end, y_start..y_end]).iter() {
+            match block_type {
+                BlockType::Empty => counts[0] += 1,
+                BlockType::Freeze => counts[1] += 1,
+                BlockType::Hookable => counts[2] += 1,
+                BlockType::Platform => counts[3] += 1,
+            }
+        }
+
+        let old_counts = self.chunk_counts[[chunk_x, chunk_y]];
+        self.empty = self.empty + counts[0] - old_counts[0];
+        self.freeze = self.freeze + counts[1] - old_counts[1];
+        self.hookable = self.hookable + counts[2] - old_counts[2];
+        self.platform = self.platform + counts[3] - old_counts[3];
+        self.chunk_counts[[chunk_x, chunk_y]] = counts;
+    }
+}
+
+/// width of the corridor at the given position, which is the shorter of the horizontal and
+/// vertical run of free (neither solid nor freeze) blocks through it
+pub fn corridor_width(map: &Map, pos: &Position) -> usize {
+    let is_open = |x: usize, y: usize| {
+        let block_type = &map.grid[[x, y]];
+        !block_type.is_solid() && !block_type.is_freeze()
+    };
+    if !map.pos_in_bounds(pos) || !is_open(pos.x, pos.y) {
+        return 0;
+    }
+
+    let left = (0..pos.x).rev().take_while(|x| is_open(*x, pos.y)).count();
+    let right = (pos.x + 1..map.width)
+        .take_while(|x| is_open(*x, pos.y))
+        .count();
+    let up = (0..pos.y).rev().take_while(|y| is_open(pos.x, *y)).count();
+    let down = (pos.y + 1..map.height)
+        .take_while(|y| is_open(pos.x, *y))
+        .count();
+
+    usize::min(left + right, up + down) + 1
+}
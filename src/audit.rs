@@ -0,0 +1,107 @@
+use crate::{
+    config::{GenerationConfig, MapConfig},
+    generator::Generator,
+    random::{set_rng_audit, RngDraw, Seed},
+};
+use std::fmt;
+
+/// way the map is generated during an audit run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditRun {
+    /// like Generator::generate_map
+    GenerateMap,
+
+    /// like the editor, which steps the generator frame by frame with the walker event log
+    Editor,
+}
+
+/// first rng draw at which two runs of the same seed differ
+#[derive(Debug, Clone)]
+pub struct RngDivergence {
+    pub index: usize,
+
+    /// draws of both runs at the divergence, None if a run has no more draws
+    pub first: Option<RngDraw>,
+    pub second: Option<RngDraw>,
+}
+
+impl fmt::Display for RngDivergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format_draw = |draw: &Option<RngDraw>| match draw {
+            Some(draw) => draw.to_string(),
+            None => "<no draw>".to_string(),
+        };
+
+        write!(
+            f,
+            "draw {}: {} vs {}",
+            self.index,
+            format_draw(&self.first),
+            format_draw(&self.second)
+        )
+    }
+}
+
+/// generates a map and returns all rng draws in order
+pub fn record_draws(
+    run: AuditRun,
+    max_steps: usize,
+    seed: &Seed,
+    gen_config: &GenerationConfig,
+    map_config: &MapConfig,
+) -> Result<Vec<RngDraw>, &'static str> {
+    set_rng_audit(true);
+    let mut gen = Generator::new(gen_config, map_config, seed.clone());
+    set_rng_audit(false);
+
+    if run == AuditRun::Editor {
+        gen.walker.enable_event_log();
+    }
+
+    for _ in 0..max_steps {
+        if gen.walker.finished {
+            break;
+        }
+        gen.step(gen_config)?;
+    }
+    gen.handle_truncation(gen_config)?;
+
+    gen.perform_all_post_processing(gen_config)?;
+
+    gen.rnd.take_audit().ok_or("rng audit was not enabled")
+}
+
+/// returns the first draw at which both runs differ in call site or value
+pub fn find_divergence(first: &[RngDraw], second: &[RngDraw]) -> Option<RngDivergence> {
+    let index = (0..usize::max(first.len(), second.len()))
+        .find(|index| first.get(*index) != second.get(*index))?;
+
+    Some(RngDivergence {
+        index,
+        first: first.get(index).cloned(),
+        second: second.get(index).cloned(),
+    })
+}
+
+/// generates the same seed like generate_map and like the editor and compares all rng draws.
+/// Returns the amount of draws if both runs are identical.
+pub fn audit_seed(
+    max_steps: usize,
+    seed: &Seed,
+    gen_config: &GenerationConfig,
+    map_config: &MapConfig,
+) -> Result<Result<usize, RngDivergence>, &'static str> {
+    let first = record_draws(
+        AuditRun::GenerateMap,
+        max_steps,
+        seed,
+        gen_config,
+        map_config,
+    )?;
+    let second = record_draws(AuditRun::Editor, max_steps, seed, gen_config, map_config)?;
+
+    Ok(match find_divergence(&first, &second) {
+        Some(divergence) => Err(divergence),
+        None => Ok(first.len()),
+    })
+}
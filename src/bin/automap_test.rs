@@ -13,8 +13,11 @@ fn main() {
     )
     .unwrap();
 
-    map.export(&PathBuf::from(
-        "/home/tobi/.local/share/ddnet/maps/automap_out.map",
-        // "./automap_out.map",
-    ));
+    map.export(
+        &PathBuf::from(
+            "/home/tobi/.local/share/ddnet/maps/automap_out.map",
+            // "./automap_out.map",
+        ),
+        None,
+    );
 }
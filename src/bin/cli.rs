@@ -0,0 +1,874 @@
+use clap::{crate_version, Parser, Subcommand};
+use gores_mapgen::analysis::{analyze_map, analyze_segments};
+use gores_mapgen::audit::audit_seed;
+use gores_mapgen::config::{GenerationConfig, MapConfig};
+use gores_mapgen::export::MapProvenance;
+use gores_mapgen::generator::Generator;
+use gores_mapgen::genrec::GenerationRecording;
+use gores_mapgen::metrics::MapMetrics;
+use gores_mapgen::naming::map_file_name;
+use gores_mapgen::random::Seed;
+use gores_mapgen::regression::{
+    format_results, load_cases, record_cases, run_regression, save_cases,
+};
+use gores_mapgen::rendering::export_map_png;
+use gores_mapgen::schema::{json_schema, SchemaKind};
+use gores_mapgen::trace::GenerationTrace;
+use gores_mapgen::twmap_import::{TwImport, DEFAULT_WAYPOINT_DIST};
+use gores_mapgen::validation::validate_map;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// suffix of the status files written by the watch mode
+const RESULT_SUFFIX: &str = ".result.json";
+
+#[derive(Parser, Debug)]
+#[command(name = "Random Gores Map Generator CLI")]
+#[command(version = crate_version!())]
+#[command(about = "Headless tooling for the map generator", long_about = None)]
+struct Args {
+    /// additionally load all generation presets from this directory
+    #[arg(short, long, global = true)]
+    preset_dir: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// generate a single map
+    Generate {
+        /// name of the generation preset
+        gen_config: String,
+
+        /// name of the map preset
+        map_config: String,
+
+        /// seed for the generation, a random seed is used if not set
+        #[arg(short, long)]
+        seed: Option<u64>,
+
+        /// output path of the generated map, defaults to the map name (e.g. Frozen_Spiral_7f3a.map)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// max amount of walker steps
+        #[arg(long, default_value_t = 200_000)]
+        max_steps: usize,
+
+        /// dump the walker event log as json to this file
+        #[arg(long)]
+        trace: Option<PathBuf>,
+
+        /// theme used for exporting, overrides the theme of the preset
+        #[arg(long)]
+        theme: Option<String>,
+
+        /// write a difficulty report of each waypoint segment as json to this file
+        #[arg(long)]
+        report: Option<PathBuf>,
+
+        /// write quality metrics of the map (path length, tunnel width, freeze ratio, skips, dead
+        /// ends, difficulty) as json to this file, e.g. to filter bad seeds
+        #[arg(long)]
+        metrics: Option<PathBuf>,
+
+        /// generate a low detail draft on a grid downscaled by this factor, e.g. 2
+        #[arg(long)]
+        draft: Option<usize>,
+
+        /// check DDNet constraints (spawn, start and finish lines) before exporting and refuse
+        /// to export invalid maps. Violations are printed as json.
+        #[arg(long)]
+        validate: bool,
+
+        /// write a .genrec recording of the generation to this file, which can be replayed in
+        /// the editor
+        #[arg(long)]
+        genrec: Option<PathBuf>,
+
+        /// include the outcomes of all rng samples in the .genrec recording, so it replays the
+        /// same map even after the rng implementation changed
+        #[arg(long, requires = "genrec")]
+        genrec_trace: bool,
+
+        /// write the seed, resolved configs and difficulty report next to the map as .json, so
+        /// the map can be traced and regenerated
+        #[arg(long)]
+        provenance: bool,
+    },
+
+    /// generate many maps in parallel, e.g. to pick the best candidates. The maps are written to
+    /// the output folder as numbered files, e.g. 0007_Frozen_Spiral_7f3a.map
+    Batch {
+        /// name of the generation preset
+        gen_config: String,
+
+        /// name of the map preset
+        map_config: String,
+
+        /// amount of maps
+        count: u64,
+
+        /// seed of the first map, the following maps use the next seeds. A random seed is used
+        /// if not set
+        #[arg(short, long)]
+        seed: Option<u64>,
+
+        /// output folder of the generated maps
+        #[arg(short, long, default_value = "batch")]
+        output: PathBuf,
+
+        /// max amount of walker steps per map
+        #[arg(long, default_value_t = 200_000)]
+        max_steps: usize,
+
+        /// amount of maps that are generated at once, defaults to the amount of cpu cores
+        #[arg(short = 'j', long)]
+        threads: Option<usize>,
+    },
+
+    /// re-generate golden maps and compare their fingerprints
+    Regression {
+        /// json file containing (seed, gen_config, map_config, hash) cases
+        file: String,
+
+        /// overwrite the expected hashes with the current generation output
+        #[arg(long)]
+        record: bool,
+    },
+
+    /// generate a seed like the editor and like generate_map and report the first rng draw at
+    /// which both runs diverge
+    Audit {
+        /// name of the generation preset
+        gen_config: String,
+
+        /// name of the map preset
+        map_config: String,
+
+        /// seed for the generation
+        seed: u64,
+
+        /// max amount of walker steps
+        #[arg(long, default_value_t = 200_000)]
+        max_steps: usize,
+    },
+
+    /// replay a .genrec recording and check whether it still generates the same map
+    Replay {
+        /// .genrec file to replay
+        genrec: PathBuf,
+
+        /// max amount of walker steps
+        #[arg(long, default_value_t = 200_000)]
+        max_steps: usize,
+    },
+
+    /// trace the path of an existing .map and store it as map config
+    Trace {
+        /// .map file to trace from spawn to finish
+        map: PathBuf,
+
+        /// output path of the map config
+        output: String,
+
+        /// amount of path steps between two waypoints
+        #[arg(long, default_value_t = DEFAULT_WAYPOINT_DIST)]
+        waypoint_dist: usize,
+    },
+
+    /// analyze corridor widths, freeze coverage and difficulty of existing .map files along the
+    /// path from spawn to finish, e.g. to calibrate presets against hand-made maps
+    Analyze {
+        /// .map files to analyze
+        #[arg(required = true)]
+        maps: Vec<PathBuf>,
+
+        /// amount of path steps per difficulty segment
+        #[arg(long, default_value_t = DEFAULT_WAYPOINT_DIST)]
+        waypoint_dist: usize,
+
+        /// write the full reports including all segments as json to this file
+        #[arg(long)]
+        json: Option<PathBuf>,
+    },
+
+    /// watch a folder for request files and generate a map and png next to each of them. A
+    /// request is a json file like {"gen_config": "hardV2", "map_config": "small_s", "seed": 42}
+    /// and results in <name>.map, <name>.png and a <name>.result.json status file.
+    Watch {
+        /// folder that is watched for requests
+        dir: PathBuf,
+
+        /// delay between two scans of the folder in milliseconds
+        #[arg(long, default_value_t = 500)]
+        interval: u64,
+
+        /// max amount of walker steps
+        #[arg(long, default_value_t = 200_000)]
+        max_steps: usize,
+
+        /// handle all pending requests once and exit instead of watching
+        #[arg(long)]
+        once: bool,
+    },
+
+    /// print the JSON Schema of a json document, e.g. to validate presets in other tools
+    Schema {
+        /// document of the schema: gen_config, map_config or sidecar
+        kind: String,
+
+        /// write the schema to this file instead of printing it
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+/// request file of the watch mode
+#[derive(Deserialize, Debug)]
+struct WatchRequest {
+    /// name of the generation preset
+    gen_config: String,
+
+    /// name of the map preset, the initial map preset is used if not set
+    map_config: Option<String>,
+
+    /// either a u64 or a string seed, a random seed is used if not set
+    seed: Option<Value>,
+}
+
+/// status file that is written next to a handled request
+#[derive(Serialize, Debug)]
+#[serde(tag = "state", rename_all = "snake_case")]
+enum WatchResult {
+    Finished {
+        seed: u64,
+        map: PathBuf,
+        png: PathBuf,
+
+        /// duration of the map export in milliseconds
+        export_ms: u64,
+
+        /// size of the map file in bytes
+        map_size: u64,
+    },
+    Failed {
+        reason: String,
+    },
+}
+
+fn parse_seed(seed: &Option<Value>) -> Result<Seed, String> {
+    match seed {
+        None | Some(Value::Null) => Ok(Seed::random()),
+        Some(Value::Number(number)) => number
+            .as_u64()
+            .map(Seed::from_u64)
+            .ok_or("seed must be a positive integer".to_string()),
+        Some(Value::String(seed_str)) => Ok(Seed::from_string(seed_str)),
+        Some(_) => Err("seed must be a number or a string".to_string()),
+    }
+}
+
+/// request files in the folder that don't have a status file yet. Files that were modified
+/// recently are skipped, as they might still be written.
+fn pending_requests(dir: &Path, min_age: Duration) -> Vec<PathBuf> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            eprintln!("couldn't read {:?}: {}", dir, err);
+            return Vec::new();
+        }
+    };
+
+    let mut requests: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            let name = path.to_string_lossy();
+            name.ends_with(".json") && !name.ends_with(RESULT_SUFFIX) && !result_path(path).exists()
+        })
+        .filter(|path| {
+            fs::metadata(path)
+                .and_then(|meta| meta.modified())
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .is_some_and(|age| age >= min_age)
+        })
+        .collect();
+    requests.sort();
+
+    requests
+}
+
+fn result_path(request_path: &Path) -> PathBuf {
+    let stem = request_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    request_path.with_file_name(format!("{}{}", stem, RESULT_SUFFIX))
+}
+
+fn handle_request(
+    request_path: &Path,
+    gen_configs: &HashMap<String, GenerationConfig>,
+    map_configs: &HashMap<String, MapConfig>,
+    max_steps: usize,
+) -> Result<WatchResult, String> {
+    let data = fs::read_to_string(request_path).map_err(|err| err.to_string())?;
+    let request: WatchRequest = serde_json::from_str(&data).map_err(|err| err.to_string())?;
+
+    let gen_config = gen_configs
+        .get(&request.gen_config)
+        .ok_or(format!("unknown gen config '{}'", request.gen_config))?;
+    let map_config = match &request.map_config {
+        Some(name) => map_configs
+            .get(name)
+            .cloned()
+            .ok_or(format!("unknown map config '{}'", name))?,
+        None => MapConfig::get_initial_config(),
+    };
+    let seed = parse_seed(&request.seed)?;
+
+    let map_path = request_path.with_extension("map");
+    let png_path = request_path.with_extension("png");
+
+    // ensure that a panic in the generator or exporter doesnt stop watching
+    let stats = panic::catch_unwind(AssertUnwindSafe(|| {
+        let map = Generator::generate_map(max_steps, &seed, gen_config, &map_config)?;
+        let stats = map.export(&map_path, gen_config.get_theme().as_ref());
+        export_map_png(&map, &png_path);
+        Ok(stats)
+    }))
+    .unwrap_or(Err("generation or export panicked"))?;
+
+    Ok(WatchResult::Finished {
+        seed: seed.seed_u64,
+        map: map_path,
+        png: png_path,
+        export_ms: stats.duration.as_millis() as u64,
+        map_size: stats.file_size,
+    })
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let gen_configs = match &args.preset_dir {
+        Some(preset_dir) => GenerationConfig::get_all_configs_with_dir(preset_dir),
+        None => GenerationConfig::get_all_configs(),
+    };
+
+    match args.command {
+        Command::Generate {
+            gen_config,
+            map_config,
+            seed,
+            output,
+            max_steps,
+            trace,
+            theme,
+            report,
+            metrics,
+            draft,
+            validate,
+            genrec,
+            genrec_trace,
+            provenance,
+        } => {
+            let map_configs = MapConfig::get_all_configs();
+            let (gen_config, map_config) =
+                match (gen_configs.get(&gen_config), map_configs.get(&map_config)) {
+                    (Some(gen_config), Some(map_config)) => (gen_config, map_config),
+                    (None, _) => {
+                        eprintln!("unknown gen config '{}'", gen_config);
+                        return ExitCode::FAILURE;
+                    }
+                    (_, None) => {
+                        eprintln!("unknown map config '{}'", map_config);
+                        return ExitCode::FAILURE;
+                    }
+                };
+            let seed = seed.map(Seed::from_u64).unwrap_or_else(Seed::random);
+
+            let mut gen_config = gen_config.clone();
+            if theme.is_some() {
+                gen_config.theme = theme;
+            }
+            let mut gen = match draft {
+                Some(scale) => {
+                    let gen = Generator::new_draft(&gen_config, map_config, seed.clone(), scale);
+                    gen_config = gen_config.downscaled(scale.max(1));
+                    gen
+                }
+                None if genrec_trace => {
+                    GenerationTrace::recording_generator(&gen_config, map_config, seed.clone())
+                }
+                None => Generator::new(&gen_config, map_config, seed.clone()),
+            };
+            let gen_config = &gen_config;
+            if trace.is_some() {
+                gen.walker.enable_event_log();
+            }
+
+            let mut result = Ok(());
+            for _ in 0..max_steps {
+                if gen.walker.finished {
+                    break;
+                }
+                if let Err(err) = gen.step(gen_config) {
+                    result = Err(err);
+                    break;
+                }
+            }
+            if result.is_ok() {
+                result = gen.handle_truncation(gen_config);
+            }
+
+            // dump trace before post processing, so failed generations can be inspected
+            if let (Some(trace), Some(event_log)) = (&trace, &gen.walker.event_log) {
+                let serialized =
+                    serde_json::to_string_pretty(event_log).expect("failed to serialize event log");
+                match fs::write(trace, serialized) {
+                    Ok(()) => println!("wrote {} walker events to {:?}", event_log.len(), trace),
+                    Err(err) => eprintln!("couldn't write trace: {}", err),
+                }
+            }
+
+            let result = result.and_then(|_| gen.perform_all_post_processing(gen_config));
+            match result {
+                Ok(()) => {
+                    if let Some(report) = &report {
+                        let segment_reports = analyze_segments(
+                            &gen.map,
+                            &gen.walker.position_history.to_vec(),
+                            gen.get_waypoints(),
+                            gen_config.waypoint_reached_dist,
+                            gen.debug_layers.get("skips").map(|layer| &layer.grid),
+                        );
+                        let serialized = serde_json::to_string_pretty(&segment_reports)
+                            .expect("failed to serialize segment reports");
+                        if let Err(err) = fs::write(report, serialized) {
+                            eprintln!("couldn't write report: {}", err);
+                        }
+                    }
+
+                    if let Some(metrics) = &metrics {
+                        let serialized =
+                            serde_json::to_string_pretty(&MapMetrics::new(&gen, gen_config))
+                                .expect("failed to serialize metrics");
+                        if let Err(err) = fs::write(metrics, serialized) {
+                            eprintln!("couldn't write metrics: {}", err);
+                        }
+                    }
+
+                    if validate {
+                        let violations = validate_map(&gen.map);
+                        if !violations.is_empty() {
+                            let serialized = serde_json::to_string_pretty(&violations)
+                                .expect("failed to serialize violations");
+                            println!("{}", serialized);
+                            eprintln!(
+                                "map violates {} constraints (seed {}), not exported",
+                                violations.len(),
+                                seed.seed_u64
+                            );
+                            return ExitCode::FAILURE;
+                        }
+                    }
+
+                    let output = output.unwrap_or_else(|| {
+                        PathBuf::from(map_file_name(seed.seed_u64, &gen_config.name))
+                            .with_extension("map")
+                    });
+                    let stats = gen.map.export(&output, gen_config.get_theme().as_ref());
+
+                    match (&genrec, draft) {
+                        (Some(_), Some(_)) => eprintln!("drafts can't be recorded"),
+                        (Some(genrec), None) => {
+                            let recording = GenerationRecording::new(
+                                &gen,
+                                gen_config,
+                                map_config,
+                                seed.seed_u64,
+                            );
+                            if let Err(err) = recording.save(genrec) {
+                                eprintln!("couldn't write recording: {}", err);
+                            }
+                        }
+                        (None, _) => {}
+                    }
+
+                    if provenance {
+                        let provenance =
+                            MapProvenance::new(&gen, gen_config, map_config, seed.seed_u64, &stats);
+                        if let Err(err) = provenance.save(&output) {
+                            eprintln!("couldn't write provenance: {}", err);
+                        }
+                    }
+
+                    println!(
+                        "generated {} (seed {}, export: {})",
+                        gen.map.name, seed.seed_u64, stats
+                    );
+                    ExitCode::SUCCESS
+                }
+                Err(err) => {
+                    eprintln!("generation failed (seed {}): {}", seed.seed_u64, err);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Command::Batch {
+            gen_config,
+            map_config,
+            count,
+            seed,
+            output,
+            max_steps,
+            threads,
+        } => {
+            let map_configs = MapConfig::get_all_configs();
+            let (gen_config, map_config) =
+                match (gen_configs.get(&gen_config), map_configs.get(&map_config)) {
+                    (Some(gen_config), Some(map_config)) => (gen_config, map_config),
+                    (None, _) => {
+                        eprintln!("unknown gen config '{}'", gen_config);
+                        return ExitCode::FAILURE;
+                    }
+                    (_, None) => {
+                        eprintln!("unknown map config '{}'", map_config);
+                        return ExitCode::FAILURE;
+                    }
+                };
+
+            if let Err(err) = fs::create_dir_all(&output) {
+                eprintln!("couldn't create {:?}: {}", output, err);
+                return ExitCode::FAILURE;
+            }
+            if let Some(threads) = threads {
+                if let Err(err) = rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build_global()
+                {
+                    eprintln!("couldn't set up {} threads: {}", threads, err);
+                    return ExitCode::FAILURE;
+                }
+            }
+
+            let first_seed = seed.unwrap_or_else(|| Seed::random().seed_u64);
+            let seeds: Vec<Seed> = (0..count)
+                .map(|index| Seed::from_u64(first_seed.wrapping_add(index)))
+                .collect();
+            let theme = gen_config.get_theme();
+
+            let results = Generator::generate_batch(
+                max_steps,
+                &seeds,
+                gen_config,
+                map_config,
+                |index, map| {
+                    let path = output.join(format!(
+                        "{:04}_{}.map",
+                        index,
+                        map_file_name(seeds[index].seed_u64, &gen_config.name)
+                    ));
+                    let stats = map.export(&path, theme.as_ref());
+                    (path, stats)
+                },
+            );
+
+            let mut failed = 0;
+            for (seed, result) in seeds.iter().zip(results) {
+                match result {
+                    Ok((path, stats)) => {
+                        println!(
+                            "generated {:?} (seed {}, export: {})",
+                            path, seed.seed_u64, stats
+                        )
+                    }
+                    Err(err) => {
+                        eprintln!("generation failed (seed {}): {}", seed.seed_u64, err);
+                        failed += 1;
+                    }
+                }
+            }
+            println!("generated {} of {} maps", seeds.len() - failed, seeds.len());
+
+            match failed {
+                0 => ExitCode::SUCCESS,
+                _ => ExitCode::FAILURE,
+            }
+        }
+        Command::Regression { file, record } => {
+            let cases = match load_cases(&file) {
+                Ok(cases) => cases,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    return ExitCode::FAILURE;
+                }
+            };
+
+            if record {
+                match record_cases(&cases, &gen_configs).and_then(|cases| save_cases(&cases, &file))
+                {
+                    Ok(()) => println!("recorded {} cases to {}", cases.len(), file),
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        return ExitCode::FAILURE;
+                    }
+                }
+                return ExitCode::SUCCESS;
+            }
+
+            let results = run_regression(&cases, &gen_configs);
+            println!("{}", format_results(&results));
+
+            if results.iter().all(|r| r.passed()) {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+        Command::Audit {
+            gen_config,
+            map_config,
+            seed,
+            max_steps,
+        } => {
+            let map_configs = MapConfig::get_all_configs();
+            let (gen_config, map_config) =
+                match (gen_configs.get(&gen_config), map_configs.get(&map_config)) {
+                    (Some(gen_config), Some(map_config)) => (gen_config, map_config),
+                    (None, _) => {
+                        eprintln!("unknown gen config '{}'", gen_config);
+                        return ExitCode::FAILURE;
+                    }
+                    (_, None) => {
+                        eprintln!("unknown map config '{}'", map_config);
+                        return ExitCode::FAILURE;
+                    }
+                };
+
+            match audit_seed(max_steps, &Seed::from_u64(seed), gen_config, map_config) {
+                Ok(Ok(draws)) => {
+                    println!("no divergence in {} rng draws", draws);
+                    ExitCode::SUCCESS
+                }
+                Ok(Err(divergence)) => {
+                    eprintln!("runs diverged at {}", divergence);
+                    ExitCode::FAILURE
+                }
+                Err(err) => {
+                    eprintln!("generation failed (seed {}): {}", seed, err);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Command::Replay { genrec, max_steps } => {
+            let recording = match GenerationRecording::load(&genrec) {
+                Ok(recording) => recording,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    return ExitCode::FAILURE;
+                }
+            };
+            if !recording.is_same_crate_version() {
+                eprintln!(
+                    "recording was created by version {}, which may generate differently",
+                    recording.crate_version
+                );
+            }
+
+            let gen_config = &recording.gen_config;
+            let mut gen = recording.replay_generator();
+            let mut result = Ok(());
+            for _ in 0..max_steps {
+                if gen.walker.finished {
+                    break;
+                }
+                if let Err(err) = gen.step(gen_config) {
+                    result = Err(err);
+                    break;
+                }
+            }
+            let result = result
+                .and_then(|_| gen.handle_truncation(gen_config))
+                .and_then(|_| gen.perform_all_post_processing(gen_config));
+
+            if let Some(index) = gen.rnd.trace_divergence() {
+                eprintln!("replay diverged from the trace at sample {}", index);
+            }
+            match result {
+                Ok(()) if Generator::fingerprint_map(&gen.map) == recording.hash => {
+                    println!("replay matches the recording (seed {})", recording.seed);
+                    ExitCode::SUCCESS
+                }
+                Ok(()) => {
+                    eprintln!(
+                        "replay differs from the recording (seed {})",
+                        recording.seed
+                    );
+                    ExitCode::FAILURE
+                }
+                Err(err) => {
+                    eprintln!("replay failed (seed {}): {}", recording.seed, err);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Command::Trace {
+            map,
+            output,
+            waypoint_dist,
+        } => {
+            let name = map
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or("imported".to_string());
+
+            match TwImport::trace_map_config(&map, &name, waypoint_dist) {
+                Ok(map_config) => {
+                    map_config.save(&output);
+                    println!(
+                        "traced {} waypoints ({}x{}) to {}",
+                        map_config.waypoints.len(),
+                        map_config.width,
+                        map_config.height,
+                        output
+                    );
+                    ExitCode::SUCCESS
+                }
+                Err(err) => {
+                    eprintln!("{}", err);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Command::Analyze {
+            maps,
+            waypoint_dist,
+            json,
+        } => {
+            let mut analyses = BTreeMap::new();
+            let mut failed = false;
+            for map in maps.iter() {
+                let analysis = TwImport::load_game_layer(map).and_then(|tiles| {
+                    let path = TwImport::trace_path(&tiles)?;
+                    let waypoints = TwImport::path_to_waypoints(&path, waypoint_dist);
+                    Ok(analyze_map(
+                        &TwImport::game_layer_to_map(&tiles),
+                        &path,
+                        &waypoints,
+                    ))
+                });
+
+                match analysis {
+                    Ok(analysis) => {
+                        println!(
+                            "{}: {}x{}, path {}, freeze {:.1}%, corridor width {:.1} ({}-{}), difficulty {:.2} (max {:.2})",
+                            map.to_string_lossy(),
+                            analysis.width,
+                            analysis.height,
+                            analysis.path_length,
+                            analysis.freeze_coverage * 100.0,
+                            analysis.avg_corridor_width,
+                            analysis.min_corridor_width,
+                            analysis.max_corridor_width,
+                            analysis.avg_difficulty,
+                            analysis.max_difficulty
+                        );
+                        analyses.insert(map.to_string_lossy().to_string(), analysis);
+                    }
+                    Err(err) => {
+                        eprintln!("couldn't analyze {:?}: {}", map, err);
+                        failed = true;
+                    }
+                }
+            }
+
+            if let Some(json) = &json {
+                let serialized =
+                    serde_json::to_string_pretty(&analyses).expect("failed to serialize analyses");
+                if let Err(err) = fs::write(json, serialized) {
+                    eprintln!("couldn't write analyses: {}", err);
+                    failed = true;
+                }
+            }
+
+            match failed {
+                true => ExitCode::FAILURE,
+                false => ExitCode::SUCCESS,
+            }
+        }
+        Command::Watch {
+            dir,
+            interval,
+            max_steps,
+            once,
+        } => {
+            let map_configs = MapConfig::get_all_configs();
+            let interval = Duration::from_millis(interval);
+            let min_age = match once {
+                true => Duration::ZERO,
+                false => interval,
+            };
+
+            loop {
+                for request_path in pending_requests(&dir, min_age) {
+                    let result =
+                        handle_request(&request_path, &gen_configs, &map_configs, max_steps)
+                            .unwrap_or_else(|reason| WatchResult::Failed { reason });
+                    println!("{:?}: {:?}", request_path, result);
+
+                    let serialized =
+                        serde_json::to_string_pretty(&result).expect("failed to serialize result");
+                    if let Err(err) = fs::write(result_path(&request_path), serialized) {
+                        eprintln!("couldn't write result of {:?}: {}", request_path, err);
+                    }
+                }
+
+                if once {
+                    return ExitCode::SUCCESS;
+                }
+                thread::sleep(interval);
+            }
+        }
+        Command::Schema { kind, output } => {
+            let kind = match SchemaKind::from_name(&kind) {
+                Some(kind) => kind,
+                None => {
+                    let names: Vec<&str> = SchemaKind::ALL.iter().map(|kind| kind.name()).collect();
+                    eprintln!(
+                        "unknown schema {:?}, expected one of {}",
+                        kind,
+                        names.join(", ")
+                    );
+                    return ExitCode::FAILURE;
+                }
+            };
+
+            let serialized = serde_json::to_string_pretty(&json_schema(kind))
+                .expect("failed to serialize schema");
+            match output {
+                Some(output) => {
+                    if let Err(err) = fs::write(&output, serialized) {
+                        eprintln!("couldn't write schema: {}", err);
+                        return ExitCode::FAILURE;
+                    }
+                    println!("wrote schema to {:?}", output);
+                }
+                None => println!("{}", serialized),
+            }
+
+            ExitCode::SUCCESS
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use std::time::Instant;
+
+use clap::Parser;
+use gores_mapgen::config::{GenerationConfig, MapConfig};
+use gores_mapgen::distance::DistanceField;
+use gores_mapgen::generator::Generator;
+use gores_mapgen::random::Seed;
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Compares all distance transform implementations against the reference",
+    long_about = None
+)]
+struct Args {
+    /// generation preset of the maps
+    #[arg(default_value = "hardV2")]
+    preset: String,
+
+    /// map config of the maps
+    #[arg(default_value = "large_spiral")]
+    layout: String,
+
+    /// amount of maps (with seeds 0, 1, ...)
+    #[arg(long, default_value_t = 3)]
+    maps: u64,
+}
+
+fn main() {
+    let args = Args::parse();
+    let gen_config = &GenerationConfig::get_all_configs()[&args.preset];
+    let map_config = &MapConfig::get_all_configs()[&args.layout];
+
+    for seed in 0..args.maps {
+        let map =
+            match Generator::generate_map(200_000, &Seed::from_u64(seed), gen_config, map_config) {
+                Ok(map) => map,
+                Err(err) => {
+                    println!("seed {}: generation failed: {}", seed, err);
+                    continue;
+                }
+            };
+        let grid = map.grid.map(|block| !block.is_empty());
+
+        let reference = DistanceField::Reference.compute(&grid);
+        for implementation in DistanceField::ALL {
+            let start = Instant::now();
+            let distance = implementation.compute(&grid);
+            let duration = start.elapsed();
+
+            let max_error = distance
+                .iter()
+                .zip(reference.iter())
+                .map(|(distance, reference)| (distance - reference).abs())
+                .fold(0.0, f32::max);
+            println!(
+                "seed {} ({}x{}): {:?} took {:?}, max error {}",
+                seed, map.width, map.height, implementation, duration, max_error
+            );
+        }
+    }
+}
@@ -0,0 +1,1134 @@
+use clap::{Parser, ValueEnum};
+use gores_mapgen::config::{format_config_errors, GenerationConfig, MapConfig, MapSize};
+use gores_mapgen::export::{current_date, MapProvenance};
+use gores_mapgen::generator::Generator;
+use gores_mapgen::naming::map_name;
+use gores_mapgen::random::Seed;
+use gores_mapgen::rendering::export_map_png;
+use gores_mapgen::twmap_export::ExportStats;
+use gores_mapgen::validation::{validate_map, Violation};
+use gores_mapgen::vote::{slot_map_name, MapVote};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use simple_logger::SimpleLogger;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// how many walker steps are performed between two progress updates of a job
+const PROGRESS_INTERVAL: usize = 1000;
+
+/// seconds between two checks whether the daily map is due
+const DAILY_CHECK_INTERVAL: u64 = 30;
+
+/// max seconds between two attempts to submit the daily map, the delay doubles after each
+/// failed attempt
+const DAILY_MAX_RETRY_DELAY: u64 = 3600;
+
+/// upper bounds of the histogram buckets for generation duration (in seconds)
+const DURATION_BUCKETS: [f64; 8] = [0.1, 0.5, 1.0, 2.0, 5.0, 10.0, 30.0, 60.0];
+
+/// upper bounds of the histogram buckets for walker steps
+const STEP_BUCKETS: [f64; 7] = [
+    1000.0, 5000.0, 10000.0, 25000.0, 50000.0, 100000.0, 200000.0,
+];
+
+/// upper bounds of the histogram buckets for map sizes (in blocks)
+const MAP_SIZE_BUCKETS: [f64; 6] = [10000.0, 50000.0, 100000.0, 250000.0, 500000.0, 1000000.0];
+
+/// upper bounds of the histogram buckets for map export duration (in seconds)
+const EXPORT_DURATION_BUCKETS: [f64; 6] = [0.01, 0.05, 0.1, 0.25, 0.5, 1.0];
+
+/// upper bounds of the histogram buckets for map file sizes (in bytes)
+const MAP_FILE_SIZE_BUCKETS: [f64; 6] = [16384.0, 65536.0, 131072.0, 262144.0, 524288.0, 1048576.0];
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum MetricsFormat {
+    /// prometheus text format, e.g. for the node exporter textfile collector
+    Prometheus,
+    Json,
+}
+
+#[derive(Parser, Debug)]
+#[command(name = "Random Gores Map Generator Service")]
+#[command(about = "Long running generation service using line based JSON-RPC over TCP", long_about = None)]
+struct Args {
+    /// address the service listens on
+    #[arg(short, long, default_value = "127.0.0.1:7777")]
+    address: String,
+
+    /// number of worker threads that generate maps in parallel
+    #[arg(short, long, default_value_t = 2)]
+    workers: usize,
+
+    /// directory where generated maps are stored
+    #[arg(short, long, default_value = "gen_server_out")]
+    output: PathBuf,
+
+    /// max amount of walker steps per generation
+    #[arg(long, default_value_t = 200_000)]
+    max_steps: usize,
+
+    /// additionally load all generation presets from this directory
+    #[arg(short, long)]
+    preset_dir: Option<PathBuf>,
+
+    /// periodically write generation metrics to this file
+    #[arg(long)]
+    metrics_file: Option<PathBuf>,
+
+    /// format of the metrics file
+    #[arg(long, value_enum, default_value_t = MetricsFormat::Prometheus)]
+    metrics_format: MetricsFormat,
+
+    /// seconds between two writes of the metrics file
+    #[arg(long, default_value_t = 15)]
+    metrics_interval: u64,
+
+    /// generate a daily map with this generation preset. The seed is derived from the date and
+    /// the presets, so every service with the same presets generates the same daily map.
+    #[arg(long)]
+    daily_gen_config: Option<String>,
+
+    /// map preset of the daily map
+    #[arg(long, default_value = "small_s")]
+    daily_map_config: String,
+
+    /// time of day (hh:mm, utc) at which the daily map of the new date is generated
+    #[arg(long, default_value = "00:00", value_parser = parse_time_of_day)]
+    daily_time: u64,
+
+    /// number of named map slots. Votes like `generate hardV2 slot2` additionally store the map
+    /// as random_map2.map, so several generated maps can be available at the same time.
+    #[arg(long, default_value_t = 0)]
+    slots: usize,
+
+    /// write the seed, resolved configs and difficulty report of each map as .json next to it,
+    /// so maps found on a server can be traced and regenerated
+    #[arg(long)]
+    provenance: bool,
+
+    /// amount of done jobs that are kept for status and fetch requests, older done jobs are
+    /// forgotten (their files stay in the output directory)
+    #[arg(long, default_value_t = 1000)]
+    job_history: usize,
+}
+
+/// parses hh:mm into seconds since midnight
+fn parse_time_of_day(time: &str) -> Result<u64, String> {
+    let (hours, minutes) = time.split_once(':').ok_or("expected hh:mm".to_string())?;
+    let hours: u64 = hours.parse().map_err(|_| "invalid hours".to_string())?;
+    let minutes: u64 = minutes.parse().map_err(|_| "invalid minutes".to_string())?;
+    if hours >= 24 || minutes >= 60 {
+        return Err("time must be between 00:00 and 23:59".to_string());
+    }
+
+    Ok(hours * 3600 + minutes * 60)
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct JobRequest {
+    /// name of the generation preset
+    gen_config: String,
+
+    /// name of the map preset
+    map_config: String,
+
+    /// scales the layout of the map preset
+    #[serde(default)]
+    size: MapSize,
+
+    /// either a u64 or a string seed, a random seed is used if not set
+    seed: Option<Value>,
+
+    /// whether a png preview should be rendered aswell
+    #[serde(default)]
+    png: bool,
+
+    /// named map slot the map is stored in aswell, see slot_map_name
+    #[serde(default)]
+    slot: Option<usize>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "state", rename_all = "snake_case")]
+enum JobState {
+    Queued,
+    Running { steps: usize },
+    Finished,
+    Failed { reason: String },
+    Cancelled,
+}
+
+impl JobState {
+    /// whether the job reached its final state, which never changes again
+    fn is_done(&self) -> bool {
+        matches!(
+            self,
+            JobState::Finished | JobState::Failed { .. } | JobState::Cancelled
+        )
+    }
+}
+
+struct Job {
+    request: JobRequest,
+    seed: Seed,
+
+    /// name of the map, so players can refer to it, see map_name
+    name: String,
+    state: JobState,
+    cancel: Arc<AtomicBool>,
+    map_path: Option<PathBuf>,
+    png_path: Option<PathBuf>,
+
+    /// DDNet constraints the generated map violates
+    violations: Vec<Violation>,
+
+    /// size and duration of the map export
+    export_stats: Option<ExportStats>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct Histogram {
+    /// upper bounds of all buckets, there is an implicit +Inf bucket
+    buckets: &'static [f64],
+
+    /// cumulative amount of observations per bucket
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(buckets: &'static [f64]) -> Histogram {
+        Histogram {
+            buckets,
+            counts: vec![0; buckets.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        for (bound, count) in self.buckets.iter().zip(self.counts.iter_mut()) {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn write_prometheus(&self, name: &str, help: &str, out: &mut String) {
+        *out += &format!("# HELP {} {}\n# TYPE {} histogram\n", name, help, name);
+        for (bound, count) in self.buckets.iter().zip(self.counts.iter()) {
+            *out += &format!("{}_bucket{{le=\"{}\"}} {}\n", name, bound, count);
+        }
+        *out += &format!("{}_bucket{{le=\"+Inf\"}} {}\n", name, self.count);
+        *out += &format!("{}_sum {}\n{}_count {}\n", name, self.sum, name, self.count);
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct Metrics {
+    generations_total: u64,
+    failures_total: u64,
+    cancellations_total: u64,
+
+    /// duration of all finished or failed generations
+    duration_seconds: Histogram,
+
+    /// walker steps and map sizes of finished generations
+    steps: Histogram,
+    map_size_blocks: Histogram,
+
+    /// export duration and file size of exported maps
+    export_duration_seconds: Histogram,
+    map_file_size_bytes: Histogram,
+}
+
+impl Metrics {
+    fn new() -> Metrics {
+        Metrics {
+            generations_total: 0,
+            failures_total: 0,
+            cancellations_total: 0,
+            duration_seconds: Histogram::new(&DURATION_BUCKETS),
+            steps: Histogram::new(&STEP_BUCKETS),
+            map_size_blocks: Histogram::new(&MAP_SIZE_BUCKETS),
+            export_duration_seconds: Histogram::new(&EXPORT_DURATION_BUCKETS),
+            map_file_size_bytes: Histogram::new(&MAP_FILE_SIZE_BUCKETS),
+        }
+    }
+
+    fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        for (name, help, value) in [
+            (
+                "mapgen_generations_total",
+                "Total amount of finished generations.",
+                self.generations_total,
+            ),
+            (
+                "mapgen_failures_total",
+                "Total amount of failed generations.",
+                self.failures_total,
+            ),
+            (
+                "mapgen_cancellations_total",
+                "Total amount of cancelled generations.",
+                self.cancellations_total,
+            ),
+        ] {
+            out += &format!(
+                "# HELP {} {}\n# TYPE {} counter\n{} {}\n",
+                name, help, name, name, value
+            );
+        }
+        self.duration_seconds.write_prometheus(
+            "mapgen_duration_seconds",
+            "Duration of generations.",
+            &mut out,
+        );
+        self.steps
+            .write_prometheus("mapgen_steps", "Walker steps of generations.", &mut out);
+        self.map_size_blocks.write_prometheus(
+            "mapgen_map_size_blocks",
+            "Size of generated maps in blocks.",
+            &mut out,
+        );
+        self.export_duration_seconds.write_prometheus(
+            "mapgen_export_duration_seconds",
+            "Duration of map exports.",
+            &mut out,
+        );
+        self.map_file_size_bytes.write_prometheus(
+            "mapgen_map_file_size_bytes",
+            "Size of exported map files in bytes.",
+            &mut out,
+        );
+
+        out
+    }
+}
+
+/// current daily map
+#[derive(Serialize, Debug, Clone)]
+struct DailyMap {
+    date: String,
+    gen_config: String,
+    map_config: String,
+    seed: u64,
+    name: String,
+    job: u64,
+}
+
+struct Service {
+    jobs: Mutex<HashMap<u64, Job>>,
+    next_job_id: Mutex<u64>,
+    queue: Mutex<mpsc::Sender<u64>>,
+    gen_configs: HashMap<String, GenerationConfig>,
+    map_configs: HashMap<String, MapConfig>,
+    output: PathBuf,
+    max_steps: usize,
+    metrics: Mutex<Metrics>,
+    daily: Mutex<Option<DailyMap>>,
+    slots: usize,
+    provenance: bool,
+    job_history: usize,
+}
+
+fn parse_seed(seed: &Option<Value>) -> Result<Seed, String> {
+    match seed {
+        None | Some(Value::Null) => Ok(Seed::random()),
+        Some(Value::Number(number)) => number
+            .as_u64()
+            .map(Seed::from_u64)
+            .ok_or("seed must be a positive integer".to_string()),
+        Some(Value::String(seed_str)) => Ok(Seed::from_string(seed_str)),
+        Some(_) => Err("seed must be a number or a string".to_string()),
+    }
+}
+
+impl Service {
+    /// updates the state of a job, unless it is already done (e.g. cancelled). Returns whether
+    /// the state was updated.
+    fn set_state(&self, job_id: u64, state: JobState) -> bool {
+        match self.jobs.lock().unwrap().get_mut(&job_id) {
+            Some(job) if !job.state.is_done() => {
+                job.state = state;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// forgets the oldest done jobs, so at most job_history done jobs are kept
+    fn evict_jobs(&self) {
+        // the daily map has to stay available until the next one is submitted
+        let daily_job = self.daily.lock().unwrap().as_ref().map(|daily| daily.job);
+
+        let mut jobs = self.jobs.lock().unwrap();
+        let mut done: Vec<u64> = jobs
+            .iter()
+            .filter(|(job_id, job)| job.state.is_done() && Some(**job_id) != daily_job)
+            .map(|(job_id, _)| *job_id)
+            .collect();
+        if done.len() <= self.job_history {
+            return;
+        }
+
+        done.sort_unstable();
+        for job_id in &done[..done.len() - self.job_history] {
+            jobs.remove(job_id);
+        }
+    }
+
+    fn submit(&self, params: Value) -> Result<Value, String> {
+        let request: JobRequest = serde_json::from_value(params).map_err(|e| e.to_string())?;
+
+        let gen_config = self
+            .gen_configs
+            .get(&request.gen_config)
+            .ok_or(format!("unknown gen config '{}'", request.gen_config))?;
+        if let Err(errors) = gen_config.validate() {
+            return Err(format!(
+                "invalid gen config '{}': {}",
+                request.gen_config,
+                format_config_errors(&errors)
+            ));
+        }
+        if !self.map_configs.contains_key(&request.map_config) {
+            return Err(format!("unknown map config '{}'", request.map_config));
+        }
+        if let Some(slot) = request.slot {
+            if slot == 0 || slot > self.slots {
+                return Err(format!("slot must be between 1 and {}", self.slots));
+            }
+        }
+
+        let seed = parse_seed(&request.seed)?;
+        let name = map_name(seed.seed_u64, &request.gen_config);
+
+        let job_id = {
+            let mut next_job_id = self.next_job_id.lock().unwrap();
+            *next_job_id += 1;
+            *next_job_id
+        };
+
+        self.jobs.lock().unwrap().insert(
+            job_id,
+            Job {
+                request,
+                seed: seed.clone(),
+                name: name.clone(),
+                state: JobState::Queued,
+                cancel: Arc::new(AtomicBool::new(false)),
+                map_path: None,
+                png_path: None,
+                violations: Vec::new(),
+                export_stats: None,
+            },
+        );
+        self.queue
+            .lock()
+            .unwrap()
+            .send(job_id)
+            .map_err(|_| "job queue closed".to_string())?;
+
+        Ok(json!({ "job": job_id, "seed": seed.seed_u64, "name": name }))
+    }
+
+    /// submits a job for a player vote like `generate hardV2 large`, the remaining params are
+    /// the same as for submit
+    fn vote(&self, params: Value) -> Result<Value, String> {
+        let command = params
+            .get("command")
+            .and_then(|v| v.as_str())
+            .ok_or("missing vote command".to_string())?;
+        let vote = MapVote::parse(command, &self.gen_configs)?;
+
+        let mut params = params.clone();
+        params["gen_config"] = json!(vote.gen_config);
+        params["size"] = serde_json::to_value(vote.size).map_err(|e| e.to_string())?;
+        if let Some(slot) = vote.slot {
+            params["slot"] = json!(slot);
+        }
+
+        self.submit(params)
+    }
+
+    fn status(&self, job_id: u64) -> Result<Value, String> {
+        let jobs = self.jobs.lock().unwrap();
+        let job = jobs.get(&job_id).ok_or("unknown job")?;
+
+        serde_json::to_value(&job.state).map_err(|e| e.to_string())
+    }
+
+    fn fetch(&self, job_id: u64) -> Result<Value, String> {
+        let jobs = self.jobs.lock().unwrap();
+        let job = jobs.get(&job_id).ok_or("unknown job")?;
+
+        if job.state != JobState::Finished {
+            return Err("job is not finished".to_string());
+        }
+
+        Ok(json!({
+            "seed": job.seed.seed_u64,
+            "name": job.name,
+            "map": job.map_path,
+            "png": job.png_path,
+            "violations": job.violations,
+            "export_ms": job.export_stats.map(|stats| stats.duration.as_millis() as u64),
+            "map_size": job.export_stats.map(|stats| stats.file_size),
+            "slot_map": job.request.slot.map(slot_map_name),
+        }))
+    }
+
+    /// server votes that switch to the map of each slot
+    fn slot_votes(&self) -> Value {
+        let votes: Vec<Value> = (1..=self.slots)
+            .map(|slot| {
+                json!({
+                    "description": format!("play slot {}", slot),
+                    "command": format!("change_map {}", slot_map_name(slot)),
+                })
+            })
+            .collect();
+
+        json!(votes)
+    }
+
+    fn cancel(&self, job_id: u64) -> Result<Value, String> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.get_mut(&job_id).ok_or("unknown job")?;
+
+        match job.state {
+            JobState::Queued | JobState::Running { .. } => {
+                job.cancel.store(true, Ordering::Relaxed);
+                job.state = JobState::Cancelled;
+                Ok(json!(true))
+            }
+            _ => Err("job already done".to_string()),
+        }
+    }
+
+    fn handle_request(&self, method: &str, params: Value) -> Result<Value, String> {
+        let job_id = || {
+            params
+                .get("job")
+                .and_then(|v| v.as_u64())
+                .ok_or("missing job id".to_string())
+        };
+
+        match method {
+            "submit" => self.submit(params.clone()),
+            "vote" => self.vote(params.clone()),
+            "status" => self.status(job_id()?),
+            "fetch" => self.fetch(job_id()?),
+            "cancel" => self.cancel(job_id()?),
+            "metrics" => {
+                serde_json::to_value(&*self.metrics.lock().unwrap()).map_err(|e| e.to_string())
+            }
+            "daily" => self.daily(),
+            "slot_votes" => Ok(self.slot_votes()),
+            "presets" => Ok(json!({
+                "gen_configs": self.gen_configs.keys().collect::<Vec<_>>(),
+                "map_configs": self.map_configs.keys().collect::<Vec<_>>(),
+            })),
+            _ => Err(format!("unknown method '{}'", method)),
+        }
+    }
+
+    /// current daily map and the state of its job
+    fn daily(&self) -> Result<Value, String> {
+        let daily = self
+            .daily
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or("no daily map yet".to_string())?;
+        let mut result = serde_json::to_value(&daily).map_err(|e| e.to_string())?;
+        result["status"] = self.status(daily.job)?;
+
+        Ok(result)
+    }
+
+    /// submits the daily map once per date, as soon as the time of day is reached
+    fn daily_scheduler(&self, gen_config: String, map_config: String, time_of_day: u64) {
+        let mut retry_delay = DAILY_CHECK_INTERVAL;
+        loop {
+            let secs = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0);
+            let date = current_date();
+            let due = secs % 86400 >= time_of_day
+                && self
+                    .daily
+                    .lock()
+                    .unwrap()
+                    .as_ref()
+                    .map(|daily| daily.date != date)
+                    .unwrap_or(true);
+
+            if due {
+                let seed = Seed::daily(&date, &gen_config, &map_config);
+                let params = json!({
+                    "gen_config": gen_config,
+                    "map_config": map_config,
+                    "seed": seed.seed_u64,
+                    "png": true,
+                });
+                match self.submit(params) {
+                    Ok(result) => {
+                        let daily = DailyMap {
+                            date,
+                            gen_config: gen_config.clone(),
+                            map_config: map_config.clone(),
+                            seed: seed.seed_u64,
+                            name: result["name"].as_str().unwrap_or_default().to_string(),
+                            job: result["job"].as_u64().unwrap(),
+                        };
+                        println!(
+                            "daily map of {}: {} ({} {}, seed {}), job {}",
+                            daily.date,
+                            daily.name,
+                            daily.gen_config,
+                            daily.map_config,
+                            daily.seed,
+                            daily.job
+                        );
+                        *self.daily.lock().unwrap() = Some(daily);
+                        retry_delay = DAILY_CHECK_INTERVAL;
+                    }
+                    Err(e) => {
+                        println!(
+                            "failed to submit daily map: {}, retrying in {}s",
+                            e, retry_delay
+                        );
+                        thread::sleep(Duration::from_secs(retry_delay));
+                        retry_delay = (retry_delay * 2).min(DAILY_MAX_RETRY_DELAY);
+                        continue;
+                    }
+                }
+            }
+
+            thread::sleep(Duration::from_secs(DAILY_CHECK_INTERVAL));
+        }
+    }
+
+    /// generates the map for a single job, periodically checking whether it got cancelled
+    fn run_job(&self, job_id: u64) -> Result<JobState, String> {
+        let (request, seed, cancel) = {
+            let jobs = self.jobs.lock().unwrap();
+            let job = jobs.get(&job_id).ok_or("unknown job")?;
+            (job.request.clone(), job.seed.clone(), job.cancel.clone())
+        };
+
+        if cancel.load(Ordering::Relaxed) {
+            return Ok(JobState::Cancelled);
+        }
+
+        let gen_config = &self.gen_configs[&request.gen_config];
+        let map_config = request.size.apply(&self.map_configs[&request.map_config]);
+        let seed_u64 = seed.seed_u64;
+        let mut gen = Generator::new(gen_config, &map_config, seed);
+
+        for step in 0..self.max_steps {
+            if gen.walker.finished {
+                break;
+            }
+            if cancel.load(Ordering::Relaxed) {
+                return Ok(JobState::Cancelled);
+            }
+            if step % PROGRESS_INTERVAL == 0 {
+                self.set_state(job_id, JobState::Running { steps: step });
+            }
+            gen.step(gen_config)?;
+        }
+        gen.handle_truncation(gen_config)?;
+
+        gen.perform_all_post_processing(gen_config)?;
+
+        {
+            let mut metrics = self.metrics.lock().unwrap();
+            metrics.steps.observe(gen.walker.steps as f64);
+            metrics
+                .map_size_blocks
+                .observe((gen.map.width * gen.map.height) as f64);
+        }
+
+        // the job may have been cancelled during post processing
+        if cancel.load(Ordering::Relaxed) {
+            return Ok(JobState::Cancelled);
+        }
+
+        let violations = validate_map(&gen.map);
+
+        let map_path = self.output.join(format!("{}.map", job_id));
+        let export_stats = gen.map.export(&map_path, gen_config.get_theme().as_ref());
+
+        {
+            let mut metrics = self.metrics.lock().unwrap();
+            metrics
+                .export_duration_seconds
+                .observe(export_stats.duration.as_secs_f64());
+            metrics
+                .map_file_size_bytes
+                .observe(export_stats.file_size as f64);
+        }
+
+        if self.provenance {
+            MapProvenance::new(&gen, gen_config, &map_config, seed_u64, &export_stats)
+                .save(&map_path)?;
+        }
+
+        // the map of a slot is only replaced once the new map is complete
+        if let Some(slot) = request.slot {
+            // hold the job lock, so the job can't be cancelled while the slot map is replaced
+            let _jobs = self.jobs.lock().unwrap();
+            if cancel.load(Ordering::Relaxed) {
+                return Ok(JobState::Cancelled);
+            }
+
+            let slot_path = self.output.join(slot_map_name(slot)).with_extension("map");
+            fs::copy(&map_path, &slot_path).map_err(|e| e.to_string())?;
+            if self.provenance {
+                fs::copy(
+                    map_path.with_extension("json"),
+                    slot_path.with_extension("json"),
+                )
+                .map_err(|e| e.to_string())?;
+            }
+        }
+
+        let png_path = if request.png {
+            let png_path = self.output.join(format!("{}.png", job_id));
+            export_map_png(&gen.map, &png_path);
+            Some(png_path)
+        } else {
+            None
+        };
+
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&job_id) {
+            job.map_path = Some(map_path);
+            job.png_path = png_path;
+            job.violations = violations;
+            job.export_stats = Some(export_stats);
+        }
+
+        Ok(JobState::Finished)
+    }
+
+    fn worker(&self, queue: Arc<Mutex<mpsc::Receiver<u64>>>) {
+        loop {
+            let job_id = match queue.lock().unwrap().recv() {
+                Ok(job_id) => job_id,
+                Err(_) => return, // queue closed -> shut down worker
+            };
+
+            // ensure that a panic in the generator doesnt kill the worker
+            let start = Instant::now();
+            let result = panic::catch_unwind(AssertUnwindSafe(|| self.run_job(job_id)))
+                .unwrap_or(Err("generation panicked".to_string()));
+
+            let state = match result {
+                Ok(state) => state,
+                Err(reason) => JobState::Failed { reason },
+            };
+
+            // a job that was cancelled in the meantime stays cancelled
+            let state = match self.set_state(job_id, state.clone()) {
+                true => state,
+                false => JobState::Cancelled,
+            };
+
+            {
+                let mut metrics = self.metrics.lock().unwrap();
+                match state {
+                    JobState::Cancelled => metrics.cancellations_total += 1,
+                    JobState::Failed { .. } => metrics.failures_total += 1,
+                    _ => metrics.generations_total += 1,
+                }
+                if state != JobState::Cancelled {
+                    metrics
+                        .duration_seconds
+                        .observe(start.elapsed().as_secs_f64());
+                }
+            }
+
+            self.evict_jobs();
+        }
+    }
+
+    /// periodically writes the metrics to a file, so they can be collected by external tools
+    fn metrics_writer(&self, path: PathBuf, format: MetricsFormat, interval: Duration) {
+        loop {
+            let data = {
+                let metrics = self.metrics.lock().unwrap();
+                match format {
+                    MetricsFormat::Prometheus => metrics.to_prometheus(),
+                    MetricsFormat::Json => serde_json::to_string_pretty(&*metrics)
+                        .expect("failed to serialize metrics"),
+                }
+            };
+
+            // write to a temporary file first, so collectors never read partial files
+            let tmp_path = path.with_extension("tmp");
+            if let Err(e) = fs::write(&tmp_path, data).and_then(|_| fs::rename(&tmp_path, &path)) {
+                println!("failed to write metrics: {}", e);
+            }
+
+            thread::sleep(interval);
+        }
+    }
+
+    /// answers a single json-rpc request line. Independent of the transport, so requests can
+    /// be handled the same way regardless of where they come from.
+    fn handle_line(&self, line: &str) -> Value {
+        match serde_json::from_str::<Value>(line) {
+            Ok(request) => {
+                let id = request.get("id").cloned().unwrap_or(Value::Null);
+                let method = request.get("method").and_then(|m| m.as_str()).unwrap_or("");
+                let params = request.get("params").cloned().unwrap_or(json!({}));
+
+                match self.handle_request(method, params) {
+                    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                    Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+                }
+            }
+            Err(e) => json!({ "jsonrpc": "2.0", "id": null, "error": e.to_string() }),
+        }
+    }
+
+    /// answers request lines until the reader is exhausted, one response line per request
+    fn serve(&self, reader: impl BufRead, mut writer: impl Write) -> std::io::Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            writeln!(writer, "{}", self.handle_line(&line))?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_connection(&self, stream: TcpStream) -> std::io::Result<()> {
+        let writer = stream.try_clone()?;
+        self.serve(BufReader::new(stream), writer)
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+    SimpleLogger::new().init().unwrap();
+
+    fs::create_dir_all(&args.output).expect("failed to create output directory");
+
+    let (sender, receiver) = mpsc::channel();
+    let receiver = Arc::new(Mutex::new(receiver));
+
+    let service = Arc::new(Service {
+        jobs: Mutex::new(HashMap::new()),
+        next_job_id: Mutex::new(0),
+        queue: Mutex::new(sender),
+        gen_configs: match &args.preset_dir {
+            Some(preset_dir) => GenerationConfig::get_all_configs_with_dir(preset_dir),
+            None => GenerationConfig::get_all_configs(),
+        },
+        map_configs: MapConfig::get_all_configs(),
+        output: args.output,
+        max_steps: args.max_steps,
+        metrics: Mutex::new(Metrics::new()),
+        daily: Mutex::new(None),
+        slots: args.slots,
+        provenance: args.provenance,
+        job_history: args.job_history,
+    });
+
+    if let Some(metrics_file) = args.metrics_file {
+        let service = service.clone();
+        let interval = Duration::from_secs(args.metrics_interval.max(1));
+        thread::spawn(move || service.metrics_writer(metrics_file, args.metrics_format, interval));
+    }
+
+    if let Some(daily_gen_config) = args.daily_gen_config {
+        let service = service.clone();
+        let daily_map_config = args.daily_map_config;
+        let daily_time = args.daily_time;
+        thread::spawn(move || {
+            service.daily_scheduler(daily_gen_config, daily_map_config, daily_time)
+        });
+    }
+
+    for _ in 0..args.workers.max(1) {
+        let service = service.clone();
+        let receiver = receiver.clone();
+        thread::spawn(move || service.worker(receiver));
+    }
+
+    let listener = TcpListener::bind(&args.address).expect("failed to bind address");
+    println!("listening on {}", args.address);
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let service = service.clone();
+                thread::spawn(move || {
+                    if let Err(e) = service.handle_connection(stream) {
+                        println!("connection closed: {}", e);
+                    }
+                });
+            }
+            Err(e) => println!("failed to accept connection: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// service without workers, so submitted jobs stay queued
+    fn test_service() -> (Service, mpsc::Receiver<u64>) {
+        let (sender, receiver) = mpsc::channel();
+        let service = Service {
+            jobs: Mutex::new(HashMap::new()),
+            next_job_id: Mutex::new(0),
+            queue: Mutex::new(sender),
+            gen_configs: GenerationConfig::get_all_configs(),
+            map_configs: MapConfig::get_all_configs(),
+            output: PathBuf::from("gen_server_test_out"),
+            max_steps: 200_000,
+            metrics: Mutex::new(Metrics::new()),
+            daily: Mutex::new(None),
+            slots: 2,
+            provenance: false,
+            job_history: 1000,
+        };
+
+        (service, receiver)
+    }
+
+    /// serves the request lines and returns the parsed response lines
+    fn serve_lines(service: &Service, lines: &[&str]) -> Vec<Value> {
+        let mut output = Vec::new();
+        service
+            .serve(Cursor::new(lines.join("\n")), &mut output)
+            .unwrap();
+
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn submit_status_cancel() {
+        let (service, queue) = test_service();
+        let responses = serve_lines(
+            &service,
+            &[
+                r#"{"id": 1, "method": "submit", "params": {"gen_config": "hardV2", "map_config": "small_s", "seed": 42}}"#,
+                r#"{"id": 2, "method": "status", "params": {"job": 1}}"#,
+                r#"{"id": 3, "method": "cancel", "params": {"job": 1}}"#,
+                r#"{"id": 4, "method": "status", "params": {"job": 1}}"#,
+                r#"{"id": 5, "method": "cancel", "params": {"job": 1}}"#,
+                r#"{"id": 6, "method": "fetch", "params": {"job": 1}}"#,
+            ],
+        );
+
+        assert_eq!(responses.len(), 6);
+        assert_eq!(responses[0]["id"], 1);
+        assert_eq!(responses[0]["result"]["job"], 1);
+        assert_eq!(responses[0]["result"]["seed"], 42);
+        assert_eq!(queue.try_recv(), Ok(1));
+
+        assert_eq!(responses[1]["result"]["state"], "queued");
+        assert_eq!(responses[2]["result"], true);
+        assert_eq!(responses[3]["result"]["state"], "cancelled");
+        assert_eq!(responses[4]["error"], "job already done");
+        assert_eq!(responses[5]["error"], "job is not finished");
+    }
+
+    #[test]
+    fn cancelled_job_stays_cancelled() {
+        let (service, _queue) = test_service();
+        serve_lines(
+            &service,
+            &[
+                r#"{"id": 1, "method": "submit", "params": {"gen_config": "hardV2", "map_config": "small_s"}}"#,
+                r#"{"id": 2, "method": "cancel", "params": {"job": 1}}"#,
+            ],
+        );
+
+        assert!(!service.set_state(1, JobState::Running { steps: 1000 }));
+        assert!(!service.set_state(1, JobState::Finished));
+        assert_eq!(service.jobs.lock().unwrap()[&1].state, JobState::Cancelled);
+    }
+
+    #[test]
+    fn done_jobs_are_evicted() {
+        let (mut service, _queue) = test_service();
+        service.job_history = 2;
+        for _ in 0..4 {
+            serve_lines(
+                &service,
+                &[
+                    r#"{"method": "submit", "params": {"gen_config": "hardV2", "map_config": "small_s"}}"#,
+                ],
+            );
+        }
+        for job_id in 1..=3 {
+            service.set_state(job_id, JobState::Finished);
+        }
+        service.evict_jobs();
+
+        let mut job_ids: Vec<u64> = service.jobs.lock().unwrap().keys().copied().collect();
+        job_ids.sort_unstable();
+        assert_eq!(job_ids, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn daily_job_is_not_evicted() {
+        let (mut service, _queue) = test_service();
+        service.job_history = 1;
+        for _ in 0..3 {
+            serve_lines(
+                &service,
+                &[
+                    r#"{"method": "submit", "params": {"gen_config": "hardV2", "map_config": "small_s"}}"#,
+                ],
+            );
+        }
+        *service.daily.lock().unwrap() = Some(DailyMap {
+            date: "2024-01-31".to_string(),
+            gen_config: "hardV2".to_string(),
+            map_config: "small_s".to_string(),
+            seed: 0,
+            name: "daily".to_string(),
+            job: 1,
+        });
+        for job_id in 1..=3 {
+            service.set_state(job_id, JobState::Finished);
+        }
+        service.evict_jobs();
+
+        let mut job_ids: Vec<u64> = service.jobs.lock().unwrap().keys().copied().collect();
+        job_ids.sort_unstable();
+        assert_eq!(job_ids, vec![1, 3]);
+
+        let responses = serve_lines(&service, &[r#"{"id": 1, "method": "daily"}"#]);
+        assert_eq!(responses[0]["result"]["job"], 1);
+        assert_eq!(responses[0]["result"]["status"]["state"], "finished");
+    }
+
+    #[test]
+    fn invalid_requests() {
+        let (service, _queue) = test_service();
+        let responses = serve_lines(
+            &service,
+            &[
+                "not json",
+                "",
+                r#"{"id": 1, "method": "explode"}"#,
+                r#"{"id": 2}"#,
+                r#"{"id": 3, "method": "status"}"#,
+                r#"{"id": 4, "method": "status", "params": {"job": 99}}"#,
+                r#"{"id": 5, "method": "submit", "params": {"gen_config": "nope", "map_config": "small_s"}}"#,
+                r#"{"id": 6, "method": "submit", "params": {"gen_config": "hardV2"}}"#,
+                r#"{"id": 7, "method": "submit", "params": {"gen_config": "hardV2", "map_config": "small_s", "seed": -1}}"#,
+                r#"{"id": 8, "method": "submit", "params": {"gen_config": "hardV2", "map_config": "small_s", "slot": 3}}"#,
+            ],
+        );
+
+        // empty lines are skipped, every other line gets exactly one response
+        assert_eq!(responses.len(), 9);
+        assert_eq!(responses[0]["id"], Value::Null);
+        assert!(responses[0]["error"].is_string());
+        assert_eq!(responses[1]["error"], "unknown method 'explode'");
+        assert_eq!(responses[2]["error"], "unknown method ''");
+        assert_eq!(responses[3]["error"], "missing job id");
+        assert_eq!(responses[4]["error"], "unknown job");
+        assert_eq!(responses[5]["error"], "unknown gen config 'nope'");
+        assert!(responses[6]["error"].is_string());
+        assert_eq!(responses[7]["error"], "seed must be a positive integer");
+        assert_eq!(responses[8]["error"], "slot must be between 1 and 2");
+        assert!(responses
+            .iter()
+            .all(|response| response.get("result").is_none()));
+        assert!(service.jobs.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn vote_submits_job() {
+        let (service, _queue) = test_service();
+        let responses = serve_lines(
+            &service,
+            &[
+                r#"{"id": 1, "method": "vote", "params": {"command": "generate HARDV2 large slot2", "map_config": "small_s"}}"#,
+                r#"{"id": 2, "method": "vote", "params": {"command": "generate hardV2 huge", "map_config": "small_s"}}"#,
+            ],
+        );
+
+        assert_eq!(responses[0]["result"]["job"], 1);
+        assert_eq!(responses[1]["error"], "unknown map size");
+
+        let jobs = service.jobs.lock().unwrap();
+        assert_eq!(jobs[&1].request.gen_config, "hardV2");
+        assert_eq!(jobs[&1].request.size, MapSize::Large);
+        assert_eq!(jobs[&1].request.slot, Some(2));
+    }
+
+    #[test]
+    fn parse_votes() {
+        let gen_configs = GenerationConfig::get_all_configs();
+        let parse = |command: &str| MapVote::parse(command, &gen_configs);
+
+        assert_eq!(
+            parse("generate hardV2"),
+            Ok(MapVote {
+                gen_config: "hardV2".to_string(),
+                size: MapSize::Medium,
+                slot: None,
+            })
+        );
+        assert_eq!(
+            parse("  generate   hardv2   slot3  small "),
+            Ok(MapVote {
+                gen_config: "hardV2".to_string(),
+                size: MapSize::Small,
+                slot: Some(3),
+            })
+        );
+        assert_eq!(parse("generate hardV2 LARGE").unwrap().size, MapSize::Large);
+
+        assert_eq!(parse(""), Err("vote must start with 'generate'"));
+        assert_eq!(parse("gen hardV2"), Err("vote must start with 'generate'"));
+        assert_eq!(parse("generate"), Err("missing preset"));
+        assert_eq!(parse("generate nope"), Err("unknown preset"));
+        assert_eq!(parse("generate hardV2 huge"), Err("unknown map size"));
+        assert_eq!(parse("generate hardV2 slotx"), Err("invalid slot"));
+        assert_eq!(parse("generate hardV2 slot"), Err("invalid slot"));
+        assert_eq!(
+            parse("generate hardV2 small large"),
+            Err("too many arguments")
+        );
+        assert_eq!(
+            parse("generate hardV2 slot1 slot2"),
+            Err("too many arguments")
+        );
+    }
+}
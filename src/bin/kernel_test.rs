@@ -0,0 +1,169 @@
+use clap::Parser;
+use gores_mapgen::{
+    config::GenerationConfig,
+    kernel::{Kernel, ValidKernelTable},
+    random::RandomDistConfig,
+};
+use macroquad::prelude::*;
+
+/// size of a single kernel block in pixels
+const BLOCK_SIZE: f32 = 6.0;
+
+/// space between two kernels in pixels
+const CELL_MARGIN: f32 = 12.0;
+
+#[derive(Parser, Debug)]
+#[command(about = "Renders all distinct (size, circularity) kernel shapes", long_about = None)]
+struct Args {
+    /// highlight the shapes that this generation preset can sample
+    preset: Option<String>,
+
+    /// largest kernel size of the sweep
+    #[arg(long, default_value_t = 11)]
+    max_size: usize,
+}
+
+/// all values of a distribution that can actually be sampled
+fn sampled_values<T: Clone>(dist: &RandomDistConfig<T>) -> Vec<T> {
+    dist.values
+        .iter()
+        .flatten()
+        .zip(dist.probs.iter())
+        .filter(|(_, prob)| **prob > 0.0)
+        .map(|(value, _)| value.clone())
+        .collect()
+}
+
+/// (inner, outer) flags of every table entry that the kernel mutation of the preset can sample
+fn sampled_entries(table: &ValidKernelTable, preset: &GenerationConfig) -> Vec<(bool, bool)> {
+    let mut sampled = vec![(false, false); table.entries.len()];
+    let circularities = sampled_values(&preset.circ_probs);
+    let margins = sampled_values(&preset.outer_margin_probs);
+
+    for inner_size in sampled_values(&preset.inner_size_probs) {
+        for circularity in circularities.iter() {
+            // mirrors the constraints of the kernel mutation, small kernels are always rect
+            let circularity_for = |size: usize| match size <= 3 {
+                true => 0.0,
+                false => *circularity,
+            };
+
+            if let Some(index) = table.find(inner_size, circularity_for(inner_size)) {
+                sampled[index].0 = true;
+            }
+            for margin in margins.iter() {
+                let outer_size = inner_size + margin;
+                if let Some(index) = table.find(outer_size, circularity_for(outer_size)) {
+                    sampled[index].1 = true;
+                }
+            }
+        }
+    }
+
+    sampled
+}
+
+fn draw_kernel(kernel: &Kernel, x: f32, y: f32) {
+    for ((kernel_x, kernel_y), active) in kernel.vector.indexed_iter() {
+        let color = match active {
+            true => DARKGRAY,
+            false => Color::new(0.0, 0.0, 0.0, 0.1),
+        };
+        draw_rectangle(
+            x + kernel_x as f32 * BLOCK_SIZE,
+            y + kernel_y as f32 * BLOCK_SIZE,
+            BLOCK_SIZE,
+            BLOCK_SIZE,
+            color,
+        );
+    }
+}
+
+#[macroquad::main("Kernel Sweep")]
+async fn main() {
+    let args = Args::parse();
+    let table = ValidKernelTable::new(args.max_size);
+
+    let preset = args.preset.as_ref().map(|name| {
+        GenerationConfig::get_all_configs()
+            .remove(name)
+            .unwrap_or_else(|| panic!("unknown gen config '{}'", name))
+    });
+    let sampled = preset
+        .as_ref()
+        .map(|preset| sampled_entries(&table, preset))
+        .unwrap_or(vec![(false, false); table.entries.len()]);
+
+    // kernel vectors only have to be generated once
+    let kernels: Vec<Kernel> = table
+        .entries
+        .iter()
+        .map(|(size, min_circ, _)| Kernel::new(*size, *min_circ))
+        .collect();
+
+    let cell_size = args.max_size as f32 * BLOCK_SIZE + CELL_MARGIN;
+
+    loop {
+        clear_background(WHITE);
+
+        // one row per kernel size, one column per distinct shape of that size
+        let mut column = 0;
+        for (index, (kernel, (size, min_circ, max_circ))) in
+            kernels.iter().zip(table.entries.iter()).enumerate()
+        {
+            if index > 0 && table.entries[index - 1].0 != *size {
+                column = 0;
+            }
+            let x = CELL_MARGIN + column as f32 * cell_size;
+            let y = CELL_MARGIN + (*size - 1) as f32 * cell_size;
+            column += 1;
+
+            draw_kernel(kernel, x, y);
+
+            let kernel_extent = *size as f32 * BLOCK_SIZE;
+            let (inner, outer) = sampled[index];
+            if inner {
+                draw_rectangle_lines(
+                    x - 2.0,
+                    y - 2.0,
+                    kernel_extent + 4.0,
+                    kernel_extent + 4.0,
+                    2.0,
+                    GREEN,
+                );
+            }
+            if outer {
+                draw_rectangle_lines(
+                    x - 4.0,
+                    y - 4.0,
+                    kernel_extent + 8.0,
+                    kernel_extent + 8.0,
+                    2.0,
+                    ORANGE,
+                );
+            }
+
+            draw_text(
+                &format!("{} {:.2}-{:.2}", size, min_circ, max_circ),
+                x,
+                y + kernel_extent + 8.0,
+                12.0,
+                BLACK,
+            );
+        }
+
+        let legend = match &args.preset {
+            Some(name) => format!("{}: green = inner kernel, orange = outer kernel", name),
+            None => "pass a preset name to highlight the sampled shapes".to_string(),
+        };
+        draw_text(
+            &legend,
+            CELL_MARGIN,
+            screen_height() - CELL_MARGIN,
+            20.0,
+            BLACK,
+        );
+
+        next_frame().await
+    }
+}
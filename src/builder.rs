@@ -0,0 +1,154 @@
+use crate::{
+    config::{GenerationConfig, MapConfig},
+    generator::Generator,
+    map::Map,
+    metrics::MapMetrics,
+    position::Position,
+    random::Seed,
+};
+
+/// changes that are applied to the generation preset, see GeneratorBuilder::override_config
+type ConfigOverride = Box<dyn FnOnce(&mut GenerationConfig)>;
+
+/// sets up a generator from a preset name, map size, waypoints and seed without having to deal
+/// with preset storage and config structs. Unless set otherwise, the initial presets of the
+/// editor and a random seed are used.
+pub struct GeneratorBuilder {
+    preset: Option<String>,
+    overrides: Vec<ConfigOverride>,
+    map_config: MapConfig,
+    spawn: Option<Position>,
+    seed: Option<Seed>,
+}
+
+impl GeneratorBuilder {
+    pub fn new() -> GeneratorBuilder {
+        GeneratorBuilder {
+            preset: None,
+            overrides: Vec::new(),
+            map_config: MapConfig::get_initial_config(),
+            spawn: None,
+            seed: None,
+        }
+    }
+
+    /// name of the generation preset, e.g. "hardV2"
+    pub fn preset(mut self, name: &str) -> GeneratorBuilder {
+        self.preset = Some(name.to_string());
+        self
+    }
+
+    /// changes the generation preset, overrides are applied in order
+    pub fn override_config(
+        mut self,
+        apply: impl FnOnce(&mut GenerationConfig) + 'static,
+    ) -> GeneratorBuilder {
+        self.overrides.push(Box::new(apply));
+        self
+    }
+
+    /// uses a map config, e.g. a map preset of MapConfig::get_all_configs()
+    pub fn map_config(mut self, map_config: MapConfig) -> GeneratorBuilder {
+        self.map_config = map_config;
+        self
+    }
+
+    /// size of the map in blocks
+    pub fn size(mut self, width: usize, height: usize) -> GeneratorBuilder {
+        self.map_config.width = width;
+        self.map_config.height = height;
+        self
+    }
+
+    /// all waypoints of the map, the first waypoint is the spawn
+    pub fn waypoints(mut self, waypoints: Vec<Position>) -> GeneratorBuilder {
+        self.map_config.waypoints = waypoints;
+        self
+    }
+
+    /// replaces the first waypoint, regardless of when the waypoints are set
+    pub fn spawn(mut self, pos: Position) -> GeneratorBuilder {
+        self.spawn = Some(pos);
+        self
+    }
+
+    pub fn seed(mut self, seed: Seed) -> GeneratorBuilder {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// resolves and validates the configs and the seed
+    fn resolve(self) -> Result<(GenerationConfig, MapConfig, Seed), &'static str> {
+        let mut gen_config = match &self.preset {
+            Some(name) => GenerationConfig::get_all_configs()
+                .remove(name)
+                .ok_or("unknown generation preset")?,
+            None => GenerationConfig::get_initial_gen_config(),
+        };
+        for apply in self.overrides {
+            apply(&mut gen_config);
+        }
+
+        let mut map_config = self.map_config.clone();
+        if let Some(spawn) = &self.spawn {
+            match map_config.waypoints.first_mut() {
+                Some(first) => *first = spawn.clone(),
+                None => map_config.waypoints.push(spawn.clone()),
+            }
+        }
+
+        gen_config
+            .validate()
+            .map_err(|_| "invalid generation config")?;
+        map_config
+            .validate(&gen_config)
+            .map_err(|_| "invalid map config")?;
+
+        Ok((
+            gen_config,
+            map_config,
+            self.seed.unwrap_or_else(Seed::random),
+        ))
+    }
+
+    /// generator that is ready to step through the generation, together with the generation
+    /// config that has to be passed to each step
+    pub fn build(self) -> Result<(Generator, GenerationConfig), &'static str> {
+        let (gen_config, map_config, seed) = self.resolve()?;
+
+        Ok((Generator::new(&gen_config, &map_config, seed), gen_config))
+    }
+
+    /// generates the entire map, see Generator::generate_map
+    pub fn generate(self, max_steps: usize) -> Result<Map, &'static str> {
+        let (gen_config, map_config, seed) = self.resolve()?;
+
+        Generator::generate_map(max_steps, &seed, &gen_config, &map_config)
+    }
+
+    /// generates n_candidates maps starting at the seed and returns the best one, see
+    /// Generator::search_best_seed
+    pub fn search_best_seed(
+        self,
+        max_steps: usize,
+        n_candidates: usize,
+        scoring_fn: impl Fn(&MapMetrics) -> f32 + Sync,
+    ) -> Result<(Seed, Map, MapMetrics), &'static str> {
+        let (gen_config, map_config, seed) = self.resolve()?;
+
+        Generator::search_best_seed(
+            max_steps,
+            &seed,
+            &gen_config,
+            &map_config,
+            n_candidates,
+            scoring_fn,
+        )
+    }
+}
+
+impl Default for GeneratorBuilder {
+    fn default() -> GeneratorBuilder {
+        GeneratorBuilder::new()
+    }
+}
@@ -1,12 +1,274 @@
+use crate::map::{BlockType, OverwriteMatrix};
 use crate::position::{Position, ShiftDirection};
+use crate::post_processing::MAX_WALL_SMOOTHING;
 use crate::random::RandomDistConfig;
+use crate::step_strategy::StepStrategyKind;
 use log::warn;
 use rust_embed::RustEmbed;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt;
 use std::fs;
 use std::fs::File;
 use std::io::Write;
+use std::path::Path;
+
+/// current GenerationConfig version. Bump this on breaking changes and add a migration step to
+/// migrate_gen_config, so older presets keep loading.
+pub const GEN_CONFIG_VERSION: &str = "1.0";
+
+/// current MapConfig version, see GEN_CONFIG_VERSION
+pub const MAP_CONFIG_VERSION: &str = "1.0";
+
+/// parses a "major.minor" version string
+fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let (major, minor) = version.split_once('.')?;
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// error message for a config version that no migration exists for
+fn version_error(version: &str, supported: &str) -> String {
+    match (parse_version(version), parse_version(supported)) {
+        (Some(parsed), Some(supported_parsed)) if parsed > supported_parsed => format!(
+            "config version {} is newer than the newest supported version {}, update the generator",
+            version, supported
+        ),
+        (Some(_), _) => format!("unknown config version {}", version),
+        (None, _) => format!("invalid config version '{}'", version),
+    }
+}
+
+/// converts a legacy list of (value, probability) pairs into a RandomDistConfig
+fn migrate_pair_list(pairs: &Value) -> Value {
+    match pairs.as_array() {
+        Some(pairs) => {
+            let values: Vec<Value> = pairs.iter().map(|p| p[0].clone()).collect();
+            let probs: Vec<Value> = pairs.iter().map(|p| p[1].clone()).collect();
+            serde_json::json!({ "values": values, "probs": probs })
+        }
+        None => pairs.clone(), // already migrated
+    }
+}
+
+/// converts a legacy list of weights into a RandomDistConfig with normalized probabilities
+fn migrate_weights(weights: &Value) -> Value {
+    match weights.as_array() {
+        Some(weights) => {
+            let weights: Vec<f64> = weights.iter().filter_map(|w| w.as_f64()).collect();
+            let sum: f64 = weights.iter().sum();
+            let probs: Vec<f64> = weights.iter().map(|w| w / sum).collect();
+            serde_json::json!({ "values": null, "probs": probs })
+        }
+        None => weights.clone(), // already migrated
+    }
+}
+
+/// parses legacy (min, max) bounds
+fn legacy_bounds(bounds: &Value) -> Option<(u64, u64)> {
+    let bounds = (bounds.get(0)?.as_u64()?, bounds.get(1)?.as_u64()?);
+    (bounds.0 <= bounds.1).then_some(bounds)
+}
+
+/// RandomDistConfig that picks all values in the inclusive bounds with equal probability
+fn uniform_dist(bounds: (u64, u64)) -> Value {
+    let values: Vec<u64> = (bounds.0..=bounds.1).collect();
+    let probs = vec![1.0 / values.len() as f64; values.len()];
+    serde_json::json!({ "values": values, "probs": probs })
+}
+
+/// Migrates the fields of the oldest presets (see old/) that predate the pair lists. They used
+/// bounds for the kernel sizes instead of distributions and stored the waypoints, which are now
+/// part of the MapConfig and therefore dropped.
+fn migrate_bounds_format(object: &mut serde_json::Map<String, Value>) -> Result<(), String> {
+    if let Some(weights) = object.remove("step_weights") {
+        object.insert("shift_weights".to_string(), weights);
+    }
+
+    let inner_bounds = match object.remove("inner_size_bounds") {
+        Some(bounds) => Some(legacy_bounds(&bounds).ok_or("invalid inner_size_bounds")?),
+        None => None,
+    };
+    if let Some(bounds) = inner_bounds {
+        object.insert("inner_size_probs".to_string(), uniform_dist(bounds));
+    }
+
+    // the outer kernel size is the inner size plus the outer margin
+    if let Some(bounds) = object.remove("outer_size_bounds") {
+        let outer_bounds = legacy_bounds(&bounds).ok_or("invalid outer_size_bounds")?;
+        let inner_bounds = inner_bounds.ok_or("outer_size_bounds requires inner_size_bounds")?;
+        let margin_bounds = (
+            outer_bounds.0.saturating_sub(inner_bounds.1),
+            outer_bounds.1.saturating_sub(inner_bounds.0),
+        );
+        object.insert(
+            "outer_margin_probs".to_string(),
+            uniform_dist(margin_bounds),
+        );
+    }
+
+    if let Some(bounds) = object.remove("platform_distance_bounds") {
+        let bounds = legacy_bounds(&bounds).ok_or("invalid platform_distance_bounds")?;
+        object.insert("plat_min_distance".to_string(), Value::from(bounds.0));
+    }
+
+    if object.remove("waypoints").is_some() {
+        warn!("dropped waypoints of legacy config, waypoints are part of the map config now");
+    }
+
+    Ok(())
+}
+
+/// migrates a serialized GenerationConfig to the current version
+pub fn migrate_gen_config(mut config: Value) -> Result<Value, String> {
+    let object = config
+        .as_object_mut()
+        .ok_or("config must be a json object")?;
+
+    // configs before 1.0 have no version field and use lists of (value, prob) pairs or bounds
+    if !object.contains_key("version") {
+        migrate_bounds_format(object)?;
+
+        for key in ["inner_size_probs", "outer_margin_probs"] {
+            if let Some(pairs) = object.get(key) {
+                let migrated = migrate_pair_list(pairs);
+                object.insert(key.to_string(), migrated);
+            }
+        }
+
+        if let Some(weights) = object.get("shift_weights") {
+            let migrated = migrate_weights(weights);
+            object.insert("shift_weights".to_string(), migrated);
+        }
+
+        // unknown fields would silently fall back to their defaults
+        let known = serde_json::to_value(GenerationConfig::default()).unwrap();
+        if let Some(key) = object.keys().find(|key| known.get(key).is_none()) {
+            return Err(format!("unknown field '{}' in legacy config", key));
+        }
+
+        object.insert("version".to_string(), Value::from("1.0"));
+    }
+
+    let version = object["version"]
+        .as_str()
+        .ok_or("config version must be a string")?
+        .to_string();
+
+    match version.as_str() {
+        "1.0" => Ok(config),
+        _ => Err(version_error(&version, GEN_CONFIG_VERSION)),
+    }
+}
+
+/// migrates a serialized MapConfig to the current version
+pub fn migrate_map_config(mut config: Value) -> Result<Value, String> {
+    let object = config
+        .as_object_mut()
+        .ok_or("config must be a json object")?;
+
+    // map configs before 1.0 have no version field, but are otherwise identical
+    if !object.contains_key("version") {
+        object.insert("version".to_string(), Value::from("1.0"));
+    }
+
+    let version = object["version"]
+        .as_str()
+        .ok_or("config version must be a string")?
+        .to_string();
+
+    match version.as_str() {
+        "1.0" => Ok(config),
+        _ => Err(version_error(&version, MAP_CONFIG_VERSION)),
+    }
+}
+
+/// Resolves the `extends` field of a serialized preset by overlaying its fields onto the
+/// (recursively resolved) base preset. Fields are always overridden as a whole, so nested values
+/// like probability distributions are not merged.
+pub fn resolve_extends(config: &Value, presets: &HashMap<String, Value>) -> Result<Value, String> {
+    let mut resolved = config.clone();
+    let mut visited: Vec<String> = config
+        .get("name")
+        .and_then(|name| name.as_str())
+        .map(|name| vec![name.to_string()])
+        .unwrap_or_default();
+
+    while let Some(base_name) = resolved.get("extends").and_then(|base| base.as_str()) {
+        let base_name = base_name.to_string();
+        if visited.contains(&base_name) {
+            return Err(format!("cyclic extends via preset '{}'", base_name));
+        }
+
+        let mut base = presets
+            .get(&base_name)
+            .ok_or(format!("unknown base preset '{}'", base_name))?
+            .clone();
+        let base_object = base
+            .as_object_mut()
+            .ok_or("base preset must be a json object")?;
+        let overlay = resolved.as_object().ok_or("preset must be a json object")?;
+
+        // keep the extends of the base, so its own base is resolved in the next iteration
+        for (key, value) in overlay.iter().filter(|(key, _)| *key != "extends") {
+            base_object.insert(key.clone(), value.clone());
+        }
+
+        visited.push(base_name);
+        resolved = base;
+    }
+
+    Ok(resolved)
+}
+
+/// describes a single invalid field of a config
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    /// name of the invalid field
+    pub field: &'static str,
+
+    /// what is wrong with the field
+    pub message: &'static str,
+
+    /// (min, max) range of valid values, if there is a simple one
+    pub suggested_range: Option<(f32, f32)>,
+}
+
+impl ConfigError {
+    pub fn new(field: &'static str, message: &'static str) -> ConfigError {
+        ConfigError {
+            field,
+            message,
+            suggested_range: None,
+        }
+    }
+
+    pub fn with_range(mut self, min: f32, max: f32) -> ConfigError {
+        self.suggested_range = Some((min, max));
+        self
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)?;
+        if let Some((min, max)) = self.suggested_range {
+            write!(f, " (valid range: {} to {})", min, max)?;
+        }
+        Ok(())
+    }
+}
+
+/// joins multiple config errors into a single message
+pub fn format_config_errors(errors: &[ConfigError]) -> String {
+    errors
+        .iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
 
 #[derive(RustEmbed)]
 #[folder = "data/gen_configs/"]
@@ -16,11 +278,79 @@ pub struct GenerationConfigStorage;
 #[folder = "data/map_configs/"]
 pub struct MapConfigStorage;
 
+#[derive(RustEmbed)]
+#[folder = "data/themes/"]
+pub struct ThemeStorage;
+
+/// visual identity of exported maps. Tilesets are ddnet mapres images, which are also used to
+/// look up the automapper rules of the same name.
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct Theme {
+    pub name: String,
+
+    /// tileset of the hookable layer
+    pub hookable_tileset: String,
+
+    /// tileset of the freeze layer
+    pub freeze_tileset: String,
+
+    /// image of the doodad layer
+    pub doodad_image: String,
+
+    /// overrides the doodad density of the generation preset
+    pub doodad_density: Option<f32>,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme {
+            name: "grass".to_string(),
+            hookable_tileset: "grass_main".to_string(),
+            freeze_tileset: "basic_freeze".to_string(),
+            doodad_image: "grass_doodads".to_string(),
+            doodad_density: None,
+        }
+    }
+}
+
+impl Theme {
+    /// returns all embedded themes
+    pub fn get_all_themes() -> HashMap<String, Theme> {
+        let mut themes = HashMap::new();
+
+        for file_name in ThemeStorage::iter() {
+            let file = ThemeStorage::get(&file_name).unwrap();
+            let data = std::str::from_utf8(&file.data).unwrap();
+            match serde_json::from_str::<Theme>(data) {
+                Ok(theme) => {
+                    themes.insert(theme.name.clone(), theme);
+                }
+                Err(e) => {
+                    warn!("couldn't parse theme {}: {}", file_name, e);
+                }
+            }
+        }
+
+        themes
+    }
+}
+
+/// rectangular area the walker may never enter and its kernels may never carve
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+pub struct NoGoZone {
+    pub top_left: Position,
+    pub bot_right: Position,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
 pub struct MapConfig {
     /// name of the map config
     pub name: String,
 
+    /// stores the MapConfig version for future migration
+    pub version: String,
+
     /// shape of a map using waypoints
     pub waypoints: Vec<Position>,
 
@@ -29,17 +359,49 @@ pub struct MapConfig {
 
     /// height of the map
     pub height: usize,
+
+    /// areas that are reserved, e.g. for hand-made sections or thick borders
+    #[serde(default)]
+    pub no_go_zones: Vec<NoGoZone>,
+
+    /// size of the start room around the first waypoint, overrides the size of the generation
+    /// preset if set
+    #[serde(default)]
+    pub start_room_size: Option<usize>,
+
+    /// size of the finish room around the last waypoint, overrides the size of the generation
+    /// preset if set
+    #[serde(default)]
+    pub finish_room_size: Option<usize>,
 }
 
 impl MapConfig {
+    /// deserializes a config, migrating it from older versions if required
+    pub fn from_json(data: &str) -> Result<MapConfig, String> {
+        let value: Value = serde_json::from_str(data).map_err(|e| e.to_string())?;
+        let value = migrate_map_config(value)?;
+        serde_json::from_value(value).map_err(|e| e.to_string())
+    }
+
+    pub fn load(path: &str) -> Result<MapConfig, String> {
+        let data = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        MapConfig::from_json(&data)
+    }
+
     pub fn get_all_configs() -> HashMap<String, MapConfig> {
         let mut configs = HashMap::new();
 
         for file_name in MapConfigStorage::iter() {
             let file = MapConfigStorage::get(&file_name).unwrap();
             let data = std::str::from_utf8(&file.data).unwrap();
-            let config: MapConfig = serde_json::from_str(data).unwrap();
-            configs.insert(config.name.clone(), config);
+            match MapConfig::from_json(data) {
+                Ok(config) => {
+                    configs.insert(config.name.clone(), config);
+                }
+                Err(e) => {
+                    warn!("couldn't parse map config {}: {}", file_name, e);
+                }
+            }
         }
 
         configs
@@ -52,16 +414,615 @@ impl MapConfig {
             .expect("failed to write to config file");
     }
 
+    /// map config for a draft, with all sizes and positions scaled down by the given factor
+    pub fn downscaled(&self, scale: usize) -> MapConfig {
+        let downscale = |pos: &Position| Position::new(pos.x / scale, pos.y / scale);
+
+        MapConfig {
+            waypoints: self.waypoints.iter().map(downscale).collect(),
+            width: self.width / scale,
+            height: self.height / scale,
+            no_go_zones: self
+                .no_go_zones
+                .iter()
+                .map(|zone| NoGoZone {
+                    top_left: downscale(&zone.top_left),
+                    bot_right: downscale(&zone.bot_right),
+                })
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// map config with all sizes and positions scaled by the given factor, e.g. for larger
+    /// versions of a layout
+    pub fn scaled(&self, factor: f32) -> MapConfig {
+        let scale = |value: usize| (value as f32 * factor).round() as usize;
+        let scale_pos = |pos: &Position| Position::new(scale(pos.x), scale(pos.y));
+
+        MapConfig {
+            waypoints: self.waypoints.iter().map(scale_pos).collect(),
+            width: scale(self.width),
+            height: scale(self.height),
+            no_go_zones: self
+                .no_go_zones
+                .iter()
+                .map(|zone| NoGoZone {
+                    top_left: scale_pos(&zone.top_left),
+                    bot_right: scale_pos(&zone.bot_right),
+                })
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// checks that the waypoints are within the map, that consecutive waypoints are neither
+    /// closer than the waypoint reached distance nor further apart than the max waypoint
+    /// distance, and that the route fits into the walker step budget
+    pub fn validate(&self, gen_config: &GenerationConfig) -> Result<(), Vec<ConfigError>> {
+        let mut errors = Vec::new();
+
+        if self.waypoints.len() < 2 {
+            errors.push(ConfigError::new(
+                "waypoints",
+                "requires at least a start and a finish waypoint",
+            ));
+        }
+
+        if self
+            .waypoints
+            .iter()
+            .any(|waypoint| waypoint.x >= self.width || waypoint.y >= self.height)
+        {
+            errors.push(ConfigError::new("waypoints", "waypoint outside of the map"));
+        }
+
+        if self
+            .waypoints
+            .windows(2)
+            .any(|segment| MapConfig::is_segment_too_short(segment, gen_config))
+        {
+            errors.push(ConfigError::new(
+                "waypoints",
+                "waypoints closer than the waypoint reached distance",
+            ));
+        }
+
+        if self
+            .waypoints
+            .windows(2)
+            .any(|segment| MapConfig::is_segment_too_long(segment, gen_config))
+        {
+            errors.push(ConfigError::new(
+                "waypoints",
+                "waypoints further apart than the max waypoint distance",
+            ));
+        }
+
+        // the walker needs at least one step per block of manhattan distance
+        let route_length: usize = self
+            .waypoints
+            .windows(2)
+            .map(|segment| {
+                segment[0].x.abs_diff(segment[1].x) + segment[0].y.abs_diff(segment[1].y)
+            })
+            .sum();
+        if gen_config.max_walker_steps > 0 && route_length > gen_config.max_walker_steps {
+            errors.push(ConfigError::new(
+                "waypoints",
+                "route is longer than the max walker steps",
+            ));
+        }
+
+        for (field, room_size, default_size, waypoint) in [
+            (
+                "start_room_size",
+                self.start_room_size,
+                gen_config.start_room_size,
+                self.waypoints.first(),
+            ),
+            (
+                "finish_room_size",
+                self.finish_room_size,
+                gen_config.finish_room_size,
+                self.waypoints.last(),
+            ),
+        ] {
+            if room_size == Some(0) {
+                errors.push(ConfigError::new(field, "room size must be at least 1"));
+            } else if let Some(waypoint) = waypoint {
+                let room_size = room_size.unwrap_or(default_size);
+                if !self.room_fits(waypoint, room_size, gen_config) {
+                    errors.push(ConfigError::new(
+                        field,
+                        "room doesn't fit into the map around its waypoint",
+                    ));
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// whether a start or finish room with the max stretch fits around the waypoint, see
+    /// generate_room
+    fn room_fits(
+        &self,
+        waypoint: &Position,
+        room_size: usize,
+        gen_config: &GenerationConfig,
+    ) -> bool {
+        let extent = room_size + gen_config.room_stretch_max;
+        waypoint.x > extent
+            && waypoint.y > extent
+            && waypoint.x + extent + 2 < self.width
+            && waypoint.y + extent + 2 < self.height
+    }
+
+    fn is_segment_too_short(segment: &[Position], gen_config: &GenerationConfig) -> bool {
+        segment[0].distance_squared(&segment[1]) <= gen_config.waypoint_reached_dist
+    }
+
+    fn is_segment_too_long(segment: &[Position], gen_config: &GenerationConfig) -> bool {
+        gen_config.max_waypoint_distance > 0
+            && segment[0].distance_squared(&segment[1]) > gen_config.max_waypoint_distance.pow(2)
+    }
+
+    /// merges waypoints that are too close and splits segments that are too long by inserting
+    /// evenly spaced waypoints, see validate. The start and finish waypoint are always kept.
+    /// Returns the amount of (inserted, removed) waypoints.
+    pub fn repair_waypoints(&mut self, gen_config: &GenerationConfig) -> (usize, usize) {
+        if self.waypoints.len() < 2 {
+            return (0, 0);
+        }
+
+        // merge waypoints that are too close to the previous one
+        let finish = self.waypoints.last().unwrap().clone();
+        let mut merged: Vec<Position> = vec![self.waypoints[0].clone()];
+        for waypoint in self.waypoints[1..self.waypoints.len() - 1].iter() {
+            let previous = merged.last().unwrap().clone();
+            if !MapConfig::is_segment_too_short(&[previous, waypoint.clone()], gen_config) {
+                merged.push(waypoint.clone());
+            }
+        }
+        let previous = merged.last().unwrap().clone();
+        if merged.len() > 1
+            && MapConfig::is_segment_too_short(&[previous, finish.clone()], gen_config)
+        {
+            merged.pop();
+        }
+        merged.push(finish);
+        let removed = self.waypoints.len() - merged.len();
+
+        // split segments that are too long
+        let mut repaired = vec![merged[0].clone()];
+        for segment in merged.windows(2) {
+            if MapConfig::is_segment_too_long(segment, gen_config) {
+                // leave some room for rounding the inserted waypoints to blocks
+                let max_length = gen_config.max_waypoint_distance.saturating_sub(2).max(1);
+                let length = segment[0].distance(&segment[1]);
+                let pieces = (length / max_length as f32).ceil() as usize;
+                for piece in 1..pieces {
+                    let t = piece as f32 / pieces as f32;
+                    let lerp = |from: usize, to: usize| {
+                        (from as f32 + (to as f32 - from as f32) * t).round() as usize
+                    };
+                    repaired.push(Position::new(
+                        lerp(segment[0].x, segment[1].x),
+                        lerp(segment[0].y, segment[1].y),
+                    ));
+                }
+            }
+            repaired.push(segment[1].clone());
+        }
+        let inserted = repaired.len() - merged.len();
+
+        self.waypoints = repaired;
+        (inserted, removed)
+    }
+
     /// This function defines the initial default config for actual map generator
     pub fn get_initial_config() -> MapConfig {
         let file = MapConfigStorage::get("small_s.json").unwrap();
         let data = std::str::from_utf8(&file.data).unwrap();
-        let config: MapConfig = serde_json::from_str(data).unwrap();
-        config
+        MapConfig::from_json(data).unwrap()
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+/// size of a map relative to the layout of its map config
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MapSize {
+    Small,
+    #[default]
+    Medium,
+    Large,
+}
+
+impl MapSize {
+    pub const ALL: [MapSize; 3] = [MapSize::Small, MapSize::Medium, MapSize::Large];
+
+    /// factor the map config is scaled by, medium keeps the layout as is
+    pub fn scale_factor(&self) -> f32 {
+        match self {
+            MapSize::Small => 0.75,
+            MapSize::Medium => 1.0,
+            MapSize::Large => 1.5,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<MapSize> {
+        MapSize::ALL
+            .iter()
+            .find(|size| format!("{:?}", size).eq_ignore_ascii_case(name))
+            .copied()
+    }
+
+    /// map config of this size for the given layout
+    pub fn apply(&self, map_config: &MapConfig) -> MapConfig {
+        match self {
+            MapSize::Medium => map_config.clone(),
+            _ => map_config.scaled(self.scale_factor()),
+        }
+    }
+}
+
+/// a single ring of a pulse, applied as kernel at the walker position
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+pub struct PulseRing {
+    /// kernel size relative to the current inner kernel size
+    pub size_delta: usize,
+
+    /// kernel circularity of the ring
+    #[serde(default)]
+    pub circularity: f32,
+
+    /// block type the ring is filled with
+    pub block_type: BlockType,
+}
+
+impl Default for PulseRing {
+    fn default() -> PulseRing {
+        PulseRing {
+            size_delta: 2,
+            circularity: 0.0,
+            block_type: BlockType::Empty,
+        }
+    }
+}
+
+/// periodic "bulge" structure that replaces the walker kernels for a single step
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(default)]
+pub struct PulsePattern {
+    /// rings are applied in order, so larger rings should come first
+    pub rings: Vec<PulseRing>,
+
+    /// min amount of straight steps before the pulse is triggered
+    pub straight_delay: usize,
+
+    /// min amount of straight steps before the pulse is triggered right after a corner
+    pub corner_delay: usize,
+
+    /// straight steps are only counted while the inner kernel is at most this large
+    pub max_kernel_size: usize,
+
+    /// min amount of walker steps between two pulses of this pattern
+    pub cooldown: usize,
+}
+
+impl Default for PulsePattern {
+    fn default() -> PulsePattern {
+        PulsePattern {
+            rings: vec![
+                PulseRing {
+                    size_delta: 4,
+                    circularity: 0.0,
+                    block_type: BlockType::Freeze,
+                },
+                PulseRing {
+                    size_delta: 2,
+                    circularity: 0.0,
+                    block_type: BlockType::Empty,
+                },
+            ],
+            straight_delay: 10,
+            corner_delay: 5,
+            max_kernel_size: 4,
+            cooldown: 0,
+        }
+    }
+}
+
+/// side on which a room is opened up towards the rest of the map
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default, JsonSchema)]
+pub enum RoomOpening {
+    /// only the walker path leads out of the room
+    #[default]
+    Closed,
+    Up,
+    Right,
+    Down,
+    Left,
+
+    /// open the room towards the next waypoint
+    TowardsWaypoint,
+}
+
+impl RoomOpening {
+    pub const ALL: [RoomOpening; 6] = [
+        RoomOpening::Closed,
+        RoomOpening::Up,
+        RoomOpening::Right,
+        RoomOpening::Down,
+        RoomOpening::Left,
+        RoomOpening::TowardsWaypoint,
+    ];
+
+    /// returns the side of a room at pos that should be opened, if any
+    pub fn get_direction(
+        &self,
+        pos: &Position,
+        next_waypoint: Option<&Position>,
+    ) -> Option<ShiftDirection> {
+        match self {
+            RoomOpening::Closed => None,
+            RoomOpening::Up => Some(ShiftDirection::Up),
+            RoomOpening::Right => Some(ShiftDirection::Right),
+            RoomOpening::Down => Some(ShiftDirection::Down),
+            RoomOpening::Left => Some(ShiftDirection::Left),
+            RoomOpening::TowardsWaypoint => next_waypoint.map(|waypoint| {
+                let x_diff = waypoint.x as i32 - pos.x as i32;
+                let y_diff = waypoint.y as i32 - pos.y as i32;
+
+                match (x_diff.abs() >= y_diff.abs(), x_diff >= 0, y_diff >= 0) {
+                    (true, true, _) => ShiftDirection::Right,
+                    (true, false, _) => ShiftDirection::Left,
+                    (false, _, true) => ShiftDirection::Down,
+                    (false, _, false) => ShiftDirection::Up,
+                }
+            }),
+        }
+    }
+}
+
+/// style of the freeze outline that the outer kernel leaves around the walker path
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default, JsonSchema)]
+pub enum FreezeStyle {
+    /// plain outline with the sampled outer kernel margin
+    #[default]
+    Single,
+
+    /// outline that is one block thicker on each side, edge-bug fixing keeps a 2 block padding
+    Double,
+
+    /// perforated outline where every other block stays hookable, edge-bug fixing freezes the
+    /// perforations instead of the empty blocks next to them
+    Dotted,
+}
+
+impl FreezeStyle {
+    pub const ALL: [FreezeStyle; 3] = [
+        FreezeStyle::Single,
+        FreezeStyle::Double,
+        FreezeStyle::Dotted,
+    ];
+
+    /// how much the outer kernel size is increased
+    pub fn outer_kernel_growth(&self) -> usize {
+        match self {
+            FreezeStyle::Double => 2,
+            FreezeStyle::Single | FreezeStyle::Dotted => 0,
+        }
+    }
+
+    /// min. amount of freeze blocks between hookable and empty blocks
+    pub fn padding(&self) -> usize {
+        match self {
+            FreezeStyle::Double => 2,
+            FreezeStyle::Single | FreezeStyle::Dotted => 1,
+        }
+    }
+
+    /// whether the outer kernel leaves the block at the position as it is
+    pub fn is_perforated(&self, pos: &Position) -> bool {
+        *self == FreezeStyle::Dotted && (pos.x + pos.y) % 2 == 1
+    }
+}
+
+/// vertical alignment of the empty corridor within the freeze of the outer kernel
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default, JsonSchema)]
+pub enum CorridorProfile {
+    /// corridor in the center of the outer kernel
+    #[default]
+    Centered,
+
+    /// corridor moved down, so the floor is close and the freeze above is thicker
+    Floor,
+
+    /// corridor moved up, so the hookable ceiling is close and the floor is freeze
+    Ceiling,
+}
+
+impl CorridorProfile {
+    pub const ALL: [CorridorProfile; 3] = [
+        CorridorProfile::Centered,
+        CorridorProfile::Floor,
+        CorridorProfile::Ceiling,
+    ];
+
+    /// vertical offset of the inner kernel wrt. the walker position. At least one freeze block
+    /// remains on each side and the walker position always stays inside the inner kernel.
+    pub fn inner_kernel_offset(&self, inner_size: usize, outer_size: usize, strength: f32) -> i32 {
+        let max_offset = (outer_size.saturating_sub(inner_size).saturating_sub(2) / 2)
+            .min(inner_size / 2) as f32;
+        let offset = (max_offset * strength.clamp(0.0, 1.0)).round() as i32;
+
+        match self {
+            CorridorProfile::Centered => 0,
+            CorridorProfile::Floor => offset,
+            CorridorProfile::Ceiling => -offset,
+        }
+    }
+}
+
+/// how open areas are filled up, see fill_open_areas
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default, JsonSchema)]
+pub enum FillStyle {
+    /// fill with a freeze outline and plain hookable
+    #[default]
+    Plain,
+
+    /// like Plain, but the fill edges are eroded by smooth noise
+    NoiseEroded,
+
+    /// like Plain, but the fill edge varies in noisy vertical columns
+    Stalactites,
+
+    /// alternating layers of freeze and hookable, following the distance to the playfield
+    FreezeBands,
+}
+
+impl FillStyle {
+    pub const ALL: [FillStyle; 4] = [
+        FillStyle::Plain,
+        FillStyle::NoiseEroded,
+        FillStyle::Stalactites,
+        FillStyle::FreezeBands,
+    ];
+}
+
+/// layout of the finish room, see generate_finish_room
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default, JsonSchema)]
+pub enum FinishRoom {
+    /// plain square room
+    #[default]
+    Generic,
+
+    /// square room with a raised platform in its center
+    TrophyPlatform,
+
+    /// square room with a pit below it that players drop into
+    DropInPit,
+
+    /// room that is twice as wide as it is high
+    WideHall,
+
+    /// pick one of the other variants based on the seed
+    Random,
+}
+
+impl FinishRoom {
+    pub const ALL: [FinishRoom; 5] = [
+        FinishRoom::Generic,
+        FinishRoom::TrophyPlatform,
+        FinishRoom::DropInPit,
+        FinishRoom::WideHall,
+        FinishRoom::Random,
+    ];
+
+    /// all variants that describe an actual room layout
+    pub const LAYOUTS: [FinishRoom; 4] = [
+        FinishRoom::Generic,
+        FinishRoom::TrophyPlatform,
+        FinishRoom::DropInPit,
+        FinishRoom::WideHall,
+    ];
+}
+
+/// how Generator::generate_map handles a walker that didn't finish within max steps
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default, JsonSchema)]
+pub enum TruncationMode {
+    /// fail with MAP_TRUNCATED instead of post processing a half-finished map
+    #[default]
+    Error,
+
+    /// skip to the nearest remaining waypoint and place the finish there. Fails with
+    /// MAP_TRUNCATED, if the waypoint isn't reached within the truncation steps.
+    NearestWaypoint,
+}
+
+impl TruncationMode {
+    pub const ALL: [TruncationMode; 2] = [TruncationMode::Error, TruncationMode::NearestWaypoint];
+}
+
+/// how freeze corner holds are cleaned up, see fix_corner_holds
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default, JsonSchema)]
+pub enum CornerHoldPolicy {
+    /// only detect corner holds, they are shown in the debug layer
+    #[default]
+    Keep,
+
+    /// replace the freeze block with empty, so the freeze padding forms a sharp corner
+    Carve,
+
+    /// extend the freeze block to both open sides, so the corner is evenly beveled
+    Chamfer,
+}
+
+impl CornerHoldPolicy {
+    pub const ALL: [CornerHoldPolicy; 3] = [
+        CornerHoldPolicy::Keep,
+        CornerHoldPolicy::Carve,
+        CornerHoldPolicy::Chamfer,
+    ];
+}
+
+/// how the difficulty develops from the start (0) to the finish (1) of a map, see
+/// GenerationConfig::get_difficulty
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default, JsonSchema)]
+pub enum DifficultyCurve {
+    /// no progression, the difficulty is always 0
+    #[default]
+    Constant,
+
+    /// difficulty grows linearly with the progress
+    Linear,
+
+    /// difficulty grows slowly at first and quickly towards the finish
+    Exponential,
+
+    /// difficulty is interpolated between the difficulty keyframes
+    Keyframes,
+}
+
+impl DifficultyCurve {
+    pub const ALL: [DifficultyCurve; 4] = [
+        DifficultyCurve::Constant,
+        DifficultyCurve::Linear,
+        DifficultyCurve::Exponential,
+        DifficultyCurve::Keyframes,
+    ];
+}
+
+/// order in which the intermediate waypoints of a map config are visited. The spawn is always
+/// the first and the finish always the last waypoint.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default, JsonSchema)]
+pub enum GoalSelection {
+    /// visit waypoints in the order of the map config
+    #[default]
+    Ordered,
+
+    /// always head to the nearest remaining waypoint
+    NearestFirst,
+
+    /// pick the next waypoint randomly, weighted by goal_distance_weight
+    Random,
+}
+
+impl GoalSelection {
+    pub const ALL: [GoalSelection; 3] = [
+        GoalSelection::Ordered,
+        GoalSelection::NearestFirst,
+        GoalSelection::Random,
+    ];
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
 #[serde(default)]
 pub struct GenerationConfig {
     /// name of the preset
@@ -98,17 +1059,118 @@ pub struct GenerationConfig {
     /// allow "soft" overlaps -> non-empty blocks below platform (e.g. freeze)
     pub plat_soft_overhang: bool,
 
+    /// min free blocks above a platform to stand on it, platforms are lowered or skipped
+    /// otherwise. 0 disables all clearance checks.
+    pub plat_min_head_room: usize,
+
+    /// blocks next to the head room of a platform that must not contain freeze. Corridor walls
+    /// are usually freeze, so larger values skip most platforms in narrow corridors.
+    pub plat_freeze_clearance: usize,
+
     // ===================================[ ]==========================================
     /// probability for doing the last shift direction again
     pub momentum_prob: f32,
 
+    /// added to the momentum probability for each step the walker has gone straight. Positive
+    /// values result in long sweeping corridors, negative values in twitchy paths.
+    pub momentum_straight_gain: f32,
+
+    /// upper bound for the momentum probability
+    pub momentum_max_prob: f32,
+
+    /// weight penalty for shifts that turn relative to the last shift. 0 keeps the shift weights,
+    /// 1 never samples a turn. Allows tuning how wiggly paths are independent of momentum.
+    pub turn_cost: f32,
+
+    /// weight penalty for shifts that reverse the last shift, see turn_cost
+    pub reverse_cost: f32,
+
+    /// cost per block of distance to the goal, used to rate the shifts from best to worst
+    pub goal_distance_cost: f32,
+
+    /// cost per previous visit of a block when rating shifts, makes the walker avoid its own
+    /// path
+    pub revisit_cost: f32,
+
+    /// if the walker is stuck, it may re-enter the least visited locked position with less than
+    /// this many visits instead of failing or backtracking. 0 keeps locked positions blocked.
+    pub max_revisits: usize,
+
+    /// cost per block that a shift gets closer to the map edge than border_cost_range when
+    /// rating shifts
+    pub border_cost: f32,
+
+    /// distance to the map edge below which the border cost applies
+    pub border_cost_range: usize,
+
+    /// cost per side of a shift that an earlier corridor runs parallel and adjacent to, see
+    /// CuteWalker::parallel_sides. Discourages doubled corridors that merge into wide areas.
+    pub parallel_cost: f32,
+
+    /// max amount of steps in the same direction before the walker is forced to turn, 0 disables
+    /// the limit
+    pub max_straight_steps: usize,
+
+    /// policy that selects the next walker shift
+    pub step_strategy: StepStrategyKind,
+
+    /// probability for picking a random direction, used by the NoiseBiased strategy
+    pub strategy_noise: f32,
+
+    /// amount of steps the Lookahead strategy checks for locked positions
+    pub strategy_lookahead: usize,
+
+    /// amount of steps the walker rewinds along its path if it got stuck in a dead end
+    pub backtrack_steps: usize,
+
+    /// maximum amount of rewinds per map before giving up, 0 disables backtracking
+    pub max_backtracks: usize,
+
+    /// aggressiveness of smoothing jagged corridor walls, 0 keeps the walls as generated
+    pub wall_smoothing: usize,
+
+    /// how single freeze blocks in the inner corners of the freeze padding are cleaned up
+    pub corner_hold_policy: CornerHoldPolicy,
+
+    /// length of the freeze lips below ceiling corners along the walker path, 0 keeps the lips
+    /// as generated
+    pub ceiling_lip_length: usize,
+
+    /// whether small pockets are carved into the freeze walls along hard segments, so a tee
+    /// can stand in them without being frozen
+    pub enable_freeze_pockets: bool,
+
+    /// min. estimated difficulty of a segment to receive freeze pockets
+    pub freeze_pocket_min_difficulty: f32,
+
+    /// min. amount of walker steps between two freeze pockets
+    pub freeze_pocket_spacing: usize,
+
+    /// places a checkpoint teleporter at every n-th waypoint, 0 disables checkpoints. Players
+    /// that fall into freeze are teleported back to their last checkpoint.
+    pub checkpoint_interval: usize,
+
     /// maximum distance from empty blocks to nearest non empty block for obstacle generation
     /// TODO: rename in new version bump, as this is not self explanatory at all xd
     pub max_distance: f32,
 
+    /// style used for filling open areas
+    pub fill_style: FillStyle,
+
+    /// how many blocks the fill edge is shifted by noise, or the band width for FreezeBands
+    pub fill_noise_amplitude: f32,
+
+    /// thickness of the solid map border. Kernels are clipped against it and it is repaired
+    /// after generation. 0 disables the border.
+    pub border_thickness: usize,
+
     /// min distance to next waypoint that is considered reached
     pub waypoint_reached_dist: usize,
 
+    /// max distance between two consecutive waypoints, see MapConfig::validate. 0 disables the
+    /// limit.
+    pub max_waypoint_distance: usize,
+
     /// probabilities for (inner_kernel_size, probability)
     pub inner_size_probs: RandomDistConfig<usize>,
 
@@ -118,6 +1180,22 @@ pub struct GenerationConfig {
     /// probabilities for (kernel circularity, probability)
     pub circ_probs: RandomDistConfig<f32>,
 
+    /// style of the freeze outline around the walker path
+    pub freeze_style: FreezeStyle,
+
+    /// vertical alignment of the corridor within the freeze outline
+    pub corridor_profile: CorridorProfile,
+
+    /// how far (0 to 1) the corridor is moved towards the floor or ceiling
+    pub corridor_profile_strength: f32,
+
+    /// corridor profile of each waypoint segment, segments without an entry use corridor_profile
+    pub segment_corridor_profiles: Vec<CorridorProfile>,
+
+    /// verify the freeze padding around the walker after each kernel application and log the
+    /// kernels and position of every violation. Slows down generation, only meant for debugging.
+    pub check_kernel_margin: bool,
+
     /// (min, max) distance for skips
     pub skip_length_bounds: (usize, usize),
 
@@ -125,21 +1203,149 @@ pub struct GenerationConfig {
     /// range are invalidated.
     pub skip_min_spacing_sqr: usize,
 
+    /// (min, max) distance for freeze skips, uses skip_length_bounds if not set
+    pub freeze_skip_length_bounds: Option<(usize, usize)>,
+
+    /// min distance between freeze skips and any other skip, uses skip_min_spacing_sqr if not
+    /// set. Actual skips are selected first, so freeze skips never invalidate them.
+    pub freeze_skip_min_spacing_sqr: Option<usize>,
+
+    /// size of the funnel shaped mouth carved into the walls at both ends of a skip, 0 keeps
+    /// the plain skip entrances
+    pub skip_mouth_size: usize,
+
+    /// block types that skip tunnels and mouths may replace, for empty and freeze skips
+    pub skip_overwrite: OverwriteMatrix,
+
+    /// probability for a skip to be 2 blocks wide instead of 1, if there is enough space
+    pub wide_skip_prob: f32,
+
     /// maximum amount of the level is allowed to skip. This ensures that different parts of a map
     /// are not connected.
     pub max_level_skip: usize,
 
+    /// only consider skip corners within this distance (in blocks) of the walker path, 0 searches
+    /// the whole map. This avoids skips in filled up dead zones and speeds up skip generation.
+    pub skip_path_band: usize,
+
+    /// min distance (in blocks) between skip tunnels and the empty blocks carved by the walker,
+    /// except within this distance of both skip ends where the skip crosses into the path. This keeps skips from
+    /// merging into other corridors along the way, 0 disables the constraint.
+    pub skip_path_separation: usize,
+
+    /// additionally search for long skips from the walker path straight through the filled
+    /// solid mass, connecting parts of the map that are far apart along the path
+    pub enable_deep_skips: bool,
+
+    /// (min, max) distance for deep skips
+    pub deep_skip_length_bounds: (usize, usize),
+
+    /// (min, max) amount of the level a deep skip skips, replaces max_level_skip for deep skips
+    pub deep_skip_level_bounds: (usize, usize),
+
+    /// max amount of deep skips per map
+    pub max_deep_skips: usize,
+
     /// min unconnected freeze obstacle size
     pub min_freeze_size: usize,
 
     /// enable pulse
     pub enable_pulse: bool,
 
-    /// TODO:
+    /// trigger conditions of the default pulse, see PulsePattern
     pub pulse_straight_delay: usize,
     pub pulse_corner_delay: usize,
     pub pulse_max_kernel_size: usize,
 
+    /// custom pulse patterns. If set, these replace the default pulse (inner+4 freeze, inner+2
+    /// empty). Patterns are checked in order and only the first triggered one is applied.
+    pub pulse_patterns: Vec<PulsePattern>,
+
+    /// instead of random kernel mutations, fade the kernel from segment_fade_max_size to
+    /// segment_fade_min_size while approaching each waypoint, resulting in funnels into
+    /// checkpoints
+    pub enable_segment_fade: bool,
+
+    /// kernel size at the start of each waypoint segment
+    pub segment_fade_max_size: usize,
+
+    /// kernel size at the end of each waypoint segment
+    pub segment_fade_min_size: usize,
+
+    /// how the difficulty develops over the course of the map
+    pub difficulty_curve: DifficultyCurve,
+
+    /// (progress, difficulty) pairs for the keyframes curve, both from 0 to 1 and sorted by
+    /// progress
+    pub difficulty_keyframes: Vec<(f32, f32)>,
+
+    /// blocks by which sampled inner kernels shrink on each side at full difficulty
+    pub difficulty_kernel_shrink: usize,
+
+    /// blocks by which sampled freeze margins grow on each side at full difficulty
+    pub difficulty_freeze_growth: usize,
+
+    /// factor for the min platform distance at full difficulty
+    pub difficulty_platform_spacing: f32,
+
+    /// carve a rest room with a platform at each intermediate waypoint
+    pub enable_rest_rooms: bool,
+
+    /// size of rest rooms, see generate_room
+    pub rest_room_size: usize,
+
+    /// margin of the rest room platform to the room walls
+    pub rest_room_platform_margin: usize,
+
+    /// probability for placing a doodad on a block that is fully surrounded by solid blocks,
+    /// 0 disables decoration
+    pub doodad_density: f32,
+
+    /// tile indices of the doodad image that are used for decoration
+    pub doodad_tiles: Vec<u8>,
+
+    /// name of the theme used for exporting, None keeps the tilesets of the export template
+    pub theme: Option<String>,
+
+    /// size of the start room, see generate_room
+    pub start_room_size: usize,
+
+    /// margin of the spawns and their platform to the start room walls
+    pub start_room_platform_margin: usize,
+
+    /// side on which the start room is opened up, so the spawn doesnt face a dead wall
+    pub start_room_opening: RoomOpening,
+
+    /// size of the finish room, see generate_room
+    pub finish_room_size: usize,
+
+    /// layout of the finish room
+    pub finish_room: FinishRoom,
+
+    /// max amount of blocks by which the width and height of a room are randomly extended, see
+    /// RoomShape. 0 keeps all rooms square.
+    pub room_stretch_max: usize,
+
+    /// max amount of blocks by which the interior platform of a room is randomly moved up
+    pub room_platform_shift_max: usize,
+
+    /// probability for hanging pillars from the ceiling of a room
+    pub room_pillar_prob: f32,
+
+    /// finish the map after this many walker steps, even if waypoints are left. 0 disables the
+    /// limit
+    pub max_walker_steps: usize,
+
+    /// finish the map after this many blocks have been carved, even if waypoints are left. 0
+    /// disables the limit
+    pub max_carved_cells: usize,
+
+    /// how maps are handled, if the walker doesn't finish within the max steps of generate_map
+    pub truncation_mode: TruncationMode,
+
+    /// additional walker steps to reach the nearest waypoint, see TruncationMode::NearestWaypoint
+    pub truncation_steps: usize,
+
     /// number of initial walker steps to perform fading. Will fade from max to min kernel size.
     pub fade_steps: usize,
 
@@ -149,6 +1355,25 @@ pub struct GenerationConfig {
     /// goal min kernel size for fading
     pub fade_min_size: usize,
 
+    /// whether blocks carved by the inner kernel within the reserved steps are marked as reserved,
+    /// which keeps post processing out of the area around the spawn
+    pub fade_reserved: bool,
+
+    /// (start, end) walker steps in which carved blocks are reserved, uses (0, fade_steps) if
+    /// not set
+    pub reserved_steps: Option<(usize, usize)>,
+
+    /// whether post processing may overwrite reserved blocks like empty blocks. This includes
+    /// the space reserved above platforms.
+    pub overwrite_reserved: bool,
+
+    /// order in which the waypoints of the map config are visited
+    pub goal_selection: GoalSelection,
+
+    /// for random goal selection, waypoints are weighted by 1 / distance^goal_distance_weight,
+    /// so higher values prefer closer waypoints. 0 picks uniformly.
+    pub goal_distance_weight: f32,
+
     /// maximum valid distance between subwaypoints
     pub max_subwaypoint_dist: f32,
 
@@ -165,27 +1390,411 @@ pub struct GenerationConfig {
     pub lock_kernel_size: usize,
 }
 
+/// kernel size for a draft, sizes stay odd so kernels keep their center
+pub fn downscale_kernel_size(size: usize, scale: usize) -> usize {
+    (size / scale) | 1
+}
+
+/// kernel margin for a draft, margins stay even so outer kernels stay centered. Non-zero margins
+/// keep at least one block of freeze on each side.
+pub fn downscale_kernel_margin(margin: usize, scale: usize) -> usize {
+    (margin / scale).div_ceil(2) * 2
+}
+
 impl GenerationConfig {
-    /// returns an error if the configuration would result in a crash
-    pub fn validate(&self) -> Result<(), &'static str> {
-        // 1. Check that there is no inner kernel size of 0
-        for inner_size in self.inner_size_probs.values.as_ref().unwrap().iter() {
-            if *inner_size == 0 {
-                return Err("Invalid Config! (inner_size = 0)");
+    /// returns all pulse patterns that are active for this config
+    pub fn active_pulse_patterns(&self) -> Cow<'_, [PulsePattern]> {
+        if !self.enable_pulse {
+            Cow::Borrowed(&[])
+        } else if !self.pulse_patterns.is_empty() {
+            Cow::Borrowed(&self.pulse_patterns)
+        } else {
+            Cow::Owned(vec![PulsePattern {
+                straight_delay: self.pulse_straight_delay,
+                corner_delay: self.pulse_corner_delay,
+                max_kernel_size: self.pulse_max_kernel_size,
+                ..PulsePattern::default()
+            }])
+        }
+    }
+
+    /// generation config for a draft with the given downscale factor. Distances in map blocks
+    /// and kernel sizes are scaled, kernel probabilities stay the same so the draft draws the
+    /// same kernel mutations. Shift weights are sharpened, as the walker would otherwise stray
+    /// relatively further from its path than on the full map. Room sizes are kept.
+    pub fn downscaled(&self, scale: usize) -> GenerationConfig {
+        let scale_values = |dist: &RandomDistConfig<usize>,
+                            downscale: fn(usize, usize) -> usize| {
+            RandomDistConfig::new(
+                dist.values.as_ref().map(|values| {
+                    values
+                        .iter()
+                        .map(|value| downscale(*value, scale))
+                        .collect()
+                }),
+                dist.probs.clone(),
+            )
+        };
+
+        GenerationConfig {
+            waypoint_reached_dist: self.waypoint_reached_dist / (scale * scale),
+            // always clip kernels, so walkers close to the map edge don't fail the draft
+            border_thickness: self.border_thickness.div_ceil(scale).max(1),
+            shift_weights: RandomDistConfig::new(
+                self.shift_weights.values.clone(),
+                self.shift_weights
+                    .probs
+                    .iter()
+                    .map(|prob| prob.powi(scale as i32))
+                    .collect(),
+            ),
+            inner_size_probs: scale_values(&self.inner_size_probs, downscale_kernel_size),
+            outer_margin_probs: scale_values(&self.outer_margin_probs, downscale_kernel_margin),
+            difficulty_kernel_shrink: self.difficulty_kernel_shrink / scale,
+            difficulty_freeze_growth: self.difficulty_freeze_growth / scale,
+            // outline styles only change single blocks, which drafts cannot resolve
+            freeze_style: FreezeStyle::Single,
+            ..self.clone()
+        }
+    }
+
+    /// corridor profile of the waypoint segment with the given index
+    pub fn get_corridor_profile(&self, segment: usize) -> CorridorProfile {
+        self.segment_corridor_profiles
+            .get(segment)
+            .copied()
+            .unwrap_or(self.corridor_profile)
+    }
+
+    /// difficulty (0 to 1) at the given progress (0 to 1) through the map
+    pub fn get_difficulty(&self, progress: f32) -> f32 {
+        let progress = progress.clamp(0.0, 1.0);
+
+        match self.difficulty_curve {
+            DifficultyCurve::Constant => 0.0,
+            DifficultyCurve::Linear => progress,
+            DifficultyCurve::Exponential => (progress * 3.0).exp_m1() / 3.0_f32.exp_m1(),
+            DifficultyCurve::Keyframes => {
+                let keyframes = &self.difficulty_keyframes;
+                match keyframes.iter().position(|(key, _)| *key >= progress) {
+                    None => keyframes.last().map_or(0.0, |(_, difficulty)| *difficulty),
+                    Some(0) => keyframes[0].1,
+                    Some(index) => {
+                        let (start, start_difficulty) = keyframes[index - 1];
+                        let (end, end_difficulty) = keyframes[index];
+                        let t = (progress - start) / (end - start);
+                        start_difficulty + (end_difficulty - start_difficulty) * t
+                    }
+                }
             }
         }
+    }
 
-        // 2. Check fade config
-        if self.fade_max_size == 0 || self.fade_min_size == 0 {
-            return Err("fade kernel sizes must be larger than zero");
+    /// amount of blocks to subtract from a sampled inner kernel size and to add to a sampled
+    /// outer kernel margin at the given difficulty
+    pub fn get_kernel_schedule(&self, difficulty: f32) -> (usize, usize) {
+        let blocks = |per_side: usize| 2 * (difficulty * per_side as f32).round() as usize;
+        (
+            blocks(self.difficulty_kernel_shrink),
+            blocks(self.difficulty_freeze_growth),
+        )
+    }
+
+    /// min platform distance at the given difficulty
+    pub fn get_plat_min_distance(&self, difficulty: f32) -> usize {
+        let factor = 1.0 + (self.difficulty_platform_spacing - 1.0) * difficulty;
+        (self.plat_min_distance as f32 * factor).round() as usize
+    }
+
+    pub fn get_freeze_skip_length_bounds(&self) -> (usize, usize) {
+        self.freeze_skip_length_bounds
+            .unwrap_or(self.skip_length_bounds)
+    }
+
+    pub fn get_reserved_steps(&self) -> (usize, usize) {
+        self.reserved_steps.unwrap_or((0, self.fade_steps))
+    }
+
+    pub fn get_freeze_skip_min_spacing_sqr(&self) -> usize {
+        self.freeze_skip_min_spacing_sqr
+            .unwrap_or(self.skip_min_spacing_sqr)
+    }
+
+    /// returns the embedded theme referenced by this preset, if any
+    pub fn get_theme(&self) -> Option<Theme> {
+        let name = self.theme.as_ref()?;
+        let theme = Theme::get_all_themes().remove(name);
+        if theme.is_none() {
+            warn!("unknown theme {}, using export template tilesets", name);
+        }
+
+        theme
+    }
+
+    /// returns the doodad density, taking the theme into account
+    pub fn get_doodad_density(&self, theme: Option<&Theme>) -> f32 {
+        theme
+            .and_then(|theme| theme.doodad_density)
+            .unwrap_or(self.doodad_density)
+    }
+
+    /// returns the momentum probability after the walker went straight for straight_steps
+    pub fn momentum_prob_at(&self, straight_steps: usize) -> f32 {
+        let prob = self.momentum_prob + self.momentum_straight_gain * straight_steps as f32;
+        prob.clamp(0.0, self.momentum_max_prob)
+    }
+
+    /// returns all errors of the configuration that would result in a crash or broken maps
+    pub fn validate(&self) -> Result<(), Vec<ConfigError>> {
+        let mut errors = Vec::new();
+
+        // 1. Check that probabilities are valid
+        let probabilities = [
+            ("inner_rad_mut_prob", self.inner_rad_mut_prob),
+            ("inner_size_mut_prob", self.inner_size_mut_prob),
+            ("outer_rad_mut_prob", self.outer_rad_mut_prob),
+            ("outer_size_mut_prob", self.outer_size_mut_prob),
+            ("momentum_prob", self.momentum_prob),
+            ("momentum_max_prob", self.momentum_max_prob),
+            ("turn_cost", self.turn_cost),
+            ("reverse_cost", self.reverse_cost),
+            ("strategy_noise", self.strategy_noise),
+            ("doodad_density", self.doodad_density),
+            ("wide_skip_prob", self.wide_skip_prob),
+            (
+                "freeze_pocket_min_difficulty",
+                self.freeze_pocket_min_difficulty,
+            ),
+            ("room_pillar_prob", self.room_pillar_prob),
+        ];
+        for (field, probability) in probabilities {
+            if !(0.0..=1.0).contains(&probability) {
+                errors.push(ConfigError::new(field, "must be a probability").with_range(0.0, 1.0));
+            }
+        }
+
+        // 2. Check that random distributions have one probability per value
+        let dists = [
+            (
+                "inner_size_probs",
+                self.inner_size_probs.probs.len(),
+                self.inner_size_probs.values.as_ref().map(|v| v.len()),
+            ),
+            (
+                "outer_margin_probs",
+                self.outer_margin_probs.probs.len(),
+                self.outer_margin_probs.values.as_ref().map(|v| v.len()),
+            ),
+            (
+                "circ_probs",
+                self.circ_probs.probs.len(),
+                self.circ_probs.values.as_ref().map(|v| v.len()),
+            ),
+        ];
+        for (field, num_probs, num_values) in dists {
+            match num_values {
+                None => errors.push(ConfigError::new(field, "values are missing")),
+                Some(num_values) if num_values != num_probs || num_probs == 0 => errors.push(
+                    ConfigError::new(field, "requires exactly one probability per value"),
+                ),
+                _ => (),
+            }
+        }
+        if self.shift_weights.probs.len() != 4 {
+            errors.push(ConfigError::new(
+                "shift_weights",
+                "requires exactly one weight per direction",
+            ));
+        }
+
+        // 3. Check that there is no inner kernel size of 0
+        if let Some(values) = self.inner_size_probs.values.as_ref() {
+            if values.contains(&0) {
+                errors.push(
+                    ConfigError::new("inner_size_probs", "inner kernel size must be >0")
+                        .with_range(1.0, f32::INFINITY),
+                );
+            }
+        }
+
+        // 4. Check that circularities are valid
+        if let Some(values) = self.circ_probs.values.as_ref() {
+            if values.iter().any(|circ| !(0.0..=1.0).contains(circ)) {
+                errors.push(
+                    ConfigError::new("circ_probs", "circularity must be in [0, 1]")
+                        .with_range(0.0, 1.0),
+                );
+            }
+        }
+
+        // 5. Check fade config
+        if self.fade_max_size == 0 {
+            errors.push(
+                ConfigError::new("fade_max_size", "fade kernel size must be >0")
+                    .with_range(1.0, f32::INFINITY),
+            );
+        }
+        if self.fade_min_size == 0 {
+            errors.push(
+                ConfigError::new("fade_min_size", "fade kernel size must be >0")
+                    .with_range(1.0, f32::INFINITY),
+            );
+        }
+
+        if self.segment_fade_max_size == 0 {
+            errors.push(
+                ConfigError::new("segment_fade_max_size", "fade kernel size must be >0")
+                    .with_range(1.0, f32::INFINITY),
+            );
+        }
+        if self.segment_fade_min_size == 0 {
+            errors.push(
+                ConfigError::new("segment_fade_min_size", "fade kernel size must be >0")
+                    .with_range(1.0, f32::INFINITY),
+            );
         }
 
-        // 3. Check subwaypoint config
+        // 6. Check subwaypoint config
         if self.max_subwaypoint_dist <= 0.0 {
-            return Err("max subwaypoint distance must be >0");
+            errors.push(
+                ConfigError::new(
+                    "max_subwaypoint_dist",
+                    "max subwaypoint distance must be >0",
+                )
+                .with_range(0.1, f32::INFINITY),
+            );
         }
 
-        Ok(())
+        if self.goal_distance_weight < 0.0 {
+            errors.push(
+                ConfigError::new("goal_distance_weight", "goal distance weight must be >=0")
+                    .with_range(0.0, f32::INFINITY),
+            );
+        }
+
+        let shift_costs = [
+            ("goal_distance_cost", self.goal_distance_cost),
+            ("revisit_cost", self.revisit_cost),
+            ("border_cost", self.border_cost),
+            ("parallel_cost", self.parallel_cost),
+        ];
+        for (field, cost) in shift_costs {
+            if cost < 0.0 {
+                errors.push(
+                    ConfigError::new(field, "shift cost must be >=0")
+                        .with_range(0.0, f32::INFINITY),
+                );
+            }
+        }
+
+        // 7. Check bounds
+        let bounds = [
+            ("plat_width_bounds", self.plat_width_bounds),
+            ("plat_height_bounds", self.plat_height_bounds),
+            ("skip_length_bounds", self.skip_length_bounds),
+            (
+                "freeze_skip_length_bounds",
+                self.get_freeze_skip_length_bounds(),
+            ),
+            ("reserved_steps", self.get_reserved_steps()),
+            ("deep_skip_length_bounds", self.deep_skip_length_bounds),
+            ("deep_skip_level_bounds", self.deep_skip_level_bounds),
+        ];
+        for (field, (min, max)) in bounds {
+            if min > max {
+                errors.push(ConfigError::new(field, "min must not be larger than max"));
+            }
+        }
+
+        // 8. Check pulse patterns
+        for pattern in self.pulse_patterns.iter() {
+            if pattern.rings.is_empty() {
+                errors.push(ConfigError::new(
+                    "pulse_patterns",
+                    "pulse pattern requires at least one ring",
+                ));
+            }
+            if pattern
+                .rings
+                .iter()
+                .any(|ring| !(0.0..=1.0).contains(&ring.circularity))
+            {
+                errors.push(
+                    ConfigError::new("pulse_patterns", "ring circularity must be in [0, 1]")
+                        .with_range(0.0, 1.0),
+                );
+            }
+        }
+
+        // 9. Check wall smoothing
+        if self.wall_smoothing > MAX_WALL_SMOOTHING {
+            errors.push(
+                ConfigError::new("wall_smoothing", "unsupported smoothing aggressiveness")
+                    .with_range(0.0, MAX_WALL_SMOOTHING as f32),
+            );
+        }
+
+        // 10. Check difficulty schedule
+        if self
+            .difficulty_keyframes
+            .iter()
+            .any(|(progress, difficulty)| {
+                !(0.0..=1.0).contains(progress) || !(0.0..=1.0).contains(difficulty)
+            })
+        {
+            errors.push(
+                ConfigError::new("difficulty_keyframes", "keyframes must be in [0, 1]")
+                    .with_range(0.0, 1.0),
+            );
+        }
+        if self
+            .difficulty_keyframes
+            .windows(2)
+            .any(|keyframes| keyframes[0].0 >= keyframes[1].0)
+        {
+            errors.push(ConfigError::new(
+                "difficulty_keyframes",
+                "keyframes must be sorted by progress",
+            ));
+        }
+        if self.difficulty_platform_spacing < 0.0 {
+            errors.push(
+                ConfigError::new("difficulty_platform_spacing", "factor must be >=0")
+                    .with_range(0.0, f32::INFINITY),
+            );
+        }
+
+        // 11. Check waypoint spacing, so repaired segments are never degenerate
+        if self.max_waypoint_distance > 0
+            && (self.max_waypoint_distance / 2).pow(2) <= self.waypoint_reached_dist
+        {
+            errors.push(ConfigError::new(
+                "max_waypoint_distance",
+                "must be more than twice the waypoint reached distance",
+            ));
+        }
+
+        // 12. Check corridor profile
+        if !(0.0..=1.0).contains(&self.corridor_profile_strength) {
+            errors.push(
+                ConfigError::new("corridor_profile_strength", "strength must be in [0, 1]")
+                    .with_range(0.0, 1.0),
+            );
+        }
+
+        // 13. Check freeze pockets
+        if self.freeze_pocket_spacing == 0 {
+            errors.push(ConfigError::new(
+                "freeze_pocket_spacing",
+                "spacing must be at least 1",
+            ));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
     }
 
     pub fn save(&self, path: &str) {
@@ -195,23 +1804,37 @@ impl GenerationConfig {
             .expect("failed to write to config file");
     }
 
-    pub fn load(path: &str) -> GenerationConfig {
-        let serialized_from_file = fs::read_to_string(path).expect("failed to read config file");
-        let deserialized: GenerationConfig =
-            serde_json::from_str(&serialized_from_file).expect("failed to deserialize config file");
+    /// deserializes a config, resolving its base preset and migrating it from older versions
+    /// if required. Base presets are looked up in the embedded presets.
+    pub fn from_json(data: &str) -> Result<GenerationConfig, String> {
+        let value: Value = serde_json::from_str(data).map_err(|e| e.to_string())?;
+        GenerationConfig::from_value(&value, &GenerationConfig::get_all_raw_configs())
+    }
 
-        deserialized
+    /// deserializes a config, resolving its base preset from the given serialized presets
+    pub fn from_value(
+        value: &Value,
+        presets: &HashMap<String, Value>,
+    ) -> Result<GenerationConfig, String> {
+        let value = resolve_extends(value, presets)?;
+        let value = migrate_gen_config(value)?;
+        serde_json::from_value(value).map_err(|e| e.to_string())
     }
 
-    pub fn get_all_configs() -> HashMap<String, GenerationConfig> {
+    /// returns all embedded presets in their serialized form, without resolving base presets
+    pub fn get_all_raw_configs() -> HashMap<String, Value> {
         let mut configs = HashMap::new();
 
         for file_name in GenerationConfigStorage::iter() {
             let file = GenerationConfigStorage::get(&file_name).unwrap();
             let data = std::str::from_utf8(&file.data).unwrap();
-            match serde_json::from_str::<GenerationConfig>(data) {
+            match serde_json::from_str::<Value>(data) {
                 Ok(config) => {
-                    configs.insert(config.name.clone(), config);
+                    let name = match config.get("name").and_then(|name| name.as_str()) {
+                        Some(name) => name.to_string(),
+                        None => file_name.trim_end_matches(".json").to_string(),
+                    };
+                    configs.insert(name, config);
                 }
                 Err(e) => {
                     warn!("couldn't parse gen config {}: {}", file_name, e);
@@ -222,11 +1845,89 @@ impl GenerationConfig {
         configs
     }
 
+    pub fn load(path: &str) -> Result<GenerationConfig, String> {
+        let data = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        GenerationConfig::from_json(&data)
+    }
+
+    /// returns all presets from a directory in their serialized form. Every *.json file is
+    /// considered a preset.
+    pub fn get_raw_configs_from_dir(dir: &Path) -> HashMap<String, Value> {
+        let mut configs = HashMap::new();
+
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("couldn't read preset directory {:?}: {}", dir, e);
+                return configs;
+            }
+        };
+
+        for path in entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+        {
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+
+            let config = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|data| serde_json::from_str::<Value>(&data).map_err(|e| e.to_string()));
+
+            match config {
+                Ok(config) => {
+                    let name = match config.get("name").and_then(|name| name.as_str()) {
+                        Some(name) => name.to_string(),
+                        None => path.file_stem().unwrap().to_string_lossy().to_string(),
+                    };
+                    configs.insert(name, config);
+                }
+                Err(e) => {
+                    warn!("couldn't parse gen config {:?}: {}", path, e);
+                }
+            }
+        }
+
+        configs
+    }
+
+    /// returns all embedded presets and all presets of the given directory. Presets of the
+    /// directory replace embedded presets with the same name and may extend embedded presets.
+    pub fn get_all_configs_with_dir(dir: &Path) -> HashMap<String, GenerationConfig> {
+        let mut raw_configs = GenerationConfig::get_all_raw_configs();
+        raw_configs.extend(GenerationConfig::get_raw_configs_from_dir(dir));
+
+        GenerationConfig::from_raw_configs(&raw_configs)
+    }
+
+    /// returns all embedded presets
+    pub fn get_all_configs() -> HashMap<String, GenerationConfig> {
+        GenerationConfig::from_raw_configs(&GenerationConfig::get_all_raw_configs())
+    }
+
+    fn from_raw_configs(raw_configs: &HashMap<String, Value>) -> HashMap<String, GenerationConfig> {
+        let mut configs = HashMap::new();
+
+        for (name, raw_config) in raw_configs.iter() {
+            match GenerationConfig::from_value(raw_config, raw_configs) {
+                Ok(config) => {
+                    configs.insert(config.name.clone(), config);
+                }
+                Err(e) => {
+                    warn!("couldn't parse gen config {}: {}", name, e);
+                }
+            }
+        }
+
+        configs
+    }
+
     /// This function defines the initial default config for actual map generator
     pub fn get_initial_gen_config() -> GenerationConfig {
         if let Some(file) = GenerationConfigStorage::get("hardV2.json") {
             if let Ok(data) = std::str::from_utf8(&file.data) {
-                if let Ok(config) = serde_json::from_str(data) {
+                if let Ok(config) = GenerationConfig::from_json(data) {
                     return config;
                 }
             }
@@ -243,7 +1944,7 @@ impl Default for GenerationConfig {
         GenerationConfig {
             name: "default".to_string(),
             description: None,
-            version: "1.0".to_string(),
+            version: GEN_CONFIG_VERSION.to_string(),
             inner_rad_mut_prob: 0.25,
             inner_size_mut_prob: 0.5,
             outer_rad_mut_prob: 0.25,
@@ -254,23 +1955,102 @@ impl Default for GenerationConfig {
             plat_height_bounds: (1, 2),
             plat_min_empty_height: 4,
             plat_soft_overhang: false,
+            plat_min_head_room: 2,
+            plat_freeze_clearance: 0,
             momentum_prob: 0.01,
+            momentum_straight_gain: 0.0,
+            momentum_max_prob: 1.0,
+            turn_cost: 0.0,
+            reverse_cost: 0.0,
+            goal_distance_cost: 1.0,
+            revisit_cost: 0.0,
+            max_revisits: 0,
+            border_cost: 0.0,
+            border_cost_range: 8,
+            parallel_cost: 0.0,
+            max_straight_steps: 0,
+            step_strategy: StepStrategyKind::default(),
+            strategy_noise: 0.1,
+            strategy_lookahead: 5,
+            backtrack_steps: 10,
+            max_backtracks: 0,
+            wall_smoothing: 0,
+            corner_hold_policy: CornerHoldPolicy::Keep,
+            ceiling_lip_length: 0,
+            enable_freeze_pockets: false,
+            freeze_pocket_min_difficulty: 0.4,
+            freeze_pocket_spacing: 50,
+            checkpoint_interval: 0,
             max_distance: 3.0,
+            fill_style: FillStyle::default(),
+            fill_noise_amplitude: 2.0,
+            border_thickness: 0,
             waypoint_reached_dist: 250,
+            max_waypoint_distance: 0,
             inner_size_probs: RandomDistConfig::new(Some(vec![3, 5]), vec![0.25, 0.75]),
             outer_margin_probs: RandomDistConfig::new(Some(vec![0, 2]), vec![0.5, 0.5]),
             circ_probs: RandomDistConfig::new(Some(vec![0.0, 0.6, 0.8]), vec![0.75, 0.15, 0.05]),
+            freeze_style: FreezeStyle::default(),
+            corridor_profile: CorridorProfile::default(),
+            corridor_profile_strength: 1.0,
+            segment_corridor_profiles: Vec::new(),
+            check_kernel_margin: false,
             skip_min_spacing_sqr: 45,
             skip_length_bounds: (3, 11),
+            freeze_skip_length_bounds: None,
+            freeze_skip_min_spacing_sqr: None,
+            skip_mouth_size: 0,
+            skip_overwrite: OverwriteMatrix::default()
+                .allow(BlockType::Empty, &[BlockType::Hookable, BlockType::Freeze])
+                .allow(BlockType::Freeze, &[BlockType::Hookable, BlockType::Freeze]),
+            wide_skip_prob: 0.0,
             max_level_skip: 90,
+            skip_path_band: 0,
+            skip_path_separation: 0,
+            enable_deep_skips: false,
+            deep_skip_length_bounds: (11, 32),
+            deep_skip_level_bounds: (100, 400),
+            max_deep_skips: 2,
             min_freeze_size: 0,
             enable_pulse: false,
             pulse_corner_delay: 5,
             pulse_straight_delay: 10,
             pulse_max_kernel_size: 4,
+            pulse_patterns: Vec::new(),
+            enable_segment_fade: false,
+            segment_fade_max_size: 7,
+            segment_fade_min_size: 3,
+            difficulty_curve: DifficultyCurve::default(),
+            difficulty_keyframes: vec![(0.0, 0.0), (1.0, 1.0)],
+            difficulty_kernel_shrink: 0,
+            difficulty_freeze_growth: 0,
+            difficulty_platform_spacing: 1.0,
+            enable_rest_rooms: false,
+            rest_room_size: 5,
+            rest_room_platform_margin: 2,
+            doodad_density: 0.0,
+            doodad_tiles: vec![1, 2, 3, 4, 5, 6, 7, 8],
+            theme: None,
+            start_room_size: 6,
+            start_room_platform_margin: 3,
+            start_room_opening: RoomOpening::default(),
+            finish_room_size: 4,
+            finish_room: FinishRoom::default(),
+            room_stretch_max: 0,
+            room_platform_shift_max: 0,
+            room_pillar_prob: 0.0,
+            max_walker_steps: 0,
+            max_carved_cells: 0,
+            truncation_mode: TruncationMode::default(),
+            truncation_steps: 20_000,
             fade_steps: 60,
             fade_max_size: 6,
             fade_min_size: 3,
+            fade_reserved: true,
+            reserved_steps: None,
+            overwrite_reserved: false,
+            goal_selection: GoalSelection::default(),
+            goal_distance_weight: 1.0,
             max_subwaypoint_dist: 50.0,
             subwaypoint_max_shift_dist: 5.0,
             pos_lock_max_delay: 1000,
@@ -284,6 +2064,7 @@ impl Default for MapConfig {
     fn default() -> MapConfig {
         MapConfig {
             name: "default".to_string(),
+            version: MAP_CONFIG_VERSION.to_string(),
             waypoints: vec![
                 Position::new(50, 250),
                 Position::new(250, 250),
@@ -294,6 +2075,116 @@ impl Default for MapConfig {
             ],
             width: 300,
             height: 300,
+            no_go_zones: Vec::new(),
+            start_room_size: None,
+            finish_room_size: None,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_bounds_presets_load() {
+        let config = GenerationConfig::from_json(include_str!("../old/insane.json")).unwrap();
+        assert_eq!(config.version, "1.0");
+        assert_eq!(config.momentum_prob, 0.5);
+        assert_eq!(config.plat_min_distance, 200);
+        assert_eq!(config.inner_size_probs.values, Some(vec![1, 2]));
+        assert_eq!(config.inner_size_probs.probs, vec![0.5, 0.5]);
+        assert_eq!(config.outer_margin_probs.values, Some(vec![1, 2]));
+        assert_eq!(config.shift_weights.values, None);
+        let expected = [20.0 / 55.0, 15.0 / 55.0, 14.0 / 55.0, 6.0 / 55.0];
+        for (prob, expected) in config.shift_weights.probs.iter().zip(expected) {
+            assert!((prob - expected).abs() < 1e-6);
+        }
+
+        let config = GenerationConfig::from_json(include_str!("../old/extreme.json")).unwrap();
+        assert_eq!(config.outer_margin_probs.values, Some(vec![2]));
+        assert_eq!(config.waypoint_reached_dist, 250);
+
+        assert!(GenerationConfig::from_json(include_str!("../old/insaneV2.json")).is_ok());
+    }
+
+    #[test]
+    fn legacy_pair_lists_migrate() {
+        let config = GenerationConfig::from_json(
+            r#"{
+                "name": "pairs",
+                "inner_size_probs": [[3, 0.25], [5, 0.75]],
+                "outer_margin_probs": [[1, 1.0]],
+                "shift_weights": [1, 1, 1, 1]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.inner_size_probs.values, Some(vec![3, 5]));
+        assert_eq!(config.inner_size_probs.probs, vec![0.25, 0.75]);
+        assert_eq!(config.outer_margin_probs.values, Some(vec![1]));
+        assert_eq!(config.shift_weights.probs, vec![0.25; 4]);
+    }
+
+    #[test]
+    fn invalid_legacy_configs_fail() {
+        let unknown =
+            serde_json::json!({ "name": "legacy", "step_weights": [1, 1, 1, 1], "foo": 1 });
+        assert_eq!(
+            migrate_gen_config(unknown),
+            Err("unknown field 'foo' in legacy config".to_string())
+        );
+
+        let outer_only = serde_json::json!({ "name": "legacy", "outer_size_bounds": [3, 3] });
+        assert!(migrate_gen_config(outer_only).is_err());
+
+        let reversed = serde_json::json!({ "name": "legacy", "inner_size_bounds": [3, 1] });
+        assert!(migrate_gen_config(reversed).is_err());
+    }
+
+    #[test]
+    fn rooms_must_fit_around_their_waypoints() {
+        let gen_config = GenerationConfig::default();
+        let room_errors = |map_config: &MapConfig| -> Vec<&'static str> {
+            map_config
+                .validate(&gen_config)
+                .err()
+                .unwrap_or_default()
+                .iter()
+                .map(|error| error.field)
+                .filter(|field| field.ends_with("room_size"))
+                .collect()
+        };
+
+        let mut map_config = MapConfig::get_all_configs()["small_s"].clone();
+        assert!(room_errors(&map_config).is_empty());
+
+        // start room with the default size of 6 needs 7 blocks of room towards the border
+        map_config.waypoints[0] = Position::new(6, 250);
+        assert_eq!(room_errors(&map_config), vec!["start_room_size"]);
+        map_config.waypoints[0] = Position::new(7, 250);
+        assert!(room_errors(&map_config).is_empty());
+
+        map_config.finish_room_size = Some(50);
+        assert_eq!(room_errors(&map_config), vec!["finish_room_size"]);
+        map_config.finish_room_size = Some(0);
+        assert_eq!(room_errors(&map_config), vec!["finish_room_size"]);
+    }
+
+    #[test]
+    fn unsupported_versions_fail() {
+        let newer = serde_json::json!({ "name": "newer", "version": "2.0" });
+        assert!(migrate_gen_config(newer)
+            .unwrap_err()
+            .contains("newer than the newest supported version"));
+
+        let invalid = serde_json::json!({ "name": "invalid", "version": "one" });
+        assert_eq!(
+            migrate_gen_config(invalid),
+            Err("invalid config version 'one'".to_string())
+        );
+
+        let current = serde_json::json!({ "name": "current", "version": GEN_CONFIG_VERSION });
+        assert!(migrate_gen_config(current).is_ok());
+    }
+}
@@ -1,6 +1,7 @@
 use crate::map::Map;
 use macroquad::color::Color;
 use ndarray::Array2;
+use std::collections::HashMap;
 
 /// Allows storing various debug information
 #[derive(Debug)]
@@ -12,6 +13,9 @@ pub struct DebugLayer {
 
     /// Color for visualization of active blocks
     pub color: Color,
+
+    /// optional description of blocks, shown when hovering them in the editor
+    pub info: HashMap<[usize; 2], String>,
 }
 
 impl DebugLayer {
@@ -20,6 +24,7 @@ impl DebugLayer {
             grid: Array2::from_elem(for_map.grid.dim(), false),
             outline,
             color,
+            info: HashMap::new(),
         }
     }
 }
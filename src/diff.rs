@@ -0,0 +1,86 @@
+use ndarray::Array2;
+
+use crate::map::{BlockType, Map};
+
+/// cell wise difference between the game layers of two maps of the same size
+#[derive(Debug, Clone)]
+pub struct MapDiff {
+    /// cells that were empty and are filled now
+    pub added: Array2<bool>,
+
+    /// cells that were filled and are empty now
+    pub removed: Array2<bool>,
+
+    /// cells that are filled in both maps, but with different block types
+    pub changed: Array2<bool>,
+
+    /// amount of cells for each (old, new) block type transition
+    pub transitions: Vec<(BlockType, BlockType, usize)>,
+}
+
+impl MapDiff {
+    pub fn new(old: &Map, new: &Map) -> Result<MapDiff, &'static str> {
+        if old.grid.dim() != new.grid.dim() {
+            return Err("maps differ in size");
+        }
+
+        let mut diff = MapDiff {
+            added: Array2::from_elem(old.grid.dim(), false),
+            removed: Array2::from_elem(old.grid.dim(), false),
+            changed: Array2::from_elem(old.grid.dim(), false),
+            transitions: Vec::new(),
+        };
+
+        for ((pos, old_type), new_type) in old.grid.indexed_iter().zip(new.grid.iter()) {
+            if old_type == new_type {
+                continue;
+            }
+
+            match (old_type.is_empty(), new_type.is_empty()) {
+                (true, false) => diff.added[pos] = true,
+                (false, true) => diff.removed[pos] = true,
+                _ => diff.changed[pos] = true,
+            }
+
+            match diff
+                .transitions
+                .iter_mut()
+                .find(|(old, new, _)| old == old_type && new == new_type)
+            {
+                Some((_, _, count)) => *count += 1,
+                None => diff
+                    .transitions
+                    .push((old_type.clone(), new_type.clone(), 1)),
+            }
+        }
+
+        diff.transitions
+            .sort_by(|(_, _, count1), (_, _, count2)| count2.cmp(count1));
+
+        Ok(diff)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transitions.is_empty()
+    }
+
+    /// human readable summary, with one line per block type transition
+    pub fn summary(&self) -> String {
+        if self.is_empty() {
+            return "maps are identical".to_string();
+        }
+
+        let count = |grid: &Array2<bool>| grid.iter().filter(|value| **value).count();
+        let mut summary = format!(
+            "{} added, {} removed, {} changed",
+            count(&self.added),
+            count(&self.removed),
+            count(&self.changed)
+        );
+        for (old_type, new_type, count) in self.transitions.iter() {
+            summary += &format!("\n{:?} -> {:?}: {}", old_type, new_type, count);
+        }
+
+        summary
+    }
+}
@@ -0,0 +1,218 @@
+use std::thread;
+
+use dt::dt_bool;
+use ndarray::{Array2, Ix2};
+
+/// grids with less blocks are always transformed on a single thread
+const PARALLEL_MIN_BLOCKS: usize = 1 << 16;
+
+/// implementations of the exact euclidean distance transform. All of them compute the distance
+/// of every block to the nearest true block of a grid.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum DistanceField {
+    /// distance transform of the dt crate
+    Reference,
+
+    /// separable passes (Felzenszwalb & Huttenlocher) on a single thread
+    Separable,
+
+    /// separable passes, with the columns of large grids split across all available threads
+    #[default]
+    Parallel,
+}
+
+impl DistanceField {
+    pub const ALL: [DistanceField; 3] = [
+        DistanceField::Reference,
+        DistanceField::Separable,
+        DistanceField::Parallel,
+    ];
+
+    pub fn compute(&self, grid: &Array2<bool>) -> Array2<f32> {
+        match self {
+            DistanceField::Reference => dt_bool::<f32>(&grid.clone().into_dyn())
+                .into_dimensionality::<Ix2>()
+                .unwrap(),
+            DistanceField::Separable => separable_transform(grid, 1),
+            DistanceField::Parallel => {
+                let threads = match grid.len() < PARALLEL_MIN_BLOCKS {
+                    true => 1,
+                    false => thread::available_parallelism().map_or(1, |threads| threads.get()),
+                };
+                separable_transform(grid, threads)
+            }
+        }
+    }
+}
+
+/// distance of every block to the nearest true block, using the default implementation
+pub fn distance_field(grid: &Array2<bool>) -> Array2<f32> {
+    DistanceField::default().compute(grid)
+}
+
+fn separable_transform(grid: &Array2<bool>, threads: usize) -> Array2<f32> {
+    let (width, height) = grid.dim();
+    if width == 0 || height == 0 {
+        return Array2::zeros((width, height));
+    }
+
+    // blocks with the same x are contiguous, so each column [x, 0..height] is one lane
+    let grid = grid.as_standard_layout();
+    let blocks = grid.as_slice().unwrap();
+    let mut distances: Vec<f32> = blocks
+        .iter()
+        .map(|blocked| match blocked {
+            true => 0.0,
+            false => f32::INFINITY,
+        })
+        .collect();
+
+    // 1. distance to the nearest true block in x direction. Both sweeps process whole columns
+    // at once using element-wise operations on contiguous lanes, which get vectorized.
+    for x in 1..width {
+        let (previous, current) = distances.split_at_mut(x * height);
+        let previous = &previous[(x - 1) * height..];
+        for (distance, previous) in current[..height].iter_mut().zip(previous) {
+            *distance = distance.min(previous + 1.0);
+        }
+    }
+    for x in (0..width - 1).rev() {
+        let (current, next) = distances.split_at_mut((x + 1) * height);
+        let current = &mut current[x * height..];
+        for (distance, next) in current.iter_mut().zip(&next[..height]) {
+            *distance = distance.min(next + 1.0);
+        }
+    }
+    for distance in distances.iter_mut() {
+        *distance *= *distance;
+    }
+
+    // 2. combine the x distances along each column, columns are independent of each other
+    let columns_per_thread = width.div_ceil(threads.max(1));
+    if columns_per_thread >= width {
+        transform_columns(&mut distances, height);
+    } else {
+        thread::scope(|scope| {
+            for chunk in distances.chunks_mut(columns_per_thread * height) {
+                scope.spawn(move || transform_columns(chunk, height));
+            }
+        });
+    }
+
+    for distance in distances.iter_mut() {
+        *distance = distance.sqrt();
+    }
+
+    Array2::from_shape_vec((width, height), distances).unwrap()
+}
+
+fn transform_columns(columns: &mut [f32], height: usize) {
+    let mut envelope = Vec::with_capacity(height);
+    let mut bounds = Vec::with_capacity(height);
+    for column in columns.chunks_exact_mut(height) {
+        transform_lane(column, &mut envelope, &mut bounds);
+    }
+}
+
+/// in-place 1d squared distance transform of the sampled function f, see "Distance Transforms of
+/// Sampled Functions" (Felzenszwalb & Huttenlocher). The buffers are reused between lanes.
+fn transform_lane(f: &mut [f32], envelope: &mut Vec<(usize, f32)>, bounds: &mut Vec<f32>) {
+    envelope.clear();
+    bounds.clear();
+
+    // lower envelope of the parabolas rooted at all finite samples
+    for (q, value) in f.iter().enumerate() {
+        if value.is_infinite() {
+            continue;
+        }
+
+        let q_height = value + (q * q) as f32;
+        while let Some(&(p, p_value)) = envelope.last() {
+            let p_height = p_value + (p * p) as f32;
+            let intersection = (q_height - p_height) / (2 * (q - p)) as f32;
+            if intersection <= *bounds.last().unwrap() {
+                envelope.pop();
+                bounds.pop();
+            } else {
+                envelope.push((q, *value));
+                bounds.push(intersection);
+                break;
+            }
+        }
+        if envelope.is_empty() {
+            envelope.push((q, *value));
+            bounds.push(f32::NEG_INFINITY);
+        }
+    }
+
+    // lanes without any finite sample stay infinite
+    if envelope.is_empty() {
+        return;
+    }
+
+    let mut k = 0;
+    for (q, value) in f.iter_mut().enumerate() {
+        while k + 1 < envelope.len() && bounds[k + 1] < q as f32 {
+            k += 1;
+        }
+        let (p, p_value) = envelope[k];
+        *value = (q.abs_diff(p) * q.abs_diff(p)) as f32 + p_value;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+    /// grids of different shapes and densities, each with at least one true block
+    fn test_grids() -> Vec<Array2<bool>> {
+        let mut rng = SmallRng::seed_from_u64(42);
+        let mut grids = Vec::new();
+        for (width, height, density) in [
+            (1, 1, 1.0),
+            (1, 37, 0.1),
+            (41, 1, 0.1),
+            (17, 23, 0.02),
+            (64, 48, 0.2),
+            (300, 300, 0.001),
+            (257, 131, 0.05),
+        ] {
+            let mut grid = Array2::from_shape_fn((width, height), |_| rng.gen_bool(density));
+            grid[[rng.gen_range(0..width), rng.gen_range(0..height)]] = true;
+            grids.push(grid);
+        }
+
+        // single block in a corner and a diagonal line, like a walker path
+        let mut corner = Array2::from_elem((120, 80), false);
+        corner[[119, 0]] = true;
+        grids.push(corner);
+        grids.push(Array2::from_shape_fn((200, 200), |(x, y)| x == y));
+
+        grids
+    }
+
+    #[test]
+    fn separable_matches_reference() {
+        for grid in test_grids() {
+            let reference = DistanceField::Reference.compute(&grid);
+            assert_eq!(DistanceField::Separable.compute(&grid), reference);
+            assert_eq!(DistanceField::Parallel.compute(&grid), reference);
+
+            // force multiple threads, even for grids below PARALLEL_MIN_BLOCKS
+            assert_eq!(separable_transform(&grid, 4), reference);
+        }
+    }
+
+    #[test]
+    fn distances_of_single_block() {
+        let mut grid = Array2::from_elem((5, 4), false);
+        grid[[1, 1]] = true;
+
+        let distances = distance_field(&grid);
+        assert_eq!(distances[[1, 1]], 0.0);
+        assert_eq!(distances[[4, 1]], 3.0);
+        assert_eq!(distances[[1, 3]], 2.0);
+        assert_eq!(distances[[4, 3]], 13f32.sqrt());
+    }
+}
@@ -2,28 +2,72 @@ use std::{collections::HashMap, path::PathBuf, str::FromStr};
 
 const STEPS_PER_FRAME: usize = 50;
 
+/// max amount of walker steps when regenerating a region
+const REGION_MAX_STEPS: usize = 50_000;
+
+/// walker steps between two snapshots for undoing steps, see Generator::undo_steps
+const UNDO_SNAPSHOT_INTERVAL: usize = 500;
+
+/// block types that can be painted with the brush
+pub const BRUSH_BLOCKS: [BlockType; 4] = [
+    BlockType::Empty,
+    BlockType::Hookable,
+    BlockType::Freeze,
+    BlockType::Platform,
+];
+
 use crate::{
-    config::{GenerationConfig, MapConfig},
-    generator::Generator,
-    gui::{debug_window, sidebar},
-    map::Map,
+    analysis::{analyze_segments, MapStats, SegmentReport},
+    config::{GenerationConfig, MapConfig, NoGoZone, Theme},
+    diff::MapDiff,
+    export::{current_date, ExportSettings, MapProvenance},
+    generator::{Generator, PostProcessingStage},
+    genrec::GenerationRecording,
+    gui::{debug_window, hover_inspector, settings_window, sidebar},
+    keymap::{is_bindable, EditorAction, Keymap},
+    map::{BlockType, KernelType, Map, Overwrite},
+    metrics::MapMetrics,
+    position::{simplify_path, Position},
+    post_processing::fix_edge_bugs_in_area,
     random::Seed,
+    regression::{self, RegressionCase},
+    rendering::{draw_walker, draw_walker_kernel, draw_waypoints},
+    session::{EditorSession, MAX_RECENT_SEEDS},
+    trace::GenerationTrace,
+    twmap_import::{TwImport, DEFAULT_WAYPOINT_DIST},
+    validation::validate_map,
 };
 use egui::{epaint::Shadow, Color32, Frame, Margin};
 use std::env;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
-use macroquad::camera::{set_camera, Camera2D};
+use macroquad::camera::{set_camera, Camera, Camera2D};
+use macroquad::color::colors;
 use macroquad::input::{
-    is_key_pressed, is_mouse_button_down, is_mouse_button_released, mouse_position, mouse_wheel,
-    KeyCode, MouseButton,
+    get_last_key_pressed, is_mouse_button_down, is_mouse_button_pressed, is_mouse_button_released,
+    mouse_position, mouse_wheel, KeyCode, MouseButton,
 };
-use macroquad::math::{Rect, Vec2};
-use macroquad::time::get_fps;
+use macroquad::math::{Rect, Vec2, Vec3};
+use macroquad::time::{get_fps, get_frame_time, get_time};
 use macroquad::window::{screen_height, screen_width};
 use rand_distr::num_traits::Zero;
 
 const ZOOM_FACTOR: f32 = 0.9;
 const AVG_FPS_FACTOR: f32 = 0.025; // how much current fps is weighted into the rolling average
+const AVG_STEP_RATE_FACTOR: f32 = 0.05; // same for the walker steps per second
+
+/// seconds without any config change before the A/B mode regenerates the map
+const AB_DEBOUNCE: f64 = 0.25;
+
+/// min. time between two progress updates and between two map snapshots of an instant job
+const JOB_UPDATE_INTERVAL: Duration = Duration::from_millis(50);
+const JOB_SNAPSHOT_INTERVAL: Duration = Duration::from_millis(250);
 
 pub fn window_frame() -> Frame {
     Frame {
@@ -57,13 +101,62 @@ enum PausedState {
     /// dont start generation yet to allow setup configuration
     Setup,
 }
+/// progress of an instant job, sent from the worker thread to the editor
+enum JobUpdate {
+    Walking {
+        steps: usize,
+        goal_index: usize,
+    },
+
+    /// post processing stage that is performed next
+    PostProcessing(PostProcessingStage),
+
+    /// current map of the worker, only sent if the job has a live preview
+    Snapshot(Box<Map>),
+}
+
+/// map generation running on a worker thread, so instant generation doesnt block the ui
+pub struct InstantJob {
+    /// returns the generator and whether post processing was performed
+    handle: JoinHandle<Result<(Generator, bool), &'static str>>,
+    cancel: Arc<AtomicBool>,
+    updates: Receiver<JobUpdate>,
+
+    /// walker steps performed so far
+    pub steps: usize,
+
+    /// index of the waypoint the walker is currently heading to
+    pub goal_index: usize,
+
+    /// post processing stage that is currently performed, None while walking
+    pub post_stage: Option<PostProcessingStage>,
+}
+
+/// custom visualization that is drawn over the map, see Editor::register_overlay
+pub type OverlayFn = fn(&Editor, &Camera2D);
+
+pub struct Overlay {
+    pub name: &'static str,
+    pub draw: OverlayFn,
+    pub enabled: bool,
+}
+
 pub struct Editor {
     state: EditorState,
     pub init_gen_configs: HashMap<String, GenerationConfig>,
     pub init_map_configs: HashMap<String, MapConfig>,
+    pub init_themes: HashMap<String, Theme>,
     pub canvas: Option<egui::Rect>,
     pub egui_wants_mouse: Option<bool>,
+    pub egui_wants_keyboard: Option<bool>,
     pub average_fps: f32,
+
+    /// rolling average of the walker steps per second
+    pub average_step_rate: f32,
+
+    /// walker steps at the start of the last frame
+    last_steps: usize,
+
     pub gen_config: GenerationConfig,
     pub map_config: MapConfig,
     pub steps_per_frame: usize,
@@ -83,14 +176,161 @@ pub struct Editor {
     /// whether to keep using the same seed for next generations
     pub fixed_seed: bool,
 
+    /// recently generated seeds, most recent first
+    pub recent_seeds: Vec<u64>,
+
+    /// whether the map is regenerated in the background with the same seed whenever a config
+    /// value changes, so the isolated effect of a change can be compared
+    pub ab_mode: bool,
+
+    /// A/B mode generates drafts downscaled by this factor to stay responsive, 1 generates the
+    /// full map
+    pub ab_draft_scale: usize,
+
+    /// configs and seed of the last A/B generation
+    ab_snapshot: Option<(GenerationConfig, MapConfig, u64)>,
+
+    /// time of the last config change that hasn't been generated yet
+    ab_changed_at: Option<f64>,
+
     /// whether to show the GenerationConfig settings
     pub edit_gen_config: bool,
 
     /// whether to show the GenerationConfig settings
     pub edit_map_config: bool,
 
+    /// show and edit positions as DDNet in-game coordinates, see Position::to_ddnet
+    pub ddnet_coordinates: bool,
+
     /// asd
     pub visualize_debug_layers: HashMap<&'static str, bool>,
+
+    /// summary of the last regression run
+    pub regression_summary: Option<String>,
+
+    /// whether the walker should record an event log during generation
+    pub record_walker_events: bool,
+
+    /// whether the outcomes of all rng samples are traced during generation, see GenerationTrace
+    pub record_trace: bool,
+
+    /// whether left mouse drags define no-go zones instead of moving the camera
+    pub draw_no_go_zones: bool,
+
+    /// map position where the current no-go zone drag started
+    no_go_start: Option<Position>,
+
+    /// whether left mouse drags draw a path that replaces the waypoints
+    pub draw_waypoint_path: bool,
+
+    /// path that is currently being drawn
+    pub drawn_path: Vec<Position>,
+
+    /// max distance of the drawn path to the simplified path
+    pub waypoint_path_tolerance: f32,
+
+    /// min distance between two waypoints of a drawn path
+    pub waypoint_path_spacing: usize,
+
+    /// difficulty report of the last generated map
+    pub segment_reports: Vec<SegmentReport>,
+
+    /// whether the segment difficulty is drawn over the walker path
+    pub draw_segment_reports: bool,
+
+    /// quality metrics of the last generated map
+    pub map_metrics: Option<MapMetrics>,
+
+    /// draw the shift costs around the walker as heatmap
+    pub draw_cost_field: bool,
+
+    /// draw how often the walker visited each block as heatmap
+    pub draw_visit_heatmap: bool,
+
+    /// draw the positions the walker may no longer enter
+    pub draw_locked_positions: bool,
+
+    /// keep the walker centered, zoomed based on its kernel size
+    pub follow_walker: bool,
+
+    /// how slowly the follow camera catches up with the walker, 0 snaps to the walker
+    pub follow_smoothing: f32,
+
+    /// size of the area that the follow camera shows, in outer kernel sizes
+    pub follow_zoom: f32,
+
+    /// visualizations that are drawn over the map in order of registration
+    pub overlays: Vec<Overlay>,
+
+    /// live block counts of the current map
+    pub map_stats: MapStats,
+
+    /// whether post processing is performed stage by stage using the playback controls
+    pub step_post_processing: bool,
+
+    /// index of the next post processing stage
+    post_stage: usize,
+
+    /// currently running instant generation
+    pub instant_job: Option<InstantJob>,
+
+    /// snapshot of a map that the current map is compared against
+    pub diff_base: Option<Map>,
+
+    /// difference between the diff base and the current map
+    pub map_diff: Option<MapDiff>,
+
+    pub export_settings: ExportSettings,
+
+    /// result of the last export, either the exported path or an error
+    pub export_status: Option<String>,
+
+    pub keymap: Keymap,
+
+    /// action that is bound to the next pressed key
+    pub rebind_action: Option<EditorAction>,
+
+    /// fingerprint that the next generation should reproduce, if a recording is replayed
+    pub replay_hash: Option<u64>,
+
+    /// trace of the loaded recording, which is replayed by the next generation
+    replay_trace: Option<GenerationTrace>,
+
+    /// result of loading and replaying the last recording
+    pub replay_status: Option<String>,
+
+    /// block type that is painted on the map with the left mouse button, None disables the brush
+    pub brush: Option<BlockType>,
+
+    /// width and height of the painted area
+    pub brush_size: usize,
+
+    /// bounding box of all manual edits since the last safety passes
+    pub edited_area: Option<(Position, Position)>,
+
+    /// result of the last safety passes
+    pub safety_status: Option<String>,
+
+    /// whether left mouse drags select the region that is regenerated
+    pub select_region: bool,
+
+    /// map position where the current region selection drag started
+    region_start: Option<Position>,
+
+    /// selected region for partial regeneration
+    pub region: Option<(Position, Position)>,
+
+    /// result of the last partial regeneration
+    pub region_status: Option<String>,
+
+    /// amount of walker steps that are undone at once
+    pub undo_step_count: usize,
+
+    /// walker steps before the last undo, which can be redone
+    pub redo_target: Option<usize>,
+
+    /// result of the last undo
+    pub undo_status: Option<String>,
 }
 
 impl Editor {
@@ -98,24 +338,31 @@ impl Editor {
         let init_gen_configs: HashMap<String, GenerationConfig> =
             GenerationConfig::get_all_configs();
         let init_map_configs: HashMap<String, MapConfig> = MapConfig::get_all_configs();
+        let init_themes: HashMap<String, Theme> = Theme::get_all_themes();
 
         // TODO: its kinda stupid to initialize this as its literally re-initialized anyways
         // when starting the first map generation. But i dont wanna bother adding an Option here as
         // the generator also holds the initial empty map which is used for visualization.
         let gen = Generator::new(&gen_config, &map_config, Seed::from_u64(0));
 
+        let map_stats = MapStats::new(&gen.map);
+
         let mut visualize_debug_layers: HashMap<&'static str, bool> = HashMap::new();
         for layer_name in gen.debug_layers.keys() {
             visualize_debug_layers.insert(layer_name, true);
         }
 
-        Editor {
+        let mut editor = Editor {
             state: EditorState::Paused(PausedState::Setup),
             init_gen_configs,
             init_map_configs,
+            init_themes,
             canvas: None,
             egui_wants_mouse: None,
+            egui_wants_keyboard: None,
             average_fps: 0.0,
+            average_step_rate: 0.0,
+            last_steps: 0,
             zoom: 1.0,
             offset: Vec2::ZERO,
             cam: None,
@@ -128,9 +375,89 @@ impl Editor {
             instant: false,
             auto_generate: false,
             fixed_seed: false,
+            ab_mode: false,
+            ab_draft_scale: 2,
+            ab_snapshot: None,
+            ab_changed_at: None,
+            recent_seeds: Vec::new(),
             edit_gen_config: false,
             edit_map_config: false,
+            ddnet_coordinates: false,
             visualize_debug_layers,
+            regression_summary: None,
+            record_walker_events: false,
+            record_trace: false,
+            draw_no_go_zones: false,
+            no_go_start: None,
+            draw_waypoint_path: false,
+            drawn_path: Vec::new(),
+            waypoint_path_tolerance: 2.0,
+            waypoint_path_spacing: 10,
+            segment_reports: Vec::new(),
+            draw_segment_reports: false,
+            map_metrics: None,
+            draw_cost_field: false,
+            draw_visit_heatmap: false,
+            draw_locked_positions: false,
+            follow_walker: false,
+            follow_smoothing: 0.9,
+            follow_zoom: 12.0,
+            overlays: Vec::new(),
+            map_stats,
+            step_post_processing: false,
+            post_stage: 0,
+            instant_job: None,
+            diff_base: None,
+            map_diff: None,
+            export_settings: ExportSettings::default(),
+            export_status: None,
+            keymap: Keymap::load_or_default(),
+            rebind_action: None,
+            replay_hash: None,
+            replay_trace: None,
+            replay_status: None,
+            brush: None,
+            brush_size: 1,
+            edited_area: None,
+            safety_status: None,
+            select_region: false,
+            region_start: None,
+            region: None,
+            region_status: None,
+            undo_step_count: 100,
+            redo_target: None,
+            undo_status: None,
+        };
+
+        editor.register_overlay("walker kernels", |editor, _| {
+            draw_walker_kernel(&editor.gen.walker, KernelType::Outer);
+            draw_walker_kernel(&editor.gen.walker, KernelType::Inner);
+        });
+        editor.register_overlay("walker", |editor, _| draw_walker(&editor.gen.walker));
+        editor.register_overlay("waypoints", |editor, _| {
+            draw_waypoints(&editor.gen.walker.waypoints, colors::BLUE);
+            draw_waypoints(&editor.map_config.waypoints, colors::RED);
+        });
+
+        editor
+    }
+
+    /// adds an overlay that is drawn over the map after all previously registered ones. Allows
+    /// binaries to add custom visualizations without changing the editor.
+    pub fn register_overlay(&mut self, name: &'static str, draw: OverlayFn) {
+        self.overlays.push(Overlay {
+            name,
+            draw,
+            enabled: true,
+        });
+    }
+
+    /// draws all enabled overlays using the camera of the last set_cam call
+    pub fn draw_overlays(&self) {
+        if let Some(cam) = &self.cam {
+            for overlay in self.overlays.iter().filter(|overlay| overlay.enabled) {
+                (overlay.draw)(self, cam);
+            }
         }
     }
 
@@ -139,10 +466,61 @@ impl Editor {
         self.average_fps =
             (self.average_fps * (1. - AVG_FPS_FACTOR)) + (get_fps() as f32 * AVG_FPS_FACTOR);
 
+        // walker progress since the last frame, a new generation restarts the step count
+        let steps = self.current_steps();
+        let frame_time = get_frame_time();
+        if frame_time > 0.0 {
+            let step_rate = steps.saturating_sub(self.last_steps) as f32 / frame_time;
+            self.average_step_rate = (self.average_step_rate * (1. - AVG_STEP_RATE_FACTOR))
+                + (step_rate * AVG_STEP_RATE_FACTOR);
+        }
+        self.last_steps = steps;
+
         // this value is only valid for each frame after calling define_egui()
         self.canvas = None;
     }
 
+    /// walker steps of the current generation, including generations on a worker thread
+    pub fn current_steps(&self) -> usize {
+        match &self.instant_job {
+            Some(job) => job.steps,
+            None => self.gen.walker.steps,
+        }
+    }
+
+    /// index of the waypoint the walker of the current generation is heading to
+    pub fn current_goal_index(&self) -> usize {
+        match &self.instant_job {
+            Some(job) => job.goal_index,
+            None => self.gen.walker.goal_index,
+        }
+    }
+
+    /// estimated remaining walker steps of the current generation. Without a step limit the
+    /// steps taken so far are extrapolated over the remaining waypoints, which requires at
+    /// least one reached waypoint.
+    pub fn remaining_steps(&self) -> Option<usize> {
+        let steps = self.current_steps();
+        let goal_index = self.current_goal_index();
+        let waypoints = self.map_config.waypoints.len();
+
+        let extrapolated =
+            (goal_index > 0).then(|| steps * waypoints.saturating_sub(goal_index) / goal_index);
+        let limited = (self.gen_config.max_walker_steps > 0)
+            .then(|| self.gen_config.max_walker_steps.saturating_sub(steps));
+
+        match (extrapolated, limited) {
+            (Some(extrapolated), Some(limited)) => Some(extrapolated.min(limited)),
+            (extrapolated, limited) => extrapolated.or(limited),
+        }
+    }
+
+    /// estimated remaining seconds of the current generation, based on the rolling step rate
+    pub fn remaining_time(&self) -> Option<f32> {
+        let remaining_steps = self.remaining_steps()?;
+        (self.average_step_rate >= 1.0).then(|| remaining_steps as f32 / self.average_step_rate)
+    }
+
     pub fn get_display_factor(&self, map: &Map) -> f32 {
         let canvas = self
             .canvas
@@ -158,10 +536,13 @@ impl Editor {
         egui_macroquad::ui(|egui_ctx| {
             sidebar(egui_ctx, self);
             debug_window(egui_ctx, self);
+            settings_window(egui_ctx, self);
+            hover_inspector(egui_ctx, self);
 
             // store remaining space for macroquad drawing
             self.canvas = Some(egui_ctx.available_rect());
             self.egui_wants_mouse = Some(egui_ctx.wants_pointer_input());
+            self.egui_wants_keyboard = Some(egui_ctx.wants_keyboard_input());
         });
     }
 
@@ -203,6 +584,7 @@ impl Editor {
     }
 
     pub fn set_setup(&mut self) {
+        self.cancel_instant_job();
         self.state = EditorState::Paused(PausedState::Setup);
     }
 
@@ -211,11 +593,473 @@ impl Editor {
     }
 
     fn initialize_generator(&mut self) {
+        // a pending A/B generation would replace the new generator
+        self.cancel_instant_job();
+
         if !self.fixed_seed {
             self.user_seed = Seed::from_random(&mut self.gen.rnd);
         }
 
-        self.gen = Generator::new(&self.gen_config, &self.map_config, self.user_seed.clone());
+        self.recent_seeds
+            .retain(|seed| *seed != self.user_seed.seed_u64);
+        self.recent_seeds.insert(0, self.user_seed.seed_u64);
+        self.recent_seeds.truncate(MAX_RECENT_SEEDS);
+
+        let seed = self.user_seed.clone();
+        self.gen = match (self.replay_trace.take(), self.record_trace) {
+            (Some(trace), _) => trace.replay_generator(&self.gen_config, &self.map_config, seed),
+            (None, true) => {
+                GenerationTrace::recording_generator(&self.gen_config, &self.map_config, seed)
+            }
+            (None, false) => Generator::new(&self.gen_config, &self.map_config, seed),
+        };
+        self.segment_reports.clear();
+        self.map_metrics = None;
+        self.map_stats = MapStats::new(&self.gen.map);
+        self.post_stage = 0;
+        self.edited_area = None;
+        self.safety_status = None;
+        self.region = None;
+        self.region_status = None;
+        self.redo_target = None;
+        self.undo_status = None;
+
+        // keep everything around that is required to debug the generation
+        self.gen.live_lock_layer = true;
+        self.gen.snapshot_interval = UNDO_SNAPSHOT_INTERVAL;
+        self.gen.walker.position_history.enable_full_history();
+
+        if self.record_walker_events {
+            self.gen.walker.enable_event_log();
+        }
+    }
+
+    /// paints the brush block type centered at pos and extends the edited area
+    fn paint(&mut self, pos: &Position, block_type: &BlockType) {
+        let map = &mut self.gen.map;
+        let radius = self.brush_size / 2;
+        let top_left = Position::new(pos.x.saturating_sub(radius), pos.y.saturating_sub(radius));
+        let bot_right = Position::new(
+            (top_left.x + self.brush_size.max(1) - 1).min(map.width - 1),
+            (top_left.y + self.brush_size.max(1) - 1).min(map.height - 1),
+        );
+        map.set_area(&top_left, &bot_right, block_type, &Overwrite::Force);
+
+        self.extend_edited_area(top_left, bot_right);
+    }
+
+    /// extends the edited area, so it also covers the given area
+    fn extend_edited_area(&mut self, top_left: Position, bot_right: Position) {
+        self.edited_area = Some(match self.edited_area.take() {
+            Some((edited_top_left, edited_bot_right)) => (
+                Position::new(
+                    edited_top_left.x.min(top_left.x),
+                    edited_top_left.y.min(top_left.y),
+                ),
+                Position::new(
+                    edited_bot_right.x.max(bot_right.x),
+                    edited_bot_right.y.max(bot_right.y),
+                ),
+            ),
+            None => (top_left, bot_right),
+        });
+    }
+
+    /// re-applies the edge bug fixing (freeze padding) to the manually edited area and checks
+    /// whether the map is still valid and solvable
+    pub fn rerun_safety_passes(&mut self) {
+        let map = &mut self.gen.map;
+        let fixed_blocks = match self.edited_area.take() {
+            Some((top_left, bot_right)) => {
+                // neighbors of edited blocks may require freeze padding aswell
+                let top_left =
+                    Position::new(top_left.x.saturating_sub(1), top_left.y.saturating_sub(1));
+                let bot_right = Position::new(
+                    (bot_right.x + 1).min(map.width - 1),
+                    (bot_right.y + 1).min(map.height - 1),
+                );
+                let edge_bugs =
+                    fix_edge_bugs_in_area(map, &top_left, &bot_right, self.gen_config.freeze_style);
+                edge_bugs.iter().filter(|fixed| **fixed).count()
+            }
+            None => 0,
+        };
+        self.map_stats = MapStats::new(&self.gen.map);
+
+        let violations = validate_map(&self.gen.map);
+        let status = match violations.is_empty() {
+            true => format!("fixed {} edge bugs, map is valid", fixed_blocks),
+            false => format!(
+                "fixed {} edge bugs, map is invalid: {}",
+                fixed_blocks,
+                violations
+                    .iter()
+                    .map(|violation| violation.kind.description())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        println!("{}", status);
+        self.safety_status = Some(status);
+    }
+
+    /// clears the selected region and carves it again using a new random sub seed, then runs the
+    /// safety passes on it
+    pub fn regenerate_region(&mut self) {
+        let (top_left, bot_right) = match self.region.clone() {
+            Some(region) => region,
+            None => return,
+        };
+
+        let sub_seed = Seed::random().seed_u64;
+        let result = self.gen.regenerate_region(
+            &self.gen_config,
+            &top_left,
+            &bot_right,
+            sub_seed,
+            REGION_MAX_STEPS,
+        );
+
+        let status = match result {
+            Ok(steps) => {
+                // the walker path is outdated now, so the segment reports are aswell
+                self.segment_reports.clear();
+                self.extend_edited_area(top_left, bot_right);
+                self.rerun_safety_passes();
+                format!(
+                    "regenerated region in {} steps (sub seed {})",
+                    steps, sub_seed
+                )
+            }
+            Err(err) => format!("couldn't regenerate region: {}", err),
+        };
+        println!("{}", status);
+        self.region_status = Some(status);
+    }
+
+    /// whether walker steps can be undone or redone right now
+    pub fn can_undo(&self) -> bool {
+        self.is_paused() && !self.is_setup() && self.instant_job.is_none()
+    }
+
+    /// reverts the walker by undo_step_count steps. Post processing is reset, so it runs again
+    /// once the walker finishes.
+    pub fn undo_steps(&mut self) {
+        let steps = self.gen.walker.steps;
+        let status = match self.gen.undo_steps(self.undo_step_count, &self.gen_config) {
+            Ok(()) => {
+                self.redo_target = Some(self.redo_target.unwrap_or(steps).max(steps));
+                self.post_stage = 0;
+                self.segment_reports.clear();
+                self.map_stats = MapStats::new(&self.gen.map);
+                format!("undone to step {}", self.gen.walker.steps)
+            }
+            Err(err) => format!("couldn't undo steps: {}", err),
+        };
+        self.undo_status = Some(status);
+    }
+
+    /// steps the walker forward to where it was before the first undo
+    pub fn redo_steps(&mut self) {
+        let target = match self.redo_target.take() {
+            Some(target) => target,
+            None => return,
+        };
+
+        while self.gen.walker.steps < target && !self.gen.walker.finished {
+            if let Err(err) = self.gen.step(&self.gen_config) {
+                self.undo_status = Some(format!("couldn't redo steps: {}", err));
+                return;
+            }
+        }
+        self.undo_status = Some(format!("redone to step {}", self.gen.walker.steps));
+    }
+
+    /// moves the current generator to a worker thread, which finishes the walker and (unless
+    /// stepping through post processing) post processing. Meanwhile a placeholder is shown,
+    /// which is updated with snapshots of the map in progress.
+    pub fn start_instant_job(&mut self) {
+        let placeholder =
+            Generator::new(&self.gen_config, &self.map_config, self.user_seed.clone());
+        let gen = std::mem::replace(&mut self.gen, placeholder);
+
+        self.spawn_job(
+            gen,
+            self.gen_config.clone(),
+            !self.step_post_processing,
+            true,
+        );
+    }
+
+    /// finishes the walker and optionally post processing of the generator on a worker thread.
+    /// With a live preview, the editor map is replaced by snapshots of the map in progress.
+    fn spawn_job(
+        &mut self,
+        mut gen: Generator,
+        gen_config: GenerationConfig,
+        post_process: bool,
+        live_preview: bool,
+    ) {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (sender, updates) = mpsc::channel();
+        let (steps, goal_index) = (gen.walker.steps, gen.walker.goal_index);
+
+        let handle = {
+            let cancel = cancel.clone();
+
+            thread::spawn(move || {
+                let mut last_update = Instant::now();
+                let mut last_snapshot = Instant::now();
+                while !gen.walker.finished {
+                    if cancel.load(Ordering::Relaxed) {
+                        return Err("generation cancelled");
+                    }
+                    gen.step(&gen_config)?;
+
+                    if last_update.elapsed() >= JOB_UPDATE_INTERVAL {
+                        last_update = Instant::now();
+                        // the editor dropped the job, if it can't receive updates anymore
+                        sender
+                            .send(JobUpdate::Walking {
+                                steps: gen.walker.steps,
+                                goal_index: gen.walker.goal_index,
+                            })
+                            .map_err(|_| "generation cancelled")?;
+                    }
+                    if live_preview && last_snapshot.elapsed() >= JOB_SNAPSHOT_INTERVAL {
+                        last_snapshot = Instant::now();
+                        let snapshot = JobUpdate::Snapshot(Box::new(gen.map.clone()));
+                        sender.send(snapshot).map_err(|_| "generation cancelled")?;
+                    }
+                }
+
+                if post_process {
+                    let draft = gen.is_draft();
+                    let post_processing = PostProcessingStage::ALL
+                        .into_iter()
+                        .filter(|stage| !draft || stage.is_draft_stage())
+                        .try_for_each(|stage| {
+                            let _ = sender.send(JobUpdate::PostProcessing(stage));
+                            gen.perform_post_processing_stage(stage, &gen_config)
+                        });
+                    post_processing.unwrap_or_else(|err| {
+                        println!("Post Processing Failed: {:}", err);
+                    });
+                }
+
+                Ok((gen, post_process))
+            })
+        };
+
+        self.instant_job = Some(InstantJob {
+            handle,
+            cancel,
+            updates,
+            steps,
+            goal_index,
+            post_stage: None,
+        });
+    }
+
+    /// applies the progress of the instant job and swaps in its generator, once it is done
+    pub fn update_instant_job(&mut self) {
+        let job = match &mut self.instant_job {
+            Some(job) => job,
+            None => return,
+        };
+
+        for update in job.updates.try_iter() {
+            match update {
+                JobUpdate::Walking { steps, goal_index } => {
+                    job.steps = steps;
+                    job.goal_index = goal_index;
+                }
+                JobUpdate::PostProcessing(stage) => job.post_stage = Some(stage),
+                JobUpdate::Snapshot(map) => self.gen.map = *map,
+            }
+        }
+        if !job.handle.is_finished() {
+            return;
+        }
+
+        let job = self.instant_job.take().unwrap();
+        match job.handle.join() {
+            Ok(Ok((gen, post_processed))) => {
+                self.gen = gen;
+                self.map_stats = MapStats::new(&self.gen.map);
+                if post_processed {
+                    self.post_stage = PostProcessingStage::ALL.len();
+
+                    // A/B generations stay in setup, so they aren't analyzed by the main loop
+                    if self.is_setup() {
+                        self.analyze_map();
+                    }
+                }
+            }
+            Ok(Err(err)) => {
+                println!("Walker Step Failed: {:}", err);
+                self.set_setup();
+            }
+            Err(_) => {
+                println!("Walker Step Failed: generation panicked");
+                self.set_setup();
+            }
+        }
+    }
+
+    /// regenerates the map in the background, once the configs or the seed changed and then
+    /// stayed unchanged for AB_DEBOUNCE seconds, see Editor::ab_mode
+    pub fn update_ab_mode(&mut self) {
+        if !self.ab_mode {
+            self.ab_snapshot = None;
+            return;
+        }
+        if !self.is_setup() {
+            return;
+        }
+
+        let changed = match &self.ab_snapshot {
+            Some((gen_config, map_config, seed)) => {
+                *gen_config != self.gen_config
+                    || *map_config != self.map_config
+                    || *seed != self.user_seed.seed_u64
+            }
+            None => true,
+        };
+        if changed {
+            self.ab_snapshot = Some((
+                self.gen_config.clone(),
+                self.map_config.clone(),
+                self.user_seed.seed_u64,
+            ));
+            self.ab_changed_at = Some(get_time());
+        }
+
+        if self
+            .ab_changed_at
+            .is_some_and(|changed_at| get_time() - changed_at >= AB_DEBOUNCE)
+        {
+            self.ab_changed_at = None;
+            self.start_ab_job();
+        }
+    }
+
+    /// generates the map (or a draft) of the current configs and seed in the background, while
+    /// the previous map stays visible
+    fn start_ab_job(&mut self) {
+        self.cancel_instant_job();
+
+        // configs are often invalid while values are being edited
+        if self.gen_config.validate().is_err()
+            || self.map_config.validate(&self.gen_config).is_err()
+        {
+            return;
+        }
+
+        let (gen, gen_config) = match self.ab_draft_scale.max(1) {
+            1 => (
+                Generator::new(&self.gen_config, &self.map_config, self.user_seed.clone()),
+                self.gen_config.clone(),
+            ),
+            scale => (
+                Generator::new_draft(
+                    &self.gen_config,
+                    &self.map_config,
+                    self.user_seed.clone(),
+                    scale,
+                ),
+                self.gen_config.downscaled(scale),
+            ),
+        };
+        // the previous map stays visible, until the new one is done
+        self.spawn_job(gen, gen_config, true, false);
+    }
+
+    /// stops the instant job, the worker thread exits with its next step
+    pub fn cancel_instant_job(&mut self) {
+        if let Some(job) = self.instant_job.take() {
+            job.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// compares the current map to the diff base, e.g. another seed or the map before a post
+    /// processing stage
+    pub fn compare_to_diff_base(&mut self) {
+        if let Some(diff_base) = &self.diff_base {
+            match MapDiff::new(diff_base, &self.gen.map) {
+                Ok(map_diff) => self.map_diff = Some(map_diff),
+                Err(err) => {
+                    println!("Map Diff Failed: {:}", err);
+                    self.map_diff = None;
+                }
+            }
+        }
+    }
+
+    /// next post processing stage, None if post processing is done
+    pub fn get_post_stage(&self) -> Option<PostProcessingStage> {
+        PostProcessingStage::ALL.get(self.post_stage).copied()
+    }
+
+    /// performs the next post processing stage and shows its debug layer, or all remaining
+    /// stages at once. Returns whether post processing is done.
+    pub fn post_process(&mut self, single_stage: bool) -> bool {
+        while let Some(stage) = self.get_post_stage() {
+            // kinda crappy, but ensure that even a panic doesnt crash the program
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                self.gen
+                    .perform_post_processing_stage(stage, &self.gen_config)
+            }))
+            .unwrap_or(Err("post processing panicked"));
+
+            if let Err(err) = result {
+                println!("Post Processing Failed: {:}", err);
+                self.post_stage = PostProcessingStage::ALL.len();
+                break;
+            }
+            self.post_stage += 1;
+
+            if single_stage {
+                if let Some(layer_name) = stage.debug_layer() {
+                    self.visualize_debug_layers.insert(layer_name, true);
+                }
+                break;
+            }
+        }
+
+        self.get_post_stage().is_none()
+    }
+
+    /// estimates the difficulty of each waypoint segment of the generated map
+    pub fn analyze_map(&mut self) {
+        self.segment_reports = analyze_segments(
+            &self.gen.map,
+            &self.gen.walker.position_history.to_vec(),
+            self.gen.get_waypoints(),
+            self.gen_config.waypoint_reached_dist,
+            self.gen.debug_layers.get("skips").map(|layer| &layer.grid),
+        );
+
+        self.map_metrics = Some(MapMetrics::new(&self.gen, &self.gen_config));
+
+        // post processing edits the grid directly, so the changes are not tracked
+        self.map_stats = MapStats::new(&self.gen.map);
+
+        if let Some(hash) = self.replay_hash.take() {
+            let status = match (
+                Generator::fingerprint_map(&self.gen.map) == hash,
+                self.gen.rnd.trace_divergence(),
+            ) {
+                (true, _) => "replay matches the recording".to_string(),
+                (false, Some(index)) => format!(
+                    "replay differs from the recording, diverged from the trace at sample {}!",
+                    index
+                ),
+                (false, None) => "replay differs from the recording!".to_string(),
+            };
+            println!("{}", status);
+            self.replay_status = Some(status);
+        }
     }
 
     fn mouse_in_viewport(cam: &Camera2D) -> bool {
@@ -226,6 +1070,81 @@ impl Editor {
             && mouse_y <= cam.viewport.unwrap().3 as f32
     }
 
+    /// converts the mouse position into a map position, if the mouse is above the map
+    pub fn mouse_to_map_pos(&self) -> Option<Position> {
+        let cam = self.cam.as_ref()?;
+        let (_, _, view_width, view_height) = cam.viewport?;
+        let (mouse_x, mouse_y) = mouse_position();
+
+        // mouse position in normalized device coordinates of the viewport
+        let ndc_x = mouse_x / view_width as f32 * 2.0 - 1.0;
+        let ndc_y = 1.0 - mouse_y / view_height as f32 * 2.0;
+        let world = cam
+            .matrix()
+            .inverse()
+            .project_point3(Vec3::new(ndc_x, ndc_y, 0.0));
+
+        let map = &self.gen.map;
+        if world.x < 0.0 || world.y < 0.0 {
+            return None;
+        }
+        let pos = Position::new(world.x as usize, world.y as usize);
+        (pos.x < map.width && pos.y < map.height).then_some(pos)
+    }
+
+    /// returns the area between the drag start and the current mouse position
+    fn get_drag_area(&self, start: &Position) -> Option<(Position, Position)> {
+        let end = self.mouse_to_map_pos()?;
+
+        Some((
+            Position::new(start.x.min(end.x), start.y.min(end.y)),
+            Position::new(start.x.max(end.x), start.y.max(end.y)),
+        ))
+    }
+
+    /// returns the no-go zone that is currently being drawn
+    pub fn get_pending_no_go_zone(&self) -> Option<NoGoZone> {
+        let (top_left, bot_right) = self.get_drag_area(self.no_go_start.as_ref()?)?;
+
+        Some(NoGoZone {
+            top_left,
+            bot_right,
+        })
+    }
+
+    /// returns the region that is currently being selected
+    pub fn get_pending_region(&self) -> Option<(Position, Position)> {
+        self.get_drag_area(self.region_start.as_ref()?)
+    }
+
+    /// simplifies the drawn path and uses it as waypoints of the map config
+    fn apply_drawn_path(&mut self) {
+        let simplified = simplify_path(&self.drawn_path, self.waypoint_path_tolerance);
+        self.drawn_path.clear();
+
+        // drop waypoints that are too close to the previous one, but always keep the last one
+        let spacing_sqr = self.waypoint_path_spacing.pow(2);
+        let mut waypoints: Vec<Position> = Vec::new();
+        for (index, pos) in simplified.iter().enumerate() {
+            let is_last = index + 1 == simplified.len();
+            match waypoints.last() {
+                Some(prev) if prev.distance_squared(pos) < spacing_sqr => {
+                    if is_last && waypoints.len() > 1 {
+                        waypoints.pop();
+                        waypoints.push(pos.clone());
+                    } else if is_last {
+                        waypoints.push(pos.clone());
+                    }
+                }
+                _ => waypoints.push(pos.clone()),
+            }
+        }
+
+        if waypoints.len() >= 2 {
+            self.map_config.waypoints = waypoints;
+        }
+    }
+
     /// this should result in the exact same behaviour as if not using a camera at all
     pub fn reset_camera() {
         set_camera(&Camera2D::from_display_rect(Rect::new(
@@ -236,7 +1155,26 @@ impl Editor {
         )));
     }
 
+    /// moves the camera towards the walker and adjusts the zoom to its kernel size
+    fn update_follow_camera(&mut self) {
+        let map = &self.gen.map;
+        let walker = &self.gen.walker;
+        let map_center = Vec2::new(map.width as f32, map.height as f32) / 2.0;
+        let walker_center = Vec2::new(walker.pos.x as f32 + 0.5, walker.pos.y as f32 + 0.5);
+        let view_size = self.follow_zoom * walker.outer_kernel.size as f32;
+        let target_zoom = (usize::max(map.width, map.height) as f32 / view_size).max(1.0);
+
+        // smoothing is relative to 60 fps, so the camera speed doesnt depend on the frame rate
+        let blend = 1.0 - self.follow_smoothing.powf(get_frame_time() * 60.0);
+        self.offset = self.offset.lerp(map_center - walker_center, blend);
+        self.zoom += (target_zoom - self.zoom) * blend;
+    }
+
     pub fn set_cam(&mut self) {
+        if self.follow_walker {
+            self.update_follow_camera();
+        }
+
         let map = &self.gen.map;
         let display_factor = self.get_display_factor(map);
         let x_view = display_factor * map.width as f32;
@@ -255,35 +1193,283 @@ impl Editor {
         self.cam = Some(cam);
     }
 
-    pub fn save_map_dialog(&self) {
-        let cwd = env::current_dir().unwrap();
-        let initial_path = cwd.join("name.map").to_string_lossy().to_string();
+    /// exports the map to the destination of the export settings
+    pub fn export_map(&mut self) {
+        let result = self.export_settings.export(
+            &mut self.gen,
+            &self.gen_config,
+            &self.map_config,
+            self.user_seed.seed_u64,
+        );
+
+        let status = match result {
+            Ok((path, stats)) => format!("exported {} ({})", path.to_string_lossy(), stats),
+            Err(err) => format!("couldn't export map: {}", err),
+        };
+        println!("{}", status);
+        self.export_status = Some(status);
+    }
+
+    pub fn save_map_dialog(&mut self) {
+        let case = RegressionCase {
+            seed: self.user_seed.seed_u64,
+            gen_config: self.gen_config.name.clone(),
+            map_config: self.map_config.name.clone(),
+            hash: 0,
+        };
+        let stem = self.export_settings.file_stem(&case, &current_date());
+        let initial_path = self
+            .export_settings
+            .directory
+            .join(stem + ".map")
+            .to_string_lossy()
+            .to_string();
         if let Some(path_out) = tinyfiledialogs::save_file_dialog("save map", &initial_path) {
-            self.gen.map.export(&PathBuf::from_str(&path_out).unwrap());
+            let path_out = PathBuf::from_str(&path_out).unwrap();
+            match self.gen.export(&path_out, &self.gen_config) {
+                Ok(stats) => {
+                    println!("saved map ({})", stats);
+                    if self.export_settings.export_json {
+                        let provenance = MapProvenance::new(
+                            &self.gen,
+                            &self.gen_config,
+                            &self.map_config,
+                            self.user_seed.seed_u64,
+                            &stats,
+                        );
+                        if let Err(err) = provenance.save(&path_out) {
+                            println!("couldn't save provenance: {}", err);
+                        }
+                    }
+                }
+                Err(err) => println!("couldn't save map: {}", err),
+            }
         }
     }
 
-    pub fn handle_user_inputs(&mut self) {
-        if is_key_pressed(KeyCode::E) {
-            self.save_map_dialog();
+    pub fn select_export_directory_dialog(&mut self) {
+        let directory = self.export_settings.directory.to_string_lossy().to_string();
+        if let Some(directory) =
+            tinyfiledialogs::select_folder_dialog("export directory", &directory)
+        {
+            self.export_settings.directory = PathBuf::from(directory);
+        }
+    }
+
+    pub fn load_map_config_dialog(&mut self) {
+        let cwd = env::current_dir().unwrap();
+        if let Some(path_in) =
+            tinyfiledialogs::open_file_dialog("load map config", &cwd.to_string_lossy(), None)
+        {
+            match MapConfig::load(&path_in) {
+                Ok(map_config) => self.map_config = map_config,
+                Err(err) => println!("couldn't load map config: {}", err),
+            }
+        }
+    }
+
+    /// replaces the current map config with the layout traced from an existing .map file
+    pub fn import_map_layout_dialog(&mut self) {
+        let cwd = env::current_dir().unwrap();
+        if let Some(path_in) =
+            tinyfiledialogs::open_file_dialog("import map layout", &cwd.to_string_lossy(), None)
+        {
+            let path_in = PathBuf::from(path_in);
+            let name = path_in
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or("imported".to_string());
+
+            match TwImport::trace_map_config(&path_in, &name, DEFAULT_WAYPOINT_DIST) {
+                Ok(map_config) => self.map_config = map_config,
+                Err(err) => println!("couldn't import map layout: {}", err),
+            }
+        }
+    }
+
+    /// loads the seed and configs of a .genrec recording, so the generation can be replayed
+    /// step by step
+    pub fn load_recording_dialog(&mut self) {
+        let cwd = env::current_dir().unwrap();
+        let path_in = match tinyfiledialogs::open_file_dialog(
+            "load recording",
+            &cwd.to_string_lossy(),
+            Some((&["*.genrec"], "generation recording")),
+        ) {
+            Some(path_in) => path_in,
+            None => return,
+        };
+
+        let recording = match GenerationRecording::load(&PathBuf::from(path_in)) {
+            Ok(recording) => recording,
+            Err(err) => {
+                self.replay_status = Some(format!("couldn't load recording: {}", err));
+                return;
+            }
+        };
+
+        self.replay_status = Some(match recording.is_same_crate_version() {
+            true => format!("loaded recording of seed {}", recording.seed),
+            false => format!(
+                "loaded recording of seed {}, created by version {} which may generate differently",
+                recording.seed, recording.crate_version
+            ),
+        });
+
+        self.set_setup();
+        self.gen_config = recording.gen_config;
+        self.map_config = recording.map_config;
+        self.user_seed = Seed::from_u64(recording.seed);
+        self.fixed_seed = true;
+        self.record_walker_events = recording.event_log.is_some();
+        self.replay_hash = Some(recording.hash);
+        self.replay_trace = recording.trace;
+    }
+
+    pub fn save_event_log_dialog(&self) {
+        let cwd = env::current_dir().unwrap();
+        let initial_path = cwd.join("trace.json").to_string_lossy().to_string();
+
+        if let Some(event_log) = &self.gen.walker.event_log {
+            if let Some(path_out) =
+                tinyfiledialogs::save_file_dialog("save event log", &initial_path)
+            {
+                let serialized =
+                    serde_json::to_string_pretty(event_log).expect("failed to serialize event log");
+                if let Err(err) = fs::write(&path_out, serialized) {
+                    println!("couldn't save event log: {}", err);
+                }
+            }
+        }
+    }
+
+    /// persists the keymap in the users config directory
+    pub fn save_keymap(&self) {
+        if let Some(path) = Keymap::get_path() {
+            if let Err(err) = self.keymap.save(&path) {
+                println!("couldn't save keymap: {}", err);
+            }
         }
+    }
+
+    /// current editor state that should be restored on the next start
+    pub fn get_session(&self) -> EditorSession {
+        EditorSession {
+            gen_config: self.gen_config.clone(),
+            map_config: self.map_config.clone(),
+            seed_u64: self.user_seed.seed_u64,
+            seed_str: self.user_seed.seed_str.clone(),
+            fixed_seed: self.fixed_seed,
+            recent_seeds: self.recent_seeds.clone(),
+            zoom: self.zoom,
+            offset: (self.offset.x, self.offset.y),
+            window_size: (screen_width(), screen_height()),
+            edit_gen_config: self.edit_gen_config,
+            edit_map_config: self.edit_map_config,
+            ddnet_coordinates: self.ddnet_coordinates,
+            visualize_debug_layers: self
+                .visualize_debug_layers
+                .iter()
+                .map(|(name, visible)| (name.to_string(), *visible))
+                .collect(),
+        }
+    }
+
+    /// restores the state of a previous session, the window size is applied on window creation
+    pub fn restore_session(&mut self, session: EditorSession) {
+        self.gen_config = session.gen_config;
+        self.map_config = session.map_config;
+        self.user_seed = Seed {
+            seed_u64: session.seed_u64,
+            seed_str: session.seed_str,
+        };
+        self.fixed_seed = session.fixed_seed;
+        self.recent_seeds = session.recent_seeds;
+        self.recent_seeds.truncate(MAX_RECENT_SEEDS);
+        self.zoom = session.zoom;
+        self.offset = Vec2::new(session.offset.0, session.offset.1);
+        self.edit_gen_config = session.edit_gen_config;
+        self.edit_map_config = session.edit_map_config;
+        self.ddnet_coordinates = session.ddnet_coordinates;
+
+        // layers that don't exist anymore are ignored
+        for (name, visible) in self.visualize_debug_layers.iter_mut() {
+            if let Some(stored) = session.visualize_debug_layers.get(*name) {
+                *visible = *stored;
+            }
+        }
+    }
+
+    /// persists the editor session in the users config directory
+    pub fn save_session(&self) {
+        if let Some(path) = EditorSession::get_path() {
+            if let Err(err) = self.get_session().save(&path) {
+                println!("couldn't save session: {}", err);
+            }
+        }
+    }
 
-        if is_key_pressed(KeyCode::Space) {
-            self.set_playing();
+    pub fn run_regression_dialog(&mut self) {
+        let cwd = env::current_dir().unwrap();
+        if let Some(path_in) =
+            tinyfiledialogs::open_file_dialog("load regression cases", &cwd.to_string_lossy(), None)
+        {
+            let summary = match regression::load_cases(&path_in) {
+                Ok(cases) => regression::format_results(&regression::run_regression(
+                    &cases,
+                    &self.init_gen_configs,
+                )),
+                Err(err) => err.to_string(),
+            };
+            println!("{}", summary);
+            self.regression_summary = Some(summary);
         }
+    }
+
+    pub fn handle_user_inputs(&mut self) {
+        let egui_wants_keyboard = self
+            .egui_wants_keyboard
+            .expect("expect to be set after define_gui()");
 
-        if is_key_pressed(KeyCode::R) {
-            self.zoom = 1.0;
-            self.offset = Vec2::ZERO;
+        if let Some(action) = self.rebind_action {
+            if let Some(key) = get_last_key_pressed() {
+                if key == KeyCode::Escape {
+                    self.rebind_action = None;
+                } else if is_bindable(key) {
+                    self.keymap.set(action, key);
+                    self.rebind_action = None;
+                    self.save_keymap();
+                }
+            }
+        } else if !egui_wants_keyboard {
+            if self.keymap.is_pressed(EditorAction::ExportMap) {
+                self.export_map();
+            }
+
+            if self.keymap.is_pressed(EditorAction::Generate) {
+                self.set_playing();
+            }
+
+            if self.keymap.is_pressed(EditorAction::ResetCamera) {
+                self.follow_walker = false;
+                self.zoom = 1.0;
+                self.offset = Vec2::ZERO;
+            }
+
+            if self.keymap.is_pressed(EditorAction::FollowWalker) {
+                self.follow_walker = !self.follow_walker;
+            }
         }
 
         // handle mouse inputs
         let mouse_wheel_y = mouse_wheel().1;
         if !mouse_wheel_y.is_zero() {
-            if mouse_wheel_y.is_sign_positive() {
-                self.zoom /= ZOOM_FACTOR;
-            } else {
-                self.zoom *= ZOOM_FACTOR;
+            // the follow camera controls the zoom, so the wheel changes its visible area instead
+            match (self.follow_walker, mouse_wheel_y.is_sign_positive()) {
+                (true, true) => self.follow_zoom *= ZOOM_FACTOR,
+                (true, false) => self.follow_zoom /= ZOOM_FACTOR,
+                (false, true) => self.zoom /= ZOOM_FACTOR,
+                (false, false) => self.zoom *= ZOOM_FACTOR,
             }
         }
 
@@ -291,7 +1477,57 @@ impl Editor {
             .egui_wants_mouse
             .expect("expect to be set after define_gui()");
 
-        if !egui_wants_mouse
+        if self.draw_waypoint_path && self.is_setup() {
+            if !egui_wants_mouse
+                && is_mouse_button_down(MouseButton::Left)
+                && Editor::mouse_in_viewport(self.cam.as_ref().unwrap())
+            {
+                if let Some(pos) = self.mouse_to_map_pos() {
+                    if self.drawn_path.last() != Some(&pos) {
+                        self.drawn_path.push(pos);
+                    }
+                }
+            } else if is_mouse_button_released(MouseButton::Left) && !self.drawn_path.is_empty() {
+                self.apply_drawn_path();
+            }
+        } else if let Some(block_type) = self.brush.clone() {
+            if self.is_setup()
+                && self.instant_job.is_none()
+                && !egui_wants_mouse
+                && is_mouse_button_down(MouseButton::Left)
+                && Editor::mouse_in_viewport(self.cam.as_ref().unwrap())
+            {
+                if let Some(pos) = self.mouse_to_map_pos() {
+                    self.paint(&pos, &block_type);
+                }
+            }
+        } else if self.select_region {
+            if self.is_setup()
+                && self.instant_job.is_none()
+                && !egui_wants_mouse
+                && is_mouse_button_pressed(MouseButton::Left)
+                && Editor::mouse_in_viewport(self.cam.as_ref().unwrap())
+            {
+                self.region_start = self.mouse_to_map_pos();
+            } else if is_mouse_button_released(MouseButton::Left) {
+                if let Some(region) = self.get_pending_region() {
+                    self.region = Some(region);
+                }
+                self.region_start = None;
+            }
+        } else if self.draw_no_go_zones {
+            if !egui_wants_mouse
+                && is_mouse_button_pressed(MouseButton::Left)
+                && Editor::mouse_in_viewport(self.cam.as_ref().unwrap())
+            {
+                self.no_go_start = self.mouse_to_map_pos();
+            } else if is_mouse_button_released(MouseButton::Left) {
+                if let Some(zone) = self.get_pending_no_go_zone() {
+                    self.map_config.no_go_zones.push(zone);
+                }
+                self.no_go_start = None;
+            }
+        } else if !egui_wants_mouse
             && is_mouse_button_down(MouseButton::Left)
             && Editor::mouse_in_viewport(self.cam.as_ref().unwrap())
         {
@@ -301,6 +1537,11 @@ impl Editor {
                 let display_factor = self.get_display_factor(&self.gen.map);
                 let local_delta = Vec2::new(mouse.0, mouse.1) - last_mouse;
                 self.offset += local_delta / (self.zoom * display_factor);
+
+                // panning switches back to the free camera
+                if local_delta != Vec2::ZERO {
+                    self.follow_walker = false;
+                }
             }
 
             self.last_mouse = Some(mouse.into());
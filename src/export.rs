@@ -0,0 +1,392 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{
+    analysis::{analyze_segments, MapStats, SegmentReport},
+    config::{GenerationConfig, MapConfig},
+    generator::Generator,
+    genrec::GenerationRecording,
+    naming::map_file_name,
+    regression::RegressionCase,
+    rendering::export_map_png,
+    twmap_export::ExportStats,
+    validation::{validate_map, Violation},
+};
+
+/// what to do if an exported file already exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverwritePolicy {
+    Overwrite,
+
+    /// dont export anything
+    Skip,
+
+    /// append _1, _2, ... to the file name until it is unused
+    #[default]
+    AddSuffix,
+}
+
+impl OverwritePolicy {
+    pub const ALL: [OverwritePolicy; 3] = [
+        OverwritePolicy::Overwrite,
+        OverwritePolicy::Skip,
+        OverwritePolicy::AddSuffix,
+    ];
+}
+
+/// destination and naming of map exports
+#[derive(Debug, Clone)]
+pub struct ExportSettings {
+    pub directory: PathBuf,
+
+    /// file name without extension, supports {name}, {preset}, {map}, {seed} and {date}
+    pub filename_template: String,
+
+    pub overwrite: OverwritePolicy,
+
+    /// also export a png preview next to the map
+    pub export_png: bool,
+
+    /// also export a json file with the provenance of the map, see MapProvenance
+    pub export_json: bool,
+
+    /// also export a .genrec recording, which allows to replay the generation
+    pub export_genrec: bool,
+}
+
+impl Default for ExportSettings {
+    fn default() -> ExportSettings {
+        ExportSettings {
+            directory: env::current_dir().unwrap_or_default(),
+            filename_template: "{name}".to_string(),
+            overwrite: OverwritePolicy::default(),
+            export_png: false,
+            export_json: false,
+            export_genrec: true,
+        }
+    }
+}
+
+/// current date as yyyy-mm-dd (utc)
+pub fn current_date() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+
+    // convert days since epoch to a civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let days = (secs / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+impl ExportSettings {
+    /// file name (without extension) of an export, characters that are not allowed in file
+    /// names are replaced with underscores
+    pub fn file_stem(&self, case: &RegressionCase, date: &str) -> String {
+        self.filename_template
+            .replace("{name}", &map_file_name(case.seed, &case.gen_config))
+            .replace("{preset}", &case.gen_config)
+            .replace("{map}", &case.map_config)
+            .replace("{seed}", &format!("{:x}", case.seed))
+            .replace("{date}", date)
+            .chars()
+            .map(|c| match c {
+                '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+                c => c,
+            })
+            .collect()
+    }
+
+    fn extensions(&self) -> Vec<&'static str> {
+        let mut extensions = vec!["map"];
+        if self.export_png {
+            extensions.push("png");
+        }
+        if self.export_json {
+            extensions.push("json");
+        }
+        if self.export_genrec {
+            extensions.push("genrec");
+        }
+        extensions
+    }
+
+    /// path of the exported map (all sidecars share its stem), based on the overwrite policy
+    pub fn resolve_map_path(&self, stem: &str) -> Result<PathBuf, &'static str> {
+        let extensions = self.extensions();
+        let is_free = |stem: &str| {
+            extensions
+                .iter()
+                .all(|ext| !self.directory.join(format!("{}.{}", stem, ext)).exists())
+        };
+
+        let stem = match self.overwrite {
+            OverwritePolicy::Overwrite => stem.to_string(),
+            OverwritePolicy::Skip if is_free(stem) => stem.to_string(),
+            OverwritePolicy::Skip => return Err("export file already exists"),
+            OverwritePolicy::AddSuffix => {
+                let mut candidate = stem.to_string();
+                let mut suffix = 1;
+                while !is_free(&candidate) {
+                    candidate = format!("{}_{}", stem, suffix);
+                    suffix += 1;
+                }
+                candidate
+            }
+        };
+
+        Ok(self.directory.join(stem + ".map"))
+    }
+
+    /// exports the map and all enabled sidecars, returns the path and stats of the exported map
+    pub fn export(
+        &self,
+        gen: &mut Generator,
+        gen_config: &GenerationConfig,
+        map_config: &MapConfig,
+        seed: u64,
+    ) -> Result<(PathBuf, ExportStats), &'static str> {
+        let case = RegressionCase {
+            seed,
+            gen_config: gen_config.name.clone(),
+            map_config: map_config.name.clone(),
+            hash: Generator::fingerprint_map(&gen.map),
+        };
+        let map_path = self.resolve_map_path(&self.file_stem(&case, &current_date()))?;
+
+        fs::create_dir_all(&self.directory).map_err(|_| "failed to create export directory")?;
+        let stats = gen.export(&map_path, gen_config)?;
+
+        if self.export_png {
+            export_map_png(&gen.map, &map_path.with_extension("png"));
+        }
+
+        if self.export_json {
+            MapProvenance::new(gen, gen_config, map_config, seed, &stats).save(&map_path)?;
+        }
+
+        if self.export_genrec {
+            GenerationRecording::new(gen, gen_config, map_config, seed)
+                .save(&map_path.with_extension("genrec"))?;
+        }
+
+        Ok((map_path, stats))
+    }
+}
+
+/// summary of a finished generation
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct GenerationReport {
+    /// walker steps until the last waypoint was reached
+    pub steps: usize,
+
+    pub empty_blocks: usize,
+    pub freeze_blocks: usize,
+    pub hookable_blocks: usize,
+    pub platform_blocks: usize,
+
+    /// DDNet constraints that the map violates
+    pub violations: Vec<Violation>,
+
+    pub export_ms: u128,
+
+    /// size of the map file in bytes
+    pub file_size: u64,
+}
+
+/// everything required to trace an exported map back to its generation and regenerate it
+/// exactly, stored as <map>.json next to the map. As it contains all fields of a
+/// RegressionCase, it can also be used as regression file.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct MapProvenance {
+    /// seed, preset names and fingerprint of the map
+    #[serde(flatten)]
+    pub case: RegressionCase,
+
+    /// name of the map, see map_name
+    pub name: String,
+
+    /// crate version that generated the map, other versions may generate different maps
+    pub crate_version: String,
+
+    /// configs as used for the generation, including all overrides of the presets
+    pub resolved_gen_config: GenerationConfig,
+    pub resolved_map_config: MapConfig,
+
+    pub report: GenerationReport,
+
+    /// difficulty estimation of each waypoint segment
+    pub segments: Vec<SegmentReport>,
+}
+
+impl MapProvenance {
+    pub fn new(
+        gen: &Generator,
+        gen_config: &GenerationConfig,
+        map_config: &MapConfig,
+        seed: u64,
+        export_stats: &ExportStats,
+    ) -> MapProvenance {
+        let map_stats = MapStats::new(&gen.map);
+
+        MapProvenance {
+            case: RegressionCase {
+                seed,
+                gen_config: gen_config.name.clone(),
+                map_config: map_config.name.clone(),
+                hash: Generator::fingerprint_map(&gen.map),
+            },
+            name: gen.map.name.clone(),
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            resolved_gen_config: gen_config.clone(),
+            resolved_map_config: map_config.clone(),
+            report: GenerationReport {
+                steps: gen.walker.steps,
+                empty_blocks: map_stats.empty,
+                freeze_blocks: map_stats.freeze,
+                hookable_blocks: map_stats.hookable,
+                platform_blocks: map_stats.platform,
+                violations: validate_map(&gen.map),
+                export_ms: export_stats.duration.as_millis(),
+                file_size: export_stats.file_size,
+            },
+            segments: analyze_segments(
+                &gen.map,
+                &gen.walker.position_history.to_vec(),
+                gen.get_waypoints(),
+                gen_config.waypoint_reached_dist,
+                gen.debug_layers.get("skips").map(|layer| &layer.grid),
+            ),
+        }
+    }
+
+    /// writes the provenance next to the map, using the map path with a .json extension
+    pub fn save(&self, map_path: &Path) -> Result<(), &'static str> {
+        let data =
+            serde_json::to_string_pretty(self).map_err(|_| "failed to serialize provenance")?;
+        fs::write(map_path.with_extension("json"), data).map_err(|_| "failed to write provenance")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn case() -> RegressionCase {
+        RegressionCase {
+            seed: 0xbeef,
+            gen_config: "hardV2".to_string(),
+            map_config: "small/s".to_string(),
+            hash: 0,
+        }
+    }
+
+    /// settings that export into an empty directory, which is unique per test
+    fn test_settings(test: &str, overwrite: OverwritePolicy) -> ExportSettings {
+        let directory = env::temp_dir().join(format!(
+            "gores_mapgen_export_{}_{}",
+            test,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+
+        ExportSettings {
+            directory,
+            overwrite,
+            ..ExportSettings::default()
+        }
+    }
+
+    #[test]
+    fn file_stem_fills_template() {
+        let mut settings = ExportSettings {
+            filename_template: "{preset}-{map}-{seed}-{date}".to_string(),
+            ..ExportSettings::default()
+        };
+        assert_eq!(
+            settings.file_stem(&case(), "2024-01-31"),
+            "hardV2-small_s-beef-2024-01-31"
+        );
+
+        settings.filename_template = "{name}".to_string();
+        assert_eq!(
+            settings.file_stem(&case(), "2024-01-31"),
+            map_file_name(0xbeef, "hardV2")
+        );
+
+        settings.filename_template = "a:b*c?d\"e<f>g|h\\i".to_string();
+        assert_eq!(settings.file_stem(&case(), ""), "a_b_c_d_e_f_g_h_i");
+    }
+
+    #[test]
+    fn resolve_map_path_with_free_name() {
+        for (test, policy) in [
+            ("free_overwrite", OverwritePolicy::Overwrite),
+            ("free_skip", OverwritePolicy::Skip),
+            ("free_suffix", OverwritePolicy::AddSuffix),
+        ] {
+            let settings = test_settings(test, policy);
+            assert_eq!(
+                settings.resolve_map_path("map"),
+                Ok(settings.directory.join("map.map"))
+            );
+            fs::remove_dir_all(&settings.directory).unwrap();
+        }
+    }
+
+    #[test]
+    fn resolve_map_path_with_existing_files() {
+        let settings = test_settings("existing_overwrite", OverwritePolicy::Overwrite);
+        fs::write(settings.directory.join("map.map"), "").unwrap();
+        assert_eq!(
+            settings.resolve_map_path("map"),
+            Ok(settings.directory.join("map.map"))
+        );
+        fs::remove_dir_all(&settings.directory).unwrap();
+
+        let settings = test_settings("existing_skip", OverwritePolicy::Skip);
+        fs::write(settings.directory.join("map.map"), "").unwrap();
+        assert_eq!(
+            settings.resolve_map_path("map"),
+            Err("export file already exists")
+        );
+        fs::remove_dir_all(&settings.directory).unwrap();
+
+        // sidecars also block a name, as long as they are exported
+        let mut settings = test_settings("existing_suffix", OverwritePolicy::AddSuffix);
+        fs::write(settings.directory.join("map.map"), "").unwrap();
+        fs::write(settings.directory.join("map_1.genrec"), "").unwrap();
+        fs::write(settings.directory.join("map_2.png"), "").unwrap();
+        assert_eq!(
+            settings.resolve_map_path("map"),
+            Ok(settings.directory.join("map_2.map"))
+        );
+        settings.export_png = true;
+        assert_eq!(
+            settings.resolve_map_path("map"),
+            Ok(settings.directory.join("map_3.map"))
+        );
+        fs::remove_dir_all(&settings.directory).unwrap();
+    }
+}
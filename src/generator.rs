@@ -1,23 +1,228 @@
-use std::collections::HashMap;
+use log::warn;
+use ndarray::Array2;
+use rayon::prelude::*;
+use std::collections::{HashMap, VecDeque};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
 use timing::Timer;
 
 use crate::{
-    config::{GenerationConfig, MapConfig},
+    analysis::analyze_segments,
+    config::{
+        downscale_kernel_margin, downscale_kernel_size, DifficultyCurve, FinishRoom, FreezeStyle,
+        GenerationConfig, GoalSelection, MapConfig, TruncationMode,
+    },
     debug::DebugLayer,
     kernel::Kernel,
-    map::{BlockType, Map, Overwrite},
-    position::Position,
+    map::{BlockType, Map, Marker, Overwrite},
+    metrics::MapMetrics,
+    naming::map_name,
+    pathfinding::route_waypoints,
+    position::{Position, ShiftDirection},
     post_processing::{self as post, get_flood_fill},
     random::{Random, Seed},
+    twmap_export::ExportStats,
     walker::CuteWalker,
 };
 
 use macroquad::color::{colors, Color};
 
+/// error of a generation that didn't finish within its max steps, see TruncationMode
+pub const MAP_TRUNCATED: &str = "map truncated, walker didn't finish within max steps";
+
 pub fn print_time(timer: &Timer, message: &str) {
     println!("{}: {:?}", message, timer.elapsed());
 }
 
+/// stages of the post processing, in the order they are performed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostProcessingStage {
+    Lock,
+    FixEdgeBugs,
+    SmoothWalls,
+    CornerHolds,
+    Rooms,
+    FreezeBlobs,
+    Platforms,
+    Skips,
+    CeilingLips,
+    FreezePockets,
+    FillOpenAreas,
+    Checkpoints,
+    Doodads,
+    Border,
+}
+
+impl PostProcessingStage {
+    pub const ALL: [PostProcessingStage; 14] = [
+        PostProcessingStage::Lock,
+        PostProcessingStage::FixEdgeBugs,
+        PostProcessingStage::SmoothWalls,
+        PostProcessingStage::CornerHolds,
+        PostProcessingStage::Rooms,
+        PostProcessingStage::FreezeBlobs,
+        PostProcessingStage::Platforms,
+        PostProcessingStage::Skips,
+        PostProcessingStage::CeilingLips,
+        PostProcessingStage::FreezePockets,
+        PostProcessingStage::FillOpenAreas,
+        PostProcessingStage::Checkpoints,
+        PostProcessingStage::Doodads,
+        PostProcessingStage::Border,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PostProcessingStage::Lock => "lock",
+            PostProcessingStage::FixEdgeBugs => "fix edge bugs",
+            PostProcessingStage::SmoothWalls => "smooth walls",
+            PostProcessingStage::CornerHolds => "fix corner holds",
+            PostProcessingStage::Rooms => "place rooms",
+            PostProcessingStage::FreezeBlobs => "detect blobs",
+            PostProcessingStage::Platforms => "platforms",
+            PostProcessingStage::Skips => "generate skips",
+            PostProcessingStage::CeilingLips => "normalize ceiling lips",
+            PostProcessingStage::FreezePockets => "carve freeze pockets",
+            PostProcessingStage::FillOpenAreas => "place obstacles",
+            PostProcessingStage::Checkpoints => "place checkpoints",
+            PostProcessingStage::Doodads => "place doodads",
+            PostProcessingStage::Border => "repair border",
+        }
+    }
+
+    /// whether this stage is performed for drafts, which skip all detail stages
+    pub fn is_draft_stage(&self) -> bool {
+        matches!(
+            self,
+            PostProcessingStage::Lock
+                | PostProcessingStage::FixEdgeBugs
+                | PostProcessingStage::Rooms
+                | PostProcessingStage::Border
+        )
+    }
+
+    /// debug layer that visualizes the result of this stage
+    pub fn debug_layer(&self) -> Option<&'static str> {
+        match self {
+            PostProcessingStage::Lock => Some("lock"),
+            PostProcessingStage::FixEdgeBugs => Some("edge_bugs"),
+            PostProcessingStage::SmoothWalls => Some("smoothed_walls"),
+            PostProcessingStage::CornerHolds => Some("corner_holds"),
+            PostProcessingStage::FreezeBlobs => Some("blobs"),
+            PostProcessingStage::Platforms => Some("platforms"),
+            PostProcessingStage::Skips => Some("skips"),
+            PostProcessingStage::CeilingLips => Some("ceiling_lips"),
+            PostProcessingStage::FreezePockets => Some("freeze_pockets"),
+            PostProcessingStage::Checkpoints => Some("checkpoints"),
+            PostProcessingStage::Border => Some("border_repairs"),
+            _ => None,
+        }
+    }
+}
+
+/// stages of the whole generation pipeline, which hooks can be registered for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenerationStage {
+    /// the walker carving the map, until it reaches the last waypoint
+    Walking,
+
+    PostProcessing(PostProcessingStage),
+
+    /// writing the map file, see Generator::export
+    Export,
+}
+
+/// whether a hook runs before or after its stage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookTiming {
+    Before,
+    After,
+}
+
+/// custom pass that is performed as part of a generation stage. A failing hook fails the stage.
+pub type StageHook =
+    Box<dyn FnMut(&mut Generator, &GenerationConfig) -> Result<(), &'static str> + Send>;
+
+/// seed derived decisions that shape the overall layout of a map. A draft makes the same
+/// decisions as the full generation of the same seed, scaled to the draft resolution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MacroDecisions {
+    /// waypoints of the map config in the order they are visited
+    pub waypoints: Vec<Position>,
+
+    /// mutated and routed subwaypoints that the walker follows
+    pub subwaypoints: Vec<Position>,
+
+    /// initial (inner, outer) kernel size of the walker
+    pub kernel_sizes: (usize, usize),
+
+    /// room sizes of the map config, which override the sizes of the generation config
+    pub start_room_size: Option<usize>,
+    pub finish_room_size: Option<usize>,
+}
+
+impl MacroDecisions {
+    /// draws all macro decisions, these are the first draws of every generation
+    pub fn new(
+        gen_config: &GenerationConfig,
+        map_config: &MapConfig,
+        map: &Map,
+        rnd: &mut Random,
+    ) -> MacroDecisions {
+        let waypoints = Generator::order_waypoints(&map_config.waypoints, gen_config, rnd);
+        let subwaypoints = Generator::generate_sub_waypoints(&waypoints, &gen_config, rnd)
+            .unwrap_or(waypoints.clone()); // on failure just use initial waypoints
+        let subwaypoints = route_waypoints(&subwaypoints, map);
+
+        let inner_kernel_size = rnd.sample_inner_kernel_size();
+        let outer_kernel_size = inner_kernel_size + rnd.sample_outer_kernel_margin();
+
+        MacroDecisions {
+            waypoints,
+            subwaypoints,
+            kernel_sizes: (inner_kernel_size, outer_kernel_size),
+            start_room_size: map_config.start_room_size,
+            finish_room_size: map_config.finish_room_size,
+        }
+    }
+
+    /// scales all positions and kernel sizes down by the given factor
+    pub fn downscaled(&self, scale: usize) -> MacroDecisions {
+        let (inner_size, outer_size) = self.kernel_sizes;
+        let downscale = |positions: &[Position]| {
+            positions
+                .iter()
+                .map(|pos| Position::new(pos.x / scale, pos.y / scale))
+                .collect()
+        };
+
+        MacroDecisions {
+            waypoints: downscale(&self.waypoints),
+            subwaypoints: downscale(&self.subwaypoints),
+            kernel_sizes: (
+                downscale_kernel_size(inner_size, scale),
+                downscale_kernel_size(inner_size, scale)
+                    + downscale_kernel_margin(outer_size - inner_size, scale),
+            ),
+            // like the generation config, drafts keep the room sizes
+            ..self.clone()
+        }
+    }
+}
+
+/// max amount of snapshots that are kept for undoing walker steps
+const MAX_SNAPSHOTS: usize = 50;
+
+/// walking state of a generator, see Generator::undo_steps
+#[derive(Clone)]
+struct GeneratorSnapshot {
+    walker: CuteWalker,
+    map: Map,
+    rnd: Random,
+    rest_waypoints: Vec<Position>,
+    segment_index: usize,
+}
+
 pub struct Generator {
     pub walker: CuteWalker,
     pub map: Map,
@@ -26,8 +231,73 @@ pub struct Generator {
     /// PRNG wrapper
     pub rnd: Random,
 
+    /// copy the lock grid into the lock debug layer after every walker step, only required to
+    /// watch the generation. Otherwise the layer is only updated once the walker is done.
+    pub live_lock_layer: bool,
+
     /// remember where generation began, so a start room can be placed in post processing
     spawn: Position,
+
+    /// intermediate waypoints that still require a rest room
+    rest_waypoints: Vec<Position>,
+
+    /// decisions made at the start of the generation, the waypoints are used to determine the
+    /// current waypoint segment
+    macro_decisions: MacroDecisions,
+
+    /// whether this is a low detail draft, see Generator::new_draft
+    draft: bool,
+
+    /// index of the waypoint at the end of the current segment
+    segment_index: usize,
+
+    /// flood fill from the spawn, kept between the platform and skip stage of post processing
+    flood_fill: Option<Array2<Option<usize>>>,
+
+    /// custom passes registered by library users, in the order they were added
+    hooks: Vec<(GenerationStage, HookTiming, StageHook)>,
+
+    /// walker steps between two snapshots for undoing steps, 0 disables snapshots
+    pub snapshot_interval: usize,
+
+    /// latest snapshots of the walking state, oldest first, see Generator::undo_steps
+    snapshots: VecDeque<GeneratorSnapshot>,
+}
+
+/// seeded variation of a room on top of its configured size
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RoomShape {
+    /// blocks by which the room is extended on the left and right
+    pub extra_width: usize,
+
+    /// blocks by which the room is extended on the top and bottom
+    pub extra_height: usize,
+
+    /// blocks by which the interior platform is moved up
+    pub platform_shift: usize,
+
+    /// hang two pillars from the ceiling
+    pub pillars: bool,
+}
+
+impl RoomShape {
+    /// samples a room shape within the bounds of the config. Doesn't consume any random values
+    /// if room variation is disabled, so existing seeds keep their layout.
+    pub fn sample(rnd: &mut Random, config: &GenerationConfig) -> RoomShape {
+        if config.room_stretch_max == 0
+            && config.room_platform_shift_max == 0
+            && config.room_pillar_prob == 0.0
+        {
+            return RoomShape::default();
+        }
+
+        RoomShape {
+            extra_width: rnd.in_range_inclusive(0, config.room_stretch_max),
+            extra_height: rnd.in_range_inclusive(0, config.room_stretch_max),
+            platform_shift: rnd.in_range_inclusive(0, config.room_platform_shift_max),
+            pillars: rnd.with_probability(config.room_pillar_prob),
+        }
+    }
 }
 
 pub fn generate_room(
@@ -35,58 +305,99 @@ pub fn generate_room(
     pos: &Position,
     room_size: usize,
     platform_margin: usize,
-    zone_type: Option<&BlockType>,
+    opening: Option<ShiftDirection>,
+    zone_type: Option<Marker>,
+    shape: &RoomShape,
 ) -> Result<(), &'static str> {
     let room_size: i32 = room_size as i32;
     let platform_margin: i32 = platform_margin as i32;
 
-    if !map.pos_in_bounds(&pos.shifted_by(room_size + 2, room_size + 1).unwrap())
-        || !map.pos_in_bounds(&pos.shifted_by(room_size + 1, room_size + 1).unwrap())
+    // half extents of the room
+    let width = room_size + shape.extra_width as i32;
+    let height = room_size + shape.extra_height as i32;
+
+    if !map.pos_in_bounds(&pos.shifted_by(width + 2, height + 1)?)
+        || !map.pos_in_bounds(&pos.shifted_by(width + 1, height + 1)?)
     {
         return Err("generate room out of bounds");
     }
 
+    // tunnel through the room wall on the opening side, reaching past the start/finish line
+    let opening_width = (room_size / 2).max(1);
+    let opening_area = match opening {
+        Some(ShiftDirection::Up) => Some((
+            pos.shifted_by(-opening_width, -(2 * height + 1))?,
+            pos.shifted_by(opening_width, -height)?,
+        )),
+        Some(ShiftDirection::Right) => Some((
+            pos.shifted_by(width, -opening_width)?,
+            pos.shifted_by(2 * width + 1, opening_width)?,
+        )),
+        Some(ShiftDirection::Down) => Some((
+            pos.shifted_by(-opening_width, height)?,
+            pos.shifted_by(opening_width, 2 * height + 1)?,
+        )),
+        Some(ShiftDirection::Left) => Some((
+            pos.shifted_by(-(2 * width + 1), -opening_width)?,
+            pos.shifted_by(-width, opening_width)?,
+        )),
+        None => None,
+    };
+
+    if let Some((top_left, bot_right)) = opening_area {
+        if !map.pos_in_bounds(&top_left) || !map.pos_in_bounds(&bot_right) {
+            return Err("room opening out of bounds");
+        }
+
+        map.set_area(
+            &top_left,
+            &bot_right,
+            &BlockType::Empty,
+            &Overwrite::ReplaceSolidFreeze,
+        );
+    }
+
     // carve room
     map.set_area_border(
-        &pos.shifted_by(-room_size, -room_size)?,
-        &pos.shifted_by(room_size, room_size)?,
+        &pos.shifted_by(-width, -height)?,
+        &pos.shifted_by(width, height)?,
         &BlockType::Empty,
         &Overwrite::Force,
     );
 
     // only reserve - 1 so that when this is used for platforms
-    map.set_area(
-        &pos.shifted_by(-room_size + 1, -room_size + 1)?,
-        &pos.shifted_by(room_size - 1, room_size - 1)?,
-        &BlockType::EmptyReserved,
+    map.set_marker_area(
+        &pos.shifted_by(-width + 1, -height + 1)?,
+        &pos.shifted_by(width - 1, height - 1)?,
+        Marker::Reserved,
         &Overwrite::Force,
     );
 
     // set start/finish line
     if let Some(zone_type) = zone_type {
-        map.set_area_border(
-            &pos.shifted_by(-room_size - 1, -room_size - 1)?,
-            &pos.shifted_by(room_size + 1, room_size + 1)?,
+        map.set_marker_area_border(
+            &pos.shifted_by(-width - 1, -height - 1)?,
+            &pos.shifted_by(width + 1, height + 1)?,
             zone_type,
             &Overwrite::ReplaceNonSolidForce,
         );
     }
 
     // set spawns
-    if zone_type == Some(&BlockType::Start) {
-        map.set_area(
-            &pos.shifted_by(-(room_size - platform_margin), room_size - 1)?,
-            &pos.shifted_by(room_size - platform_margin, room_size - 1)?,
-            &BlockType::Spawn,
+    if zone_type == Some(Marker::Start) {
+        map.set_marker_area(
+            &pos.shifted_by(-(width - platform_margin), height - 1)?,
+            &pos.shifted_by(width - platform_margin, height - 1)?,
+            Marker::Spawn,
             &Overwrite::Force,
         );
     }
 
     // set platform below spawns
-    if zone_type == Some(&BlockType::Start) {
+    if zone_type == Some(Marker::Start) {
         map.set_area(
-            &pos.shifted_by(-(room_size - platform_margin), room_size + 1)?,
-            &pos.shifted_by(room_size - platform_margin, room_size + 1)?,
+            &pos.shifted_by(-(width - platform_margin), height + 1)?,
+            &pos.shifted_by(width - platform_margin, height + 1)?,
             &BlockType::Platform,
             &Overwrite::Force,
         );
@@ -94,38 +405,210 @@ pub fn generate_room(
 
     // for non start/finish rooms -> place center platform
     if zone_type.is_none() {
+        // the platform may be moved up to right below the ceiling
+        let platform_shift = i32::min(shape.platform_shift as i32, 2 * height - 4);
+        let platform_y = height - 3 - platform_shift.max(0);
         map.set_area(
-            &pos.shifted_by(-(room_size - platform_margin), room_size - 3)?,
-            &pos.shifted_by(room_size - platform_margin, room_size - 3)?,
+            &pos.shifted_by(-(width - platform_margin), platform_y)?,
+            &pos.shifted_by(width - platform_margin, platform_y)?,
             &BlockType::Platform,
             &Overwrite::Force,
         );
     }
 
+    // pillars hanging from the ceiling, halfway between the center and the side walls
+    if shape.pillars && width >= 4 {
+        for pillar_x in [-width / 2, width / 2] {
+            map.set_area(
+                &pos.shifted_by(pillar_x, -height)?,
+                &pos.shifted_by(pillar_x, -height + height / 2)?,
+                &BlockType::Platform,
+                &Overwrite::Force,
+            );
+        }
+    }
+
+    // later walker passes may not carve into the finished room, including its start/finish line
+    let protected_offset = match zone_type {
+        Some(_) => 1,
+        None => 0,
+    };
+    map.protect_area(
+        &pos.shifted_by(-width - protected_offset, -height - protected_offset)?,
+        &pos.shifted_by(width + protected_offset, height + protected_offset)?,
+    );
+
     Ok(())
 }
 
+/// carves the finish room in the given layout. The finish line is placed around the whole room,
+/// so it can't be skipped no matter from which side the room is entered. The wide hall layout
+/// ignores the room shape.
+pub fn generate_finish_room(
+    map: &mut Map,
+    pos: &Position,
+    room_size: usize,
+    layout: FinishRoom,
+    shape: &RoomShape,
+) -> Result<(), &'static str> {
+    let size: i32 = room_size as i32;
+
+    // offset of the room floor wrt. its center
+    let floor = size + shape.extra_height as i32;
+
+    match layout {
+        FinishRoom::Generic | FinishRoom::Random => {
+            generate_room(map, pos, room_size, 3, None, Some(Marker::Finish), shape)
+        }
+        FinishRoom::TrophyPlatform => {
+            generate_room(map, pos, room_size, 3, None, Some(Marker::Finish), shape)?;
+
+            // pedestal with a small trophy on top
+            map.set_area(
+                &pos.shifted_by(-(size / 2), floor - 1)?,
+                &pos.shifted_by(size / 2, floor - 1)?,
+                &BlockType::Platform,
+                &Overwrite::Force,
+            );
+            map.set_area(
+                &pos.shifted_by(0, floor - 3)?,
+                &pos.shifted_by(0, floor - 2)?,
+                &BlockType::Platform,
+                &Overwrite::Force,
+            );
+
+            Ok(())
+        }
+        FinishRoom::DropInPit => {
+            let pit_top_left = pos.shifted_by(-(size / 2), floor)?;
+            let pit_bot_right = pos.shifted_by(size / 2, floor + 2 * size)?;
+            if !map.pos_in_bounds(&pit_bot_right) {
+                return Err("finish pit out of bounds");
+            }
+
+            // carve pit first, so the finish line is also placed where it leaves the room
+            map.set_marker_area(
+                &pit_top_left,
+                &pit_bot_right,
+                Marker::Reserved,
+                &Overwrite::Force,
+            );
+            map.set_area(
+                &pit_top_left.shifted_by(0, 2 * size)?,
+                &pit_bot_right,
+                &BlockType::Freeze,
+                &Overwrite::Force,
+            );
+
+            generate_room(map, pos, room_size, 3, None, Some(Marker::Finish), shape)
+        }
+        FinishRoom::WideHall => {
+            let top_left = pos.shifted_by(-2 * size, -size)?;
+            let bot_right = pos.shifted_by(2 * size, size)?;
+            if !map.pos_in_bounds(&top_left.shifted_by(-1, -1)?)
+                || !map.pos_in_bounds(&bot_right.shifted_by(1, 1)?)
+            {
+                return Err("finish hall out of bounds");
+            }
+
+            map.set_area_border(&top_left, &bot_right, &BlockType::Empty, &Overwrite::Force);
+            map.set_marker_area(
+                &top_left.shifted_by(1, 1)?,
+                &bot_right.shifted_by(-1, -1)?,
+                Marker::Reserved,
+                &Overwrite::Force,
+            );
+            map.set_marker_area_border(
+                &top_left.shifted_by(-1, -1)?,
+                &bot_right.shifted_by(1, 1)?,
+                Marker::Finish,
+                &Overwrite::ReplaceNonSolidForce,
+            );
+
+            Ok(())
+        }
+    }
+}
+
 impl Generator {
+    fn create_map(gen_config: &GenerationConfig, map_config: &MapConfig) -> Map {
+        let mut map = Map::new(map_config.width, map_config.height, BlockType::Hookable);
+        for zone in map_config.no_go_zones.iter() {
+            map.add_no_go_zone(&zone.top_left, &zone.bot_right);
+        }
+        if gen_config.border_thickness > 0 {
+            map.add_border(gen_config.border_thickness);
+        }
+        map
+    }
+
     /// derive an initial generator state based on a GenerationConfig
     pub fn new(gen_config: &GenerationConfig, map_config: &MapConfig, seed: Seed) -> Generator {
-        let map = Map::new(map_config.width, map_config.height, BlockType::Hookable);
-        let spawn = map_config.waypoints.get(0).unwrap().clone();
-        let mut rnd = Random::new(seed, gen_config);
+        Generator::with_random(gen_config, map_config, Random::new(seed, gen_config))
+    }
 
-        let subwaypoints =
-            Generator::generate_sub_waypoints(&map_config.waypoints, &gen_config, &mut rnd)
-                .unwrap_or(map_config.waypoints.clone()); // on failure just use initial waypoints
+    /// like Generator::new, but draws from the given rng, e.g. to record or replay a
+    /// GenerationTrace
+    pub fn with_random(
+        gen_config: &GenerationConfig,
+        map_config: &MapConfig,
+        mut rnd: Random,
+    ) -> Generator {
+        let mut map = Generator::create_map(gen_config, map_config);
+        map.name = map_name(rnd.seed.seed_u64, &gen_config.name);
+        let macro_decisions = MacroDecisions::new(gen_config, map_config, &map, &mut rnd);
+
+        Generator::from_macro_decisions(map, rnd, macro_decisions, false)
+    }
+
+    /// Derive a low detail draft, generated on a grid that is downscaled by the given factor.
+    /// The draft uses the same macro decisions as the full generation of the same seed (which
+    /// are scaled down) and only performs the draft stages of the post processing. This is
+    /// meant for fast previews, the draft map itself is not seed compatible to the full map.
+    /// Steps have to use the config returned by GenerationConfig::downscaled.
+    pub fn new_draft(
+        gen_config: &GenerationConfig,
+        map_config: &MapConfig,
+        seed: Seed,
+        scale: usize,
+    ) -> Generator {
+        let scale = scale.max(1);
+        let draft_config = gen_config.downscaled(scale);
+
+        // the draft config only scales the sampled values, so the same seed results in the same
+        // draws. Routing uses the full map, so positions match the full generation.
+        let full_map = Generator::create_map(gen_config, map_config);
+        let mut rnd = Random::new(seed, &draft_config);
+        let macro_decisions = MacroDecisions::new(gen_config, map_config, &full_map, &mut rnd);
+
+        let mut map = Generator::create_map(&draft_config, &map_config.downscaled(scale));
+        map.name = map_name(rnd.seed.seed_u64, &gen_config.name);
+        // kernel sizes are already drawn from the draft config
+        let macro_decisions = MacroDecisions {
+            kernel_sizes: macro_decisions.kernel_sizes,
+            ..macro_decisions.downscaled(scale)
+        };
+        Generator::from_macro_decisions(map, rnd, macro_decisions, true)
+    }
+
+    fn from_macro_decisions(
+        mut map: Map,
+        rnd: Random,
+        macro_decisions: MacroDecisions,
+        draft: bool,
+    ) -> Generator {
+        map.decoration_seed = rnd.seed.decoration_seed();
+        let spawn = macro_decisions.waypoints.first().unwrap().clone();
 
         // initialize walker
-        let inner_kernel_size = rnd.sample_inner_kernel_size();
-        let outer_kernel_size = inner_kernel_size + rnd.sample_outer_kernel_margin();
+        let (inner_kernel_size, outer_kernel_size) = macro_decisions.kernel_sizes;
         let inner_kernel = Kernel::new(inner_kernel_size, 0.0);
         let outer_kernel = Kernel::new(outer_kernel_size, 0.0);
         let walker = CuteWalker::new(
             spawn.clone(),
             inner_kernel,
             outer_kernel,
-            subwaypoints,
+            macro_decisions.subwaypoints.clone(),
             &map,
         );
 
@@ -141,6 +624,18 @@ impl Generator {
             ("skips", DebugLayer::new(true, colors::GREEN, &map)),
             ("skips_invalid", DebugLayer::new(true, colors::RED, &map)),
             ("blobs", DebugLayer::new(false, colors::RED, &map)),
+            ("ceiling_lips", DebugLayer::new(true, colors::SKYBLUE, &map)),
+            ("freeze_pockets", DebugLayer::new(true, colors::LIME, &map)),
+            ("checkpoints", DebugLayer::new(true, colors::GOLD, &map)),
+            (
+                "smoothed_walls",
+                DebugLayer::new(true, colors::YELLOW, &map),
+            ),
+            ("corner_holds", DebugLayer::new(true, colors::VIOLET, &map)),
+            (
+                "border_repairs",
+                DebugLayer::new(true, colors::MAGENTA, &map),
+            ),
             (
                 "lock",
                 DebugLayer::new(false, Color::new(1.0, 0.2, 0.2, 0.3), &map),
@@ -161,29 +656,112 @@ impl Generator {
                 "platforms_walker_pos",
                 DebugLayer::new(false, Color::new(0.7, 0.7, 0.0, 0.8), &map),
             ),
+            (
+                "platforms_unreachable",
+                DebugLayer::new(false, Color::new(1.0, 0.0, 1.0, 0.8), &map),
+            ),
         ]);
 
+        // every waypoint except the first (start) and last (finish) gets a rest room
+        let waypoints = &macro_decisions.waypoints;
+        let rest_waypoints = match waypoints.len() {
+            0..=2 => Vec::new(),
+            len => waypoints[1..len - 1].to_vec(),
+        };
+
         Generator {
             walker,
             map,
             rnd,
             debug_layers,
             spawn,
+            rest_waypoints,
+            macro_decisions,
+            draft,
+            segment_index: 1,
+            flood_fill: None,
+            live_lock_layer: false,
+            hooks: Vec::new(),
+            snapshot_interval: 0,
+            snapshots: VecDeque::new(),
         }
     }
 
+    /// registers a custom pass that runs before or after the given stage. Hooks of the same
+    /// stage and timing run in the order they were added.
+    pub fn add_hook(&mut self, stage: GenerationStage, timing: HookTiming, hook: StageHook) {
+        self.hooks.push((stage, timing, hook));
+    }
+
+    fn run_hooks(
+        &mut self,
+        stage: GenerationStage,
+        timing: HookTiming,
+        config: &GenerationConfig,
+    ) -> Result<(), &'static str> {
+        if self.hooks.is_empty() {
+            return Ok(());
+        }
+
+        // hooks get mutable access to the generator, so they are taken out while running
+        let mut hooks = std::mem::take(&mut self.hooks);
+        let result = hooks
+            .iter_mut()
+            .filter(|(hook_stage, hook_timing, _)| *hook_stage == stage && *hook_timing == timing)
+            .try_for_each(|(_, _, hook)| hook(self, config));
+
+        // keep hooks that were registered by hooks
+        hooks.append(&mut self.hooks);
+        self.hooks = hooks;
+
+        result
+    }
+
+    /// exports the map, running the export hooks around it
+    pub fn export(
+        &mut self,
+        path: &PathBuf,
+        config: &GenerationConfig,
+    ) -> Result<ExportStats, &'static str> {
+        self.run_hooks(GenerationStage::Export, HookTiming::Before, config)?;
+        let stats = self.map.export(path, config.get_theme().as_ref());
+        self.run_hooks(GenerationStage::Export, HookTiming::After, config)?;
+
+        Ok(stats)
+    }
+
     /// perform one step of the map generation
     pub fn step(&mut self, config: &GenerationConfig) -> Result<(), &'static str> {
+        let was_finished = self.walker.finished;
+        if !was_finished {
+            self.record_snapshot();
+        }
+        if !was_finished && self.walker.position_history.is_empty() {
+            self.run_hooks(GenerationStage::Walking, HookTiming::Before, config)?;
+        }
+
         // check if walker has reached goal position
         if self.walker.is_goal_reached(&config.waypoint_reached_dist) == Some(true) {
             self.walker.next_waypoint();
         }
 
         if !self.walker.finished {
-            config.validate()?; // TODO: how much does this slow down generation?
+            // TODO: how much does this slow down generation?
+            config.validate().map_err(|_| "invalid generation config")?;
+
+            if config.difficulty_curve != DifficultyCurve::Constant {
+                self.walker.difficulty = config.get_difficulty(self.get_map_progress());
+            }
 
             // randomly mutate kernel
-            if self.walker.steps > config.fade_steps {
+            if self.walker.steps > config.fade_steps && config.enable_segment_fade {
+                let progress = self.get_segment_progress(config);
+                self.walker.set_segment_fade_kernel(
+                    progress,
+                    config.segment_fade_min_size,
+                    config.segment_fade_max_size,
+                );
+            } else if self.walker.steps > config.fade_steps {
                 self.walker.mutate_kernel(config, &mut self.rnd);
             } else {
                 self.walker.set_fade_kernel(
@@ -195,12 +773,29 @@ impl Generator {
             }
 
             // perform one step
-            self.walker
-                .probabilistic_step(&mut self.map, config, &mut self.rnd)?;
+            self.walker.probabilistic_step(
+                &mut self.map,
+                config,
+                &mut self.rnd,
+                config.step_strategy.get_strategy(),
+            )?;
+
+            // carve rest room once the walker reaches the next intermediate waypoint
+            if config.enable_rest_rooms {
+                self.place_rest_room(config);
+            }
+
+            // finish early if the walker ran out of budget, finish room is placed at its position
+            if self.walker.is_budget_exceeded(config) {
+                self.walker.finish();
+            }
 
             // TODO: very imperformant clone here, REVERT REVERT
             // fuck i want to call this in post procesing aswell -> move to map/generator
-            self.debug_layers.get_mut("lock").unwrap().grid = self.walker.locked_positions.clone();
+            if self.live_lock_layer {
+                self.debug_layers.get_mut("lock").unwrap().grid =
+                    self.walker.locked_positions.clone();
+            }
 
             // handle platforms TODO: remove once post processing is implemented
             // self.walker.check_platform(
@@ -210,9 +805,222 @@ impl Generator {
             // )?;
         }
 
+        if !was_finished && self.walker.finished {
+            self.run_hooks(GenerationStage::Walking, HookTiming::After, config)?;
+        }
+
         Ok(())
     }
 
+    /// stores the walking state every snapshot_interval steps, dropping the oldest snapshot if
+    /// there are more than MAX_SNAPSHOTS
+    fn record_snapshot(&mut self) {
+        let steps = self.walker.steps;
+        if self.snapshot_interval == 0
+            || !steps.is_multiple_of(self.snapshot_interval)
+            || self
+                .snapshots
+                .back()
+                .is_some_and(|snapshot| snapshot.walker.steps == steps)
+        {
+            return;
+        }
+
+        if self.snapshots.len() >= MAX_SNAPSHOTS {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(GeneratorSnapshot {
+            walker: self.walker.clone(),
+            map: self.map.clone(),
+            rnd: self.rnd.clone(),
+            rest_waypoints: self.rest_waypoints.clone(),
+            segment_index: self.segment_index,
+        });
+    }
+
+    /// Reverts the walker by the given amount of steps, e.g. to inspect why it got stuck. The
+    /// newest snapshot before the target step is restored and then stepped forward, so the
+    /// generator ends up exactly as if it was stopped at the target step. Stepping forward again
+    /// redoes the undone steps. Fails without any changes, if no snapshot is old enough.
+    pub fn undo_steps(
+        &mut self,
+        steps: usize,
+        config: &GenerationConfig,
+    ) -> Result<(), &'static str> {
+        let target = self.walker.steps.saturating_sub(steps);
+        let index = self
+            .snapshots
+            .iter()
+            .rposition(|snapshot| snapshot.walker.steps <= target)
+            .ok_or("no snapshot old enough")?;
+
+        // newer snapshots are recorded again while stepping forward
+        self.snapshots.truncate(index + 1);
+        let snapshot = self.snapshots[index].clone();
+        self.walker = snapshot.walker;
+        self.map = snapshot.map;
+        self.rnd = snapshot.rnd;
+        self.rest_waypoints = snapshot.rest_waypoints;
+        self.segment_index = snapshot.segment_index;
+
+        while self.walker.steps < target && !self.walker.finished {
+            self.step(config)?;
+        }
+        if self.live_lock_layer {
+            self.debug_layers.get_mut("lock").unwrap().grid = self.walker.locked_positions.clone();
+        }
+
+        Ok(())
+    }
+
+    /// returns how far (0 to 1) the walker progressed along all of its waypoints
+    fn get_map_progress(&self) -> f32 {
+        if self.walker.finished {
+            return 1.0;
+        }
+
+        let points: Vec<&Position> = std::iter::once(&self.spawn)
+            .chain(self.walker.waypoints.iter())
+            .collect();
+        let lengths: Vec<f32> = points
+            .windows(2)
+            .map(|segment| segment[0].distance(segment[1]))
+            .collect();
+        let total_length: f32 = lengths.iter().sum();
+        if total_length == 0.0 {
+            return 1.0;
+        }
+
+        let goal_index = self.walker.goal_index.min(lengths.len() - 1);
+        let completed_length: f32 = lengths[..goal_index].iter().sum();
+        let remaining_length = self
+            .walker
+            .pos
+            .distance(points[goal_index + 1])
+            .min(lengths[goal_index]);
+
+        (completed_length + lengths[goal_index] - remaining_length) / total_length
+    }
+
+    /// returns how far (0 to 1) the walker progressed in the current waypoint segment. Once the
+    /// end of a segment is reached, the next segment begins.
+    fn get_segment_progress(&mut self, config: &GenerationConfig) -> f32 {
+        if self.segment_index >= self.macro_decisions.waypoints.len() {
+            return 1.0;
+        }
+
+        let segment_end = &self.macro_decisions.waypoints[self.segment_index];
+        if segment_end.distance_squared(&self.walker.pos) <= config.waypoint_reached_dist
+            && self.segment_index + 1 < self.macro_decisions.waypoints.len()
+        {
+            self.segment_index += 1;
+        }
+
+        let segment_start = &self.macro_decisions.waypoints[self.segment_index - 1];
+        let segment_end = &self.macro_decisions.waypoints[self.segment_index];
+        let segment_length = (segment_start.distance_squared(segment_end) as f32).sqrt();
+        let remaining_length = (segment_end.distance_squared(&self.walker.pos) as f32).sqrt();
+
+        if segment_length == 0.0 {
+            return 1.0;
+        }
+
+        1.0 - remaining_length / segment_length
+    }
+
+    fn place_rest_room(&mut self, config: &GenerationConfig) {
+        let reached_index = self.rest_waypoints.iter().position(|waypoint| {
+            waypoint.distance_squared(&self.walker.pos) <= config.waypoint_reached_dist
+        });
+
+        if let Some(reached_index) = reached_index {
+            self.rest_waypoints.remove(reached_index);
+            let shape = RoomShape::sample(&mut self.rnd, config);
+            let room = generate_room(
+                &mut self.map,
+                &self.walker.pos.clone(),
+                config.rest_room_size,
+                config.rest_room_platform_margin,
+                None,
+                None,
+                &shape,
+            );
+
+            match room {
+                Ok(()) => {
+                    // route the walker around the room platform instead of through it
+                    let width = (config.rest_room_size + shape.extra_width) as i32;
+                    let height = (config.rest_room_size + shape.extra_height) as i32;
+                    let pos = self.walker.pos.clone();
+                    if let (Ok(top_left), Ok(bot_right)) = (
+                        pos.shifted_by(-width, -height),
+                        pos.shifted_by(width, height),
+                    ) {
+                        self.walker
+                            .lock_solid_blocks(&self.map, &top_left, &bot_right);
+                    }
+                }
+                Err(err) => warn!("skipped rest room at {:?}: {}", self.walker.pos, err),
+            }
+        }
+    }
+
+    /// waypoints in the order they are visited, based on the goal selection of the config
+    pub fn order_waypoints(
+        waypoints: &[Position],
+        gen_config: &GenerationConfig,
+        rnd: &mut Random,
+    ) -> Vec<Position> {
+        if gen_config.goal_selection == GoalSelection::Ordered || waypoints.len() <= 3 {
+            return waypoints.to_vec();
+        }
+
+        let mut remaining = waypoints[1..waypoints.len() - 1].to_vec();
+        let mut ordered = vec![waypoints[0].clone()];
+        while !remaining.is_empty() {
+            let current = ordered.last().unwrap();
+            let distances: Vec<f32> = remaining.iter().map(|pos| current.distance(pos)).collect();
+
+            let next_index = match gen_config.goal_selection {
+                GoalSelection::NearestFirst => {
+                    distances
+                        .iter()
+                        .enumerate()
+                        .min_by(|(_, d1), (_, d2)| d1.total_cmp(d2))
+                        .unwrap()
+                        .0
+                }
+                _ => {
+                    let weights: Vec<f32> = distances
+                        .iter()
+                        .map(|distance| {
+                            1.0 / distance.max(1.0).powf(gen_config.goal_distance_weight)
+                        })
+                        .collect();
+                    rnd.sample_weighted_index(&weights)
+                }
+            };
+
+            ordered.push(remaining.remove(next_index));
+        }
+        ordered.push(waypoints.last().unwrap().clone());
+
+        ordered
+    }
+
+    /// waypoints of the map config in the order they are visited
+    pub fn get_waypoints(&self) -> &[Position] {
+        &self.macro_decisions.waypoints
+    }
+
+    pub fn get_macro_decisions(&self) -> &MacroDecisions {
+        &self.macro_decisions
+    }
+
+    pub fn is_draft(&self) -> bool {
+        self.draft
+    }
+
     /// Generate subwaypoints for more consistent distance between walker waypoints. This
     /// ensures more controllable and consistent behaviour of the walker with respect to the
     /// distance to the target waypoint.
@@ -258,59 +1066,186 @@ impl Generator {
         &mut self,
         gen_config: &GenerationConfig,
     ) -> Result<(), &'static str> {
-        let timer = Timer::start();
-
-        // lock all remaining blocks
-        self.walker
-            .lock_previous_location(&self.map, &gen_config, true)?;
-        // TODO: REVERT
-        self.debug_layers.get_mut("lock").unwrap().grid = self.walker.locked_positions.clone();
-
-        let edge_bugs = post::fix_edge_bugs(self).expect("fix edge bugs failed");
-        self.debug_layers.get_mut("edge_bugs").unwrap().grid = edge_bugs;
-        print_time(&timer, "fix edge bugs");
-
-        generate_room(&mut self.map, &self.spawn, 6, 3, Some(&BlockType::Start))
-            .expect("start room generation failed");
-        generate_room(
-            &mut self.map,
-            &self.walker.pos.clone(),
-            4,
-            3,
-            Some(&BlockType::Finish),
-        )
-        .expect("start finish room generation");
-        print_time(&timer, "place rooms");
-
-        if gen_config.min_freeze_size > 0 {
-            // TODO: Maybe add some alternative function for the case of min_freeze_size=1
-            post::remove_freeze_blobs(self, gen_config.min_freeze_size);
-            print_time(&timer, "detect blobs");
+        for stage in PostProcessingStage::ALL {
+            if !self.draft || stage.is_draft_stage() {
+                self.perform_post_processing_stage(stage, gen_config)?;
+            }
         }
 
-        let flood_fill = get_flood_fill(self, &self.spawn);
-        print_time(&timer, "flood fill");
+        Ok(())
+    }
 
-        post::gen_all_platform_candidates(
-            &self.walker.position_history,
-            &flood_fill,
-            &mut self.map,
-            gen_config,
-            &mut self.debug_layers,
-        );
-        print_time(&timer, "platforms");
+    /// performs a single post processing stage. Stages have to be performed in the order of
+    /// PostProcessingStage::ALL, as later stages depend on the results of earlier ones.
+    pub fn perform_post_processing_stage(
+        &mut self,
+        stage: PostProcessingStage,
+        gen_config: &GenerationConfig,
+    ) -> Result<(), &'static str> {
+        let timer = Timer::start();
+        let hook_stage = GenerationStage::PostProcessing(stage);
+        self.run_hooks(hook_stage, HookTiming::Before, gen_config)?;
 
-        post::generate_all_skips(
-            self,
-            gen_config.skip_length_bounds,
-            gen_config.skip_min_spacing_sqr,
-            gen_config.max_level_skip,
-            &flood_fill,
-        );
-        print_time(&timer, "generate skips");
+        match stage {
+            PostProcessingStage::Lock => {
+                // lock all remaining blocks
+                self.walker
+                    .lock_previous_location(&self.map, &gen_config, true)?;
+                // TODO: REVERT
+                self.debug_layers.get_mut("lock").unwrap().grid =
+                    self.walker.locked_positions.clone();
+            }
+            PostProcessingStage::FixEdgeBugs => {
+                let edge_bugs = post::fix_edge_bugs(self, gen_config.freeze_style)
+                    .expect("fix edge bugs failed");
+                self.debug_layers.get_mut("edge_bugs").unwrap().grid = edge_bugs;
+            }
+            PostProcessingStage::SmoothWalls => {
+                let smoothed = post::smooth_walls(
+                    &mut self.map,
+                    gen_config.wall_smoothing,
+                    gen_config.overwrite_reserved,
+                );
+                self.debug_layers.get_mut("smoothed_walls").unwrap().grid = smoothed;
+            }
+            PostProcessingStage::CornerHolds => {
+                let corner_holds = post::fix_corner_holds(
+                    &mut self.map,
+                    gen_config.corner_hold_policy,
+                    gen_config.overwrite_reserved,
+                );
+                self.debug_layers.get_mut("corner_holds").unwrap().grid = corner_holds;
+            }
+            PostProcessingStage::Rooms => {
+                let start_room_size = self
+                    .macro_decisions
+                    .start_room_size
+                    .unwrap_or(gen_config.start_room_size);
+                let finish_room_size = self
+                    .macro_decisions
+                    .finish_room_size
+                    .unwrap_or(gen_config.finish_room_size);
+                let start_room_opening = gen_config
+                    .start_room_opening
+                    .get_direction(&self.spawn, self.macro_decisions.waypoints.get(1));
+                let start_room_shape = RoomShape::sample(&mut self.rnd, gen_config);
+                if gen_config.freeze_style == FreezeStyle::Dotted {
+                    // the start line around the room must not be split by perforations
+                    let extent_x = (start_room_size + start_room_shape.extra_width + 1) as i32;
+                    let extent_y = (start_room_size + start_room_shape.extra_height + 1) as i32;
+                    post::close_perforations(
+                        &mut self.map,
+                        &self.spawn.shifted_by(-extent_x, -extent_y)?,
+                        &self.spawn.shifted_by(extent_x, extent_y)?,
+                    );
+                }
+                generate_room(
+                    &mut self.map,
+                    &self.spawn,
+                    start_room_size,
+                    gen_config.start_room_platform_margin,
+                    start_room_opening,
+                    Some(Marker::Start),
+                    &start_room_shape,
+                )?;
+                let finish_room = match gen_config.finish_room {
+                    FinishRoom::Random => *self.rnd.pick_element(&FinishRoom::LAYOUTS),
+                    layout => layout,
+                };
+                let finish_room_shape = RoomShape::sample(&mut self.rnd, gen_config);
+                generate_finish_room(
+                    &mut self.map,
+                    &self.walker.pos.clone(),
+                    finish_room_size,
+                    finish_room,
+                    &finish_room_shape,
+                )?;
+            }
+            PostProcessingStage::FreezeBlobs => {
+                if gen_config.min_freeze_size > 0 {
+                    // TODO: Maybe add some alternative function for the case of min_freeze_size=1
+                    post::remove_freeze_blobs(self, gen_config.min_freeze_size);
+                }
+            }
+            PostProcessingStage::Platforms => {
+                let flood_fill = get_flood_fill(self, &self.spawn);
+                post::gen_all_platform_candidates(
+                    &self.walker.position_history,
+                    &flood_fill,
+                    &mut self.map,
+                    gen_config,
+                    &mut self.debug_layers,
+                );
 
-        post::fill_open_areas(self, &gen_config.max_distance);
-        print_time(&timer, "place obstacles");
+                // skips are based on the flood fill before platforms are placed
+                self.flood_fill = Some(flood_fill);
+            }
+            PostProcessingStage::Skips => {
+                let flood_fill = self
+                    .flood_fill
+                    .take()
+                    .ok_or("skips require the platform stage")?;
+                post::generate_all_skips(self, gen_config, &flood_fill);
+            }
+            PostProcessingStage::CeilingLips => {
+                let lips = post::normalize_ceiling_lips(
+                    &mut self.map,
+                    &self.walker.position_history,
+                    gen_config.ceiling_lip_length,
+                );
+                self.debug_layers.get_mut("ceiling_lips").unwrap().grid = lips;
+            }
+            PostProcessingStage::FreezePockets => {
+                if gen_config.enable_freeze_pockets {
+                    let position_history = self.walker.position_history.to_vec();
+                    let reports = analyze_segments(
+                        &self.map,
+                        &position_history,
+                        self.get_waypoints(),
+                        gen_config.waypoint_reached_dist,
+                        self.debug_layers.get("skips").map(|layer| &layer.grid),
+                    );
+                    let pockets = post::carve_freeze_pockets(
+                        &mut self.map,
+                        &position_history,
+                        &reports,
+                        gen_config.freeze_pocket_min_difficulty,
+                        gen_config.freeze_pocket_spacing,
+                    );
+                    self.debug_layers.get_mut("freeze_pockets").unwrap().grid = pockets;
+                }
+            }
+            PostProcessingStage::FillOpenAreas => {
+                post::fill_open_areas(
+                    self,
+                    &gen_config.max_distance,
+                    gen_config.fill_style,
+                    gen_config.fill_noise_amplitude,
+                    gen_config.overwrite_reserved,
+                );
+            }
+            PostProcessingStage::Checkpoints => {
+                if gen_config.checkpoint_interval > 0 {
+                    let tele = post::place_checkpoints(
+                        &mut self.map,
+                        &self.walker.position_history,
+                        &self.macro_decisions.waypoints,
+                        gen_config.checkpoint_interval,
+                    );
+                    self.debug_layers.get_mut("checkpoints").unwrap().grid = tele;
+                }
+            }
+            PostProcessingStage::Doodads => {
+                let doodad_density = gen_config.get_doodad_density(gen_config.get_theme().as_ref());
+                post::place_doodads(self, doodad_density, &gen_config.doodad_tiles);
+            }
+            PostProcessingStage::Border => {
+                let repaired = post::repair_border(&mut self.map);
+                self.debug_layers.get_mut("border_repairs").unwrap().grid = repaired;
+            }
+        }
+        self.run_hooks(hook_stage, HookTiming::After, gen_config)?;
+        print_time(&timer, stage.label());
 
         // post::remove_unused_blocks(&mut self.map, &self.walker.locked_positions);
 
@@ -326,6 +1261,16 @@ impl Generator {
         gen_config: &GenerationConfig,
         map_config: &MapConfig,
     ) -> Result<Map, &'static str> {
+        Generator::generate_finished(max_steps, seed, gen_config, map_config).map(|gen| gen.map)
+    }
+
+    /// like generate_map, but returns the whole generator, e.g. to compute its metrics
+    fn generate_finished(
+        max_steps: usize,
+        seed: &Seed,
+        gen_config: &GenerationConfig,
+        map_config: &MapConfig,
+    ) -> Result<Generator, &'static str> {
         let mut gen = Generator::new(gen_config, map_config, seed.clone());
 
         for _ in 0..max_steps {
@@ -334,9 +1279,380 @@ impl Generator {
             }
             gen.step(gen_config)?;
         }
+        gen.handle_truncation(gen_config)?;
 
         gen.perform_all_post_processing(gen_config)?;
 
+        Ok(gen)
+    }
+
+    /// Handles a walker that is not finished after the max steps of a generation, see
+    /// TruncationMode
+    pub fn handle_truncation(&mut self, gen_config: &GenerationConfig) -> Result<(), &'static str> {
+        if self.walker.finished {
+            return Ok(());
+        }
+
+        if gen_config.truncation_mode == TruncationMode::NearestWaypoint {
+            self.walker.truncate_route();
+            for _ in 0..gen_config.truncation_steps {
+                if self.walker.finished {
+                    return Ok(());
+                }
+                self.step(gen_config)?;
+            }
+        }
+
+        match self.walker.finished {
+            true => Ok(()),
+            false => Err(MAP_TRUNCATED),
+        }
+    }
+
+    /// Generates an entire low detail draft, see Generator::new_draft
+    pub fn generate_draft(
+        max_steps: usize,
+        seed: &Seed,
+        gen_config: &GenerationConfig,
+        map_config: &MapConfig,
+        scale: usize,
+    ) -> Result<Map, &'static str> {
+        let mut gen = Generator::new_draft(gen_config, map_config, seed.clone(), scale);
+        let draft_config = gen_config.downscaled(scale.max(1));
+
+        for _ in 0..max_steps {
+            if gen.walker.finished {
+                break;
+            }
+            gen.step(&draft_config)?;
+        }
+        gen.handle_truncation(&draft_config)?;
+
+        gen.perform_all_post_processing(&draft_config)?;
+
         Ok(gen.map)
     }
+
+    /// Generates a map for each seed in parallel. Each map is passed to handle_map together with
+    /// the index of its seed on the thread that generated it, e.g. to export it, so the maps
+    /// don't have to be kept in memory. Results are in the order of the seeds.
+    pub fn generate_batch<T, F>(
+        max_steps: usize,
+        seeds: &[Seed],
+        gen_config: &GenerationConfig,
+        map_config: &MapConfig,
+        handle_map: F,
+    ) -> Vec<Result<T, &'static str>>
+    where
+        T: Send,
+        F: Fn(usize, Map) -> T + Sync,
+    {
+        seeds
+            .par_iter()
+            .enumerate()
+            .map(|(index, seed)| {
+                // a panic for one seed shouldn't abort the entire batch
+                panic::catch_unwind(AssertUnwindSafe(|| {
+                    Generator::generate_map(max_steps, seed, gen_config, map_config)
+                        .map(|map| handle_map(index, map))
+                }))
+                .map_err(|_| "generation panicked")?
+            })
+            .collect()
+    }
+
+    /// Generates n_candidates maps in parallel, scores them by their metrics and returns the map
+    /// with the highest score together with its seed and metrics. The candidates use the given
+    /// seed and the following seeds, like generate_batch. Failed candidates and candidates with a
+    /// non-finite score (NaN or infinite) are skipped, on equal scores the lower seed wins.
+    pub fn search_best_seed<F>(
+        max_steps: usize,
+        seed: &Seed,
+        gen_config: &GenerationConfig,
+        map_config: &MapConfig,
+        n_candidates: usize,
+        scoring_fn: F,
+    ) -> Result<(Seed, Map, MapMetrics), &'static str>
+    where
+        F: Fn(&MapMetrics) -> f32 + Sync,
+    {
+        (0..n_candidates)
+            .into_par_iter()
+            .filter_map(|index| {
+                let seed = Seed::from_u64(seed.seed_u64.wrapping_add(index as u64));
+
+                // a panic for one candidate shouldn't abort the entire search
+                let gen = panic::catch_unwind(AssertUnwindSafe(|| {
+                    Generator::generate_finished(max_steps, &seed, gen_config, map_config)
+                }))
+                .ok()?
+                .ok()?;
+
+                let metrics = MapMetrics::new(&gen, gen_config);
+                let score = scoring_fn(&metrics);
+                if !score.is_finite() {
+                    return None;
+                }
+
+                Some((index, score, seed, gen.map, metrics))
+            })
+            .max_by(|(index_a, score_a, ..), (index_b, score_b, ..)| {
+                score_a.total_cmp(score_b).then(index_b.cmp(index_a))
+            })
+            .map(|(_, _, seed, map, metrics)| (seed, map, metrics))
+            .ok_or("no candidate generated a map with a finite score")
+    }
+
+    /// Clears the area (inclusive) and carves it again with a new walker, which uses the given
+    /// sub seed. The new walker starts where the previous path first enters the area and walks
+    /// to where it finally leaves the area, passing all sub waypoints within the area and all
+    /// positions where the previous path crossed the area border. Everything outside of the area
+    /// is kept intact. The map is only modified if the walker finishes, returns the amount of
+    /// walker steps.
+    pub fn regenerate_region(
+        &mut self,
+        config: &GenerationConfig,
+        top_left: &Position,
+        bot_right: &Position,
+        sub_seed: u64,
+        max_steps: usize,
+    ) -> Result<usize, &'static str> {
+        let in_area = |pos: &Position| {
+            pos.x >= top_left.x
+                && pos.x <= bot_right.x
+                && pos.y >= top_left.y
+                && pos.y <= bot_right.y
+        };
+
+        let path = self.walker.position_history.to_vec();
+        let entry = path
+            .iter()
+            .position(in_area)
+            .ok_or("walker path doesn't pass the region")?;
+        let exit = path.iter().rposition(in_area).unwrap();
+
+        // waypoints with the step at which the previous path passed them. Passing all border
+        // crossings keeps the parts that were carved outside of the area connected.
+        let mut waypoints: Vec<(usize, Position)> = Vec::new();
+        for step in (entry + 1)..=exit {
+            let (prev, pos) = (&path[step - 1], &path[step]);
+            match (in_area(prev), in_area(pos)) {
+                (true, false) => waypoints.push((step, prev.clone())),
+                (false, true) => waypoints.push((step, pos.clone())),
+                _ => {}
+            }
+        }
+        for waypoint in self.walker.waypoints.iter().filter(|pos| in_area(pos)) {
+            let reached = (entry..=exit).find(|step| {
+                path[*step].distance_squared(waypoint) <= config.waypoint_reached_dist
+            });
+            if let Some(step) = reached {
+                waypoints.push((step, waypoint.clone()));
+            }
+        }
+        waypoints.push((exit, path[exit].clone()));
+        waypoints.sort_by_key(|(step, _)| *step);
+        let waypoints = waypoints.into_iter().map(|(_, pos)| pos).collect();
+
+        // the walker can neither move nor carve outside of the area
+        let mut map = self.map.clone();
+        for ((x, y), no_go) in map.no_go.indexed_iter_mut() {
+            if !in_area(&Position::new(x, y)) {
+                *no_go = true;
+            }
+        }
+
+        for x in top_left.x..=bot_right.x.min(map.width - 1) {
+            for y in top_left.y..=bot_right.y.min(map.height - 1) {
+                if map.no_go[[x, y]] || map.protected[[x, y]] {
+                    continue;
+                }
+                map.set_block([x, y], BlockType::Hookable);
+                map.doodads[[x, y]] = 0;
+
+                let chunk_pos = map.pos_to_chunk_pos(Position::new(x, y));
+                map.chunk_edited[chunk_pos.as_index()] = true;
+                map.chunk_dirty[chunk_pos.as_index()] = true;
+            }
+        }
+
+        let mut rnd = Random::new(Seed::from_u64(sub_seed), config);
+        let mut walker = CuteWalker::new(
+            path[entry].clone(),
+            self.walker.inner_kernel.clone(),
+            self.walker.outer_kernel.clone(),
+            waypoints,
+            &map,
+        );
+        // the border crossings have to be passed closely to connect to the carved parts outside
+        let reached_dist = usize::min(
+            config.waypoint_reached_dist,
+            (walker.inner_kernel.size / 2).pow(2),
+        );
+        Generator::walk(
+            &mut walker,
+            &mut map,
+            config,
+            &mut rnd,
+            reached_dist,
+            max_steps,
+        )?;
+
+        map.no_go = self.map.no_go.clone();
+        self.map = map;
+
+        Ok(walker.steps)
+    }
+
+    /// carves a path between two arbitrary points of an existing map, e.g. an imported one,
+    /// without running any post processing. Like the regular walker, it only carves through
+    /// solid blocks outside of protected and no-go areas. The map is only modified if the walker
+    /// reaches the target, returns the amount of walker steps.
+    pub fn connect(
+        map: &mut Map,
+        from: &Position,
+        to: &Position,
+        config: &GenerationConfig,
+        seed: &Seed,
+        max_steps: usize,
+    ) -> Result<usize, &'static str> {
+        if !map.pos_in_bounds(from) || !map.pos_in_bounds(to) {
+            return Err("connect position out of bounds");
+        }
+
+        let mut rnd = Random::new(seed.clone(), config);
+        let inner_kernel_size = rnd.sample_inner_kernel_size();
+        let outer_kernel_size = inner_kernel_size + rnd.sample_outer_kernel_margin();
+
+        let mut new_map = map.clone();
+        let mut walker = CuteWalker::new(
+            from.clone(),
+            Kernel::new(inner_kernel_size, 0.0),
+            Kernel::new(outer_kernel_size, 0.0),
+            vec![to.clone()],
+            &new_map,
+        );
+
+        // the target has to be reached closely, so the path actually connects to it
+        let reached_dist = usize::min(config.waypoint_reached_dist, (inner_kernel_size / 2).pow(2));
+        Generator::walk(
+            &mut walker,
+            &mut new_map,
+            config,
+            &mut rnd,
+            reached_dist,
+            max_steps,
+        )?;
+
+        *map = new_map;
+
+        Ok(walker.steps)
+    }
+
+    /// steps the walker until it has passed all of its waypoints
+    fn walk(
+        walker: &mut CuteWalker,
+        map: &mut Map,
+        config: &GenerationConfig,
+        rnd: &mut Random,
+        reached_dist: usize,
+        max_steps: usize,
+    ) -> Result<(), &'static str> {
+        for _ in 0..max_steps {
+            if walker.is_goal_reached(&reached_dist) == Some(true) {
+                walker.next_waypoint();
+            }
+            if walker.finished {
+                return Ok(());
+            }
+
+            walker.mutate_kernel(config, rnd);
+            walker.probabilistic_step(map, config, rnd, config.step_strategy.get_strategy())?;
+        }
+
+        match walker.finished {
+            true => Ok(()),
+            false => Err("max steps exceeded, walker stuck?"),
+        }
+    }
+
+    /// Stable hash of the map grid. Two maps have the same fingerprint if they have the same
+    /// dimensions and the same block types, markers and tele tiles at every position. This is used to
+    /// detect whether changes to the generator break seed compatibility.
+    pub fn fingerprint_map(map: &Map) -> u64 {
+        let mut data: Vec<u8> = Vec::with_capacity(16 + map.width * map.height);
+        data.extend_from_slice(&(map.width as u64).to_le_bytes());
+        data.extend_from_slice(&(map.height as u64).to_le_bytes());
+
+        // explicit ids instead of enum discriminants, so reordering BlockType keeps hashes stable
+        for (block_type, marker) in map.grid.iter().zip(map.markers.iter()) {
+            let block_id = match block_type {
+                BlockType::Empty => 0,
+                BlockType::Hookable => 2,
+                BlockType::Platform => 3,
+                BlockType::Freeze => 4,
+            };
+            let marker_id = match marker {
+                None => None,
+                Some(Marker::Reserved) => Some(1),
+                Some(Marker::Spawn) => Some(5),
+                Some(Marker::Start) => Some(6),
+                Some(Marker::Finish) => Some(7),
+            };
+
+            // marked empty blocks only store the marker, so their hashes stay the same as before
+            // the block below markers was hashed
+            data.push(match (marker_id, block_type) {
+                (None, _) => block_id,
+                (Some(marker_id), BlockType::Empty) => marker_id,
+                (Some(marker_id), _) => (marker_id << 4) | block_id,
+            });
+        }
+
+        // only appended if present, so maps without tele tiles keep their previous hashes
+        if map.tele.iter().any(|tile| tile.is_some()) {
+            for tile in map.tele.iter() {
+                let (id, number) = match tile {
+                    None => (0, 0),
+                    Some(tile) => (tile.tele_type.to_tw_tele_id(), tile.number),
+                };
+                data.extend_from_slice(&[id, number]);
+            }
+        }
+
+        seahash::hash(&data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fingerprint_includes_blocks_below_markers() {
+        let mut map = Map::new(8, 8, BlockType::Empty);
+        let empty = Generator::fingerprint_map(&map);
+
+        map.markers[[3, 3]] = Some(Marker::Reserved);
+        let reserved = Generator::fingerprint_map(&map);
+        assert_ne!(reserved, empty);
+
+        map.grid[[3, 3]] = BlockType::Freeze;
+        let reserved_freeze = Generator::fingerprint_map(&map);
+        assert_ne!(reserved_freeze, reserved);
+
+        map.markers[[3, 3]] = None;
+        assert_ne!(Generator::fingerprint_map(&map), reserved_freeze);
+    }
+
+    #[test]
+    fn room_out_of_bounds_is_an_error() {
+        let gen_config = GenerationConfig::default();
+        let mut map_config = MapConfig::get_all_configs()["small_s"].clone();
+        map_config.waypoints[0] = Position::new(3, 250);
+
+        let mut gen = Generator::new(&gen_config, &map_config, Seed::from_u64(1));
+        let result = gen.perform_post_processing_stage(PostProcessingStage::Rooms, &gen_config);
+        assert!(result.is_err());
+    }
 }
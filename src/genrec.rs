@@ -0,0 +1,93 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{GenerationConfig, MapConfig},
+    generator::Generator,
+    random::Seed,
+    trace::GenerationTrace,
+    walker::WalkerEvent,
+};
+
+/// current .genrec format version. Bump this on breaking changes of GenerationRecording.
+pub const GENREC_VERSION: &str = "1.0";
+
+/// everything required to exactly replay a generation, stored as .genrec file. Configs are
+/// stored as snapshot, so recordings stay replayable after presets changed.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GenerationRecording {
+    /// .genrec format version
+    pub version: String,
+
+    /// crate version that generated the map, other versions may generate different maps
+    pub crate_version: String,
+
+    pub seed: u64,
+
+    pub gen_config: GenerationConfig,
+
+    pub map_config: MapConfig,
+
+    /// fingerprint of the generated map, to verify that a replay produced the same map
+    pub hash: u64,
+
+    /// walker events, if they were recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_log: Option<Vec<WalkerEvent>>,
+
+    /// outcomes of all samples, if they were traced. Replays use them instead of the rng.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace: Option<GenerationTrace>,
+}
+
+impl GenerationRecording {
+    pub fn new(
+        gen: &Generator,
+        gen_config: &GenerationConfig,
+        map_config: &MapConfig,
+        seed: u64,
+    ) -> GenerationRecording {
+        GenerationRecording {
+            version: GENREC_VERSION.to_string(),
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            seed,
+            gen_config: gen_config.clone(),
+            map_config: map_config.clone(),
+            hash: Generator::fingerprint_map(&gen.map),
+            event_log: gen.walker.event_log.clone(),
+            trace: GenerationTrace::from_generator(gen),
+        }
+    }
+
+    /// generator that replays the recording step by step, using the trace if it was recorded
+    pub fn replay_generator(&self) -> Generator {
+        let seed = Seed::from_u64(self.seed);
+        match &self.trace {
+            Some(trace) => trace.replay_generator(&self.gen_config, &self.map_config, seed),
+            None => Generator::new(&self.gen_config, &self.map_config, seed),
+        }
+    }
+
+    /// whether the recording was created by the running crate version
+    pub fn is_same_crate_version(&self) -> bool {
+        self.crate_version == env!("CARGO_PKG_VERSION")
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), &'static str> {
+        let data = serde_json::to_string(self).map_err(|_| "failed to serialize recording")?;
+        fs::write(path, data).map_err(|_| "failed to write recording")
+    }
+
+    pub fn load(path: &Path) -> Result<GenerationRecording, &'static str> {
+        let data = fs::read_to_string(path).map_err(|_| "failed to read recording")?;
+        let recording: GenerationRecording =
+            serde_json::from_str(&data).map_err(|_| "failed to parse recording")?;
+
+        if recording.version != GENREC_VERSION {
+            return Err("unsupported recording version");
+        }
+
+        Ok(recording)
+    }
+}
@@ -4,14 +4,27 @@ use egui::RichText;
 use tinyfiledialogs;
 
 use crate::{
-    editor::{window_frame, Editor},
+    analysis::corridor_width,
+    config::{
+        ConfigError, CornerHoldPolicy, CorridorProfile, DifficultyCurve, FillStyle, FinishRoom,
+        FreezeStyle, GoalSelection, PulsePattern, PulseRing, RoomOpening, TruncationMode,
+    },
+    editor::{window_frame, Editor, BRUSH_BLOCKS},
+    export::OverwritePolicy,
+    generator::PostProcessingStage,
+    keymap::{key_name, EditorAction},
+    map::{BlockType, OverwriteMatrix},
     position::{Position, ShiftDirection},
     random::{RandomDistConfig, Seed},
+    step_strategy::StepStrategyKind,
 };
 use egui::Context;
-use egui::{CollapsingHeader, Label, Ui};
+use egui::{CollapsingHeader, Color32, Label, Ui};
 use macroquad::time::get_fps;
 
+/// number of latest walker events that are shown in the timeline
+const TIMELINE_EVENTS: usize = 50;
+
 pub fn vec_edit_widget<T, F>(
     ui: &mut Ui,
     vec: &mut Vec<T>,
@@ -147,6 +160,24 @@ pub fn field_edit_widget<T, F>(
     }
 }
 
+/// highlights the contained widgets in red if the config has an error for the given field
+pub fn config_error_scope<F>(ui: &mut Ui, errors: &[ConfigError], field: &str, add_contents: F)
+where
+    F: FnOnce(&mut Ui),
+{
+    match errors.iter().find(|e| e.field == field) {
+        Some(error) => {
+            ui.scope(|ui| {
+                ui.visuals_mut().override_text_color = Some(Color32::RED);
+                add_contents(ui);
+            })
+            .response
+            .on_hover_text(error.to_string());
+        }
+        None => add_contents(ui),
+    }
+}
+
 /// edit u64 using a crappy textfield, as DragValue results in numeric instabilities
 fn edit_u64_textfield(ui: &mut egui::Ui, value: &mut u64) -> egui::Response {
     let mut int_as_str = format!("{}", value);
@@ -213,6 +244,25 @@ pub fn edit_probability_f32(ui: &mut Ui, value: &mut (f32, f32)) {
     });
 }
 
+pub fn edit_difficulty_keyframe(ui: &mut Ui, value: &mut (f32, f32)) {
+    ui.horizontal(|ui| {
+        ui.vertical(|ui| {
+            ui.label("progress:");
+            edit_f32_prob(ui, &mut value.0);
+        });
+        ui.vertical(|ui| {
+            ui.label("difficulty:");
+            edit_f32_prob(ui, &mut value.1)
+        });
+    });
+}
+
+pub fn edit_corridor_profile(ui: &mut Ui, value: &mut CorridorProfile) {
+    for profile in CorridorProfile::ALL {
+        ui.selectable_value(value, profile, format!("{:?}", profile));
+    }
+}
+
 pub fn edit_position(ui: &mut Ui, position: &mut Position) {
     ui.horizontal(|ui| {
         ui.label("x:");
@@ -222,6 +272,20 @@ pub fn edit_position(ui: &mut Ui, position: &mut Position) {
     });
 }
 
+/// like edit_position, but shows and accepts DDNet in-game coordinates
+pub fn edit_ddnet_position(ui: &mut Ui, position: &mut Position) {
+    let (mut x, mut y) = position.to_ddnet();
+    ui.horizontal(|ui| {
+        ui.label("x:");
+        let x_changed = ui.add(egui::widgets::DragValue::new(&mut x)).changed();
+        ui.label("y:");
+        let y_changed = ui.add(egui::widgets::DragValue::new(&mut y)).changed();
+        if x_changed || y_changed {
+            *position = Position::from_ddnet(x, y);
+        }
+    });
+}
+
 pub fn edit_range_usize(ui: &mut Ui, values: &mut (usize, usize)) {
     ui.horizontal(|ui| {
         ui.label("min:");
@@ -237,6 +301,80 @@ pub fn edit_bool(ui: &mut Ui, value: &mut bool) {
     ui.add(egui::Checkbox::new(value, ""));
 }
 
+/// checkbox grid of which block types (columns) each of the target block types (rows) may replace
+pub fn edit_overwrite_matrix(
+    ui: &mut Ui,
+    matrix: &mut OverwriteMatrix,
+    targets: &[BlockType],
+    label: &str,
+) {
+    CollapsingHeader::new(label)
+        .default_open(false)
+        .show(ui, |ui| {
+            egui::Grid::new(label).show(ui, |ui| {
+                ui.label("");
+                for block_type in BlockType::ALL {
+                    ui.label(format!("{:?}", block_type));
+                }
+                ui.end_row();
+
+                for target in targets {
+                    ui.label(format!("{:?}", target));
+                    for block_type in BlockType::ALL {
+                        let mut allowed = matrix.is_allowed(target, &block_type);
+                        if ui.checkbox(&mut allowed, "").changed() {
+                            matrix.set_allowed(target, &block_type, allowed);
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+}
+
+pub fn edit_pulse_ring(ui: &mut Ui, ring: &mut PulseRing) {
+    ui.horizontal(|ui| {
+        ui.label("+");
+        edit_usize(ui, &mut ring.size_delta);
+        edit_f32_prob(ui, &mut ring.circularity);
+        ui.selectable_value(&mut ring.block_type, BlockType::Freeze, "freeze");
+        ui.selectable_value(&mut ring.block_type, BlockType::Empty, "empty");
+        ui.selectable_value(&mut ring.block_type, BlockType::Hookable, "hookable");
+    });
+}
+
+pub fn edit_pulse_pattern(ui: &mut Ui, pattern: &mut PulsePattern) {
+    ui.vertical(|ui| {
+        ui.horizontal(|ui| {
+            ui.label("straight:");
+            edit_usize(ui, &mut pattern.straight_delay);
+            ui.label("corner:");
+            edit_usize(ui, &mut pattern.corner_delay);
+        });
+        ui.horizontal(|ui| {
+            ui.label("max kernel:");
+            edit_usize(ui, &mut pattern.max_kernel_size);
+            ui.label("cooldown:");
+            edit_usize(ui, &mut pattern.cooldown);
+        });
+
+        for ring in pattern.rings.iter_mut() {
+            edit_pulse_ring(ui, ring);
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("+ ring").clicked() {
+                pattern.rings.push(Default::default());
+            };
+
+            if ui.button("- ring").clicked() && pattern.rings.len() > 1 {
+                pattern.rings.pop();
+            };
+        });
+        ui.separator();
+    });
+}
+
 pub fn sidebar(ctx: &Context, editor: &mut Editor) {
     egui::SidePanel::right("right_panel").show(ctx, |ui| {
         // =======================================[ STATE CONTROL ]===================================
@@ -269,6 +407,24 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
             }
         });
 
+        // =======================================[ UNDO CONTROL ]====================================
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(editor.can_undo(), |ui| {
+                if ui.button("undo").clicked() {
+                    editor.undo_steps();
+                }
+                ui.add_enabled_ui(editor.redo_target.is_some(), |ui| {
+                    if ui.button("redo").clicked() {
+                        editor.redo_steps();
+                    }
+                });
+            });
+            field_edit_widget(ui, &mut editor.undo_step_count, edit_usize, "steps", true);
+        });
+        if let Some(status) = &editor.undo_status {
+            ui.label(status);
+        }
+
         // =======================================[ SPEED CONTROL ]===================================
         ui.horizontal(|ui| {
             ui.add_enabled_ui(!editor.instant, |ui| {
@@ -277,8 +433,61 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
             ui.vertical(|ui| {
                 ui.checkbox(&mut editor.instant, "instant");
                 ui.checkbox(&mut editor.auto_generate, "auto generate");
+                ui.checkbox(&mut editor.step_post_processing, "step post processing");
             });
         });
+        if !editor.is_setup() && !editor.gen.walker.finished {
+            let steps = match editor.gen_config.max_walker_steps {
+                0 => format!("step {}", editor.current_steps()),
+                max_steps => format!("step {} / {}", editor.current_steps(), max_steps),
+            };
+            let eta = match editor.remaining_time() {
+                Some(seconds) => format!("eta {:.1}s", seconds),
+                None => "eta unknown".to_string(),
+            };
+            ui.label(format!(
+                "{}, {:.0} steps/s, {}",
+                steps, editor.average_step_rate, eta
+            ));
+            ui.label(format!(
+                "waypoint {} / {}",
+                editor.current_goal_index(),
+                editor.map_config.waypoints.len()
+            ));
+        }
+        if let Some(job) = &editor.instant_job {
+            let (progress, text) = match job.post_stage {
+                Some(stage) => (
+                    PostProcessingStage::ALL
+                        .iter()
+                        .position(|other| *other == stage)
+                        .unwrap_or(0) as f32
+                        / PostProcessingStage::ALL.len() as f32,
+                    stage.label().to_string(),
+                ),
+                None => (
+                    job.goal_index as f32 / editor.map_config.waypoints.len().max(1) as f32,
+                    format!("{} steps", job.steps),
+                ),
+            };
+            let mut cancel = false;
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::ProgressBar::new(progress)
+                        .desired_width(150.0)
+                        .text(format!("{:.0}%, {}", progress * 100.0, text)),
+                );
+                cancel = ui.button("cancel").clicked();
+            });
+            if cancel {
+                editor.set_setup();
+            }
+        }
+        if editor.gen.walker.finished && !editor.is_setup() {
+            if let Some(stage) = editor.get_post_stage() {
+                ui.label(format!("next post processing stage: {}", stage.label()));
+            }
+        }
 
         // =======================================[ SEED CONTROL ]===================================
         if editor.is_setup() {
@@ -305,7 +514,156 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                     editor.save_map_dialog();
                 }
             });
+
+            ui.horizontal(|ui| {
+                // A/B mode compares configs on the same seed
+                if ui.checkbox(&mut editor.ab_mode, "A/B mode").changed() && editor.ab_mode {
+                    editor.fixed_seed = true;
+                }
+                ui.add_enabled_ui(editor.ab_mode, |ui| {
+                    field_edit_widget(
+                        ui,
+                        &mut editor.ab_draft_scale,
+                        edit_usize,
+                        "draft scale",
+                        true,
+                    );
+                });
+            });
+
+            if !editor.recent_seeds.is_empty() {
+                egui::ComboBox::from_label("recent seeds")
+                    .selected_text(editor.user_seed.seed_u64.to_string())
+                    .show_ui(ui, |ui| {
+                        for seed in editor.recent_seeds.clone() {
+                            if ui
+                                .selectable_label(
+                                    editor.user_seed.seed_u64 == seed,
+                                    seed.to_string(),
+                                )
+                                .clicked()
+                            {
+                                editor.user_seed = Seed::from_u64(seed);
+                            }
+                        }
+                    });
+            }
         }
+        ui.separator();
+        // =======================================[ EXPORT ]===================================
+        CollapsingHeader::new("export")
+            .default_open(false)
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "directory: {}",
+                        editor.export_settings.directory.to_string_lossy()
+                    ));
+                    if ui.button("browse").clicked() {
+                        editor.select_export_directory_dialog();
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("file name");
+                    ui.text_edit_singleline(&mut editor.export_settings.filename_template);
+                });
+                ui.label("supports {name}, {preset}, {map}, {seed} and {date}");
+
+                ui.label("if file exists:");
+                ui.horizontal_wrapped(|ui| {
+                    for policy in OverwritePolicy::ALL {
+                        ui.selectable_value(
+                            &mut editor.export_settings.overwrite,
+                            policy,
+                            format!("{:?}", policy),
+                        );
+                    }
+                });
+
+                ui.checkbox(&mut editor.export_settings.export_png, "png preview");
+                ui.checkbox(&mut editor.export_settings.export_json, "json provenance");
+                ui.checkbox(
+                    &mut editor.export_settings.export_genrec,
+                    "genrec recording",
+                );
+
+                let export_key = key_name(editor.keymap.get(EditorAction::ExportMap));
+                if ui.button(format!("export map ({})", export_key)).clicked() {
+                    editor.export_map();
+                }
+                if let Some(status) = &editor.export_status {
+                    ui.label(status);
+                }
+            });
+
+        ui.separator();
+        // =======================================[ MANUAL EDITS ]===================================
+        CollapsingHeader::new("manual edits")
+            .default_open(false)
+            .show(ui, |ui| {
+                ui.label("brush:");
+                ui.horizontal_wrapped(|ui| {
+                    ui.selectable_value(&mut editor.brush, None, "None");
+                    for block_type in BRUSH_BLOCKS {
+                        let label = format!("{:?}", block_type);
+                        ui.selectable_value(&mut editor.brush, Some(block_type), label);
+                    }
+                });
+                field_edit_widget(ui, &mut editor.brush_size, edit_usize, "brush size", true);
+
+                ui.add_enabled_ui(editor.edited_area.is_some(), |ui| {
+                    if ui.button("re-run safety passes").clicked() {
+                        editor.rerun_safety_passes();
+                    }
+                });
+                if let Some(status) = &editor.safety_status {
+                    ui.label(status);
+                }
+
+                ui.horizontal(|ui| {
+                    if ui
+                        .checkbox(&mut editor.select_region, "select region")
+                        .changed()
+                    {
+                        editor.brush = None;
+                    }
+                    ui.add_enabled_ui(editor.region.is_some(), |ui| {
+                        if ui.button("regenerate region").clicked() {
+                            editor.regenerate_region();
+                        }
+                    });
+                });
+                if let Some(status) = &editor.region_status {
+                    ui.label(status);
+                }
+            });
+
+        ui.separator();
+        // =======================================[ MAP STATISTICS ]===================================
+        CollapsingHeader::new("map statistics")
+            .default_open(false)
+            .show(ui, |ui| {
+                let stats = &editor.map_stats;
+                ui.label(format!("empty: {}", stats.empty));
+                ui.label(format!("freeze: {}", stats.freeze));
+                ui.label(format!("hookable: {}", stats.hookable));
+                ui.label(format!("platform blocks: {}", stats.platform));
+
+                let walker = &editor.gen.walker;
+                ui.label(format!(
+                    "corridor width: {}",
+                    corridor_width(&editor.gen.map, &walker.pos)
+                ));
+                match &walker.goal {
+                    Some(goal) => ui.label(format!(
+                        "distance to goal: {:.1}",
+                        walker.pos.distance(goal)
+                    )),
+                    None => ui.label("distance to goal: -"),
+                };
+            });
+
         ui.separator();
         // =======================================[ DEBUG LAYERS ]===================================
 
@@ -316,6 +674,30 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
             "debug layers",
             true,
         );
+        ui.checkbox(&mut editor.draw_cost_field, "draw shift costs");
+        ui.checkbox(&mut editor.draw_visit_heatmap, "draw visit heatmap");
+        ui.checkbox(&mut editor.draw_locked_positions, "draw locked positions");
+        for overlay in editor.overlays.iter_mut() {
+            ui.checkbox(&mut overlay.enabled, format!("draw {}", overlay.name));
+        }
+
+        ui.checkbox(&mut editor.follow_walker, "follow walker");
+        ui.add_enabled_ui(editor.follow_walker, |ui| {
+            field_edit_widget(
+                ui,
+                &mut editor.follow_smoothing,
+                edit_f32_bounded(0.0, 0.99),
+                "follow smoothing",
+                true,
+            );
+            field_edit_widget(
+                ui,
+                &mut editor.follow_zoom,
+                edit_f32_bounded(2.0, 100.0),
+                "follow zoom (kernels)",
+                true,
+            );
+        });
 
         ui.separator();
         // =======================================[ CONFIG STORAGE ]===================================
@@ -326,7 +708,7 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
             //     if let Some(path_in) =
             //         tinyfiledialogs::open_file_dialog("load config", &cwd.to_string_lossy(), None)
             //     {
-            //         editor.gen_config = GenerationConfig::load(&path_in);
+            //         editor.gen_config = GenerationConfig::load(&path_in).unwrap();
             //     }
             // }
             if ui.button("gen config").clicked() {
@@ -348,7 +730,7 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                 let cwd = env::current_dir().unwrap();
 
                 let initial_path = cwd
-                    .join(editor.gen_config.name.clone() + ".json")
+                    .join(editor.map_config.name.clone() + ".json")
                     .to_string_lossy()
                     .to_string();
 
@@ -360,6 +742,24 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
             };
         });
 
+        ui.label("load map layout:");
+        ui.horizontal(|ui| {
+            if ui.button("map config").clicked() {
+                editor.load_map_config_dialog();
+            }
+            if ui.button("trace .map").clicked() {
+                editor.import_map_layout_dialog();
+            }
+        });
+
+        ui.label("replay generation:");
+        if ui.button("load .genrec").clicked() {
+            editor.load_recording_dialog();
+        }
+        if let Some(status) = &editor.replay_status {
+            ui.label(status);
+        }
+
         ui.label("load generation config:");
         egui::ComboBox::from_label("")
             .selected_text(format!("{:}", editor.gen_config.name))
@@ -383,69 +783,135 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
             ui.checkbox(&mut editor.edit_map_config, "edit map");
         });
 
+        let config_errors = editor.gen_config.validate().err().unwrap_or_default();
+
         egui::ScrollArea::vertical().show(ui, |ui| {
             // =======================================[ GENERATION CONFIG EDIT ]===================================
             if editor.edit_gen_config {
                 ui.separator();
 
-                field_edit_widget(ui, &mut editor.gen_config.name, edit_string, "name", false);
+                if !config_errors.is_empty() {
+                    ui.label(
+                        RichText::new(format!("{} invalid fields!", config_errors.len()))
+                            .color(Color32::RED),
+                    );
+                }
 
-                field_edit_widget(
-                    ui,
-                    &mut editor.gen_config.inner_rad_mut_prob,
-                    edit_f32_prob,
-                    "inner rad mut prob",
-                    true,
-                );
-                field_edit_widget(
-                    ui,
-                    &mut editor.gen_config.inner_size_mut_prob,
-                    edit_f32_prob,
-                    "inner size mut prob",
-                    true,
-                );
+                field_edit_widget(ui, &mut editor.gen_config.name, edit_string, "name", false);
 
-                field_edit_widget(
-                    ui,
-                    &mut editor.gen_config.outer_rad_mut_prob,
-                    edit_f32_prob,
-                    "outer rad mut prob",
-                    true,
-                );
-                field_edit_widget(
-                    ui,
-                    &mut editor.gen_config.outer_size_mut_prob,
-                    edit_f32_prob,
-                    "outer size mut prob",
-                    true,
-                );
+                config_error_scope(ui, &config_errors, "inner_rad_mut_prob", |ui| {
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.inner_rad_mut_prob,
+                        edit_f32_prob,
+                        "inner rad mut prob",
+                        true,
+                    );
+                });
+                config_error_scope(ui, &config_errors, "inner_size_mut_prob", |ui| {
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.inner_size_mut_prob,
+                        edit_f32_prob,
+                        "inner size mut prob",
+                        true,
+                    );
+                });
 
-                ui.add_enabled_ui(editor.is_setup(), |ui| {
-                    random_dist_cfg_edit(
+                config_error_scope(ui, &config_errors, "outer_rad_mut_prob", |ui| {
+                    field_edit_widget(
                         ui,
-                        &mut editor.gen_config.inner_size_probs,
-                        Some(edit_usize),
-                        "inner size probs",
+                        &mut editor.gen_config.outer_rad_mut_prob,
+                        edit_f32_prob,
+                        "outer rad mut prob",
+                        true,
+                    );
+                });
+                config_error_scope(ui, &config_errors, "outer_size_mut_prob", |ui| {
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.outer_size_mut_prob,
+                        edit_f32_prob,
+                        "outer size mut prob",
                         true,
-                        false,
                     );
+                });
+
+                ui.add_enabled_ui(editor.is_setup(), |ui| {
+                    config_error_scope(ui, &config_errors, "inner_size_probs", |ui| {
+                        random_dist_cfg_edit(
+                            ui,
+                            &mut editor.gen_config.inner_size_probs,
+                            Some(edit_usize),
+                            "inner size probs",
+                            true,
+                            false,
+                        );
+                    });
+
+                    config_error_scope(ui, &config_errors, "outer_margin_probs", |ui| {
+                        random_dist_cfg_edit(
+                            ui,
+                            &mut editor.gen_config.outer_margin_probs,
+                            Some(edit_usize),
+                            "outer margin probs",
+                            true,
+                            false,
+                        );
+                    });
+
+                    config_error_scope(ui, &config_errors, "circ_probs", |ui| {
+                        random_dist_cfg_edit(
+                            ui,
+                            &mut editor.gen_config.circ_probs,
+                            Some(edit_f32_prob),
+                            "circularity probs",
+                            true,
+                            false,
+                        );
+                    });
+
+                    ui.label("freeze style");
+                    ui.horizontal_wrapped(|ui| {
+                        for style in FreezeStyle::ALL {
+                            ui.selectable_value(
+                                &mut editor.gen_config.freeze_style,
+                                style,
+                                format!("{:?}", style),
+                            );
+                        }
+                    });
+
+                    ui.label("corridor profile");
+                    ui.horizontal_wrapped(|ui| {
+                        edit_corridor_profile(ui, &mut editor.gen_config.corridor_profile)
+                    });
+
+                    config_error_scope(ui, &config_errors, "corridor_profile_strength", |ui| {
+                        field_edit_widget(
+                            ui,
+                            &mut editor.gen_config.corridor_profile_strength,
+                            edit_f32_prob,
+                            "corridor profile strength",
+                            true,
+                        );
+                    });
 
-                    random_dist_cfg_edit(
+                    vec_edit_widget(
                         ui,
-                        &mut editor.gen_config.outer_margin_probs,
-                        Some(edit_usize),
-                        "outer margin probs",
+                        &mut editor.gen_config.segment_corridor_profiles,
+                        edit_corridor_profile,
+                        "segment corridor profiles",
                         true,
                         false,
                     );
 
-                    random_dist_cfg_edit(
+                    field_edit_widget(
                         ui,
-                        &mut editor.gen_config.circ_probs,
-                        Some(edit_f32_prob),
-                        "circularity probs",
+                        &mut editor.gen_config.check_kernel_margin,
+                        edit_bool,
+                        "check kernel margin",
                         true,
-                        false,
                     );
                 });
 
@@ -459,81 +925,356 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                             "min distance",
                             true,
                         );
+                        config_error_scope(ui, &config_errors, "plat_width_bounds", |ui| {
+                            field_edit_widget(
+                                ui,
+                                &mut editor.gen_config.plat_width_bounds,
+                                edit_range_usize,
+                                "width bounds",
+                                true,
+                            );
+                        });
+                        config_error_scope(ui, &config_errors, "plat_height_bounds", |ui| {
+                            field_edit_widget(
+                                ui,
+                                &mut editor.gen_config.plat_height_bounds,
+                                edit_range_usize,
+                                "height bounds",
+                                true,
+                            );
+                        });
                         field_edit_widget(
                             ui,
-                            &mut editor.gen_config.plat_width_bounds,
-                            edit_range_usize,
-                            "width bounds",
+                            &mut editor.gen_config.plat_min_empty_height,
+                            edit_usize,
+                            "min empty height",
                             true,
                         );
                         field_edit_widget(
                             ui,
-                            &mut editor.gen_config.plat_height_bounds,
-                            edit_range_usize,
-                            "height bounds",
+                            &mut editor.gen_config.plat_soft_overhang,
+                            edit_bool,
+                            "soft overhang",
                             true,
                         );
                         field_edit_widget(
                             ui,
-                            &mut editor.gen_config.plat_min_empty_height,
+                            &mut editor.gen_config.plat_min_head_room,
                             edit_usize,
-                            "min empty height",
+                            "min head room",
                             true,
                         );
                         field_edit_widget(
                             ui,
-                            &mut editor.gen_config.plat_soft_overhang,
-                            edit_bool,
-                            "soft overhang",
+                            &mut editor.gen_config.plat_freeze_clearance,
+                            edit_usize,
+                            "freeze clearance",
                             true,
                         );
                     });
-                field_edit_widget(
-                    ui,
-                    &mut editor.gen_config.momentum_prob,
-                    edit_f32_prob,
-                    "momentum prob",
-                    true,
-                );
+                config_error_scope(ui, &config_errors, "momentum_prob", |ui| {
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.momentum_prob,
+                        edit_f32_prob,
+                        "momentum prob",
+                        true,
+                    );
+                });
 
                 field_edit_widget(
                     ui,
-                    &mut editor.gen_config.max_distance,
-                    edit_f32_bounded(0.1, 15.0),
-                    "max distance",
+                    &mut editor.gen_config.momentum_straight_gain,
+                    edit_f32_bounded(-0.1, 0.1),
+                    "momentum straight gain",
                     true,
                 );
 
-                field_edit_widget(
-                    ui,
-                    &mut editor.gen_config.waypoint_reached_dist,
-                    edit_usize,
-                    "waypoint reached dist",
-                    true,
-                );
+                config_error_scope(ui, &config_errors, "momentum_max_prob", |ui| {
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.momentum_max_prob,
+                        edit_f32_prob,
+                        "momentum max prob",
+                        true,
+                    );
+                });
 
-                ui.add_enabled_ui(editor.is_setup(), |ui| {
-                    random_dist_cfg_edit(
+                config_error_scope(ui, &config_errors, "turn_cost", |ui| {
+                    field_edit_widget(
                         ui,
-                        &mut editor.gen_config.shift_weights,
-                        // TODO: this is stupid wtf, but thats fine as this functionality
-                        // will be reworked with the upcoming dynamic weighting for cells anyways
-                        None::<fn(&mut Ui, &mut ShiftDirection)>,
-                        "step weights",
-                        false,
+                        &mut editor.gen_config.turn_cost,
+                        edit_f32_prob,
+                        "turn cost",
                         true,
                     );
                 });
 
-                field_edit_widget(
-                    ui,
-                    &mut editor.gen_config.skip_length_bounds,
-                    edit_range_usize,
-                    "skip length bounds",
-                    true,
-                );
+                config_error_scope(ui, &config_errors, "reverse_cost", |ui| {
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.reverse_cost,
+                        edit_f32_prob,
+                        "reverse cost",
+                        true,
+                    );
+                });
 
-                field_edit_widget(
+                config_error_scope(ui, &config_errors, "goal_distance_cost", |ui| {
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.goal_distance_cost,
+                        edit_f32_bounded(0.0, 10.0),
+                        "goal distance cost",
+                        true,
+                    );
+                });
+
+                config_error_scope(ui, &config_errors, "revisit_cost", |ui| {
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.revisit_cost,
+                        edit_f32_bounded(0.0, 10.0),
+                        "revisit cost",
+                        true,
+                    );
+                });
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.max_revisits,
+                    edit_usize,
+                    "max revisits",
+                    true,
+                );
+
+                config_error_scope(ui, &config_errors, "border_cost", |ui| {
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.border_cost,
+                        edit_f32_bounded(0.0, 10.0),
+                        "border cost",
+                        true,
+                    );
+                });
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.border_cost_range,
+                    edit_usize,
+                    "border cost range",
+                    true,
+                );
+
+                config_error_scope(ui, &config_errors, "parallel_cost", |ui| {
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.parallel_cost,
+                        edit_f32_bounded(0.0, 10.0),
+                        "parallel cost",
+                        true,
+                    );
+                });
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.max_straight_steps,
+                    edit_usize,
+                    "max straight steps",
+                    true,
+                );
+
+                ui.label("step strategy");
+                ui.horizontal_wrapped(|ui| {
+                    for kind in StepStrategyKind::ALL {
+                        ui.selectable_value(
+                            &mut editor.gen_config.step_strategy,
+                            kind,
+                            format!("{:?}", kind),
+                        );
+                    }
+                });
+
+                config_error_scope(ui, &config_errors, "strategy_noise", |ui| {
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.strategy_noise,
+                        edit_f32_prob,
+                        "strategy noise",
+                        true,
+                    );
+                });
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.strategy_lookahead,
+                    edit_usize,
+                    "strategy lookahead",
+                    true,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.backtrack_steps,
+                    edit_usize,
+                    "backtrack steps",
+                    true,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.max_backtracks,
+                    edit_usize,
+                    "max backtracks",
+                    true,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.max_distance,
+                    edit_f32_bounded(0.1, 15.0),
+                    "max distance",
+                    true,
+                );
+
+                ui.label("fill style");
+                ui.horizontal_wrapped(|ui| {
+                    for style in FillStyle::ALL {
+                        ui.selectable_value(
+                            &mut editor.gen_config.fill_style,
+                            style,
+                            format!("{:?}", style),
+                        );
+                    }
+                });
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.fill_noise_amplitude,
+                    edit_f32_bounded(0.0, 10.0),
+                    "fill noise amplitude",
+                    true,
+                );
+
+                config_error_scope(ui, &config_errors, "wall_smoothing", |ui| {
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.wall_smoothing,
+                        edit_usize,
+                        "wall smoothing",
+                        true,
+                    );
+                });
+
+                ui.label("corner holds");
+                ui.horizontal_wrapped(|ui| {
+                    for policy in CornerHoldPolicy::ALL {
+                        ui.selectable_value(
+                            &mut editor.gen_config.corner_hold_policy,
+                            policy,
+                            format!("{:?}", policy),
+                        );
+                    }
+                });
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.ceiling_lip_length,
+                    edit_usize,
+                    "ceiling lip length",
+                    true,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.enable_freeze_pockets,
+                    edit_bool,
+                    "enable freeze pockets",
+                    true,
+                );
+
+                ui.add_enabled_ui(editor.gen_config.enable_freeze_pockets, |ui| {
+                    config_error_scope(ui, &config_errors, "freeze_pocket_min_difficulty", |ui| {
+                        field_edit_widget(
+                            ui,
+                            &mut editor.gen_config.freeze_pocket_min_difficulty,
+                            edit_f32_prob,
+                            "freeze pocket min difficulty",
+                            true,
+                        );
+                    });
+
+                    config_error_scope(ui, &config_errors, "freeze_pocket_spacing", |ui| {
+                        field_edit_widget(
+                            ui,
+                            &mut editor.gen_config.freeze_pocket_spacing,
+                            edit_usize,
+                            "freeze pocket spacing",
+                            true,
+                        );
+                    });
+                });
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.checkpoint_interval,
+                    edit_usize,
+                    "checkpoint interval",
+                    true,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.border_thickness,
+                    edit_usize,
+                    "border thickness",
+                    true,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.waypoint_reached_dist,
+                    edit_usize,
+                    "waypoint reached dist",
+                    true,
+                );
+
+                config_error_scope(ui, &config_errors, "max_waypoint_distance", |ui| {
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.max_waypoint_distance,
+                        edit_usize,
+                        "max waypoint distance",
+                        true,
+                    );
+                });
+
+                ui.add_enabled_ui(editor.is_setup(), |ui| {
+                    config_error_scope(ui, &config_errors, "shift_weights", |ui| {
+                        random_dist_cfg_edit(
+                            ui,
+                            &mut editor.gen_config.shift_weights,
+                            // TODO: this is stupid wtf, but thats fine as this functionality
+                            // will be reworked with the upcoming dynamic weighting for cells anyways
+                            None::<fn(&mut Ui, &mut ShiftDirection)>,
+                            "step weights",
+                            false,
+                            true,
+                        );
+                    });
+                });
+
+                config_error_scope(ui, &config_errors, "skip_length_bounds", |ui| {
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.skip_length_bounds,
+                        edit_range_usize,
+                        "skip length bounds",
+                        true,
+                    );
+                });
+
+                field_edit_widget(
                     ui,
                     &mut editor.gen_config.skip_min_spacing_sqr,
                     edit_usize,
@@ -541,6 +1282,57 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                     true,
                 );
 
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.skip_mouth_size,
+                    edit_usize,
+                    "skip mouth size",
+                    true,
+                );
+
+                config_error_scope(ui, &config_errors, "wide_skip_prob", |ui| {
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.wide_skip_prob,
+                        edit_f32_prob,
+                        "wide skip prob",
+                        true,
+                    );
+                });
+
+                edit_overwrite_matrix(
+                    ui,
+                    &mut editor.gen_config.skip_overwrite,
+                    &[BlockType::Empty, BlockType::Freeze],
+                    "skip overwrite",
+                );
+
+                let mut separate_freeze_skips =
+                    editor.gen_config.freeze_skip_length_bounds.is_some();
+                if ui
+                    .checkbox(&mut separate_freeze_skips, "separate freeze skip config")
+                    .changed()
+                {
+                    editor.gen_config.freeze_skip_length_bounds =
+                        separate_freeze_skips.then_some(editor.gen_config.skip_length_bounds);
+                    editor.gen_config.freeze_skip_min_spacing_sqr =
+                        separate_freeze_skips.then_some(editor.gen_config.skip_min_spacing_sqr);
+                }
+                if let Some(bounds) = editor.gen_config.freeze_skip_length_bounds.as_mut() {
+                    config_error_scope(ui, &config_errors, "freeze_skip_length_bounds", |ui| {
+                        field_edit_widget(
+                            ui,
+                            bounds,
+                            edit_range_usize,
+                            "freeze skip length bounds",
+                            true,
+                        );
+                    });
+                }
+                if let Some(spacing) = editor.gen_config.freeze_skip_min_spacing_sqr.as_mut() {
+                    field_edit_widget(ui, spacing, edit_usize, "freeze skip min spacing sqr", true);
+                }
+
                 field_edit_widget(
                     ui,
                     &mut editor.gen_config.max_level_skip,
@@ -549,6 +1341,60 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                     true,
                 );
 
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.skip_path_band,
+                    edit_usize,
+                    "skip path band",
+                    true,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.skip_path_separation,
+                    edit_usize,
+                    "skip path separation",
+                    true,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.enable_deep_skips,
+                    edit_bool,
+                    "enable deep skips",
+                    true,
+                );
+
+                ui.add_enabled_ui(editor.gen_config.enable_deep_skips, |ui| {
+                    config_error_scope(ui, &config_errors, "deep_skip_length_bounds", |ui| {
+                        field_edit_widget(
+                            ui,
+                            &mut editor.gen_config.deep_skip_length_bounds,
+                            edit_range_usize,
+                            "deep skip length bounds",
+                            true,
+                        );
+                    });
+
+                    config_error_scope(ui, &config_errors, "deep_skip_level_bounds", |ui| {
+                        field_edit_widget(
+                            ui,
+                            &mut editor.gen_config.deep_skip_level_bounds,
+                            edit_range_usize,
+                            "deep skip level bounds",
+                            true,
+                        );
+                    });
+
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.max_deep_skips,
+                        edit_usize,
+                        "max deep skips",
+                        true,
+                    );
+                });
+
                 field_edit_widget(
                     ui,
                     &mut editor.gen_config.min_freeze_size,
@@ -557,6 +1403,35 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                     false,
                 );
 
+                config_error_scope(ui, &config_errors, "doodad_density", |ui| {
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.doodad_density,
+                        edit_f32_prob,
+                        "doodad density",
+                        false,
+                    );
+                });
+
+                egui::ComboBox::from_label("theme")
+                    .selected_text(
+                        editor
+                            .gen_config
+                            .theme
+                            .clone()
+                            .unwrap_or("template".to_string()),
+                    )
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut editor.gen_config.theme, None, "template");
+                        for name in editor.init_themes.keys() {
+                            ui.selectable_value(
+                                &mut editor.gen_config.theme,
+                                Some(name.clone()),
+                                name,
+                            );
+                        }
+                    });
+
                 field_edit_widget(
                     ui,
                     &mut editor.gen_config.enable_pulse,
@@ -589,38 +1464,327 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                     false,
                 );
 
+                config_error_scope(ui, &config_errors, "pulse_patterns", |ui| {
+                    vec_edit_widget(
+                        ui,
+                        &mut editor.gen_config.pulse_patterns,
+                        edit_pulse_pattern,
+                        "pulse patterns",
+                        true,
+                        false,
+                    );
+                });
+
+                CollapsingHeader::new("SEGMENT FADE")
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        field_edit_widget(
+                            ui,
+                            &mut editor.gen_config.enable_segment_fade,
+                            edit_bool,
+                            "enable",
+                            false,
+                        );
+                        config_error_scope(ui, &config_errors, "segment_fade_max_size", |ui| {
+                            field_edit_widget(
+                                ui,
+                                &mut editor.gen_config.segment_fade_max_size,
+                                edit_usize,
+                                "max size",
+                                false,
+                            );
+                        });
+                        config_error_scope(ui, &config_errors, "segment_fade_min_size", |ui| {
+                            field_edit_widget(
+                                ui,
+                                &mut editor.gen_config.segment_fade_min_size,
+                                edit_usize,
+                                "min size",
+                                false,
+                            );
+                        });
+                    });
+
+                CollapsingHeader::new("DIFFICULTY SCHEDULE")
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        ui.label("curve");
+                        ui.horizontal_wrapped(|ui| {
+                            for curve in DifficultyCurve::ALL {
+                                ui.selectable_value(
+                                    &mut editor.gen_config.difficulty_curve,
+                                    curve,
+                                    format!("{:?}", curve),
+                                );
+                            }
+                        });
+                        if editor.gen_config.difficulty_curve == DifficultyCurve::Keyframes {
+                            config_error_scope(ui, &config_errors, "difficulty_keyframes", |ui| {
+                                vec_edit_widget(
+                                    ui,
+                                    &mut editor.gen_config.difficulty_keyframes,
+                                    edit_difficulty_keyframe,
+                                    "keyframes",
+                                    false,
+                                    false,
+                                );
+                            });
+                        }
+                        field_edit_widget(
+                            ui,
+                            &mut editor.gen_config.difficulty_kernel_shrink,
+                            edit_usize,
+                            "kernel shrink",
+                            false,
+                        );
+                        field_edit_widget(
+                            ui,
+                            &mut editor.gen_config.difficulty_freeze_growth,
+                            edit_usize,
+                            "freeze growth",
+                            false,
+                        );
+                        config_error_scope(
+                            ui,
+                            &config_errors,
+                            "difficulty_platform_spacing",
+                            |ui| {
+                                field_edit_widget(
+                                    ui,
+                                    &mut editor.gen_config.difficulty_platform_spacing,
+                                    edit_f32_bounded(0.0, 10.0),
+                                    "platform spacing",
+                                    false,
+                                );
+                            },
+                        );
+                    });
+
+                CollapsingHeader::new("REST ROOMS")
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        field_edit_widget(
+                            ui,
+                            &mut editor.gen_config.enable_rest_rooms,
+                            edit_bool,
+                            "enable",
+                            false,
+                        );
+                        field_edit_widget(
+                            ui,
+                            &mut editor.gen_config.rest_room_size,
+                            edit_usize,
+                            "room size",
+                            false,
+                        );
+                        field_edit_widget(
+                            ui,
+                            &mut editor.gen_config.rest_room_platform_margin,
+                            edit_usize,
+                            "platform margin",
+                            false,
+                        );
+                    });
+
+                CollapsingHeader::new("START/FINISH ROOMS")
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        field_edit_widget(
+                            ui,
+                            &mut editor.gen_config.start_room_size,
+                            edit_usize,
+                            "start room size",
+                            false,
+                        );
+                        field_edit_widget(
+                            ui,
+                            &mut editor.gen_config.start_room_platform_margin,
+                            edit_usize,
+                            "start platform margin",
+                            false,
+                        );
+                        ui.label("start room opening");
+                        ui.horizontal_wrapped(|ui| {
+                            for opening in RoomOpening::ALL {
+                                ui.selectable_value(
+                                    &mut editor.gen_config.start_room_opening,
+                                    opening,
+                                    format!("{:?}", opening),
+                                );
+                            }
+                        });
+                        field_edit_widget(
+                            ui,
+                            &mut editor.gen_config.finish_room_size,
+                            edit_usize,
+                            "finish room size",
+                            false,
+                        );
+                        ui.label("finish room");
+                        ui.horizontal_wrapped(|ui| {
+                            for layout in FinishRoom::ALL {
+                                ui.selectable_value(
+                                    &mut editor.gen_config.finish_room,
+                                    layout,
+                                    format!("{:?}", layout),
+                                );
+                            }
+                        });
+                        field_edit_widget(
+                            ui,
+                            &mut editor.gen_config.room_stretch_max,
+                            edit_usize,
+                            "room stretch max",
+                            false,
+                        );
+                        field_edit_widget(
+                            ui,
+                            &mut editor.gen_config.room_platform_shift_max,
+                            edit_usize,
+                            "room platform shift max",
+                            false,
+                        );
+                        config_error_scope(ui, &config_errors, "room_pillar_prob", |ui| {
+                            field_edit_widget(
+                                ui,
+                                &mut editor.gen_config.room_pillar_prob,
+                                edit_f32_prob,
+                                "room pillar prob",
+                                false,
+                            );
+                        });
+                    });
+
                 field_edit_widget(
                     ui,
-                    &mut editor.gen_config.fade_steps,
+                    &mut editor.gen_config.max_walker_steps,
                     edit_usize,
-                    "fade steps",
+                    "max walker steps",
                     false,
                 );
 
                 field_edit_widget(
                     ui,
-                    &mut editor.gen_config.fade_max_size,
+                    &mut editor.gen_config.max_carved_cells,
                     edit_usize,
-                    "fade max size",
+                    "max carved cells",
                     false,
                 );
 
+                ui.label("truncation mode");
+                ui.horizontal_wrapped(|ui| {
+                    for mode in TruncationMode::ALL {
+                        ui.selectable_value(
+                            &mut editor.gen_config.truncation_mode,
+                            mode,
+                            format!("{:?}", mode),
+                        );
+                    }
+                });
+
+                ui.add_enabled_ui(
+                    editor.gen_config.truncation_mode == TruncationMode::NearestWaypoint,
+                    |ui| {
+                        field_edit_widget(
+                            ui,
+                            &mut editor.gen_config.truncation_steps,
+                            edit_usize,
+                            "truncation steps",
+                            false,
+                        );
+                    },
+                );
+
                 field_edit_widget(
                     ui,
-                    &mut editor.gen_config.fade_min_size,
+                    &mut editor.gen_config.fade_steps,
                     edit_usize,
-                    "fade min size",
+                    "fade steps",
                     false,
                 );
 
+                config_error_scope(ui, &config_errors, "fade_max_size", |ui| {
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.fade_max_size,
+                        edit_usize,
+                        "fade max size",
+                        false,
+                    );
+                });
+
+                config_error_scope(ui, &config_errors, "fade_min_size", |ui| {
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.fade_min_size,
+                        edit_usize,
+                        "fade min size",
+                        false,
+                    );
+                });
+
                 field_edit_widget(
                     ui,
-                    &mut editor.gen_config.max_subwaypoint_dist,
-                    edit_f32_bounded(0.1, 100.0),
-                    "subpoint max dist",
+                    &mut editor.gen_config.fade_reserved,
+                    edit_bool,
+                    "fade reserved",
                     false,
                 );
 
+                let mut separate_reserved_steps = editor.gen_config.reserved_steps.is_some();
+                if ui
+                    .checkbox(&mut separate_reserved_steps, "separate reserved steps")
+                    .changed()
+                {
+                    editor.gen_config.reserved_steps =
+                        separate_reserved_steps.then_some(editor.gen_config.get_reserved_steps());
+                }
+                if let Some(steps) = editor.gen_config.reserved_steps.as_mut() {
+                    config_error_scope(ui, &config_errors, "reserved_steps", |ui| {
+                        field_edit_widget(ui, steps, edit_range_usize, "reserved steps", false);
+                    });
+                }
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.overwrite_reserved,
+                    edit_bool,
+                    "overwrite reserved",
+                    false,
+                );
+
+                ui.label("goal selection:");
+                ui.horizontal_wrapped(|ui| {
+                    for goal_selection in GoalSelection::ALL {
+                        ui.selectable_value(
+                            &mut editor.gen_config.goal_selection,
+                            goal_selection,
+                            format!("{:?}", goal_selection),
+                        );
+                    }
+                });
+                if editor.gen_config.goal_selection == GoalSelection::Random {
+                    config_error_scope(ui, &config_errors, "goal_distance_weight", |ui| {
+                        field_edit_widget(
+                            ui,
+                            &mut editor.gen_config.goal_distance_weight,
+                            edit_f32_bounded(0.0, 5.0),
+                            "goal distance weight",
+                            false,
+                        );
+                    });
+                }
+
+                config_error_scope(ui, &config_errors, "max_subwaypoint_dist", |ui| {
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.max_subwaypoint_dist,
+                        edit_f32_bounded(0.1, 100.0),
+                        "subpoint max dist",
+                        false,
+                    );
+                });
+
                 field_edit_widget(
                     ui,
                     &mut editor.gen_config.subwaypoint_max_shift_dist,
@@ -671,18 +1835,175 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                     "map height",
                     true,
                 );
+                let map_config_errors = editor
+                    .map_config
+                    .validate(&editor.gen_config)
+                    .err()
+                    .unwrap_or_default();
+                let mut override_room_sizes = editor.map_config.start_room_size.is_some()
+                    || editor.map_config.finish_room_size.is_some();
+                if ui
+                    .checkbox(&mut override_room_sizes, "override room sizes")
+                    .changed()
+                {
+                    editor.map_config.start_room_size =
+                        override_room_sizes.then_some(editor.gen_config.start_room_size);
+                    editor.map_config.finish_room_size =
+                        override_room_sizes.then_some(editor.gen_config.finish_room_size);
+                }
+                if let Some(size) = editor.map_config.start_room_size.as_mut() {
+                    config_error_scope(ui, &map_config_errors, "start_room_size", |ui| {
+                        field_edit_widget(ui, size, edit_usize, "start room size", true);
+                    });
+                }
+                if let Some(size) = editor.map_config.finish_room_size.as_mut() {
+                    config_error_scope(ui, &map_config_errors, "finish_room_size", |ui| {
+                        field_edit_widget(ui, size, edit_usize, "finish room size", true);
+                    });
+                }
+                ui.checkbox(&mut editor.ddnet_coordinates, "ddnet coordinates");
                 ui.add_enabled_ui(editor.is_setup(), |ui| {
-                    vec_edit_widget(
+                    config_error_scope(ui, &map_config_errors, "waypoints", |ui| {
+                        vec_edit_widget(
+                            ui,
+                            &mut editor.map_config.waypoints,
+                            match editor.ddnet_coordinates {
+                                true => edit_ddnet_position,
+                                false => edit_position,
+                            },
+                            "waypoints",
+                            true,
+                            false,
+                        );
+                    });
+                    for error in map_config_errors.iter() {
+                        ui.label(RichText::new(error.to_string()).color(Color32::RED));
+                    }
+                    if !map_config_errors.is_empty() && ui.button("repair waypoints").clicked() {
+                        let (inserted, removed) =
+                            editor.map_config.repair_waypoints(&editor.gen_config);
+                        println!(
+                            "repaired waypoints: {} inserted, {} removed",
+                            inserted, removed
+                        );
+                    }
+                });
+                ui.horizontal(|ui| {
+                    if ui
+                        .checkbox(&mut editor.draw_waypoint_path, "draw waypoint path")
+                        .changed()
+                    {
+                        editor.draw_no_go_zones = false;
+                    }
+                });
+                if editor.draw_waypoint_path {
+                    field_edit_widget(
                         ui,
-                        &mut editor.map_config.waypoints,
-                        edit_position,
-                        "waypoints",
+                        &mut editor.waypoint_path_tolerance,
+                        edit_f32_bounded(0.0, 20.0),
+                        "path tolerance",
                         true,
-                        false,
                     );
+                    field_edit_widget(
+                        ui,
+                        &mut editor.waypoint_path_spacing,
+                        edit_usize,
+                        "waypoint spacing",
+                        true,
+                    );
+                }
+                ui.horizontal(|ui| {
+                    if ui
+                        .checkbox(&mut editor.draw_no_go_zones, "draw no-go zones")
+                        .changed()
+                    {
+                        editor.draw_waypoint_path = false;
+                    }
+                    if ui.button("clear").clicked() {
+                        editor.map_config.no_go_zones.clear();
+                    }
+                });
+                ui.label(format!(
+                    "{} no-go zones",
+                    editor.map_config.no_go_zones.len()
+                ));
+            }
+        });
+    });
+}
+
+pub fn settings_window(ctx: &Context, editor: &mut Editor) {
+    egui::Window::new("SETTINGS")
+        .frame(window_frame())
+        .default_open(false)
+        .show(ctx, |ui| {
+            ui.label("keybinds:");
+            for action in EditorAction::ALL {
+                ui.horizontal(|ui| {
+                    ui.label(action.label());
+                    let text = match editor.rebind_action == Some(action) {
+                        true => "press a key (esc to cancel)".to_string(),
+                        false => key_name(editor.keymap.get(action)),
+                    };
+                    if ui.button(text).clicked() {
+                        editor.rebind_action = Some(action);
+                    }
                 });
             }
+
+            if ui.button("reset keybinds").clicked() {
+                editor.keymap = Default::default();
+                editor.rebind_action = None;
+                editor.save_keymap();
+            }
         });
+}
+
+/// shows the block below the mouse and the info of all visible debug layers at its position
+pub fn hover_inspector(ctx: &Context, editor: &Editor) {
+    if ctx.is_pointer_over_area() || editor.is_playing() {
+        return;
+    }
+    let pos = match editor.mouse_to_map_pos() {
+        Some(pos) => pos,
+        None => return,
+    };
+
+    let mut layer_infos: Vec<(&str, &String)> = editor
+        .gen
+        .debug_layers
+        .iter()
+        .filter(|(name, _)| editor.visualize_debug_layers.get(*name) == Some(&true))
+        .filter_map(|(name, layer)| Some((*name, layer.info.get(&pos.as_index())?)))
+        .collect();
+    if layer_infos.is_empty() {
+        return;
+    }
+    layer_infos.sort_by_key(|(name, _)| *name);
+
+    egui::show_tooltip_at_pointer(ctx, egui::Id::new("hover_inspector"), |ui| {
+        let marker = match &editor.gen.map.markers[pos.as_index()] {
+            Some(marker) => format!(" ({:?})", marker),
+            None => String::new(),
+        };
+        let coordinates = match editor.ddnet_coordinates {
+            true => {
+                let (x, y) = pos.to_ddnet();
+                format!("{:.1}, {:.1}", x, y)
+            }
+            false => format!("{}, {}", pos.x, pos.y),
+        };
+        ui.label(format!(
+            "{}: {:?}{}",
+            coordinates,
+            editor.gen.map.grid[pos.as_index()],
+            marker
+        ));
+        for (name, info) in layer_infos {
+            ui.separator();
+            ui.label(RichText::new(name).strong());
+            ui.label(info);
+        }
     });
 }
 
@@ -699,5 +2020,74 @@ pub fn debug_window(ctx: &Context, editor: &mut Editor) {
             ui.add(Label::new(format!("seed: {:?}", editor.user_seed)));
             ui.add(Label::new(format!("config: {:?}", &editor.gen_config)));
             ui.add(Label::new(format!("walker: {:?}", &editor.gen.walker)));
+
+            ui.separator();
+            ui.checkbox(&mut editor.record_walker_events, "record walker events");
+            ui.checkbox(&mut editor.record_trace, "trace rng samples");
+            if let Some(event_log) = &editor.gen.walker.event_log {
+                CollapsingHeader::new(format!("timeline ({} events)", event_log.len()))
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        for event in event_log.iter().rev().take(TIMELINE_EVENTS) {
+                            ui.label(format!("{}: {:?}", event.step, event.kind));
+                        }
+                    });
+                if ui.button("save event log").clicked() {
+                    editor.save_event_log_dialog();
+                }
+            }
+
+            ui.separator();
+            ui.checkbox(&mut editor.draw_segment_reports, "draw segment difficulty");
+            CollapsingHeader::new(format!("segments ({})", editor.segment_reports.len()))
+                .default_open(false)
+                .show(ui, |ui| {
+                    for report in editor.segment_reports.iter() {
+                        ui.label(format!(
+                            "{}: {:.2} ({:?})",
+                            report.index, report.difficulty, report.feature
+                        ));
+                    }
+                });
+            if let Some(metrics) = &editor.map_metrics {
+                CollapsingHeader::new("metrics")
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        ui.label(format!("path length: {}", metrics.path_length));
+                        ui.label(format!("avg tunnel width: {:.2}", metrics.avg_tunnel_width));
+                        ui.label(format!("freeze ratio: {:.2}", metrics.freeze_ratio));
+                        ui.label(format!("skips: {}", metrics.skips));
+                        ui.label(format!("dead ends: {}", metrics.dead_ends));
+                        ui.label(format!("difficulty: {:.2}", metrics.difficulty));
+                    });
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("set diff base").clicked() {
+                    editor.diff_base = Some(editor.gen.map.clone());
+                    editor.map_diff = None;
+                }
+                ui.add_enabled_ui(editor.diff_base.is_some(), |ui| {
+                    if ui.button("compare").clicked() {
+                        editor.compare_to_diff_base();
+                    }
+                    if ui.button("clear").clicked() {
+                        editor.diff_base = None;
+                        editor.map_diff = None;
+                    }
+                });
+            });
+            if let Some(map_diff) = &editor.map_diff {
+                ui.add(Label::new(map_diff.summary()));
+            }
+
+            ui.separator();
+            if ui.button("run regression").clicked() {
+                editor.run_regression_dialog();
+            }
+            if let Some(summary) = &editor.regression_summary {
+                ui.add(Label::new(summary));
+            }
         });
 }
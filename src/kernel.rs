@@ -61,3 +61,58 @@ impl Kernel {
         kernel
     }
 }
+
+/// step size used to sweep circularities when building a ValidKernelTable
+const CIRCULARITY_SWEEP_STEP: f32 = 0.01;
+
+/// all distinct kernel shapes up to a max size. Many circularities result in the same kernel
+/// vector, so each shape is stored once with the range of circularities that produce it.
+#[derive(Debug, Clone)]
+pub struct ValidKernelTable {
+    /// (size, min circularity, max circularity) of every distinct shape, ordered by size and
+    /// circularity
+    pub entries: Vec<(usize, f32, f32)>,
+}
+
+impl ValidKernelTable {
+    pub fn new(max_size: usize) -> ValidKernelTable {
+        let num_steps = (1.0 / CIRCULARITY_SWEEP_STEP).round() as usize;
+        let mut entries: Vec<(usize, f32, f32)> = Vec::new();
+
+        for size in 1..=max_size {
+            let mut last_vector: Option<Array2<bool>> = None;
+            for step in 0..=num_steps {
+                let circularity = step as f32 / num_steps as f32;
+                if !ValidKernelTable::is_valid(size, circularity) {
+                    break;
+                }
+
+                let vector = Kernel::new(size, circularity).vector;
+                if last_vector.as_ref() == Some(&vector) {
+                    entries.last_mut().unwrap().2 = circularity;
+                } else {
+                    entries.push((size, circularity, circularity));
+                    last_vector = Some(vector);
+                }
+            }
+        }
+
+        ValidKernelTable { entries }
+    }
+
+    /// whether the walker can use this combination, small kernels are always rect
+    pub fn is_valid(size: usize, circularity: f32) -> bool {
+        size > 3 || circularity == 0.0
+    }
+
+    /// index of the entry with the same shape, None if the combination is not valid
+    pub fn find(&self, size: usize, circularity: f32) -> Option<usize> {
+        if !ValidKernelTable::is_valid(size, circularity) {
+            return None;
+        }
+
+        self.entries
+            .iter()
+            .rposition(|(entry_size, min_circ, _)| *entry_size == size && *min_circ <= circularity)
+    }
+}
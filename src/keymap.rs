@@ -0,0 +1,203 @@
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use macroquad::input::{is_key_pressed, KeyCode};
+use serde::{Deserialize, Serialize};
+
+/// editor actions that can be triggered by a hotkey
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EditorAction {
+    ExportMap,
+    Generate,
+    ResetCamera,
+    FollowWalker,
+}
+
+impl EditorAction {
+    pub const ALL: [EditorAction; 4] = [
+        EditorAction::ExportMap,
+        EditorAction::Generate,
+        EditorAction::ResetCamera,
+        EditorAction::FollowWalker,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            EditorAction::ExportMap => "export map",
+            EditorAction::Generate => "generate map",
+            EditorAction::ResetCamera => "refocus camera",
+            EditorAction::FollowWalker => "follow walker",
+        }
+    }
+
+    fn default_key(&self) -> KeyCode {
+        match self {
+            EditorAction::ExportMap => KeyCode::E,
+            EditorAction::Generate => KeyCode::Space,
+            EditorAction::ResetCamera => KeyCode::R,
+            EditorAction::FollowWalker => KeyCode::F,
+        }
+    }
+}
+
+/// keys that can be bound to editor actions
+const BINDABLE_KEYS: [KeyCode; 58] = [
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+    KeyCode::Space,
+    KeyCode::Enter,
+    KeyCode::Tab,
+    KeyCode::Backspace,
+    KeyCode::Delete,
+    KeyCode::Insert,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+];
+
+pub fn key_name(key: KeyCode) -> String {
+    format!("{:?}", key)
+}
+
+/// inverse of key_name, None if the key can't be bound
+pub fn parse_key(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS
+        .iter()
+        .find(|key| key_name(**key) == name)
+        .copied()
+}
+
+pub fn is_bindable(key: KeyCode) -> bool {
+    BINDABLE_KEYS.contains(&key)
+}
+
+/// hotkeys of all editor actions
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keymap {
+    bindings: HashMap<EditorAction, KeyCode>,
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        Keymap {
+            bindings: EditorAction::ALL
+                .iter()
+                .map(|action| (*action, action.default_key()))
+                .collect(),
+        }
+    }
+}
+
+impl Keymap {
+    pub fn get(&self, action: EditorAction) -> KeyCode {
+        self.bindings
+            .get(&action)
+            .copied()
+            .unwrap_or(action.default_key())
+    }
+
+    /// binds the key to the action. Other actions using the same key are swapped to the previous
+    /// key of the action, so every action keeps a unique hotkey.
+    pub fn set(&mut self, action: EditorAction, key: KeyCode) {
+        let previous_key = self.get(action);
+        for other in EditorAction::ALL {
+            if other != action && self.get(other) == key {
+                self.bindings.insert(other, previous_key);
+            }
+        }
+        self.bindings.insert(action, key);
+    }
+
+    pub fn is_pressed(&self, action: EditorAction) -> bool {
+        is_key_pressed(self.get(action))
+    }
+
+    /// location of the persisted keymap in the users config directory
+    pub fn get_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("gores-mapgen").join("keymap.json"))
+    }
+
+    /// loads the keymap, unknown or missing bindings use their default key
+    pub fn load(path: &PathBuf) -> Result<Keymap, &'static str> {
+        let data = fs::read_to_string(path).map_err(|_| "failed to read keymap")?;
+        let stored: HashMap<EditorAction, String> =
+            serde_json::from_str(&data).map_err(|_| "failed to parse keymap")?;
+
+        let mut keymap = Keymap::default();
+        for (action, name) in stored.iter() {
+            if let Some(key) = parse_key(name) {
+                keymap.set(*action, key);
+            }
+        }
+
+        Ok(keymap)
+    }
+
+    pub fn save(&self, path: &PathBuf) -> Result<(), &'static str> {
+        let stored: HashMap<EditorAction, String> = self
+            .bindings
+            .iter()
+            .map(|(action, key)| (*action, key_name(*key)))
+            .collect();
+        let data =
+            serde_json::to_string_pretty(&stored).map_err(|_| "failed to serialize keymap")?;
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|_| "failed to create keymap directory")?;
+        }
+        fs::write(path, data).map_err(|_| "failed to write keymap")
+    }
+
+    /// loads the persisted keymap, or the default keymap if there is none
+    pub fn load_or_default() -> Keymap {
+        Keymap::get_path()
+            .and_then(|path| Keymap::load(&path).ok())
+            .unwrap_or_default()
+    }
+}
@@ -1,14 +1,34 @@
+pub mod analysis;
+pub mod audit;
+pub mod builder;
 pub mod config;
 pub mod debug;
+pub mod diff;
+pub mod distance;
 pub mod editor;
+pub mod export;
 pub mod fps_control;
 pub mod generator;
+pub mod genrec;
 pub mod gui;
 pub mod kernel;
+pub mod keymap;
 pub mod map;
+pub mod metrics;
+pub mod naming;
+pub mod pathfinding;
 pub mod position;
+pub mod position_history;
 pub mod post_processing;
 pub mod random;
+pub mod regression;
 pub mod rendering;
+pub mod schema;
+pub mod session;
+pub mod step_strategy;
+pub mod trace;
 pub mod twmap_export;
+pub mod twmap_import;
+pub mod validation;
+pub mod vote;
 pub mod walker;
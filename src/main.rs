@@ -5,16 +5,19 @@ use gores_mapgen::{
     config::{GenerationConfig, MapConfig},
     editor::*,
     fps_control::*,
-    map::*,
     rendering::*,
+    session::EditorSession,
 };
-use macroquad::{color::*, miniquad, window::*};
+use macroquad::{color::*, input::*, miniquad, window::*};
 use miniquad::conf::{Conf, Platform};
 use simple_logger::SimpleLogger;
-use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
 
 const DISABLE_VSYNC: bool = true;
 
+/// radius around the walker in which shift costs are drawn
+const COST_FIELD_RADIUS: usize = 15;
+
 #[derive(Parser, Debug)]
 #[command(name = "Random Gores Map Generator")]
 #[command(version = crate_version!())]
@@ -26,11 +29,24 @@ struct Args {
     /// enable instant, auto generate and fixed seed
     #[arg(short, long)]
     testing: bool,
+
+    /// additionally load all generation presets from this directory
+    #[arg(short, long)]
+    preset_dir: Option<PathBuf>,
+
+    /// neither restore nor save the editor session
+    #[arg(short, long)]
+    no_session: bool,
 }
 
 fn window_conf() -> Conf {
+    let (window_width, window_height) =
+        EditorSession::load_stored().unwrap_or_default().window_size;
+
     Conf {
         window_title: "egui with macroquad".to_owned(),
+        window_width: window_width as i32,
+        window_height: window_height as i32,
         platform: Platform {
             swap_interval: match DISABLE_VSYNC {
                 true => Some(0), // set swap_interval to 0 to disable vsync
@@ -53,6 +69,15 @@ async fn main() {
     );
     let mut fps_ctrl = FPSControl::new().with_max_fps(60);
 
+    if !args.no_session {
+        if let Some(session) = EditorSession::load_stored() {
+            editor.restore_session(session);
+        }
+
+        // keep the window open until the session is saved
+        prevent_quit();
+    }
+
     if args.testing {
         editor.instant = true;
         editor.fixed_seed = true;
@@ -60,6 +85,10 @@ async fn main() {
         editor.edit_gen_config = true;
     }
 
+    if let Some(preset_dir) = args.preset_dir {
+        editor.init_gen_configs = GenerationConfig::get_all_configs_with_dir(&preset_dir);
+    }
+
     if let Some(config_name) = args.config {
         if editor.init_gen_configs.contains_key(&config_name) {
             editor.gen_config = editor.init_gen_configs.get(&config_name).unwrap().clone();
@@ -67,6 +96,11 @@ async fn main() {
     }
 
     loop {
+        if is_quit_requested() {
+            editor.save_session();
+            break;
+        }
+
         fps_ctrl.on_frame_start();
         editor.on_frame_start();
 
@@ -75,9 +109,20 @@ async fn main() {
             editor.set_playing();
         }
 
+        // instant generation runs on a worker thread, so the ui stays responsive
+        if editor.instant
+            && editor.is_playing()
+            && !editor.gen.walker.finished
+            && editor.instant_job.is_none()
+        {
+            editor.start_instant_job();
+        }
+        editor.update_instant_job();
+        editor.update_ab_mode();
+
         // perform walker step
-        let steps = match editor.instant {
-            true => usize::max_value(),
+        let steps = match editor.instant_job.is_some() {
+            true => 0,
             false => editor.steps_per_frame,
         };
 
@@ -96,21 +141,26 @@ async fn main() {
                 editor.set_stopped();
             }
         }
+        editor.map_stats.update(&mut editor.gen.map);
 
-        // this is called ONCE after map was generated
-        if editor.gen.walker.finished && !editor.is_setup() {
-            // kinda crappy, but ensure that even a panic doesnt crash the program
-            let _ = panic::catch_unwind(AssertUnwindSafe(|| {
-                editor
-                    .gen
-                    .perform_all_post_processing(&editor.gen_config)
-                    .unwrap_or_else(|err| {
-                        println!("Post Processing Failed: {:}", err);
-                    });
-            }));
-
-            // switch into setup mode for next map
-            editor.set_setup();
+        // post processing after map was generated, either at once or one stage per step
+        let post_process = match editor.step_post_processing {
+            true => editor.is_playing(),
+            false => !editor.is_setup(),
+        };
+        if editor.gen.walker.finished && post_process {
+            let done = editor.post_process(editor.step_post_processing);
+
+            if editor.step_post_processing && editor.is_single_setp() {
+                editor.set_stopped();
+            }
+
+            if done {
+                editor.analyze_map();
+
+                // switch into setup mode for next map
+                editor.set_setup();
+            }
         }
 
         editor.define_egui();
@@ -121,16 +171,43 @@ async fn main() {
         // draw_grid_blocks(&editor.gen.map.grid);
         draw_chunked_grid(
             &editor.gen.map.grid,
+            &editor.gen.map.markers,
             &editor.gen.map.chunk_edited,
             editor.gen.map.chunk_size,
         );
 
-        // TODO: group in some "debug" visualization call
-        draw_walker_kernel(&editor.gen.walker, KernelType::Outer);
-        draw_walker_kernel(&editor.gen.walker, KernelType::Inner);
-        draw_walker(&editor.gen.walker);
-        draw_waypoints(&editor.gen.walker.waypoints, colors::BLUE);
-        draw_waypoints(&editor.map_config.waypoints, colors::RED);
+        editor.draw_overlays();
+        draw_no_go_zones(&editor.map_config.no_go_zones, colors::MAGENTA);
+        draw_waypoints(&editor.drawn_path, colors::GREEN);
+        if let Some(zone) = editor.get_pending_no_go_zone() {
+            draw_no_go_zones(&[zone], colors::PINK);
+        }
+        if let Some((top_left, bot_right)) = editor.get_pending_region().or(editor.region.clone()) {
+            draw_area(&top_left, &bot_right, colors::ORANGE);
+        }
+        if let (true, Some(positions)) = (
+            editor.draw_segment_reports,
+            editor.gen.walker.position_history.full(),
+        ) {
+            draw_segment_reports(positions, &editor.segment_reports);
+        }
+        if let Some(map_diff) = &editor.map_diff {
+            draw_map_diff(map_diff);
+        }
+        if editor.draw_visit_heatmap {
+            draw_visit_heatmap(&editor.gen.walker);
+        }
+        if editor.draw_locked_positions {
+            draw_locked_positions(&editor.gen.walker);
+        }
+        if editor.draw_cost_field {
+            draw_cost_field(
+                &editor.gen.walker,
+                &editor.gen.map,
+                &editor.gen_config,
+                COST_FIELD_RADIUS,
+            );
+        }
 
         // draw debug layers
         for (layer_name, debug_layer) in editor.gen.debug_layers.iter() {
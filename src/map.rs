@@ -1,11 +1,14 @@
 use crate::{
+    config::Theme,
     kernel::Kernel,
     position::{Position, ShiftDirection},
-    twmap_export::TwExport,
+    twmap_export::{ExportStats, TwExport},
 };
 use ndarray::{s, Array2};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
-use std::path::PathBuf;
+use std::{collections::BTreeMap, path::PathBuf};
 
 const CHUNK_SIZE: usize = 5;
 const MAX_SHIFT_UNTIL_STEPS: usize = 25;
@@ -17,40 +20,39 @@ pub enum BlockTypeTW {
     Empty,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// gameplay blocks of the game layer
+#[derive(
+    Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
 pub enum BlockType {
     Empty,
-    /// Empty Block that should not be overwritten
-    EmptyReserved,
     Hookable,
     Platform,
     Freeze,
-    Spawn,
-    Start,
-    Finish,
 }
 
 impl BlockType {
+    pub const ALL: [BlockType; 4] = [
+        BlockType::Empty,
+        BlockType::Hookable,
+        BlockType::Platform,
+        BlockType::Freeze,
+    ];
+
     /// maps BlockType to tw game layer id for map export
     pub fn to_tw_game_id(&self) -> u8 {
         match self {
-            BlockType::Empty | BlockType::EmptyReserved => 0,
+            BlockType::Empty => 0,
             BlockType::Hookable | BlockType::Platform => 1,
             BlockType::Freeze => 9,
-            BlockType::Spawn => 192,
-            BlockType::Start => 33,
-            BlockType::Finish => 34,
         }
     }
 
     pub fn to_tw_block_type(&self) -> BlockTypeTW {
         match self {
             BlockType::Platform | BlockType::Hookable => BlockTypeTW::Hookable,
-            BlockType::Empty | BlockType::EmptyReserved => BlockTypeTW::Empty,
+            BlockType::Empty => BlockTypeTW::Empty,
             BlockType::Freeze => BlockTypeTW::Freeze,
-
-            // every other block is just mapped to empty
-            _ => BlockTypeTW::Empty,
         }
     }
 
@@ -67,7 +69,101 @@ impl BlockType {
     }
 }
 
-pub enum Overwrite {
+/// non-gameplay markers, stored in a separate layer on top of the game layer. Marked blocks are
+/// always empty in the game layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Marker {
+    /// empty block that should not be overwritten
+    Reserved,
+    Spawn,
+    Start,
+    Finish,
+}
+
+impl Marker {
+    pub const ALL: [Marker; 4] = [
+        Marker::Reserved,
+        Marker::Spawn,
+        Marker::Start,
+        Marker::Finish,
+    ];
+
+    /// maps Marker to tw game layer id for map export, None if the marker is not exported
+    pub fn to_tw_game_id(&self) -> Option<u8> {
+        match self {
+            Marker::Reserved => None,
+            Marker::Spawn => Some(192),
+            Marker::Start => Some(33),
+            Marker::Finish => Some(34),
+        }
+    }
+}
+
+/// tiles of the tele layer, which link checkpoints to teleporters. DDNet resets players that
+/// enter a TeleFrom tile to the TeleTo tile of the last Checkpoint they passed, or to the spawn
+/// if they didn't pass any checkpoint yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum TeleType {
+    TeleFrom,
+    TeleTo,
+    Checkpoint,
+}
+
+impl TeleType {
+    pub const ALL: [TeleType; 3] = [TeleType::TeleFrom, TeleType::TeleTo, TeleType::Checkpoint];
+
+    /// maps TeleType to tw tele layer id for map export
+    pub fn to_tw_tele_id(&self) -> u8 {
+        match self {
+            TeleType::TeleFrom => 31,   // TELECHECKIN
+            TeleType::TeleTo => 30,     // TELECHECKOUT
+            TeleType::Checkpoint => 29, // TELECHECK
+        }
+    }
+}
+
+/// tile of the tele layer. TeleTo and Checkpoint tiles with the same number belong together,
+/// the number of TeleFrom tiles is ignored by DDNet but may not be 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TeleTile {
+    pub tele_type: TeleType,
+    pub number: u8,
+}
+
+/// for each placed block type, which existing block types it may replace. Placing a block type
+/// without an entry replaces nothing.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(transparent)]
+pub struct OverwriteMatrix {
+    pub replaceable: BTreeMap<BlockType, Vec<BlockType>>,
+}
+
+impl OverwriteMatrix {
+    /// allows new_type to replace the given block types
+    pub fn allow(mut self, new_type: BlockType, replaced: &[BlockType]) -> OverwriteMatrix {
+        for block_type in replaced {
+            self.set_allowed(&new_type, block_type, true);
+        }
+        self
+    }
+
+    pub fn is_allowed(&self, new_type: &BlockType, current_type: &BlockType) -> bool {
+        self.replaceable
+            .get(new_type)
+            .is_some_and(|replaceable| replaceable.contains(current_type))
+    }
+
+    pub fn set_allowed(&mut self, new_type: &BlockType, current_type: &BlockType, allowed: bool) {
+        let replaceable = self.replaceable.entry(new_type.clone()).or_default();
+        replaceable.retain(|block_type| block_type != current_type);
+        if allowed {
+            replaceable.push(current_type.clone());
+            replaceable.sort();
+        }
+    }
+}
+
+pub enum Overwrite<'a> {
     /// Replace EVERYTHING
     Force,
 
@@ -83,24 +179,37 @@ pub enum Overwrite {
     /// Replace Freeze+Empty
     ReplaceNonSolid,
 
-    /// Replace Freeze+Empty+EmptyReserved
+    /// Replace Freeze+Empty+Reserved
     ReplaceNonSolidForce,
+
+    /// Replace whatever the matrix allows for the placed block type, never replaces markers
+    Matrix(&'a OverwriteMatrix),
 }
 
-impl Overwrite {
-    fn will_override(&self, btype: &BlockType) -> bool {
-        match self {
-            Overwrite::Force => true,
-            Overwrite::ReplaceSolidFreeze => {
+impl Overwrite<'_> {
+    fn will_override(
+        &self,
+        new_type: &BlockType,
+        btype: &BlockType,
+        marker: &Option<Marker>,
+    ) -> bool {
+        match (self, marker) {
+            (Overwrite::Force, _) => true,
+            (Overwrite::ReplaceSolidFreeze, _) => {
                 matches!(&btype, BlockType::Hookable | BlockType::Freeze)
             }
-            Overwrite::ReplaceSolidOnly => matches!(&btype, BlockType::Hookable),
-            Overwrite::ReplaceEmptyOnly => matches!(&btype, BlockType::Empty),
-            Overwrite::ReplaceNonSolid => matches!(&btype, BlockType::Freeze | BlockType::Empty),
-            Overwrite::ReplaceNonSolidForce => matches!(
-                &btype,
-                BlockType::Freeze | BlockType::Empty | BlockType::EmptyReserved
-            ),
+            (Overwrite::ReplaceSolidOnly, _) => matches!(&btype, BlockType::Hookable),
+            (Overwrite::ReplaceEmptyOnly, None) => matches!(&btype, BlockType::Empty),
+            (Overwrite::ReplaceNonSolid, None) => {
+                matches!(&btype, BlockType::Freeze | BlockType::Empty)
+            }
+            (Overwrite::ReplaceNonSolidForce, None | Some(Marker::Reserved)) => {
+                matches!(&btype, BlockType::Freeze | BlockType::Empty)
+            }
+            (Overwrite::Matrix(matrix), None) => matrix.is_allowed(new_type, btype),
+
+            // markers are only replaced explicitly
+            _ => false,
         }
     }
 }
@@ -110,15 +219,48 @@ pub enum KernelType {
     Inner,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Map {
+    /// game layer
     pub grid: Array2<BlockType>,
+
+    /// marker layer on top of the game layer, see Marker
+    pub markers: Array2<Option<Marker>>,
+
+    /// tele layer, see TeleTile
+    pub tele: Array2<Option<TeleTile>>,
+
     pub height: usize,
     pub width: usize,
     pub chunk_edited: Array2<bool>, // TODO: make this optional in case editor is not used!
     pub chunk_size: usize,
+
+    /// chunks that changed since the map statistics were last updated
+    pub chunk_dirty: Array2<bool>,
+
+    /// blocks that may never be entered by the walker or carved by its kernels
+    pub no_go: Array2<bool>,
+
+    /// blocks of finalized structures like rooms and platforms, which kernels may never carve
+    pub protected: Array2<bool>,
+
+    /// non-gameplay decoration tiles, 0 means no doodad
+    pub doodads: Array2<u8>,
+
+    /// seed of all decoration randomness, gameplay must never depend on it
+    pub decoration_seed: u64,
+
+    /// name of the map, see map_name. Written into the map info on export if set.
+    pub name: String,
+
+    /// thickness of the solid map border that kernels may never carve, 0 means no border
+    pub border_thickness: usize,
 }
 
+/// decoration seed of maps that weren't generated from a seed, thanks Tater for the epic
+/// **random** seed
+const DEFAULT_DECORATION_SEED: u64 = 3777777777;
+
 fn get_maps_path() -> PathBuf {
     if cfg!(target_os = "windows") {
         dirs::data_dir().unwrap().join("Teeworlds").join("maps")
@@ -138,6 +280,8 @@ impl Map {
     pub fn new(width: usize, height: usize, default: BlockType) -> Map {
         Map {
             grid: Array2::from_elem((width, height), default),
+            markers: Array2::from_elem((width, height), None),
+            tele: Array2::from_elem((width, height), None),
             width,
             height,
             chunk_edited: Array2::from_elem(
@@ -145,15 +289,88 @@ impl Map {
                 false,
             ),
             chunk_size: CHUNK_SIZE,
+            chunk_dirty: Array2::from_elem(
+                (width.div_ceil(CHUNK_SIZE), height.div_ceil(CHUNK_SIZE)),
+                true,
+            ),
+            no_go: Array2::from_elem((width, height), false),
+            protected: Array2::from_elem((width, height), false),
+            doodads: Array2::zeros((width, height)),
+            decoration_seed: DEFAULT_DECORATION_SEED,
+            name: String::new(),
+            border_thickness: 0,
+        }
+    }
+
+    /// marks all blocks within thickness of the map edge as no-go, so they can never be carved
+    pub fn add_border(&mut self, thickness: usize) {
+        self.border_thickness = thickness;
+
+        let (width, height) = (self.width, self.height);
+        for ((x, y), no_go) in self.no_go.indexed_iter_mut() {
+            if x < thickness || y < thickness || x + thickness >= width || y + thickness >= height {
+                *no_go = true;
+            }
+        }
+    }
+
+    /// checks whether a position is part of the map border
+    pub fn is_border(&self, pos: &Position) -> bool {
+        pos.x < self.border_thickness
+            || pos.y < self.border_thickness
+            || pos.x + self.border_thickness >= self.width
+            || pos.y + self.border_thickness >= self.height
+    }
+
+    /// marks all blocks in the area (inclusive) as no-go, parts outside of the map are ignored
+    pub fn add_no_go_zone(&mut self, top_left: &Position, bot_right: &Position) {
+        let x_start = usize::min(top_left.x, bot_right.x);
+        let y_start = usize::min(top_left.y, bot_right.y);
+        if x_start >= self.width || y_start >= self.height {
+            return;
+        }
+
+        let x_end = usize::min(usize::max(top_left.x, bot_right.x), self.width - 1);
+        let y_end = usize::min(usize::max(top_left.y, bot_right.y), self.height - 1);
+        self.no_go
+            .slice_mut(s![x_start..=x_end, y_start..=y_end])
+            .fill(true);
+    }
+
+    /// marks all blocks in the area (inclusive) as protected, parts outside of the map are ignored
+    pub fn protect_area(&mut self, top_left: &Position, bot_right: &Position) {
+        if top_left.x >= self.width || top_left.y >= self.height {
+            return;
         }
+
+        let x_end = usize::min(bot_right.x, self.width - 1);
+        let y_end = usize::min(bot_right.y, self.height - 1);
+        self.protected
+            .slice_mut(s![top_left.x..=x_end, top_left.y..=y_end])
+            .fill(true);
     }
 
+    /// applies the kernel at pos, returns the number of blocks that were replaced. Replaced
+    /// blocks are marked with the given marker.
     pub fn apply_kernel(
         &mut self,
         pos: &Position,
         kernel: &Kernel,
         new_block_type: BlockType,
-    ) -> Result<(), &'static str> {
+        marker: Option<Marker>,
+    ) -> Result<usize, &'static str> {
+        self.apply_kernel_masked(pos, kernel, new_block_type, marker, |_| true)
+    }
+
+    /// like apply_kernel, but only replaces blocks at absolute positions that pass the mask
+    pub fn apply_kernel_masked(
+        &mut self,
+        pos: &Position,
+        kernel: &Kernel,
+        new_block_type: BlockType,
+        marker: Option<Marker>,
+        mask: impl Fn(&Position) -> bool,
+    ) -> Result<usize, &'static str> {
         let offset: usize = kernel.size / 2; // offset of kernel wrt. position (top/left)
         let extend: usize = kernel.size - offset; // how much kernel extends position (bot/right)
 
@@ -162,14 +379,27 @@ impl Map {
         let exceeds_right_bound = (pos.x + extend) > self.width;
         let exceeds_lower_bound = (pos.y + extend) > self.height;
 
-        if exceeds_left_bound || exceeds_upper_bound || exceeds_right_bound || exceeds_lower_bound {
+        // with a map border, kernels are clipped against the map instead
+        if (exceeds_left_bound || exceeds_upper_bound || exceeds_right_bound || exceeds_lower_bound)
+            && self.border_thickness == 0
+        {
             return Err("Kernel out of bounds");
         }
 
-        let root_pos = Position::new(pos.x - offset, pos.y - offset);
+        let mut replaced_blocks = 0;
         for ((kernel_x, kernel_y), kernel_active) in kernel.vector.indexed_iter() {
-            let absolute_pos = Position::new(root_pos.x + kernel_x, root_pos.y + kernel_y);
-            if *kernel_active {
+            let absolute_pos = match (
+                (pos.x + kernel_x).checked_sub(offset),
+                (pos.y + kernel_y).checked_sub(offset),
+            ) {
+                (Some(x), Some(y)) if x < self.width && y < self.height => Position::new(x, y),
+                _ => continue,
+            };
+            if *kernel_active
+                && mask(&absolute_pos)
+                && !self.no_go[absolute_pos.as_index()]
+                && !self.protected[absolute_pos.as_index()]
+            {
                 let current_type = &self.grid[absolute_pos.as_index()];
 
                 let new_type = match current_type {
@@ -179,22 +409,25 @@ impl Map {
 
                 if let Some(new_type) = new_type {
                     self.grid[absolute_pos.as_index()] = new_type;
+                    self.markers[absolute_pos.as_index()] = marker;
+                    replaced_blocks += 1;
                 }
 
                 let chunk_pos = self.pos_to_chunk_pos(absolute_pos);
                 self.chunk_edited[chunk_pos.as_index()] = true;
+                self.chunk_dirty[chunk_pos.as_index()] = true;
             }
         }
 
-        Ok(())
+        Ok(replaced_blocks)
     }
 
-    fn pos_to_chunk_pos(&self, pos: Position) -> Position {
+    pub fn pos_to_chunk_pos(&self, pos: Position) -> Position {
         Position::new(pos.x / self.chunk_size, pos.y / self.chunk_size)
     }
 
-    pub fn export(&self, path: &PathBuf) {
-        TwExport::export(self, path)
+    pub fn export(&self, path: &PathBuf, theme: Option<&Theme>) -> ExportStats {
+        TwExport::export(self, path, theme)
     }
 
     pub fn pos_in_bounds(&self, pos: &Position) -> bool {
@@ -202,23 +435,39 @@ impl Map {
         pos.x < self.width && pos.y < self.height
     }
 
+    /// whether the block at the index is empty and not marked
+    pub fn is_plain_empty(&self, index: [usize; 2]) -> bool {
+        self.is_empty_at(index, false)
+    }
+
+    /// whether the block at the index is empty and not marked. Reserved blocks also count as
+    /// empty, if they may be overwritten.
+    pub fn is_empty_at(&self, index: [usize; 2], overwrite_reserved: bool) -> bool {
+        self.grid[index] == BlockType::Empty
+            && match self.markers[index] {
+                None => true,
+                Some(Marker::Reserved) => overwrite_reserved,
+                Some(_) => false,
+            }
+    }
+
+    /// sets the block at the index and removes its marker
+    pub fn set_block(&mut self, index: [usize; 2], block_type: BlockType) {
+        self.grid[index] = block_type;
+        self.markers[index] = None;
+    }
+
+    /// whether any unmarked block of the area (inclusive) has the given block type
     pub fn check_area_exists(
         &self,
         top_left: &Position,
         bot_right: &Position,
         value: &BlockType,
     ) -> Result<bool, &'static str> {
-        if !self.pos_in_bounds(top_left) || !self.pos_in_bounds(bot_right) {
-            return Err("checking area out of bounds");
-        }
-
-        let area = self
-            .grid
-            .slice(s![top_left.x..=bot_right.x, top_left.y..=bot_right.y]);
-
-        Ok(area.iter().any(|block| block == value))
+        Ok(self.count_occurence_in_area(top_left, bot_right, value)? > 0)
     }
 
+    /// whether all blocks of the area (inclusive) are unmarked and have the given block type
     pub fn check_area_all(
         &self,
         top_left: &Position,
@@ -228,13 +477,17 @@ impl Map {
         if !self.pos_in_bounds(top_left) || !self.pos_in_bounds(bot_right) {
             return Err("checking area out of bounds");
         }
-        let area = self
-            .grid
-            .slice(s![top_left.x..=bot_right.x, top_left.y..=bot_right.y]);
+        let area = s![top_left.x..=bot_right.x, top_left.y..=bot_right.y];
 
-        Ok(area.iter().all(|block| block == value))
+        Ok(self
+            .grid
+            .slice(area)
+            .iter()
+            .zip(self.markers.slice(area).iter())
+            .all(|(block, marker)| block == value && marker.is_none()))
     }
 
+    /// counts the unmarked blocks of the area (inclusive) that have the given block type
     pub fn count_occurence_in_area(
         &self,
         top_left: &Position,
@@ -244,26 +497,30 @@ impl Map {
         if !self.pos_in_bounds(top_left) || !self.pos_in_bounds(bot_right) {
             return Err("checking area out of bounds");
         }
-        let area = self
-            .grid
-            .slice(s![top_left.x..=bot_right.x, top_left.y..=bot_right.y]);
+        let area = s![top_left.x..=bot_right.x, top_left.y..=bot_right.y];
 
-        Ok(area.iter().filter(|&block| block == value).count())
+        Ok(self
+            .grid
+            .slice(area)
+            .iter()
+            .zip(self.markers.slice(area).iter())
+            .filter(|(block, marker)| *block == value && marker.is_none())
+            .count())
     }
 
     pub fn check_position_type(&self, pos: &Position, block_type: BlockType) -> bool {
         match self.grid.get(pos.as_index()) {
-            Some(value) => *value == block_type,
+            Some(value) => *value == block_type && self.markers[pos.as_index()].is_none(),
             None => false,
         }
     }
 
     pub fn check_position_crit<F>(&self, pos: &Position, criterion: F) -> bool
     where
-        F: Fn(&BlockType) -> bool,
+        F: Fn(&BlockType, &Option<Marker>) -> bool,
     {
         match self.grid.get(pos.as_index()) {
-            Some(value) => criterion(&value),
+            Some(value) => criterion(value, &self.markers[pos.as_index()]),
             None => false,
         }
     }
@@ -274,24 +531,56 @@ impl Map {
         bot_right: &Position,
         value: &BlockType,
         overide: &Overwrite,
+    ) {
+        self.set_marked_area(top_left, bot_right, value, None, overide);
+    }
+
+    /// sets the area to empty blocks with the given marker
+    pub fn set_marker_area(
+        &mut self,
+        top_left: &Position,
+        bot_right: &Position,
+        marker: Marker,
+        overide: &Overwrite,
+    ) {
+        self.set_marked_area(
+            top_left,
+            bot_right,
+            &BlockType::Empty,
+            Some(marker),
+            overide,
+        );
+    }
+
+    fn set_marked_area(
+        &mut self,
+        top_left: &Position,
+        bot_right: &Position,
+        value: &BlockType,
+        marker: Option<Marker>,
+        overide: &Overwrite,
     ) {
         if !self.pos_in_bounds(top_left) || !self.pos_in_bounds(bot_right) {
             return;
         }
 
         let chunk_size = self.chunk_size;
+        let area = s![top_left.x..=bot_right.x, top_left.y..=bot_right.y];
 
-        let mut view = self
-            .grid
-            .slice_mut(s![top_left.x..=bot_right.x, top_left.y..=bot_right.y]);
+        let mut view = self.grid.slice_mut(area);
+        let mut marker_view = self.markers.slice_mut(area);
 
-        for ((x, y), current_value) in view.indexed_iter_mut() {
-            if overide.will_override(current_value) {
+        for (((x, y), current_value), current_marker) in
+            view.indexed_iter_mut().zip(marker_view.iter_mut())
+        {
+            if overide.will_override(value, current_value, current_marker) {
                 *current_value = value.clone();
+                *current_marker = marker;
 
                 let chunk_pos =
                     Position::new((top_left.x + x) / chunk_size, (top_left.y + y) / chunk_size);
                 self.chunk_edited[chunk_pos.as_index()] = true;
+                self.chunk_dirty[chunk_pos.as_index()] = true;
             }
         }
     }
@@ -313,6 +602,23 @@ impl Map {
         self.set_area(&bot_left, bot_right, value, overwrite);
     }
 
+    /// sets the outline of an area define by two positions to empty blocks with the given marker
+    pub fn set_marker_area_border(
+        &mut self,
+        top_left: &Position,
+        bot_right: &Position,
+        marker: Marker,
+        overwrite: &Overwrite,
+    ) {
+        let top_right = Position::new(bot_right.x, top_left.y);
+        let bot_left = Position::new(top_left.x, bot_right.y);
+
+        self.set_marker_area(top_left, &top_right, marker, overwrite);
+        self.set_marker_area(&top_right, bot_right, marker, overwrite);
+        self.set_marker_area(top_left, &bot_left, marker, overwrite);
+        self.set_marker_area(&bot_left, bot_right, marker, overwrite);
+    }
+
     /// shifts position in given direction until block fulfills criterion
     pub fn shift_pos_until<F>(
         &self,
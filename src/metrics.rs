@@ -0,0 +1,141 @@
+use std::collections::VecDeque;
+
+use ndarray::Array2;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{
+    analysis::{analyze_segments, corridor_width, MapStats},
+    config::GenerationConfig,
+    distance::distance_field,
+    generator::Generator,
+    map::BlockType,
+    position::Position,
+};
+
+/// distance (in blocks) from the walker path at which empty blocks count as off the path
+const DEAD_END_DISTANCE: f32 = 6.0;
+
+/// min amount of connected off-path empty blocks that count as dead end
+const MIN_DEAD_END_BLOCKS: usize = 8;
+
+/// quality metrics of a generated map, e.g. to automatically filter bad seeds
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct MapMetrics {
+    /// amount of blocks of the walker path from spawn to finish
+    pub path_length: usize,
+
+    /// average corridor width at all path blocks that are not freeze, see corridor_width
+    pub avg_tunnel_width: f32,
+
+    /// amount of freeze blocks per empty block
+    pub freeze_ratio: f32,
+
+    /// amount of valid skips
+    pub skips: usize,
+
+    /// amount of empty areas away from the walker path, e.g. leftovers of backtracks
+    pub dead_ends: usize,
+
+    /// average difficulty of all waypoint segments between 0 (easy) and 1 (hard), see
+    /// analyze_segments
+    pub difficulty: f32,
+}
+
+impl MapMetrics {
+    /// computes the metrics of a generator that finished post processing
+    pub fn new(gen: &Generator, gen_config: &GenerationConfig) -> MapMetrics {
+        let map = &gen.map;
+        let path = gen.walker.position_history.to_vec();
+        let skips = gen.debug_layers.get("skips").map(|layer| &layer.grid);
+
+        let widths: Vec<usize> = path
+            .iter()
+            .map(|pos| corridor_width(map, pos))
+            .filter(|width| *width > 0)
+            .collect();
+        let avg_tunnel_width = match widths.len() {
+            0 => 0.0,
+            len => widths.iter().sum::<usize>() as f32 / len as f32,
+        };
+
+        let stats = MapStats::new(map);
+        let freeze_ratio = match stats.empty {
+            0 => 0.0,
+            empty => stats.freeze as f32 / empty as f32,
+        };
+
+        let mut on_path = Array2::from_elem((map.width, map.height), false);
+        for pos in path.iter() {
+            on_path[pos.as_index()] = true;
+        }
+        let path_distance = distance_field(&on_path);
+        let off_path = Array2::from_shape_fn((map.width, map.height), |index| {
+            map.grid[index] == BlockType::Empty && path_distance[index] > DEAD_END_DISTANCE
+        });
+
+        let segments = analyze_segments(
+            map,
+            &path,
+            gen.get_waypoints(),
+            gen_config.waypoint_reached_dist,
+            skips,
+        );
+        let difficulty = match segments.len() {
+            0 => 0.0,
+            len => {
+                segments
+                    .iter()
+                    .map(|segment| segment.difficulty)
+                    .sum::<f32>()
+                    / len as f32
+            }
+        };
+
+        MapMetrics {
+            path_length: path.len(),
+            avg_tunnel_width,
+            freeze_ratio,
+            skips: skips.map_or(0, |skips| count_components(skips, 1)),
+            dead_ends: count_components(&off_path, MIN_DEAD_END_BLOCKS),
+            difficulty,
+        }
+    }
+}
+
+/// counts the 4-connected areas of set blocks that consist of at least min_size blocks
+fn count_components(grid: &Array2<bool>, min_size: usize) -> usize {
+    let (width, height) = grid.dim();
+    let mut visited = Array2::from_elem((width, height), false);
+    let mut count = 0;
+
+    for ((x, y), value) in grid.indexed_iter() {
+        if !*value || visited[[x, y]] {
+            continue;
+        }
+
+        let mut size = 0;
+        let mut queue = VecDeque::from([Position::new(x, y)]);
+        visited[[x, y]] = true;
+        while let Some(pos) = queue.pop_front() {
+            size += 1;
+            for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                let neighbor = match pos.shifted_by(dx, dy) {
+                    Ok(neighbor) if neighbor.x < width && neighbor.y < height => neighbor,
+                    _ => continue,
+                };
+
+                if grid[neighbor.as_index()] && !visited[neighbor.as_index()] {
+                    visited[neighbor.as_index()] = true;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+
+        if size >= min_size {
+            count += 1;
+        }
+    }
+
+    count
+}
@@ -0,0 +1,91 @@
+use seahash::hash;
+
+const ADJECTIVES: [&str; 32] = [
+    "Frozen",
+    "Icy",
+    "Hollow",
+    "Twisted",
+    "Silent",
+    "Narrow",
+    "Endless",
+    "Broken",
+    "Crooked",
+    "Sunken",
+    "Shattered",
+    "Tangled",
+    "Lonely",
+    "Restless",
+    "Bitter",
+    "Cruel",
+    "Gentle",
+    "Lost",
+    "Pale",
+    "Rusty",
+    "Salty",
+    "Sleepy",
+    "Sneaky",
+    "Stormy",
+    "Tiny",
+    "Vast",
+    "Wicked",
+    "Wild",
+    "Dusty",
+    "Hidden",
+    "Jagged",
+    "Quiet",
+];
+
+const NOUNS: [&str; 32] = [
+    "Spiral",
+    "Tower",
+    "Cavern",
+    "Tunnel",
+    "Maze",
+    "Canyon",
+    "Glacier",
+    "Chimney",
+    "Abyss",
+    "Corridor",
+    "Crevice",
+    "Descent",
+    "Fortress",
+    "Garden",
+    "Grotto",
+    "Harbor",
+    "Hideout",
+    "Labyrinth",
+    "Ladder",
+    "Mine",
+    "Nest",
+    "Passage",
+    "Peak",
+    "Pit",
+    "Ravine",
+    "Ridge",
+    "Shaft",
+    "Spire",
+    "Summit",
+    "Trench",
+    "Valley",
+    "Vault",
+];
+
+/// human-pronounceable name of a map, e.g. "Frozen Spiral 7f3a". The words are derived from the
+/// seed and the name of the generation preset, the suffix are the lowest 16 bits of the seed.
+pub fn map_name(seed: u64, gen_config: &str) -> String {
+    let mut data = seed.to_le_bytes().to_vec();
+    data.extend_from_slice(gen_config.as_bytes());
+    let words = hash(&data);
+
+    format!(
+        "{} {} {:04x}",
+        ADJECTIVES[(words % ADJECTIVES.len() as u64) as usize],
+        NOUNS[((words >> 32) % NOUNS.len() as u64) as usize],
+        seed & 0xffff
+    )
+}
+
+/// map name that can be used in file names and server commands, e.g. "Frozen_Spiral_7f3a"
+pub fn map_file_name(seed: u64, gen_config: &str) -> String {
+    map_name(seed, gen_config).replace(' ', "_")
+}
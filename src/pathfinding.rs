@@ -0,0 +1,155 @@
+use std::collections::VecDeque;
+
+use ndarray::Array2;
+
+use crate::{map::Map, position::Position};
+
+/// min distance of routed waypoints to the map border, so walker kernels stay in bounds
+const BORDER_MARGIN: usize = 3;
+
+/// min distance of routed paths to no-go zones
+const NO_GO_MARGIN: usize = 1;
+
+/// shortest 4-connected path from start to goal (both inclusive) that avoids all blocked
+/// positions, None if the goal is not reachable
+pub fn find_path(
+    blocked: &Array2<bool>,
+    start: &Position,
+    goal: &Position,
+) -> Option<Vec<Position>> {
+    let (width, height) = blocked.dim();
+    let mut parents: Array2<Option<Position>> = Array2::from_elem((width, height), None);
+    let mut queue = VecDeque::from([start.clone()]);
+    parents[start.as_index()] = Some(start.clone());
+
+    while let Some(pos) = queue.pop_front() {
+        if pos == *goal {
+            let mut path = vec![pos.clone()];
+            let mut current = pos;
+            while current != *start {
+                current = parents[current.as_index()].clone().unwrap();
+                path.push(current.clone());
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            let neighbor = match pos.shifted_by(dx, dy) {
+                Ok(neighbor) if neighbor.x < width && neighbor.y < height => neighbor,
+                _ => continue,
+            };
+
+            if !blocked[neighbor.as_index()] && parents[neighbor.as_index()].is_none() {
+                parents[neighbor.as_index()] = Some(pos.clone());
+                queue.push_back(neighbor);
+            }
+        }
+    }
+
+    None
+}
+
+/// checks whether the straight line between two positions avoids all blocked positions. The
+/// start is ignored, so lines can always leave a blocked position (e.g. the spawn).
+fn is_line_clear(blocked: &Array2<bool>, start: &Position, end: &Position) -> bool {
+    let steps = usize::max(start.x.abs_diff(end.x), start.y.abs_diff(end.y)).max(1);
+    (1..=steps).all(|step| !blocked[start.lerp(end, step as f32 / steps as f32).as_index()])
+}
+
+/// nearest position that is not blocked, None if everything is blocked
+fn nearest_free(blocked: &Array2<bool>, pos: &Position) -> Option<Position> {
+    let (width, height) = blocked.dim();
+    let mut visited = Array2::from_elem((width, height), false);
+    let mut queue = VecDeque::from([pos.clone()]);
+    visited[pos.as_index()] = true;
+
+    while let Some(pos) = queue.pop_front() {
+        if !blocked[pos.as_index()] {
+            return Some(pos);
+        }
+
+        for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            if let Ok(neighbor) = pos.shifted_by(dx, dy) {
+                if neighbor.x < width && neighbor.y < height && !visited[neighbor.as_index()] {
+                    visited[neighbor.as_index()] = true;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// positions the walker should never aim for: no-go zones (plus margin) and the map border
+fn get_blocked(map: &Map) -> Array2<bool> {
+    let mut blocked = Array2::from_elem((map.width, map.height), false);
+    for ((x, y), value) in blocked.indexed_iter_mut() {
+        *value = x < BORDER_MARGIN
+            || y < BORDER_MARGIN
+            || x + BORDER_MARGIN >= map.width
+            || y + BORDER_MARGIN >= map.height;
+    }
+
+    for ((x, y), no_go) in map.no_go.indexed_iter() {
+        if *no_go {
+            let x_range =
+                x.saturating_sub(NO_GO_MARGIN)..=usize::min(x + NO_GO_MARGIN, map.width - 1);
+            for blocked_x in x_range {
+                let y_range =
+                    y.saturating_sub(NO_GO_MARGIN)..=usize::min(y + NO_GO_MARGIN, map.height - 1);
+                for blocked_y in y_range {
+                    blocked[[blocked_x, blocked_y]] = true;
+                }
+            }
+        }
+    }
+
+    blocked
+}
+
+/// moves waypoints out of no-go zones and into the map, and inserts intermediate waypoints
+/// for segments that cross no-go zones. The first waypoint (spawn) is never changed.
+pub fn route_waypoints(waypoints: &[Position], map: &Map) -> Vec<Position> {
+    let in_bounds = waypoints.iter().all(|pos| map.pos_in_bounds(pos));
+    if waypoints.len() < 2 || (in_bounds && !map.no_go.iter().any(|no_go| *no_go)) {
+        return waypoints.to_vec();
+    }
+
+    let blocked = get_blocked(map);
+    let mut routed = vec![waypoints[0].clone()];
+    for waypoint in waypoints[1..].iter() {
+        let clamped = Position::new(
+            usize::min(waypoint.x, map.width - 1),
+            usize::min(waypoint.y, map.height - 1),
+        );
+        let waypoint = match nearest_free(&blocked, &clamped) {
+            Some(waypoint) => waypoint,
+            None => return waypoints.to_vec(),
+        };
+
+        let prev = routed.last().unwrap().clone();
+        if !map.pos_in_bounds(&prev) || is_line_clear(&blocked, &prev, &waypoint) {
+            routed.push(waypoint);
+            continue;
+        }
+
+        // reduce the path to the waypoints required to keep a clear line of sight
+        if let Some(path) = find_path(&blocked, &prev, &waypoint) {
+            let mut anchor = 0;
+            while anchor + 1 < path.len() {
+                let next = (anchor + 1..path.len())
+                    .rev()
+                    .find(|index| is_line_clear(&blocked, &path[anchor], &path[*index]))
+                    .unwrap_or(anchor + 1);
+                routed.push(path[next].clone());
+                anchor = next;
+            }
+        } else {
+            routed.push(waypoint);
+        }
+    }
+
+    routed
+}
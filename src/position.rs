@@ -1,4 +1,5 @@
 use dt::num::{integer::Roots, Float, ToPrimitive};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::{map::Map, random::Random};
@@ -9,13 +10,15 @@ use std::usize;
 //
 // while glam has nice performance benefits, the amount of expensive operations
 // on the position vector will be very limited, so this should be fine..
-#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Position {
     pub x: usize,
     pub y: usize,
 }
 
-#[derive(Debug, Clone, Copy, PartialOrd, PartialEq, Serialize, Deserialize, Default)]
+#[derive(
+    Debug, Clone, Copy, PartialOrd, PartialEq, Serialize, Deserialize, Default, JsonSchema,
+)]
 pub enum ShiftDirection {
     #[default]
     Up = 0,
@@ -24,6 +27,17 @@ pub enum ShiftDirection {
     Left = 3,
 }
 
+impl ShiftDirection {
+    pub fn opposite(&self) -> ShiftDirection {
+        match self {
+            ShiftDirection::Up => ShiftDirection::Down,
+            ShiftDirection::Right => ShiftDirection::Left,
+            ShiftDirection::Down => ShiftDirection::Up,
+            ShiftDirection::Left => ShiftDirection::Right,
+        }
+    }
+}
+
 impl Position {
     pub fn new(x: usize, y: usize) -> Position {
         Position { x, y }
@@ -33,6 +47,18 @@ impl Position {
         [self.x, self.y]
     }
 
+    /// in-game coordinates (in tiles) of the center of the block, as shown by the DDNet client
+    /// for a tee at this block. Maps are exported without offset or flip, so block x, y is
+    /// tile x, y.
+    pub fn to_ddnet(&self) -> (f32, f32) {
+        (self.x as f32 + 0.5, self.y as f32 + 0.5)
+    }
+
+    /// block that contains the DDNet in-game coordinates (in tiles), see to_ddnet
+    pub fn from_ddnet(x: f32, y: f32) -> Position {
+        Position::new(x.max(0.0) as usize, y.max(0.0) as usize)
+    }
+
     /// returns a new position shifted by some x and y value
     pub fn shifted_by(&self, x_shift: i32, y_shift: i32) -> Result<Position, &'static str> {
         let new_x = match x_shift >= 0 {
@@ -140,27 +166,78 @@ impl Position {
         }
     }
 
+    /// euclidean distance to the line segment between start and end
+    pub fn distance_to_segment(&self, start: &Position, end: &Position) -> f32 {
+        let (px, py) = (self.x as f32, self.y as f32);
+        let (sx, sy) = (start.x as f32, start.y as f32);
+        let (dx, dy) = (end.x as f32 - sx, end.y as f32 - sy);
+
+        let length_sqr = dx * dx + dy * dy;
+        if length_sqr == 0.0 {
+            return self.distance(start);
+        }
+
+        let t = (((px - sx) * dx + (py - sy) * dy) / length_sqr).clamp(0.0, 1.0);
+        ((px - (sx + t * dx)).powi(2) + (py - (sy + t * dy)).powi(2)).sqrt()
+    }
+
     /// returns a Vec with all possible shifts, sorted by how close they get
     /// towards the goal position
-    pub fn get_rated_shifts(&self, goal: &Position, map: &Map) -> [ShiftDirection; 4] {
-        let mut shifts = [
+    /// shifts ordered from the lowest to the highest cost of the shifted position
+    pub fn get_rated_shifts<F>(&self, map: &Map, cost: F) -> [ShiftDirection; 4]
+    where
+        F: Fn(&Position) -> f32,
+    {
+        let shifts = [
             ShiftDirection::Left,
             ShiftDirection::Up,
             ShiftDirection::Right,
             ShiftDirection::Down,
         ];
 
-        shifts.sort_by_cached_key(|shift| {
+        let costs = shifts.map(|shift| {
             let mut shifted_pos = self.clone();
-            if let Ok(()) = shifted_pos.shift_in_direction(shift, map) {
-                shifted_pos.distance_squared(goal)
+            if let Ok(()) = shifted_pos.shift_in_direction(&shift, map) {
+                cost(&shifted_pos)
             } else {
-                // assign maximum distance to invalid shifts
+                // assign maximum cost to invalid shifts
                 // TODO: i could also return a vec and completly remove invalid moves?
-                usize::MAX
+                f32::INFINITY
             }
         });
 
-        shifts
+        let mut order = [0, 1, 2, 3];
+        order.sort_by(|a, b| costs[*a].total_cmp(&costs[*b]));
+
+        order.map(|index| shifts[index])
+    }
+}
+
+/// simplifies a path using the Douglas-Peucker algorithm. All removed positions are at most
+/// tolerance away from the simplified path. The first and last position are always kept.
+pub fn simplify_path(path: &[Position], tolerance: f32) -> Vec<Position> {
+    if path.len() < 3 {
+        return path.to_vec();
+    }
+
+    let (first, last) = (&path[0], &path[path.len() - 1]);
+    let (max_index, max_distance) = path[1..path.len() - 1]
+        .iter()
+        .enumerate()
+        .map(|(index, pos)| (index + 1, pos.distance_to_segment(first, last)))
+        .fold(
+            (0, 0.0),
+            |max, current| if current.1 > max.1 { current } else { max },
+        );
+
+    if max_distance <= tolerance {
+        return vec![first.clone(), last.clone()];
     }
+
+    // keep the furthest position and simplify both halves independently
+    let mut simplified = simplify_path(&path[..=max_index], tolerance);
+    simplified.pop();
+    simplified.extend(simplify_path(&path[max_index..], tolerance));
+
+    simplified
 }
@@ -0,0 +1,242 @@
+use std::collections::VecDeque;
+
+use crate::position::{Position, ShiftDirection};
+
+/// positions of the walker before each of its steps. The walker itself only looks back up to
+/// the lock delay, so only the most recent positions are kept as ring buffer. The full path is
+/// stored as one shift per step and decoded on demand, which takes a fraction of the memory.
+#[derive(Debug, Clone, Default)]
+pub struct PositionHistory {
+    /// first position of the path
+    start: Option<Position>,
+
+    /// shift from each position of the path to the next one
+    shifts: Vec<ShiftDirection>,
+
+    /// most recent positions, the last one belongs to step len() - 1
+    recent: VecDeque<Position>,
+
+    /// all positions, only kept if enabled for debugging
+    full: Option<Vec<Position>>,
+}
+
+/// shift that moves from one position to an adjacent one
+fn shift_between(from: &Position, to: &Position) -> Option<ShiftDirection> {
+    match (to.x as i64 - from.x as i64, to.y as i64 - from.y as i64) {
+        (0, -1) => Some(ShiftDirection::Up),
+        (1, 0) => Some(ShiftDirection::Right),
+        (0, 1) => Some(ShiftDirection::Down),
+        (-1, 0) => Some(ShiftDirection::Left),
+        _ => None,
+    }
+}
+
+/// adjacent position in the direction of the shift
+fn shifted(pos: &Position, shift: &ShiftDirection) -> Position {
+    match shift {
+        ShiftDirection::Up => Position::new(pos.x, pos.y - 1),
+        ShiftDirection::Right => Position::new(pos.x + 1, pos.y),
+        ShiftDirection::Down => Position::new(pos.x, pos.y + 1),
+        ShiftDirection::Left => Position::new(pos.x - 1, pos.y),
+    }
+}
+
+impl PositionHistory {
+    pub fn new() -> PositionHistory {
+        PositionHistory::default()
+    }
+
+    /// additionally keep all positions as plain list, so every step can be looked up
+    pub fn enable_full_history(&mut self) {
+        if self.full.is_none() {
+            self.full = Some(self.iter().collect());
+        }
+    }
+
+    /// all positions, if the full history is enabled
+    pub fn full(&self) -> Option<&[Position]> {
+        self.full.as_deref()
+    }
+
+    pub fn len(&self) -> usize {
+        match self.start {
+            Some(_) => self.shifts.len() + 1,
+            None => 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start.is_none()
+    }
+
+    /// appends the position of the next step, keeping at most capacity recent positions. The
+    /// position has to be adjacent to the previous one.
+    pub fn push(&mut self, pos: Position, capacity: usize) -> Result<(), &'static str> {
+        match self.recent.back() {
+            Some(last) => {
+                let shift = shift_between(last, &pos).ok_or("walker positions not adjacent")?;
+                self.shifts.push(shift);
+            }
+            None => self.start = Some(pos.clone()),
+        }
+
+        if let Some(full) = self.full.as_mut() {
+            full.push(pos.clone());
+        }
+
+        self.recent.push_back(pos);
+        while self.recent.len() > capacity.max(1) {
+            self.recent.pop_front();
+        }
+
+        Ok(())
+    }
+
+    /// position of the given step, if it is still kept
+    pub fn get(&self, step: usize) -> Option<&Position> {
+        if let Some(full) = self.full.as_ref() {
+            return full.get(step);
+        }
+
+        let first_recent = self.len() - self.recent.len();
+        step.checked_sub(first_recent)
+            .and_then(|index| self.recent.get(index))
+    }
+
+    /// removes all steps starting at len. Positions before len stay available, as long as they
+    /// were kept before.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len() {
+            return;
+        }
+
+        let removed = self.len() - len;
+        let keep_recent = self.recent.len().saturating_sub(removed);
+        self.recent.truncate(keep_recent);
+        if let Some(full) = self.full.as_mut() {
+            full.truncate(len);
+        }
+
+        match len {
+            0 => {
+                self.start = None;
+                self.shifts.clear();
+            }
+            _ => self.shifts.truncate(len - 1),
+        }
+
+        // the ring buffer ran empty, so restart it from the last remaining position
+        if self.recent.is_empty() {
+            if let Some(last) = self.iter().last() {
+                self.recent.push_back(last);
+            }
+        }
+    }
+
+    /// decodes the full path
+    pub fn iter(&self) -> impl Iterator<Item = Position> + '_ {
+        let mut pos = self.start.clone();
+        let mut shifts = self.shifts.iter();
+        std::iter::from_fn(move || {
+            let current = pos.take()?;
+            pos = shifts.next().map(|shift| shifted(&current, shift));
+            Some(current)
+        })
+    }
+
+    /// decodes the full path into a plain list
+    pub fn to_vec(&self) -> Vec<Position> {
+        match self.full.as_ref() {
+            Some(full) => full.clone(),
+            None => self.iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// history of a walk along the given shifts, starting at (10, 10)
+    fn walk(shifts: &[ShiftDirection], capacity: usize) -> (PositionHistory, Vec<Position>) {
+        let mut history = PositionHistory::new();
+        let mut positions = vec![Position::new(10, 10)];
+        for shift in shifts {
+            let next = shifted(positions.last().unwrap(), shift);
+            positions.push(next);
+        }
+        for pos in positions.iter() {
+            history.push(pos.clone(), capacity).unwrap();
+        }
+
+        (history, positions)
+    }
+
+    const SHIFTS: [ShiftDirection; 8] = [
+        ShiftDirection::Right,
+        ShiftDirection::Right,
+        ShiftDirection::Down,
+        ShiftDirection::Left,
+        ShiftDirection::Down,
+        ShiftDirection::Down,
+        ShiftDirection::Up,
+        ShiftDirection::Right,
+    ];
+
+    #[test]
+    fn keeps_recent_positions() {
+        let (history, positions) = walk(&SHIFTS, 3);
+        assert_eq!(history.len(), positions.len());
+        for (step, pos) in positions.iter().enumerate() {
+            match step + 3 < positions.len() {
+                true => assert_eq!(history.get(step), None),
+                false => assert_eq!(history.get(step), Some(pos)),
+            }
+        }
+        assert_eq!(history.get(positions.len()), None);
+        assert_eq!(history.to_vec(), positions);
+    }
+
+    #[test]
+    fn full_history_keeps_all_positions() {
+        let (mut history, positions) = walk(&SHIFTS, 3);
+        history.enable_full_history();
+        for (step, pos) in positions.iter().enumerate() {
+            assert_eq!(history.get(step), Some(pos));
+        }
+        assert_eq!(history.full(), Some(positions.as_slice()));
+    }
+
+    #[test]
+    fn truncate_keeps_earlier_positions() {
+        let (mut history, positions) = walk(&SHIFTS, 4);
+
+        history.truncate(7);
+        assert_eq!(history.len(), 7);
+        assert_eq!(history.to_vec(), positions[..7]);
+        assert_eq!(history.get(6), Some(&positions[6]));
+        assert_eq!(history.get(7), None);
+
+        // beyond the ring buffer only the last remaining position is restored
+        history.truncate(3);
+        assert_eq!(history.to_vec(), positions[..3]);
+        assert_eq!(history.get(2), Some(&positions[2]));
+        assert_eq!(history.get(1), None);
+
+        history.push(positions[3].clone(), 4).unwrap();
+        assert_eq!(history.to_vec(), positions[..4]);
+
+        history.truncate(0);
+        assert!(history.is_empty());
+        assert_eq!(history.iter().count(), 0);
+    }
+
+    #[test]
+    fn rejects_positions_that_are_not_adjacent() {
+        let mut history = PositionHistory::new();
+        history.push(Position::new(5, 5), 2).unwrap();
+        assert!(history.push(Position::new(7, 5), 2).is_err());
+        assert!(history.push(Position::new(6, 6), 2).is_err());
+        assert_eq!(history.len(), 1);
+    }
+}
@@ -1,9 +1,12 @@
 use crate::{
-    config::GenerationConfig,
+    analysis::SegmentReport,
+    config::{CornerHoldPolicy, FillStyle, FreezeStyle, GenerationConfig},
     debug::DebugLayer,
+    distance::distance_field,
     generator::Generator,
-    map::{BlockType, Map, Overwrite},
+    map::{BlockType, Map, Marker, Overwrite, OverwriteMatrix, TeleTile, TeleType},
     position::{Position, ShiftDirection},
+    position_history::PositionHistory,
 };
 
 use std::{
@@ -12,83 +15,799 @@ use std::{
     usize,
 };
 
-use dt::dt_bool;
-use ndarray::{s, Array2, ArrayBase, Dim, Ix2, ViewRepr};
+use log::warn;
+use ndarray::{s, Array2, ArrayBase, Dim, ViewRepr};
+use seahash::hash;
 
 /// Post processing step to fix all existing edge-bugs, as certain inner/outer kernel
 /// configurations do not ensure a min. 1-block freeze padding consistently.
-pub fn fix_edge_bugs(gen: &mut Generator) -> Result<Array2<bool>, &'static str> {
-    let mut edge_bug = Array2::from_elem((gen.map.width, gen.map.height), false);
+pub fn fix_edge_bugs(
+    gen: &mut Generator,
+    freeze_style: FreezeStyle,
+) -> Result<Array2<bool>, &'static str> {
     let width = gen.map.width;
     let height = gen.map.height;
 
-    for x in 0..width {
-        for y in 0..height {
-            let value = &gen.map.grid[[x, y]];
-            if *value == BlockType::Empty {
-                for dx in 0..=2 {
-                    for dy in 0..=2 {
-                        if dx == 1 && dy == 1 {
-                            continue;
-                        }
+    // empty blocks at the top or left map edge have no neighbors to check
+    let edge_empty = (0..width).any(|x| gen.map.is_plain_empty([x, 0]))
+        || (0..height).any(|y| gen.map.is_plain_empty([0, y]));
+    if edge_empty {
+        return Err("fix edge bug out of bounds");
+    }
 
-                        let neighbor_x = (x + dx)
-                            .checked_sub(1)
-                            .ok_or("fix edge bug out of bounds")?;
-                        let neighbor_y = (y + dy)
-                            .checked_sub(1)
-                            .ok_or("fix edge bug out of bounds")?;
-                        if neighbor_x < width && neighbor_y < height {
-                            let neighbor_value = &gen.map.grid[[neighbor_x, neighbor_y]];
-                            if *neighbor_value == BlockType::Hookable {
-                                edge_bug[[x, y]] = true;
-                                // break;
-                                // TODO: this should be easy to optimize
-                            }
+    Ok(fix_edge_bugs_in_area(
+        &mut gen.map,
+        &Position::new(0, 0),
+        &Position::new(width - 1, height - 1),
+        freeze_style,
+    ))
+}
+
+/// ensures the freeze padding of the freeze style between all hookable and empty blocks within
+/// the area. Usually the empty blocks are replaced with freeze, but for dotted freeze the
+/// hookable blocks are replaced instead, so the perforations don't eat into the playfield.
+/// Returns the empty blocks that had an edge bug.
+pub fn fix_edge_bugs_in_area(
+    map: &mut Map,
+    top_left: &Position,
+    bot_right: &Position,
+    freeze_style: FreezeStyle,
+) -> Array2<bool> {
+    let mut edge_bug = Array2::from_elem((map.width, map.height), false);
+    let padding = freeze_style.padding();
+
+    for x in top_left.x..=bot_right.x {
+        for y in top_left.y..=bot_right.y {
+            if !map.is_plain_empty([x, y]) {
+                continue;
+            }
+
+            let (min_x, min_y) = (x.saturating_sub(padding), y.saturating_sub(padding));
+            let (max_x, max_y) = (
+                (x + padding).min(map.width - 1),
+                (y + padding).min(map.height - 1),
+            );
+
+            if freeze_style == FreezeStyle::Dotted {
+                for hook_x in min_x..=max_x {
+                    for hook_y in min_y..=max_y {
+                        let hook_pos = Position::new(hook_x, hook_y);
+                        if map.grid[[hook_x, hook_y]] == BlockType::Hookable
+                            && !map.is_border(&hook_pos)
+                            && !map.protected[[hook_x, hook_y]]
+                        {
+                            map.grid[[hook_x, hook_y]] = BlockType::Freeze;
+                            edge_bug[[x, y]] = true;
                         }
                     }
                 }
+            }
+
+            // remaining hookable blocks can't be replaced, so the empty block is frozen
+            let remaining_bug = map
+                .grid
+                .slice(s![min_x..=max_x, min_y..=max_y])
+                .iter()
+                .any(|block| *block == BlockType::Hookable);
+
+            if remaining_bug {
+                map.grid[[x, y]] = BlockType::Freeze;
+                edge_bug[[x, y]] = true;
+            }
+        }
+    }
+
+    edge_bug
+}
+
+/// replaces the hookable perforations of dotted freeze within the area (inclusive) that are
+/// enclosed by freeze on opposite sides, so markers placed along the freeze outline stay
+/// connected. Returns the amount of closed perforations.
+pub fn close_perforations(map: &mut Map, top_left: &Position, bot_right: &Position) -> usize {
+    let mut closed = 0;
+    for x in top_left.x.max(1)..=bot_right.x.min(map.width - 2) {
+        for y in top_left.y.max(1)..=bot_right.y.min(map.height - 2) {
+            let pos = Position::new(x, y);
+            if map.grid[[x, y]] != BlockType::Hookable
+                || !FreezeStyle::Dotted.is_perforated(&pos)
+                || map.is_border(&pos)
+            {
+                continue;
+            }
+
+            let is_freeze = |x: usize, y: usize| map.grid[[x, y]] == BlockType::Freeze;
+            if (is_freeze(x - 1, y) && is_freeze(x + 1, y))
+                || (is_freeze(x, y - 1) && is_freeze(x, y + 1))
+            {
+                map.grid[[x, y]] = BlockType::Freeze;
+                closed += 1;
+            }
+        }
+    }
+
+    closed
+}
+
+/// scale of the value noise used by fill styles, in blocks
+const FILL_NOISE_SCALE: usize = 6;
+
+/// deterministic hash of a seed and position
+fn position_hash(seed: u64, x: usize, y: usize) -> u64 {
+    let mut data = seed.to_le_bytes().to_vec();
+    data.extend_from_slice(&(x as u64).to_le_bytes());
+    data.extend_from_slice(&(y as u64).to_le_bytes());
+    hash(&data)
+}
+
+/// smooth noise in [0, 1], interpolated between random values that are scale blocks apart
+fn value_noise(seed: u64, x: usize, y: usize, scale: usize) -> f32 {
+    let (cell_x, cell_y) = (x / scale, y / scale);
+    let frac_x = (x % scale) as f32 / scale as f32;
+    let frac_y = (y % scale) as f32 / scale as f32;
+    let corner = |dx: usize, dy: usize| {
+        position_hash(seed, cell_x + dx, cell_y + dy) as f32 / u64::MAX as f32
+    };
+
+    let top = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * frac_x;
+    let bot = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * frac_x;
+    top + (bot - top) * frac_y
+}
+
+/// restores all border blocks that are not hookable anymore, returns the repaired positions
+pub fn repair_border(map: &mut Map) -> Array2<bool> {
+    let mut repaired = Array2::from_elem((map.width, map.height), false);
+    if map.border_thickness == 0 {
+        return repaired;
+    }
 
-                if edge_bug[[x, y]] {
-                    gen.map.grid[[x, y]] = BlockType::Freeze;
+    for x in 0..map.width {
+        for y in 0..map.height {
+            let pos = Position::new(x, y);
+            if map.is_border(&pos) && map.grid[[x, y]] != BlockType::Hookable {
+                map.set_block([x, y], BlockType::Hookable);
+                repaired[[x, y]] = true;
+            }
+        }
+    }
+
+    let num_repaired = repaired.iter().filter(|value| **value).count();
+    if num_repaired > 0 {
+        warn!("repaired {} border blocks", num_repaired);
+    }
+
+    repaired
+}
+
+/// max distance (in blocks) below a lip at which the walker path has to pass
+const LIP_PATH_RANGE: usize = 4;
+
+/// min amount of open blocks that have to remain below an extended lip
+const LIP_CLEARANCE: usize = 2;
+
+/// normalizes the freeze lips below ceiling corners along the walker path to the given
+/// length. A ceiling corner is a hookable block with no solid blocks below it and on one
+/// (outer) side. Lips are only extended into empty blocks and only trimmed where the freeze
+/// borders open space on the outer side and no hookable block, so freeze padding stays intact.
+/// Returns the changed positions.
+pub fn normalize_ceiling_lips(
+    map: &mut Map,
+    position_history: &PositionHistory,
+    lip_length: usize,
+) -> Array2<bool> {
+    let mut changed = Array2::from_elem((map.width, map.height), false);
+    if lip_length == 0 {
+        return changed;
+    }
+
+    let mut on_path = Array2::from_elem((map.width, map.height), false);
+    for pos in position_history.iter() {
+        if map.pos_in_bounds(&pos) {
+            on_path[pos.as_index()] = true;
+        }
+    }
+
+    let is_open = |block_type: &BlockType| !block_type.is_solid() && !block_type.is_freeze();
+    let path_range = lip_length + LIP_PATH_RANGE;
+
+    for x in 1..map.width - 1 {
+        for y in 0..map.height - 1 {
+            if map.grid[[x, y]] != BlockType::Hookable
+                || map.is_border(&Position::new(x, y))
+                || (x - 1..=x + 1).any(|below_x| map.grid[[below_x, y + 1]].is_solid())
+            {
+                continue;
+            }
+
+            // the ceiling has to end on the outer side of the corner
+            let outer_x = match [x - 1, x + 1]
+                .into_iter()
+                .find(|side_x| !map.grid[[*side_x, y]].is_solid())
+            {
+                Some(outer_x) => outer_x,
+                None => continue,
+            };
+
+            let x_range = x.saturating_sub(path_range)..=usize::min(x + path_range, map.width - 1);
+            let y_range = y + 1..=usize::min(y + path_range, map.height - 1);
+            if !on_path
+                .slice(s![x_range, y_range])
+                .iter()
+                .any(|value| *value)
+            {
+                continue;
+            }
+
+            // extend the lip into empty blocks, as long as the corridor stays passable
+            let lip_end = y + 1 + lip_length;
+            let mut lip_y = y + 1;
+            while lip_y < usize::min(lip_end, map.height) {
+                let block_type = &map.grid[[x, lip_y]];
+                if block_type.is_freeze() {
+                    lip_y += 1;
+                    continue;
+                }
+
+                let clearance_end = lip_y + LIP_CLEARANCE;
+                if !map.is_plain_empty([x, lip_y])
+                    || clearance_end >= map.height
+                    || !(lip_y + 1..=clearance_end).all(|check_y| is_open(&map.grid[[x, check_y]]))
+                {
+                    break;
+                }
+
+                map.grid[[x, lip_y]] = BlockType::Freeze;
+                changed[[x, lip_y]] = true;
+                lip_y += 1;
+            }
+            if lip_y < lip_end {
+                continue;
+            }
+
+            // trim the freeze below the lip, if it ends in open space
+            let tail_end =
+                match (lip_y..map.height).find(|tail_y| !map.grid[[x, *tail_y]].is_freeze()) {
+                    Some(tail_end) => tail_end,
+                    None => continue,
+                };
+            let can_trim = is_open(&map.grid[[x, tail_end]])
+                && (lip_y..=tail_end).all(|tail_y| is_open(&map.grid[[outer_x, tail_y]]))
+                && (lip_y..tail_end).all(|tail_y| {
+                    (x - 1..=x + 1).all(|check_x| {
+                        (tail_y - 1..=tail_y + 1)
+                            .all(|check_y| map.grid[[check_x, check_y]] != BlockType::Hookable)
+                    })
+                });
+            if can_trim {
+                for tail_y in lip_y..tail_end {
+                    map.grid[[x, tail_y]] = BlockType::Empty;
+                    changed[[x, tail_y]] = true;
                 }
             }
         }
     }
 
-    Ok(edge_bug)
+    changed
 }
 
-/// Using a distance transform this function will fill up all empty blocks that are too far
-/// from the next solid/non-empty block
-pub fn fill_open_areas(gen: &mut Generator, max_distance: &f32) -> Array2<f32> {
-    let grid = gen.map.grid.map(|val| *val != BlockType::Empty);
+/// highest supported wall smoothing aggressiveness
+pub const MAX_WALL_SMOOTHING: usize = 2;
 
-    // euclidean distance transform
-    let distance = dt_bool::<f32>(&grid.into_dyn())
-        .into_dimensionality::<Ix2>()
-        .unwrap();
+/// smooths single block jaggies along corridor walls, which are left by the manhattan steps of
+/// the walker. Freeze blocks sticking out into the corridor are carved and empty notches in the
+/// wall are filled with freeze. Aggressiveness 1 only smooths single block bumps and notches, 2
+/// also smooths single block steps of staircases. Freeze next to hookable blocks is never
+/// carved, so walls keep their freeze padding. Reserved blocks are only treated as empty, if
+/// they may be overwritten. Returns the changed positions.
+pub fn smooth_walls(
+    map: &mut Map,
+    aggressiveness: usize,
+    overwrite_reserved: bool,
+) -> Array2<bool> {
+    let mut changed = Array2::from_elem((map.width, map.height), false);
+    if aggressiveness == 0 {
+        return changed;
+    }
 
-    gen.map
-        .grid
-        .zip_mut_with(&distance, |block_type, distance| {
-            // only modify empty blocks
-            if *block_type != BlockType::Empty {
-                return;
+    // a block sticks out, if at least this many of its 8 neighbors are of the other type. Clean
+    // walls and diagonal staircases have at most 3.
+    let min_other = 6 - usize::min(aggressiveness, MAX_WALL_SMOOTHING);
+
+    // decide on the original walls, so smoothing doesnt cascade along a wall
+    let original = map.clone();
+    let is_empty = |index: &[usize; 2]| original.is_empty_at(*index, overwrite_reserved);
+    for x in 1..map.width - 1 {
+        for y in 1..map.height - 1 {
+            let is_freeze = original.grid[[x, y]] == BlockType::Freeze;
+            if !is_freeze && !is_empty(&[x, y]) {
+                continue;
+            }
+
+            let window: Vec<[usize; 2]> = (x - 1..=x + 1)
+                .flat_map(|window_x| (y - 1..=y + 1).map(move |window_y| [window_x, window_y]))
+                .collect();
+            let other_count = match is_freeze {
+                true => window.iter().filter(|index| is_empty(index)).count(),
+                false => window
+                    .iter()
+                    .filter(|index| original.grid[**index] == BlockType::Freeze)
+                    .count(),
+            };
+            if other_count < min_other {
+                continue;
+            }
+
+            let keep = match is_freeze {
+                true => window
+                    .iter()
+                    .any(|index| original.grid[*index] == BlockType::Hookable),
+                // only fill notches, a corridor continues on two or more sides
+                false => {
+                    [[x - 1, y], [x + 1, y], [x, y - 1], [x, y + 1]]
+                        .iter()
+                        .filter(|index| is_empty(index))
+                        .count()
+                        > 1
+                }
+            };
+            if !keep {
+                let new_type = match is_freeze {
+                    true => BlockType::Empty,
+                    false => BlockType::Freeze,
+                };
+                map.set_block([x, y], new_type);
+                changed[[x, y]] = true;
+            }
+        }
+    }
+
+    changed
+}
+
+/// detects corner holds: single freeze blocks that bulge out of the inner corner of the freeze
+/// padding around a hookable wall corner into open space. These are left by overlapping outer
+/// kernels at tight turns and turn an easy hook into the corner into a precise one. Depending
+/// on the policy they are kept, carved or chamfered. Reserved blocks are only treated as empty,
+/// if they may be overwritten. Returns the detected positions.
+pub fn fix_corner_holds(
+    map: &mut Map,
+    policy: CornerHoldPolicy,
+    overwrite_reserved: bool,
+) -> Array2<bool> {
+    let mut detected = Array2::from_elem((map.width, map.height), false);
+    if map.width < 5 || map.height < 5 {
+        return detected;
+    }
+
+    // decide on the original padding, so fixes dont create new corner holds
+    let original = map.clone();
+    for x in 2..map.width - 2 {
+        for y in 2..map.height - 2 {
+            if original.grid[[x, y]] != BlockType::Freeze {
+                continue;
+            }
+
+            // (dx, dy) points towards the wall corner
+            for (dx, dy) in [(-1, -1), (1, -1), (-1, 1), (1, 1)] {
+                let at = |ox: isize, oy: isize| {
+                    [
+                        (x as isize + ox * dx) as usize,
+                        (y as isize + oy * dy) as usize,
+                    ]
+                };
+
+                let padding_corner = [at(1, 0), at(0, 1), at(1, 1)]
+                    .iter()
+                    .all(|index| original.grid[*index] == BlockType::Freeze);
+                let open_side = [at(-1, 0), at(0, -1), at(-1, -1)]
+                    .iter()
+                    .all(|index| original.is_empty_at(*index, overwrite_reserved));
+                // removing the block must not expose the wall
+                let hookable_near = [at(1, -1), at(-1, 1)]
+                    .iter()
+                    .any(|index| original.grid[*index] == BlockType::Hookable);
+
+                if !padding_corner
+                    || !open_side
+                    || hookable_near
+                    || original.grid[at(2, 2)] != BlockType::Hookable
+                {
+                    continue;
+                }
+
+                detected[[x, y]] = true;
+                match policy {
+                    CornerHoldPolicy::Keep => {}
+                    CornerHoldPolicy::Carve => map.set_block([x, y], BlockType::Empty),
+                    CornerHoldPolicy::Chamfer => {
+                        map.set_block([(x as isize - dx) as usize, y], BlockType::Freeze);
+                        map.set_block([x, (y as isize - dy) as usize], BlockType::Freeze);
+                    }
+                }
+                break;
+            }
+        }
+    }
+
+    detected
+}
+
+/// max thickness of the freeze that a freeze pocket is carved through
+const POCKET_MAX_DEPTH: usize = 2;
+
+/// carves small pockets into the freeze walls next to the walker path of all segments with at
+/// least the given difficulty, at most one every spacing steps. Floor pockets are carved down
+/// to the hookable below the freeze, side pockets are 2 blocks high and carved into the wall
+/// above a hookable floor, so a tee can stand in both. Returns the carved positions.
+pub fn carve_freeze_pockets(
+    map: &mut Map,
+    position_history: &[Position],
+    reports: &[SegmentReport],
+    min_difficulty: f32,
+    spacing: usize,
+) -> Array2<bool> {
+    let mut carved = Array2::from_elem((map.width, map.height), false);
+
+    for report in reports
+        .iter()
+        .filter(|report| report.difficulty >= min_difficulty)
+    {
+        let mut step = report.start_step;
+        while step < report.end_step.min(position_history.len()) {
+            let pocket = [
+                ShiftDirection::Down,
+                ShiftDirection::Left,
+                ShiftDirection::Right,
+            ]
+            .iter()
+            .find_map(|direction| find_pocket(map, &position_history[step], direction));
+
+            match pocket {
+                Some(pocket) => {
+                    for pos in pocket {
+                        map.grid[pos.as_index()] = BlockType::Empty;
+                        carved[pos.as_index()] = true;
+                    }
+                    step += spacing.max(1);
+                }
+                None => step += 1,
+            }
+        }
+    }
+
+    carved
+}
+
+/// blocks of a pocket in the wall that is hit when moving from the position into the given
+/// direction, if the wall allows a pocket. Pockets are never carved into ceilings.
+fn find_pocket(
+    map: &Map,
+    path_pos: &Position,
+    direction: &ShiftDirection,
+) -> Option<Vec<Position>> {
+    if !map.is_plain_empty(path_pos.as_index()) {
+        return None;
+    }
+
+    // move to the last empty block in front of the wall
+    let mut entrance = path_pos.clone();
+    loop {
+        let mut next_pos = entrance.clone();
+        next_pos.shift_in_direction(direction, map).ok()?;
+        if !map.is_plain_empty(next_pos.as_index()) {
+            break;
+        }
+        entrance = next_pos;
+    }
+
+    // freeze blocks up to the hookable behind them
+    let freeze_run = |start: &Position| -> Option<Vec<Position>> {
+        let mut blocks = Vec::new();
+        let mut pos = start.clone();
+        loop {
+            pos.shift_in_direction(direction, map).ok()?;
+            let block_type = &map.grid[pos.as_index()];
+            if map.protected[pos.as_index()] || map.no_go[pos.as_index()] {
+                return None;
             }
+            match block_type {
+                BlockType::Freeze if blocks.len() < POCKET_MAX_DEPTH => blocks.push(pos.clone()),
+                BlockType::Hookable if !blocks.is_empty() => return Some(blocks),
+                _ => return None,
+            }
+        }
+    };
+
+    match direction {
+        ShiftDirection::Down => freeze_run(&entrance),
+        ShiftDirection::Left | ShiftDirection::Right => {
+            let lower = freeze_run(&entrance)?;
 
-            if *distance > *max_distance + SQRT_2 {
-                *block_type = BlockType::Hookable;
-            } else if *distance > *max_distance {
-                *block_type = BlockType::Freeze;
+            // the tee has to fit into the pocket, so the wall above has the same thickness
+            let upper_entrance = entrance.shifted_by(0, -1).ok()?;
+            if !map.is_plain_empty(upper_entrance.as_index()) {
+                return None;
             }
+            let upper = freeze_run(&upper_entrance)?;
+            if upper.len() != lower.len() {
+                return None;
+            }
+
+            // and stands on a hookable floor
+            let has_floor = lower.iter().all(|pos| {
+                pos.shifted_by(0, 1)
+                    .is_ok_and(|below| map.grid.get(below.as_index()) == Some(&BlockType::Hookable))
+            });
+
+            has_floor.then(|| lower.into_iter().chain(upper).collect())
+        }
+        ShiftDirection::Up => None,
+    }
+}
+
+/// max steps along the walker path between the teleport target and its checkpoint
+const CHECKPOINT_LINE_RANGE: usize = 20;
+
+/// places a checkpoint at every interval-th waypoint, except for the spawn and the last
+/// waypoint. Each checkpoint is a line across the walker path, right behind a TeleTo tile above
+/// the first solid floor along the path after the waypoint. If any checkpoint was placed, all
+/// freeze floors become TeleFrom tiles, so players that fall into freeze are reset to their last
+/// checkpoint. Returns the positions of all placed tele tiles.
+pub fn place_checkpoints(
+    map: &mut Map,
+    position_history: &PositionHistory,
+    waypoints: &[Position],
+    interval: usize,
+) -> Array2<bool> {
+    let mut placed = Array2::from_elem((map.width, map.height), false);
+    let path = position_history.to_vec();
+
+    let mut number: u8 = 1;
+    let mut search_start = 0;
+    let last_waypoint = waypoints.len().saturating_sub(1);
+    for waypoint in (interval.max(1)..last_waypoint)
+        .step_by(interval.max(1))
+        .map(|index| &waypoints[index])
+    {
+        // the path may pass a waypoint several times, so checkpoints are kept in path order
+        let step = match (search_start..path.len().saturating_sub(1))
+            .min_by_key(|step| path[*step].distance_squared(waypoint))
+        {
+            Some(step) => step,
+            None => break,
+        };
+
+        // teleported players should neither skip nor repeat much of the map, so the target is
+        // the first safe landing spot after the waypoint, right in front of the checkpoint line
+        let landing = (step..path.len()).find_map(|landing_step| {
+            landing_pos(map, &path[landing_step])
+                .filter(|target| map.tele[target.as_index()].is_none())
+                .map(|target| (landing_step, target))
+        });
+        let (landing_step, target) = match landing {
+            Some(landing) => landing,
+            None => break,
+        };
+        let line = (landing_step + 1..path.len() - 1)
+            .take(CHECKPOINT_LINE_RANGE)
+            .map(|line_step| {
+                let (prev, next) = (&path[line_step - 1], &path[line_step + 1]);
+                let vertical = prev.x.abs_diff(next.x) >= prev.y.abs_diff(next.y);
+                (line_step, checkpoint_line(map, &path[line_step], vertical))
+            })
+            .find(|(_, line)| !line.is_empty() && !line.contains(&target));
+        let (line_step, line) = match line {
+            Some(line) => line,
+            None => {
+                search_start = landing_step + 1;
+                continue;
+            }
+        };
+
+        for pos in line {
+            map.tele[pos.as_index()] = Some(TeleTile {
+                tele_type: TeleType::Checkpoint,
+                number,
+            });
+            placed[pos.as_index()] = true;
+        }
+        map.tele[target.as_index()] = Some(TeleTile {
+            tele_type: TeleType::TeleTo,
+            number,
         });
+        placed[target.as_index()] = true;
+
+        search_start = line_step + 1;
+        if number == u8::MAX {
+            break;
+        }
+        number += 1;
+    }
+
+    if number == 1 {
+        return placed;
+    }
+
+    for x in 0..map.width {
+        for y in 1..map.height {
+            if map.grid[[x, y]].is_freeze()
+                && map.grid[[x, y - 1]].is_empty()
+                && map.tele[[x, y]].is_none()
+            {
+                map.tele[[x, y]] = Some(TeleTile {
+                    tele_type: TeleType::TeleFrom,
+                    number: 1,
+                });
+                placed[[x, y]] = true;
+            }
+        }
+    }
+
+    placed
+}
+
+/// checkpoint line through the position that spans all empty blocks up to the walls, so it
+/// can't be passed without touching it. Empty if the position itself is not empty.
+fn checkpoint_line(map: &Map, pos: &Position, vertical: bool) -> Vec<Position> {
+    let can_cross = |pos: &Position| {
+        map.is_empty_at(pos.as_index(), true) && map.tele[pos.as_index()].is_none()
+    };
+    if !can_cross(pos) {
+        return Vec::new();
+    }
+
+    let directions = match vertical {
+        true => [ShiftDirection::Up, ShiftDirection::Down],
+        false => [ShiftDirection::Left, ShiftDirection::Right],
+    };
+
+    let mut line = vec![pos.clone()];
+    for direction in directions {
+        let mut current = pos.clone();
+        while current.shift_in_direction(&direction, map).is_ok() && can_cross(&current) {
+            line.push(current.clone());
+        }
+    }
+
+    line
+}
+
+/// position above the floor that a tee falls onto from the given position, if the floor is
+/// solid and the fall doesn't touch freeze or markers
+fn landing_pos(map: &Map, pos: &Position) -> Option<Position> {
+    let mut landing = pos.clone();
+    if !map.is_empty_at(landing.as_index(), true) {
+        return None;
+    }
+
+    loop {
+        let mut below = landing.clone();
+        below.shift_in_direction(&ShiftDirection::Down, map).ok()?;
+        if map.is_empty_at(below.as_index(), true) {
+            landing = below;
+        } else if map.grid[below.as_index()].is_solid() {
+            return Some(landing);
+        } else {
+            return None;
+        }
+    }
+}
+
+/// Using a distance transform this function will fill up all empty blocks that are too far
+/// from the next solid/non-empty block. Reserved blocks are only filled, if they may be
+/// overwritten.
+pub fn fill_open_areas(
+    gen: &mut Generator,
+    max_distance: &f32,
+    fill_style: FillStyle,
+    noise_amplitude: f32,
+    overwrite_reserved: bool,
+) -> Array2<f32> {
+    let fillable = Array2::from_shape_fn((gen.map.width, gen.map.height), |(x, y)| {
+        gen.map.is_empty_at([x, y], overwrite_reserved)
+    });
+    let grid = fillable.map(|fillable| !fillable);
+
+    // euclidean distance transform
+    let distance = distance_field(&grid);
+
+    let seed = gen.rnd.seed.seed_u64;
+    for ((x, y), fillable) in fillable.indexed_iter() {
+        // only modify empty blocks
+        if !fillable {
+            continue;
+        }
+
+        // push back the fill edge depending on the fill style
+        let fill_distance = *max_distance
+            + match fill_style {
+                FillStyle::Plain | FillStyle::FreezeBands => 0.0,
+                FillStyle::NoiseEroded => {
+                    value_noise(seed, x, y, FILL_NOISE_SCALE) * noise_amplitude
+                }
+                FillStyle::Stalactites => value_noise(seed, x, 0, 2) * noise_amplitude,
+            };
+
+        let distance = distance[[x, y]];
+        if distance > fill_distance + SQRT_2 {
+            let block_type = match fill_style {
+                FillStyle::FreezeBands => {
+                    let band_width = noise_amplitude.max(1.0);
+                    let band = ((distance - fill_distance - SQRT_2) / band_width) as usize;
+                    if band % 2 == 1 {
+                        BlockType::Freeze
+                    } else {
+                        BlockType::Hookable
+                    }
+                }
+                _ => BlockType::Hookable,
+            };
+            gen.map.set_block([x, y], block_type);
+        } else if distance > fill_distance {
+            gen.map.set_block([x, y], BlockType::Freeze);
+        }
+    }
 
     distance
 }
 
-// returns a vec of corner candidates and their respective direction to the wall
-pub fn find_corners(gen: &Generator) -> Result<Vec<(Position, ShiftDirection)>, &'static str> {
+/// scatters decoration tiles over solid areas. Placement only depends on the decoration seed and
+/// block position, so decoration doesnt influence the rest of the generation.
+pub fn place_doodads(gen: &mut Generator, density: f32, doodad_tiles: &[u8]) {
+    if density <= 0.0 || doodad_tiles.is_empty() {
+        return;
+    }
+
+    let seed = gen.map.decoration_seed;
+    let map = &mut gen.map;
+    for x in 1..map.width.saturating_sub(1) {
+        for y in 1..map.height.saturating_sub(1) {
+            let surrounded = map
+                .grid
+                .slice(s![x - 1..=x + 1, y - 1..=y + 1])
+                .iter()
+                .all(|block_type| block_type.is_solid());
+            if !surrounded {
+                continue;
+            }
+
+            let noise = position_hash(seed, x, y);
+
+            if (noise as f32) < (u64::MAX as f32 * density) {
+                let index = (noise >> 32) as usize % doodad_tiles.len();
+                map.doodads[[x, y]] = doodad_tiles[index];
+            }
+        }
+    }
+}
+
+/// marks all blocks within the given distance (in blocks) of the walker path
+pub fn get_path_band(map: &Map, position_history: &PositionHistory, band: usize) -> Array2<bool> {
+    let mut path = Array2::from_elem((map.width, map.height), false);
+    for pos in position_history.iter() {
+        path[pos.as_index()] = true;
+    }
+
+    // euclidean distance transform
+    let distance = distance_field(&path);
+
+    distance.map(|distance| *distance <= band as f32)
+}
+
+/// blocks that are closer than the separation to an empty block, i.e. to the area carved by the
+/// walker rather than to its center line
+fn get_carved_band(map: &Map, separation: usize) -> Array2<bool> {
+    let carved = map.grid.map(BlockType::is_empty);
+    let distance = distance_field(&carved);
+
+    distance.map(|distance| *distance < separation as f32)
+}
+
+/// finds corner candidates for skips. If path_band is not 0, only candidates within that
+/// distance of the walker path are considered.
+pub fn find_corners(
+    gen: &Generator,
+    path_band: usize,
+) -> Result<Vec<(Position, ShiftDirection)>, &'static str> {
     let mut candidates: Vec<(Position, ShiftDirection)> = Vec::new();
 
     let width = gen.map.width;
@@ -96,14 +815,25 @@ pub fn find_corners(gen: &Generator) -> Result<Vec<(Position, ShiftDirection)>,
 
     let window_size = 2; // 2 -> 5x5 windows
 
+    let band = match path_band {
+        0 => None,
+        band => Some(get_path_band(&gen.map, &gen.walker.position_history, band)),
+    };
+
     for window_x in window_size..(width - window_size) {
         for window_y in window_size..(height - window_size) {
+            if let Some(band) = &band {
+                if !band[[window_x, window_y]] {
+                    continue;
+                }
+            }
+
             let window = &gen.map.grid.slice(s![
                 window_x - window_size..=window_x + window_size,
                 window_y - window_size..=window_y + window_size
             ]);
 
-            if window[[2, 2]] != BlockType::Empty {
+            if !gen.map.is_plain_empty([window_x, window_y]) {
                 continue;
             }
 
@@ -211,26 +941,58 @@ pub fn find_corners(gen: &Generator) -> Result<Vec<(Position, ShiftDirection)>,
 
 /// Replace all map blocks with empty, that were not locked in the generation
 pub fn remove_unused_blocks(map: &mut Map, position_lock: &Array2<bool>) {
-    for (map_block, lock_status) in map.grid.iter_mut().zip(position_lock.iter()) {
+    for ((map_block, marker), lock_status) in map
+        .grid
+        .iter_mut()
+        .zip(map.markers.iter_mut())
+        .zip(position_lock.iter())
+    {
         if !lock_status {
             *map_block = BlockType::Empty;
+            *marker = None;
         }
     }
 }
 
+#[derive(Clone)]
 pub struct Skip {
     start_pos: Position,
     end_pos: Position,
     length: usize,
     direction: ShiftDirection,
+
+    /// amount of parallel tunnels, additional tunnels are placed right of/below the first one
+    width: usize,
+}
+
+impl Skip {
+    /// (top left, bot right) of the area covered by all tunnels of the skip
+    fn area(&self) -> (Position, Position) {
+        let top_left = Position::new(
+            usize::min(self.start_pos.x, self.end_pos.x),
+            usize::min(self.start_pos.y, self.end_pos.y),
+        );
+        let mut bot_right = Position::new(
+            usize::max(self.start_pos.x, self.end_pos.x),
+            usize::max(self.start_pos.y, self.end_pos.y),
+        );
+        match self.direction {
+            ShiftDirection::Left | ShiftDirection::Right => bot_right.y += self.width - 1,
+            ShiftDirection::Up | ShiftDirection::Down => bot_right.x += self.width - 1,
+        }
+
+        (top_left, bot_right)
+    }
 }
 
-/// if a skip has been found, this returns the end position and length
+/// if a skip has been found, this returns the end position and length. Skips only end in
+/// reserved blocks, if reserved blocks may be overwritten.
 pub fn check_corner_skip(
     gen: &Generator,
     init_pos: &Position,
     shift: &ShiftDirection,
     tunnel_bounds: (usize, usize),
+    overwrite_reserved: bool,
 ) -> Option<Skip> {
     let mut pos = init_pos.clone();
 
@@ -242,6 +1004,7 @@ pub fn check_corner_skip(
             return None;
         };
         let curr_block_type = gen.map.grid.get(pos.as_index()).unwrap();
+        let curr_empty = gen.map.is_empty_at(pos.as_index(), overwrite_reserved);
 
         stage = match (stage, curr_block_type) {
             // proceed to / or stay in stage 1 if freeze is found
@@ -254,7 +1017,7 @@ pub fn check_corner_skip(
             (2 | 3, BlockType::Freeze) => 3,
 
             // proceed to final state if (first) empty block is found
-            (3, BlockType::Empty) => 4,
+            (3, BlockType::Empty) if curr_empty => 4,
 
             // no match -> invalid sequence, abort!
             _ => return None,
@@ -269,25 +1032,62 @@ pub fn check_corner_skip(
             end_pos: pos,
             length,
             direction: shift.clone(),
+            width: 1,
         })
     } else {
         None
     }
 }
 
+/// tries to add a parallel tunnel of the same length on either side of the skip. Returns the 2
+/// wide skip, if the parallel tunnel is a valid skip itself and still has hookable neighbors.
+pub fn widen_skip(gen: &mut Generator, skip: &Skip, overwrite_reserved: bool) -> Option<Skip> {
+    let (dx, dy) = match skip.direction {
+        ShiftDirection::Left | ShiftDirection::Right => (0, 1),
+        ShiftDirection::Up | ShiftDirection::Down => (1, 0),
+    };
+
+    for side in [1, -1] {
+        let lane_start = match skip.start_pos.shifted_by(dx * side, dy * side) {
+            Ok(lane_start) if gen.map.pos_in_bounds(&lane_start) => lane_start,
+            _ => continue,
+        };
+        if !gen.map.is_plain_empty(lane_start.as_index()) {
+            continue;
+        }
+
+        let bounds = (skip.length - 1, skip.length);
+        let lane = match check_corner_skip(
+            gen,
+            &lane_start,
+            &skip.direction,
+            bounds,
+            overwrite_reserved,
+        ) {
+            Some(lane) => lane,
+            None => continue,
+        };
+
+        // the first tunnel is always the one at the top/left
+        let first = if side == 1 { skip } else { &lane };
+        let wide_skip = Skip {
+            width: 2,
+            ..first.clone()
+        };
+        if count_skip_neighbours(gen, &wide_skip, 2).unwrap_or(0) > 0 {
+            return Some(wide_skip);
+        }
+    }
+
+    None
+}
+
 pub fn count_skip_neighbours(
     gen: &mut Generator,
     skip: &Skip,
     offset: usize,
 ) -> Result<usize, &'static str> {
-    let top_left = Position::new(
-        usize::min(skip.start_pos.x, skip.end_pos.x),
-        usize::min(skip.start_pos.y, skip.end_pos.y),
-    );
-    let bot_right = Position::new(
-        usize::max(skip.start_pos.x, skip.end_pos.x),
-        usize::max(skip.start_pos.y, skip.end_pos.y),
-    );
+    let (top_left, bot_right) = skip.area();
 
     let offset: i32 = offset as i32;
 
@@ -323,21 +1123,21 @@ pub fn count_skip_neighbours(
     }
 }
 
-pub fn generate_skip(gen: &mut Generator, skip: &Skip, block_type: &BlockType) {
-    let top_left = Position::new(
-        usize::min(skip.start_pos.x, skip.end_pos.x),
-        usize::min(skip.start_pos.y, skip.end_pos.y),
-    );
-    let bot_right = Position::new(
-        usize::max(skip.start_pos.x, skip.end_pos.x),
-        usize::max(skip.start_pos.y, skip.end_pos.y),
-    );
+/// carves the skip tunnels with the given block type, replacing what the overwrite matrix
+/// allows. Empty tunnels are lined with freeze.
+pub fn generate_skip(
+    gen: &mut Generator,
+    skip: &Skip,
+    block_type: &BlockType,
+    overwrite: &OverwriteMatrix,
+) {
+    let (top_left, bot_right) = skip.area();
 
     gen.map.set_area(
         &top_left,
         &bot_right,
         block_type,
-        &Overwrite::ReplaceSolidFreeze,
+        &Overwrite::Matrix(overwrite),
     );
 
     if block_type.is_freeze() {
@@ -376,103 +1176,408 @@ pub fn generate_skip(gen: &mut Generator, skip: &Skip, block_type: &BlockType) {
     }
 }
 
+/// carves a funnel shaped mouth into the wall at a skip entrance, so the skip can be spotted
+/// and entered at speed. The mouth is lined with freeze like the skip itself.
+fn shape_skip_entrance(
+    gen: &mut Generator,
+    entrance: &Position,
+    direction: &ShiftDirection,
+    mouth_size: usize,
+    overwrite: &OverwriteMatrix,
+) {
+    let (dx, dy): (i32, i32) = match direction {
+        ShiftDirection::Up => (0, -1),
+        ShiftDirection::Right => (1, 0),
+        ShiftDirection::Down => (0, 1),
+        ShiftDirection::Left => (-1, 0),
+    };
+
+    // carve mouth, getting narrower the deeper it goes into the wall
+    let mut mouth_cells = Vec::new();
+    for depth in 1..=mouth_size as i32 {
+        let half_width = mouth_size as i32 + 1 - depth;
+        for offset in -half_width..=half_width {
+            if let Ok(pos) = entrance.shifted_by(dx * depth + dy * offset, dy * depth + dx * offset)
+            {
+                if gen.map.pos_in_bounds(&pos) {
+                    mouth_cells.push(pos);
+                }
+            }
+        }
+    }
+
+    for pos in mouth_cells.iter() {
+        gen.map
+            .set_area(pos, pos, &BlockType::Empty, &Overwrite::Matrix(overwrite));
+    }
+
+    // line mouth with freeze
+    for pos in mouth_cells.iter() {
+        if let (Ok(top_left), Ok(bot_right)) = (pos.shifted_by(-1, -1), pos.shifted_by(1, 1)) {
+            gen.map.set_area(
+                &top_left,
+                &bot_right,
+                &BlockType::Freeze,
+                &Overwrite::ReplaceSolidOnly,
+            );
+        }
+    }
+}
+
 #[derive(Clone, PartialEq)]
 enum SkipStatus {
-    Invalid,
+    Invalid(&'static str),
     ValidFreezeSkipOnly,
     Valid,
+    ValidDeepSkip,
+}
+
+impl SkipStatus {
+    fn describe(&self) -> String {
+        match self {
+            SkipStatus::Invalid(reason) => format!("invalid: {}", reason),
+            SkipStatus::ValidFreezeSkipOnly => "valid freeze skip".to_string(),
+            SkipStatus::Valid => "valid".to_string(),
+            SkipStatus::ValidDeepSkip => "valid deep skip".to_string(),
+        }
+    }
+}
+
+/// checks whether two skips start or end too close to each other
+fn skips_conflict(skip: &Skip, skip_other: &Skip, min_spacing_sqr: usize) -> bool {
+    skip.start_pos.distance_squared(&skip_other.start_pos) < min_spacing_sqr
+        || skip.start_pos.distance_squared(&skip_other.end_pos) < min_spacing_sqr
+        || skip.end_pos.distance_squared(&skip_other.start_pos) < min_spacing_sqr
+        || skip.end_pos.distance_squared(&skip_other.end_pos) < min_spacing_sqr
+}
+
+/// whether a tunnel block of the skip is closer than the separation to the carved path. The
+/// blocks within the separation of both skip ends are the crossing points into the path and
+/// always allowed. carved_band has to be the carved band of the separation, see get_carved_band.
+fn is_skip_too_close_to_path(
+    map: &Map,
+    skip: &Skip,
+    carved_band: &Array2<bool>,
+    separation: usize,
+) -> bool {
+    let mut pos = skip.start_pos.clone();
+    for step in 1..skip.length {
+        if pos.shift_in_direction(&skip.direction, map).is_err() {
+            return false;
+        }
+        if step >= separation && step + separation <= skip.length && carved_band[pos.as_index()] {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// greedily selects up to max_count skips of a candidate pool (shortest first), so that they
+/// dont conflict with any previously selected skip
+fn select_skips(
+    skips: &[Skip],
+    mut pool: Vec<usize>,
+    selected: &mut Vec<usize>,
+    min_spacing_sqr: usize,
+    max_count: usize,
+) -> Vec<usize> {
+    pool.sort_by_key(|skip_index| skips[*skip_index].length);
+
+    let mut pool_selected = Vec::new();
+    for skip_index in pool {
+        if pool_selected.len() >= max_count {
+            break;
+        }
+
+        let conflict = selected.iter().any(|selected_index| {
+            skips_conflict(&skips[skip_index], &skips[*selected_index], min_spacing_sqr)
+        });
+
+        if !conflict {
+            selected.push(skip_index);
+            pool_selected.push(skip_index);
+        }
+    }
+
+    pool_selected
+}
+
+/// finds long skips from the walker path straight through the solid mass next to it. Only the
+/// first wall in each direction of each path position is checked.
+fn find_deep_skips(gen: &Generator, gen_config: &GenerationConfig) -> Vec<Skip> {
+    let overwrite_reserved = gen_config.overwrite_reserved;
+
+    // bitmask of the directions that were already checked for each wall position
+    let mut checked = Array2::<u8>::zeros(gen.map.grid.dim());
+    let mut skips = Vec::new();
+    for path_pos in gen.walker.position_history.iter() {
+        for shift in [
+            ShiftDirection::Up,
+            ShiftDirection::Right,
+            ShiftDirection::Down,
+            ShiftDirection::Left,
+        ] {
+            // move to the last empty block in front of the wall
+            let mut start_pos = path_pos.clone();
+            loop {
+                let mut next_pos = start_pos.clone();
+                if next_pos.shift_in_direction(&shift, &gen.map).is_err()
+                    || !gen.map.is_empty_at(next_pos.as_index(), overwrite_reserved)
+                {
+                    break;
+                }
+                start_pos = next_pos;
+            }
+
+            let direction_bit = 1 << shift as u8;
+            if checked[start_pos.as_index()] & direction_bit != 0 {
+                continue;
+            }
+            checked[start_pos.as_index()] |= direction_bit;
+
+            if let Some(skip) = check_corner_skip(
+                gen,
+                &start_pos,
+                &shift,
+                gen_config.deep_skip_length_bounds,
+                overwrite_reserved,
+            ) {
+                skips.push(skip);
+            }
+        }
+    }
+
+    skips
 }
 
 pub fn generate_all_skips(
     gen: &mut Generator,
-    length_bounds: (usize, usize),
-    min_spacing_sqr: usize,
-    max_level_skip: usize,
+    gen_config: &GenerationConfig,
     flood_fill: &Array2<Option<usize>>,
 ) {
+    let length_bounds = gen_config.skip_length_bounds;
+    let min_spacing_sqr = gen_config.skip_min_spacing_sqr;
+    let freeze_length_bounds = gen_config.get_freeze_skip_length_bounds();
+    let freeze_min_spacing_sqr = gen_config.get_freeze_skip_min_spacing_sqr();
+    let max_level_skip = gen_config.max_level_skip;
+
     // get corner candidates
-    let corner_candidates = find_corners(gen).expect("corner detection failed");
+    let corner_candidates =
+        find_corners(gen, gen_config.skip_path_band).expect("corner detection failed");
 
-    // get possible skips
+    // get possible skips, covering the length bounds of both skip types
+    let search_bounds = (
+        usize::min(length_bounds.0, freeze_length_bounds.0),
+        usize::max(length_bounds.1, freeze_length_bounds.1),
+    );
     let mut skips: Vec<Skip> = Vec::new();
     for (start_pos, shift) in corner_candidates {
-        if let Some(skip) = check_corner_skip(gen, &start_pos, &shift, length_bounds) {
+        if let Some(skip) = check_corner_skip(
+            gen,
+            &start_pos,
+            &shift,
+            search_bounds,
+            gen_config.overwrite_reserved,
+        ) {
             skips.push(skip);
         }
     }
 
-    // pick final selection of skips
-    skips.sort_unstable_by(|s1, s2| usize::cmp(&s1.length, &s2.length)); // sort by length
-    let mut skip_status = vec![SkipStatus::Valid; skips.len()];
-    for skip_index in 0..skips.len() {
-        // skip if already invalidated
-        if skip_status[skip_index] == SkipStatus::Invalid {
-            continue;
-        }
-
-        let skip = &skips[skip_index];
+    // deep skips are appended, so the corner skips keep their indices
+    let corner_skip_count = skips.len();
+    if gen_config.enable_deep_skips {
+        skips.append(&mut find_deep_skips(gen, gen_config));
+    }
 
-        // check if too much of the level would be skipped
-        let level_distance_start = flood_fill[skip.start_pos.as_index()].unwrap();
-        let level_distance_end = flood_fill[skip.end_pos.as_index()].unwrap();
-        let level_skip_distance = usize::abs_diff(level_distance_start, level_distance_end);
-        if level_skip_distance > max_level_skip {
-            skip_status[skip_index] = SkipStatus::Invalid;
+    let in_bounds = |length: usize, bounds: (usize, usize)| length > bounds.0 && length <= bounds.1;
+
+    let separation = gen_config.skip_path_separation;
+    let separation_band = match separation {
+        0 => None,
+        _ => Some(get_carved_band(&gen.map, separation)),
+    };
+
+    // split skips into separate pools for actual skips and freeze skips
+    let mut skip_pool = Vec::new();
+    let mut freeze_skip_pool = Vec::new();
+    let mut deep_skip_pool = Vec::new();
+    let mut skip_status = vec![SkipStatus::Invalid("conflicts with another skip"); skips.len()];
+    let mut skip_info = Vec::with_capacity(skips.len());
+    for (skip_index, skip) in skips.iter().enumerate() {
+        let level_skip_distance = match (
+            flood_fill[skip.start_pos.as_index()],
+            flood_fill[skip.end_pos.as_index()],
+        ) {
+            (Some(start), Some(end)) => Some(usize::abs_diff(start, end)),
+            _ => None,
+        };
+        let neighbours = count_skip_neighbours(gen, skip, 2).unwrap_or(0);
+        let direct_neighbours = count_skip_neighbours(gen, skip, 1).unwrap_or(0);
+        let level_skip = match level_skip_distance {
+            Some(distance) => distance.to_string(),
+            None => "unreachable".to_string(),
+        };
+        skip_info.push(format!(
+            "neighbours: {}, direct: {}\nlevel skip: {}",
+            neighbours, direct_neighbours, level_skip
+        ));
+
+        // check if too much (or for deep skips, too little) of the level would be skipped
+        let deep = skip_index >= corner_skip_count;
+        let level_bounds = match deep {
+            true => gen_config.deep_skip_level_bounds,
+            false => (0, max_level_skip),
+        };
+        let invalid_reason = match level_skip_distance {
+            None => Some("not reachable from spawn"),
+            Some(distance) if distance > level_bounds.1 => Some("skips too much of the level"),
+            Some(distance) if distance < level_bounds.0 => Some("skips too little of the level"),
+            _ => None,
+        };
+        if let Some(reason) = invalid_reason {
+            skip_status[skip_index] = SkipStatus::Invalid(reason);
             continue;
         }
 
-        // invalidate if skip would have no neighboring blocks
-        if count_skip_neighbours(gen, skip, 2).unwrap_or(0) <= 0 {
-            // if yes, test if freeze skip would have neighboring blocks
-            if count_skip_neighbours(gen, skip, 1).unwrap_or(0) >= 1 {
-                skip_status[skip_index] = SkipStatus::ValidFreezeSkipOnly;
-            } else {
-                // if both are not the case -> invalidate
-                skip_status[skip_index] = SkipStatus::Invalid;
+        if let Some(band) = &separation_band {
+            if is_skip_too_close_to_path(&gen.map, skip, band, separation) {
+                skip_status[skip_index] = SkipStatus::Invalid("too close to the walker path");
                 continue;
             }
         }
 
-        // skip is valid -> invalidate all following conflicting skips
-        // TODO: right now skips can still cross each other
-        // TODO: i feel like i need a config seperation between skips and freeze skips
-        //       would be nice to not have freeze invalidate actual skips, and have different
-        //       length
-        for other_index in (skip_index + 1)..skips.len() {
-            let skip_other = &skips[other_index];
-
-            // check if skips are too close
-            if skip.start_pos.distance_squared(&skip_other.start_pos) < min_spacing_sqr
-                || skip.start_pos.distance_squared(&skip_other.end_pos) < min_spacing_sqr
-                || skip.end_pos.distance_squared(&skip_other.start_pos) < min_spacing_sqr
-                || skip.end_pos.distance_squared(&skip_other.end_pos) < min_spacing_sqr
-            {
-                skip_status[other_index] = SkipStatus::Invalid;
+        if deep {
+            match neighbours > 0 {
+                true => deep_skip_pool.push(skip_index),
+                false => {
+                    skip_status[skip_index] = SkipStatus::Invalid("not enough neighbouring blocks")
+                }
             }
+            continue;
         }
+
+        // actual skips require neighboring blocks, freeze skips only direct neighbors
+        if in_bounds(skip.length, length_bounds) && neighbours > 0 {
+            skip_pool.push(skip_index);
+        } else if in_bounds(skip.length, freeze_length_bounds) && direct_neighbours >= 1 {
+            freeze_skip_pool.push(skip_index);
+        } else if in_bounds(skip.length, length_bounds)
+            || in_bounds(skip.length, freeze_length_bounds)
+        {
+            skip_status[skip_index] = SkipStatus::Invalid("not enough neighbouring blocks");
+        } else {
+            skip_status[skip_index] = SkipStatus::Invalid("length out of bounds");
+        }
+    }
+
+    // select actual skips first, so freeze skips can never invalidate them
+    // TODO: right now skips can still cross each other
+    let mut selected = Vec::new();
+    for skip_index in select_skips(
+        &skips,
+        skip_pool,
+        &mut selected,
+        min_spacing_sqr,
+        usize::MAX,
+    ) {
+        skip_status[skip_index] = SkipStatus::Valid;
+    }
+    for skip_index in select_skips(
+        &skips,
+        deep_skip_pool,
+        &mut selected,
+        min_spacing_sqr,
+        gen_config.max_deep_skips,
+    ) {
+        skip_status[skip_index] = SkipStatus::ValidDeepSkip;
+    }
+    for skip_index in select_skips(
+        &skips,
+        freeze_skip_pool,
+        &mut selected,
+        freeze_min_spacing_sqr,
+        usize::MAX,
+    ) {
+        skip_status[skip_index] = SkipStatus::ValidFreezeSkipOnly;
     }
 
     // generate all remaining valid skips
+    let mut widths = vec![1; skips.len()];
     for skip_index in 0..skips.len() {
         match skip_status[skip_index] {
-            SkipStatus::Valid => generate_skip(gen, &skips[skip_index], &BlockType::Empty),
-            SkipStatus::ValidFreezeSkipOnly => {
-                generate_skip(gen, &skips[skip_index], &BlockType::Freeze)
+            SkipStatus::Valid | SkipStatus::ValidDeepSkip => {
+                // only draw if wide skips are enabled, so other presets keep their seeds. Deep
+                // skips are always 1 wide.
+                let wide = skip_status[skip_index] == SkipStatus::Valid
+                    && gen_config.wide_skip_prob > 0.0
+                    && gen.rnd.with_probability(gen_config.wide_skip_prob);
+                let skip = match wide {
+                    true => widen_skip(gen, &skips[skip_index], gen_config.overwrite_reserved)
+                        .unwrap_or(skips[skip_index].clone()),
+                    false => skips[skip_index].clone(),
+                };
+                let skip = &skip;
+                widths[skip_index] = skip.width;
+                generate_skip(gen, skip, &BlockType::Empty, &gen_config.skip_overwrite);
+
+                if gen_config.skip_mouth_size > 0 {
+                    let mouth_size = gen_config.skip_mouth_size;
+                    let overwrite = &gen_config.skip_overwrite;
+                    shape_skip_entrance(
+                        gen,
+                        &skip.start_pos,
+                        &skip.direction,
+                        mouth_size,
+                        overwrite,
+                    );
+                    shape_skip_entrance(
+                        gen,
+                        &skip.end_pos,
+                        &skip.direction.opposite(),
+                        mouth_size,
+                        overwrite,
+                    );
+                }
             }
+            SkipStatus::ValidFreezeSkipOnly => generate_skip(
+                gen,
+                &skips[skip_index],
+                &BlockType::Freeze,
+                &gen_config.skip_overwrite,
+            ),
             _ => (),
         }
     }
 
     // add debug visualizations
-    for (skip, status) in skips.iter().zip(skip_status.iter()) {
-        let debug_layer = match *status {
-            SkipStatus::Valid => gen.debug_layers.get_mut("skips").unwrap(),
-            SkipStatus::Invalid => gen.debug_layers.get_mut("skips_invalid").unwrap(),
+    for (skip_index, skip) in skips.iter().enumerate() {
+        let status = &skip_status[skip_index];
+        let debug_layer = match status {
+            SkipStatus::Valid | SkipStatus::ValidDeepSkip => {
+                gen.debug_layers.get_mut("skips").unwrap()
+            }
+            SkipStatus::Invalid(_) => gen.debug_layers.get_mut("skips_invalid").unwrap(),
             SkipStatus::ValidFreezeSkipOnly => gen.debug_layers.get_mut("freeze_skips").unwrap(),
         };
 
-        debug_layer.grid[skip.start_pos.as_index()] = true;
-        debug_layer.grid[skip.end_pos.as_index()] = true;
+        let info = format!(
+            "skip {:?}, length {}, width {}\n{}\n{}",
+            skip.direction,
+            skip.length,
+            widths[skip_index],
+            status.describe(),
+            skip_info[skip_index]
+        );
+        for pos in [&skip.start_pos, &skip.end_pos] {
+            debug_layer.grid[pos.as_index()] = true;
+            // skips can share an end position
+            debug_layer
+                .info
+                .entry(pos.as_index())
+                .and_modify(|existing| *existing = format!("{}\n\n{}", existing, info))
+                .or_insert(info.clone());
+        }
     }
 }
 
@@ -694,13 +1799,13 @@ pub fn get_optimal_greedy_platform_candidate(
         // try to expand left
         if !left_locked {
             // check if platform has no overhang
-            let no_overhang =
-                map.check_position_crit(&pos.shifted_by(-(left_limit + 1), 1)?, |b| {
-                    match (gen_config.plat_soft_overhang, b) {
-                        (true, block) => !block.is_empty(),
-                        (false, block) => block.is_solid(),
-                    }
-                });
+            let no_overhang = map.check_position_crit(
+                &pos.shifted_by(-(left_limit + 1), 1)?,
+                |b, marker| match (gen_config.plat_soft_overhang, b) {
+                    (true, block) => !block.is_empty() || marker.is_some(),
+                    (false, block) => block.is_solid(),
+                },
+            );
 
             let next_limit_valid = map.check_area_all(
                 &pos.shifted_by(-(left_limit + 1), -up_limit)?,
@@ -717,12 +1822,13 @@ pub fn get_optimal_greedy_platform_candidate(
 
         // try to expand right
         if !right_locked {
-            let no_overhang = map.check_position_crit(&pos.shifted_by(right_limit + 1, 1)?, |b| {
-                match (gen_config.plat_soft_overhang, b) {
-                    (true, block) => !block.is_empty(),
-                    (false, block) => block.is_solid(),
-                }
-            });
+            let no_overhang =
+                map.check_position_crit(&pos.shifted_by(right_limit + 1, 1)?, |b, marker| {
+                    match (gen_config.plat_soft_overhang, b) {
+                        (true, block) => !block.is_empty() || marker.is_some(),
+                        (false, block) => block.is_solid(),
+                    }
+                });
             let next_limit_valid = map.check_area_all(
                 &pos.shifted_by(right_limit + 1, -up_limit)?,
                 &pos.shifted_by(right_limit + 1, -1)?, // dont check y=0 as freeze expected
@@ -767,8 +1873,65 @@ pub fn get_optimal_greedy_platform_candidate(
     })
 }
 
+/// checks whether the area above a platform position is reachable from spawn
+fn is_platform_reachable(platform_pos: &Position, flood_fill: &Array2<Option<usize>>) -> bool {
+    match platform_pos.shifted_by(0, -1) {
+        Ok(pos) => flood_fill[pos.as_index()].is_some(),
+        Err(_) => false,
+    }
+}
+
+/// checks the standing zone above a platform of the given height: plat_min_head_room blocks that
+/// are neither solid nor freeze, and no freeze within plat_freeze_clearance blocks next to them.
+/// A platform height of 0 places no platform, so the floor below the candidate is used.
+fn has_platform_clearance(
+    map: &Map,
+    platform: &Platform,
+    platform_height: usize,
+    gen_config: &GenerationConfig,
+) -> bool {
+    let head_room = gen_config.plat_min_head_room;
+    if head_room == 0 {
+        return true;
+    }
+
+    let standing_y = match platform_height {
+        0 => platform.pos.y,
+        _ => platform.pos.y.saturating_sub(platform_height + 1),
+    };
+    let (zone_top, zone_bot) = match (standing_y + 1).checked_sub(head_room) {
+        Some(top_y) => (top_y, standing_y),
+        None => return false,
+    };
+
+    let clearance = gen_config.plat_freeze_clearance;
+    let (left, right) = (
+        platform.pos.x - platform.width_left,
+        platform.pos.x + platform.width_right,
+    );
+    if left < clearance || right + clearance >= map.width {
+        return false;
+    }
+
+    let head_room_free = map
+        .grid
+        .slice(s![left..=right, zone_top..=zone_bot])
+        .iter()
+        .all(|block| !block.is_solid() && !block.is_freeze());
+    let freeze_free = map
+        .grid
+        .slice(s![
+            left - clearance..=right + clearance,
+            zone_top..=zone_bot
+        ])
+        .iter()
+        .all(|block| !block.is_freeze());
+
+    head_room_free && freeze_free
+}
+
 pub fn gen_all_platform_candidates(
-    walker_pos_history: &Vec<Position>,
+    walker_pos_history: &PositionHistory,
     flood_fill: &Array2<Option<usize>>,
     map: &mut Map,
     gen_config: &GenerationConfig,
@@ -776,19 +1939,26 @@ pub fn gen_all_platform_candidates(
 ) {
     let mut platform_candidates: Vec<Platform> = Vec::new();
     let mut last_platform_level_distance = 0;
+    let history_length = walker_pos_history.len().max(1);
 
-    for pos_index in 0..walker_pos_history.len() {
-        let pos = &walker_pos_history[pos_index];
+    for (step, pos) in walker_pos_history.iter().enumerate() {
+        let pos = &pos;
 
         // skip if initial walker pos is non empty
-        if map.grid[pos.as_index()] != BlockType::Empty {
+        if !map.is_plain_empty(pos.as_index()) {
             continue;
         }
 
+        // skip if walker pos is not reachable from spawn
+        let level_distance = match flood_fill[pos.as_index()] {
+            Some(level_distance) => level_distance,
+            None => continue,
+        };
+
         // skip if previous platform is still to close
-        let level_distance = flood_fill[pos.as_index()].unwrap();
+        let difficulty = gen_config.get_difficulty(step as f32 / history_length as f32);
         if level_distance.saturating_sub(last_platform_level_distance)
-            < gen_config.plat_min_distance
+            < gen_config.get_plat_min_distance(difficulty)
         {
             continue;
         }
@@ -798,11 +1968,33 @@ pub fn gen_all_platform_candidates(
         if floor_pos.is_none() {
             continue;
         }
-        let floor_pos = floor_pos.unwrap();
+        let mut floor_pos = floor_pos.unwrap();
 
         // try to get optimal platform candidate
-        let platform_pos = floor_pos.shifted_by(0, -1).unwrap();
-        let result = get_optimal_greedy_platform_candidate(&platform_pos, map, gen_config);
+        let mut platform_pos = floor_pos.shifted_by(0, -1).unwrap();
+        let mut result = get_optimal_greedy_platform_candidate(&platform_pos, map, gen_config);
+
+        // platform is in a pocket behind freeze -> nudge it up onto the freeze, so it is
+        // reachable from the corridor. Otherwise skip the platform.
+        if result.is_ok() && !is_platform_reachable(&platform_pos, flood_fill) {
+            let nudged_pos =
+                map.shift_pos_until(pos, ShiftDirection::Down, |b| b.is_solid() || b.is_freeze());
+
+            result = match nudged_pos {
+                Some(nudged_pos) if is_platform_reachable(&nudged_pos, flood_fill) => {
+                    floor_pos = nudged_pos.shifted_by(0, 1).unwrap();
+                    platform_pos = nudged_pos;
+                    get_optimal_greedy_platform_candidate(&platform_pos, map, gen_config)
+                }
+                _ => Err("platform not reachable"),
+            };
+
+            if result.is_err() {
+                let platforms_unreachable = debug_layers.get_mut("platforms_unreachable").unwrap();
+                platforms_unreachable.grid[platform_pos.as_index()] = true;
+            }
+        }
+
         if let Ok(platform_candidate) = result {
             // draw debug
             let platforms_walker_pos = debug_layers.get_mut("platforms_walker_pos").unwrap();
@@ -829,8 +2021,17 @@ pub fn gen_all_platform_candidates(
 
     // generate platforms
     for platform_candidate in platform_candidates {
-        let platform_height =
+        // lower platforms until there is enough clearance to stand on them, skip them otherwise
+        let mut platform_height =
             platform_candidate.available_height - gen_config.plat_min_empty_height;
+        while platform_height > 0
+            && !has_platform_clearance(map, &platform_candidate, platform_height, gen_config)
+        {
+            platform_height -= 1;
+        }
+        if !has_platform_clearance(map, &platform_candidate, platform_height, gen_config) {
+            continue;
+        }
 
         if platform_height > 0 {
             map.set_area(
@@ -850,7 +2051,7 @@ pub fn gen_all_platform_candidates(
             );
         }
 
-        map.set_area(
+        map.set_marker_area(
             &platform_candidate
                 .pos
                 .shifted_by(
@@ -865,8 +2066,69 @@ pub fn gen_all_platform_candidates(
                     -(platform_height as i32),
                 )
                 .unwrap(),
-            &BlockType::EmptyReserved,
+            Marker::Reserved,
             &Overwrite::Force,
         );
+
+        map.protect_area(
+            &platform_candidate
+                .pos
+                .shifted_by(
+                    -(platform_candidate.width_left as i32),
+                    -((platform_candidate.available_height - 1) as i32),
+                )
+                .unwrap(),
+            &platform_candidate
+                .pos
+                .shifted_by(platform_candidate.width_right as i32, 0)
+                .unwrap(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// solid map with two empty corridors at x = 2 and x = 15 and a straight skip between them
+    fn skip_between_corridors() -> (Map, Skip) {
+        let mut map = Map::new(20, 9, BlockType::Hookable);
+        for y in 0..map.height {
+            map.grid[[2, y]] = BlockType::Empty;
+            map.grid[[15, y]] = BlockType::Empty;
+        }
+
+        let skip = Skip {
+            start_pos: Position::new(2, 4),
+            end_pos: Position::new(15, 4),
+            length: 13,
+            direction: ShiftDirection::Right,
+            width: 1,
+        };
+
+        (map, skip)
+    }
+
+    #[test]
+    fn skip_crossing_points_are_allowed() {
+        let (map, skip) = skip_between_corridors();
+        for separation in 1..=6 {
+            let band = get_carved_band(&map, separation);
+            assert!(!is_skip_too_close_to_path(&map, &skip, &band, separation));
+        }
+    }
+
+    #[test]
+    fn skip_closer_than_separation_is_rejected() {
+        let (mut map, skip) = skip_between_corridors();
+
+        // carved block 2 blocks below the middle of the tunnel
+        map.grid[[8, 6]] = BlockType::Empty;
+
+        let band = get_carved_band(&map, 3);
+        assert!(is_skip_too_close_to_path(&map, &skip, &band, 3));
+
+        let band = get_carved_band(&map, 2);
+        assert!(!is_skip_too_close_to_path(&map, &skip, &band, 2));
     }
 }
@@ -1,12 +1,52 @@
 use crate::config::GenerationConfig;
 use crate::position::ShiftDirection;
+use crate::trace::TracedSample;
 use rand::prelude::*;
 use rand::rngs::SmallRng;
-use rand_distr::WeightedAliasIndex;
+use rand_distr::{WeightedAliasIndex, WeightedIndex};
+use schemars::JsonSchema;
 use seahash::hash;
 use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::fmt;
+use std::panic::Location;
+use std::sync::Arc;
 
-#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+thread_local! {
+    /// whether newly created Random instances of this thread record their draws
+    static RNG_AUDIT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// enables or disables recording of rng draws for all Random instances created afterwards on
+/// the current thread. This is only meant for debugging seed stability, as it slows down
+/// generation considerably.
+pub fn set_rng_audit(enabled: bool) {
+    RNG_AUDIT.with(|audit| audit.set(enabled));
+}
+
+/// a single recorded rng draw
+#[derive(Debug, Clone, PartialEq)]
+pub struct RngDraw {
+    /// source location that requested the draw
+    pub location: &'static Location<'static>,
+
+    /// drawn value, for weighted samples this is the sampled index
+    pub value: u64,
+}
+
+impl fmt::Display for RngDraw {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{} -> {}",
+            self.location.file(),
+            self.location.line(),
+            self.value
+        )
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct RandomDistConfig<T> {
     pub values: Option<Vec<T>>,
     pub probs: Vec<f32>,
@@ -39,6 +79,7 @@ impl<T> RandomDistConfig<T> {
     }
 }
 
+#[derive(Clone)]
 pub struct RandomDist<T> {
     rnd_cfg: RandomDistConfig<T>,
     rnd_dist: WeightedAliasIndex<f32>,
@@ -60,6 +101,19 @@ impl<T: Clone> RandomDist<T> {
     }
 }
 
+/// samples of a GenerationTrace that are returned instead of drawing new ones
+#[derive(Clone)]
+struct TraceReplay {
+    samples: Arc<[TracedSample]>,
+
+    /// index of the next sample
+    next: usize,
+
+    /// index of the first sample that didn't match the requested sample
+    divergence: Option<usize>,
+}
+
+#[derive(Clone)]
 pub struct Random {
     pub seed: Seed,
     gen: SmallRng,
@@ -67,6 +121,15 @@ pub struct Random {
     inner_kernel_size_dist: RandomDist<usize>,
     outer_kernel_margin_dist: RandomDist<usize>,
     circ_dist: RandomDist<f32>,
+
+    /// recorded draws, if the rng audit was enabled on creation
+    audit: Option<Vec<RngDraw>>,
+
+    /// outcomes of all samples, if tracing is enabled
+    trace: Option<Vec<TracedSample>>,
+
+    /// traced samples that are replayed instead of drawing new ones
+    replay: Option<TraceReplay>,
 }
 
 #[derive(Debug, Clone)]
@@ -98,9 +161,23 @@ impl Seed {
         Seed::from_u64(Random::get_random_u64())
     }
 
+    /// seed of the daily map, which is the same for everyone generating the same presets on the
+    /// same date (yyyy-mm-dd)
+    pub fn daily(date: &str, gen_config: &str, map_config: &str) -> Seed {
+        Seed::from_string(&format!("daily {} {} {}", date, gen_config, map_config))
+    }
+
     pub fn str_to_u64(seed_str: &String) -> u64 {
         hash(seed_str.as_bytes())
     }
+
+    /// separate random stream for decoration (doodads, automapper), so changing decoration
+    /// settings never changes the gameplay layout of a seed
+    pub fn decoration_seed(&self) -> u64 {
+        let mut data = self.seed_u64.to_le_bytes().to_vec();
+        data.extend_from_slice(b"decoration");
+        hash(&data)
+    }
 }
 
 impl Random {
@@ -113,49 +190,219 @@ impl Random {
             inner_kernel_size_dist: RandomDist::new(config.inner_size_probs.clone()),
             circ_dist: RandomDist::new(config.circ_probs.clone()),
             // TODO: clones here fine?
+            audit: RNG_AUDIT.with(|audit| audit.get()).then(Vec::new),
+            trace: None,
+            replay: None,
         }
     }
 
+    /// start recording the outcomes of all samples, see GenerationTrace
+    pub fn enable_trace(&mut self) {
+        if self.trace.is_none() {
+            self.trace = Some(Vec::new());
+        }
+    }
+
+    /// all traced samples so far, if tracing is enabled
+    pub fn get_trace(&self) -> Option<&Vec<TracedSample>> {
+        self.trace.as_ref()
+    }
+
+    /// Returns the given samples in order instead of drawing new ones. Once a requested sample
+    /// doesn't match the next traced sample, new samples are drawn again, see trace_divergence.
+    pub fn replay_trace(&mut self, samples: &[TracedSample]) {
+        self.replay = Some(TraceReplay {
+            samples: samples.into(),
+            next: 0,
+            divergence: None,
+        });
+    }
+
+    /// index of the first traced sample that didn't match the requested sample during a replay
+    pub fn trace_divergence(&self) -> Option<usize> {
+        self.replay.as_ref().and_then(|replay| replay.divergence)
+    }
+
+    /// next traced sample while replaying, None if there is no replay or it already diverged. A
+    /// traced sample that extract rejects, e.g. of another kind or out of bounds, diverges the
+    /// replay.
+    fn replayed<T>(&mut self, extract: impl Fn(&TracedSample) -> Option<T>) -> Option<T> {
+        let replay = self.replay.as_mut()?;
+        if replay.divergence.is_some() {
+            return None;
+        }
+
+        let value = replay.samples.get(replay.next).and_then(extract);
+        match value {
+            Some(_) => replay.next += 1,
+            None => replay.divergence = Some(replay.next),
+        }
+        value
+    }
+
+    fn traced(&mut self, sample: TracedSample) {
+        if let Some(trace) = self.trace.as_mut() {
+            trace.push(sample);
+        }
+    }
+
+    /// returns all recorded draws, if the rng audit was enabled on creation
+    pub fn take_audit(&mut self) -> Option<Vec<RngDraw>> {
+        self.audit.take()
+    }
+
+    #[track_caller]
+    fn record(&mut self, value: u64) {
+        if let Some(audit) = self.audit.as_mut() {
+            audit.push(RngDraw {
+                location: Location::caller(),
+                value,
+            });
+        }
+    }
+
+    #[track_caller]
+    fn next_u64(&mut self) -> u64 {
+        let value = self.gen.next_u64();
+        self.record(value);
+        value
+    }
+
+    #[track_caller]
     pub fn sample_inner_kernel_size(&mut self) -> usize {
-        let dist = &self.inner_kernel_size_dist;
-        let index = dist.rnd_dist.sample(&mut self.gen);
-        dist.rnd_cfg
+        if let Some(size) = self.replayed(|sample| match sample {
+            TracedSample::InnerKernelSize(size) => Some(*size),
+            _ => None,
+        }) {
+            return size;
+        }
+
+        let index = self.inner_kernel_size_dist.rnd_dist.sample(&mut self.gen);
+        self.record(index as u64);
+        let size = self
+            .inner_kernel_size_dist
+            .rnd_cfg
             .values
             .as_ref()
             .unwrap()
             .get(index)
             .unwrap()
-            .clone()
+            .clone();
+        self.traced(TracedSample::InnerKernelSize(size));
+        size
     }
 
+    #[track_caller]
     pub fn sample_outer_kernel_margin(&mut self) -> usize {
-        let dist = &self.outer_kernel_margin_dist;
-        let index = dist.rnd_dist.sample(&mut self.gen);
-        dist.rnd_cfg
+        if let Some(margin) = self.replayed(|sample| match sample {
+            TracedSample::OuterKernelMargin(margin) => Some(*margin),
+            _ => None,
+        }) {
+            return margin;
+        }
+
+        let index = self.outer_kernel_margin_dist.rnd_dist.sample(&mut self.gen);
+        self.record(index as u64);
+        let margin = self
+            .outer_kernel_margin_dist
+            .rnd_cfg
             .values
             .as_ref()
             .unwrap()
             .get(index)
             .unwrap()
-            .clone()
+            .clone();
+        self.traced(TracedSample::OuterKernelMargin(margin));
+        margin
     }
 
+    #[track_caller]
     pub fn sample_circularity(&mut self) -> f32 {
-        let dist = &self.circ_dist;
-        let index = dist.rnd_dist.sample(&mut self.gen);
-        dist.rnd_cfg
+        if let Some(circularity) = self.replayed(|sample| match sample {
+            TracedSample::Circularity(circularity) => Some(*circularity),
+            _ => None,
+        }) {
+            return circularity;
+        }
+
+        let index = self.circ_dist.rnd_dist.sample(&mut self.gen);
+        self.record(index as u64);
+        let circularity = self
+            .circ_dist
+            .rnd_cfg
             .values
             .as_ref()
             .unwrap()
             .get(index)
             .unwrap()
-            .clone()
+            .clone();
+        self.traced(TracedSample::Circularity(circularity));
+        circularity
     }
 
+    #[track_caller]
     pub fn sample_shift(&mut self, ordered_shifts: &[ShiftDirection; 4]) -> ShiftDirection {
-        let dist = &self.shift_dist;
-        let index = dist.rnd_dist.sample(&mut self.gen);
-        ordered_shifts.get(index).unwrap().clone()
+        if let Some(shift) = self.replayed(TracedSample::as_shift) {
+            return shift;
+        }
+
+        let index = self.shift_dist.rnd_dist.sample(&mut self.gen);
+        self.record(index as u64);
+        let shift = ordered_shifts.get(index).unwrap().clone();
+        self.traced(TracedSample::Shift(shift));
+        shift
+    }
+
+    /// like sample_shift, but each shift weight is multiplied by the given scale first. Falls back
+    /// to the unscaled weights if all scaled weights are zero.
+    #[track_caller]
+    pub fn sample_shift_scaled(
+        &mut self,
+        ordered_shifts: &[ShiftDirection; 4],
+        scales: &[f32; 4],
+    ) -> ShiftDirection {
+        if let Some(shift) = self.replayed(TracedSample::as_shift) {
+            return shift;
+        }
+
+        let weights = self
+            .shift_dist
+            .rnd_cfg
+            .probs
+            .iter()
+            .zip(scales.iter())
+            .map(|(prob, scale)| prob * scale);
+
+        match WeightedIndex::new(weights) {
+            Ok(dist) => {
+                let index = dist.sample(&mut self.gen);
+                self.record(index as u64);
+                self.traced(TracedSample::Shift(ordered_shifts[index]));
+                ordered_shifts[index]
+            }
+            Err(_) => self.sample_shift(ordered_shifts),
+        }
+    }
+
+    /// samples an index with probability proportional to its weight. Falls back to a uniform
+    /// pick if the weights are invalid, e.g. all zero.
+    #[track_caller]
+    pub fn sample_weighted_index(&mut self, weights: &[f32]) -> usize {
+        if let Some(index) =
+            self.replayed(|sample| sample.as_index().filter(|index| *index < weights.len()))
+        {
+            return index;
+        }
+
+        match WeightedIndex::new(weights) {
+            Ok(dist) => {
+                let index = dist.sample(&mut self.gen);
+                self.record(index as u64);
+                self.traced(TracedSample::Index(index));
+                index
+            }
+            Err(_) => self.in_range_exclusive(0, weights.len()),
+        }
     }
 
     /// derive a u64 seed from entropy
@@ -164,55 +411,110 @@ impl Random {
         tmp_rng.next_u64()
     }
 
+    #[track_caller]
     pub fn in_range_inclusive(&mut self, low: usize, high: usize) -> usize {
         assert!(high >= low, "no valid range");
+        if let Some(index) = self.replayed(|sample| {
+            sample
+                .as_index()
+                .filter(|index| (low..=high).contains(index))
+        }) {
+            return index;
+        }
+
         let n = (high - low) + 1;
-        let rnd_value = self.gen.next_u64() as usize;
+        let rnd_value = self.next_u64() as usize;
 
-        low + (rnd_value % n)
+        let index = low + (rnd_value % n);
+        self.traced(TracedSample::Index(index));
+        index
     }
 
+    #[track_caller]
     pub fn in_range_exclusive(&mut self, low: usize, high: usize) -> usize {
         assert!(high > low, "no valid range");
+        if let Some(index) = self.replayed(|sample| {
+            sample
+                .as_index()
+                .filter(|index| (low..high).contains(index))
+        }) {
+            return index;
+        }
+
         let n = high - low;
-        let rnd_value = self.gen.next_u64() as usize;
+        let rnd_value = self.next_u64() as usize;
 
-        low + (rnd_value % n)
+        let index = low + (rnd_value % n);
+        self.traced(TracedSample::Index(index));
+        index
     }
 
+    #[track_caller]
     pub fn random_u64(&mut self) -> u64 {
-        self.gen.next_u64()
+        if let Some(value) = self.replayed(|sample| match sample {
+            TracedSample::Value(value) => Some(*value),
+            _ => None,
+        }) {
+            return value;
+        }
+
+        let value = self.next_u64();
+        self.traced(TracedSample::Value(value));
+        value
     }
 
+    #[track_caller]
     pub fn with_probability(&mut self, probability: f32) -> bool {
-        if probability == 1.0 {
+        if let Some(chance) = self.replayed(|sample| match sample {
+            TracedSample::Chance(chance) => Some(*chance),
+            _ => None,
+        }) {
+            return chance;
+        }
+
+        let chance = if probability == 1.0 {
             self.skip();
             true
         } else if probability == 0.0 {
             self.skip();
             false
         } else {
-            (self.gen.next_u64() as f32) < (u64::max_value() as f32 * probability)
-        }
+            (self.next_u64() as f32) < (u64::max_value() as f32 * probability)
+        };
+        self.traced(TracedSample::Chance(chance));
+        chance
     }
 
     /// skip one gen step to ensure that a value is consumed in any case
+    #[track_caller]
     pub fn skip(&mut self) {
-        self.gen.next_u64();
+        self.next_u64();
     }
 
     /// skip n gen steps to ensure that n values are consumed in any case
+    #[track_caller]
     pub fn skip_n(&mut self, n: usize) {
         for _ in 0..n {
-            self.gen.next_u64();
+            self.next_u64();
         }
     }
 
+    #[track_caller]
     pub fn pick_element<'a, T>(&'a mut self, values: &'a [T]) -> &T {
         &values[self.in_range_exclusive(0, values.len())]
     }
 
+    #[track_caller]
     pub fn random_fraction(&mut self) -> f32 {
-        self.gen.next_u64() as f32 / u64::max_value() as f32
+        if let Some(fraction) = self.replayed(|sample| match sample {
+            TracedSample::Fraction(fraction) => Some(*fraction),
+            _ => None,
+        }) {
+            return fraction;
+        }
+
+        let fraction = self.next_u64() as f32 / u64::max_value() as f32;
+        self.traced(TracedSample::Fraction(fraction));
+        fraction
     }
 }
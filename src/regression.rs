@@ -0,0 +1,173 @@
+use crate::{
+    config::{GenerationConfig, MapConfig},
+    generator::Generator,
+    random::Seed,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+
+/// max amount of walker steps used for regression generations
+pub const REGRESSION_MAX_STEPS: usize = 200_000;
+
+/// A single golden map, defined by its generation inputs and the expected map fingerprint
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct RegressionCase {
+    pub seed: u64,
+    pub gen_config: String,
+    pub map_config: String,
+    pub hash: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegressionStatus {
+    /// generated map matches the expected fingerprint
+    Passed,
+
+    /// generated map differs from the expected fingerprint
+    Mismatch { actual: u64 },
+
+    /// map could not be generated at all
+    Failed(&'static str),
+}
+
+#[derive(Debug, Clone)]
+pub struct RegressionResult {
+    pub case: RegressionCase,
+    pub status: RegressionStatus,
+}
+
+impl RegressionResult {
+    pub fn passed(&self) -> bool {
+        self.status == RegressionStatus::Passed
+    }
+}
+
+/// loads a list of cases, or a single case like the provenance sidecar of an exported map
+pub fn load_cases(path: &str) -> Result<Vec<RegressionCase>, &'static str> {
+    let data = fs::read_to_string(path).map_err(|_| "failed to read regression file")?;
+    serde_json::from_str(&data)
+        .or_else(|_| serde_json::from_str(&data).map(|case| vec![case]))
+        .map_err(|_| "failed to parse regression file")
+}
+
+pub fn save_cases(cases: &[RegressionCase], path: &str) -> Result<(), &'static str> {
+    let data = serde_json::to_string_pretty(cases).map_err(|_| "failed to serialize cases")?;
+    fs::write(path, data).map_err(|_| "failed to write regression file")
+}
+
+/// re-generates the map of a case and returns its fingerprint
+pub fn generate_fingerprint(
+    case: &RegressionCase,
+    gen_configs: &HashMap<String, GenerationConfig>,
+    map_configs: &HashMap<String, MapConfig>,
+) -> Result<u64, &'static str> {
+    let gen_config = gen_configs
+        .get(&case.gen_config)
+        .ok_or("unknown gen config")?;
+    let map_config = map_configs
+        .get(&case.map_config)
+        .ok_or("unknown map config")?;
+
+    // post processing may panic for broken maps, which should just count as failed case
+    let map = panic::catch_unwind(AssertUnwindSafe(|| {
+        Generator::generate_map(
+            REGRESSION_MAX_STEPS,
+            &Seed::from_u64(case.seed),
+            gen_config,
+            map_config,
+        )
+    }))
+    .map_err(|_| "generation panicked")??;
+
+    Ok(Generator::fingerprint_map(&map))
+}
+
+/// re-generates all cases and compares them to their expected fingerprint
+pub fn run_regression(
+    cases: &[RegressionCase],
+    gen_configs: &HashMap<String, GenerationConfig>,
+) -> Vec<RegressionResult> {
+    let map_configs = MapConfig::get_all_configs();
+
+    cases
+        .iter()
+        .map(|case| {
+            let status = match generate_fingerprint(case, gen_configs, &map_configs) {
+                Ok(hash) if hash == case.hash => RegressionStatus::Passed,
+                Ok(hash) => RegressionStatus::Mismatch { actual: hash },
+                Err(err) => RegressionStatus::Failed(err),
+            };
+
+            RegressionResult {
+                case: case.clone(),
+                status,
+            }
+        })
+        .collect()
+}
+
+/// re-generates all cases and returns them with updated fingerprints. Use this to accept
+/// intended changes of the generation output.
+pub fn record_cases(
+    cases: &[RegressionCase],
+    gen_configs: &HashMap<String, GenerationConfig>,
+) -> Result<Vec<RegressionCase>, &'static str> {
+    let map_configs = MapConfig::get_all_configs();
+
+    cases
+        .iter()
+        .map(|case| {
+            Ok(RegressionCase {
+                hash: generate_fingerprint(case, gen_configs, &map_configs)?,
+                ..case.clone()
+            })
+        })
+        .collect()
+}
+
+/// human readable summary, one line per case
+pub fn format_results(results: &[RegressionResult]) -> String {
+    let mut summary = String::new();
+    for result in results {
+        let case = &result.case;
+        let status = match &result.status {
+            RegressionStatus::Passed => "ok".to_string(),
+            RegressionStatus::Mismatch { actual } => {
+                format!("MISMATCH (expected {:x}, got {:x})", case.hash, actual)
+            }
+            RegressionStatus::Failed(err) => format!("FAILED ({})", err),
+        };
+        summary += &format!(
+            "{} / {} / {}: {}\n",
+            case.gen_config, case.map_config, case.seed, status
+        );
+    }
+    let passed = results.iter().filter(|r| r.passed()).count();
+    summary += &format!("{}/{} passed", passed, results.len());
+
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// golden maps of shipped presets, see data/regression/golden.json
+    const GOLDEN_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/data/regression/golden.json");
+
+    #[test]
+    fn golden_maps_match() {
+        let cases = load_cases(GOLDEN_FILE).unwrap();
+        assert!(!cases.is_empty());
+
+        let results = run_regression(&cases, &GenerationConfig::get_all_configs());
+        assert!(
+            results.iter().all(|result| result.passed()),
+            "{}",
+            format_results(&results)
+        );
+    }
+}
@@ -1,19 +1,41 @@
-use crate::{map::BlockType, map::KernelType, position::Position, walker::CuteWalker};
+use crate::{
+    analysis::SegmentReport,
+    config::{GenerationConfig, NoGoZone},
+    diff::MapDiff,
+    map::{BlockType, KernelType, Map, Marker},
+    position::Position,
+    walker::CuteWalker,
+};
 use macroquad::color::colors;
 use macroquad::color::Color;
 use macroquad::shapes::*;
+use macroquad::texture::Image;
 use ndarray::Array2;
+use std::path::Path;
 
 fn blocktype_to_color(value: &BlockType) -> Color {
     match value {
         BlockType::Hookable => Color::new(0.76, 0.48, 0.29, 0.8),
         BlockType::Freeze => Color::new(0.0, 0.0, 0.0, 0.8),
         BlockType::Empty => Color::new(0.0, 0.0, 0.0, 0.0),
-        BlockType::EmptyReserved => Color::new(0.3, 0.0, 0.0, 0.1),
-        BlockType::Finish => Color::new(1.0, 0.1, 0.1, 0.8),
-        BlockType::Start => Color::new(0.1, 1.0, 0.1, 0.8),
         BlockType::Platform => Color::new(0.83, 0.64, 0.51, 0.8),
-        BlockType::Spawn => Color::new(0.2, 0.2, 0.7, 0.8),
+    }
+}
+
+fn marker_to_color(value: &Marker) -> Color {
+    match value {
+        Marker::Reserved => Color::new(0.3, 0.0, 0.0, 0.1),
+        Marker::Finish => Color::new(1.0, 0.1, 0.1, 0.8),
+        Marker::Start => Color::new(0.1, 1.0, 0.1, 0.8),
+        Marker::Spawn => Color::new(0.2, 0.2, 0.7, 0.8),
+    }
+}
+
+/// markers are drawn instead of the block below them
+fn block_to_color(block_type: &BlockType, marker: &Option<Marker>) -> Color {
+    match marker {
+        Some(marker) => marker_to_color(marker),
+        None => blocktype_to_color(block_type),
     }
 }
 
@@ -45,6 +67,7 @@ pub fn draw_bool_grid(grid: &Array2<bool>, color: &Color, outline: &bool) {
 /// drawn individually as in the unoptimized variant.
 pub fn draw_chunked_grid(
     grid: &Array2<BlockType>,
+    markers: &Array2<Option<Marker>>,
     chunks_edited: &Array2<bool>,
     chunk_size: usize,
 ) {
@@ -57,8 +80,8 @@ pub fn draw_chunked_grid(
 
             for x in x_start..x_end {
                 for y in y_start..y_end {
-                    let value = &grid[[x, y]];
-                    draw_rectangle(x as f32, y as f32, 1.0, 1.0, blocktype_to_color(value));
+                    let color = block_to_color(&grid[[x, y]], &markers[[x, y]]);
+                    draw_rectangle(x as f32, y as f32, 1.0, 1.0, color);
                 }
             }
         } else {
@@ -128,3 +151,147 @@ pub fn draw_waypoints(waypoints: &[Position], color: Color) {
         draw_circle(pos.x as f32 + 0.5, pos.y as f32 + 0.5, 0.5, color)
     }
 }
+
+pub fn draw_no_go_zones(zones: &[NoGoZone], color: Color) {
+    for zone in zones.iter() {
+        draw_area(&zone.top_left, &zone.bot_right, color);
+    }
+}
+
+/// draws the outline of an area (inclusive)
+pub fn draw_area(top_left: &Position, bot_right: &Position, color: Color) {
+    draw_rectangle_lines(
+        top_left.x as f32,
+        top_left.y as f32,
+        (bot_right.x - top_left.x + 1) as f32,
+        (bot_right.y - top_left.y + 1) as f32,
+        0.5,
+        color,
+    );
+}
+
+/// draws the walker path of each segment, colored from green (easy) to red (hard)
+pub fn draw_segment_reports(position_history: &[Position], reports: &[SegmentReport]) {
+    for report in reports.iter() {
+        let color = Color::new(report.difficulty, 1.0 - report.difficulty, 0.0, 0.8);
+        let end_step = usize::min(report.end_step, position_history.len());
+        for step in (report.start_step + 1)..end_step {
+            let from = &position_history[step - 1];
+            let to = &position_history[step];
+            draw_line(
+                from.x as f32 + 0.5,
+                from.y as f32 + 0.5,
+                to.x as f32 + 0.5,
+                to.y as f32 + 0.5,
+                0.5,
+                color,
+            );
+        }
+    }
+}
+
+/// draws how often the walker visited each block, from yellow (once) to red (most visits)
+pub fn draw_visit_heatmap(walker: &CuteWalker) {
+    let max_visits = walker.visit_counts.iter().copied().max().unwrap_or(0);
+    if max_visits == 0 {
+        return;
+    }
+
+    for ((x, y), visits) in walker.visit_counts.indexed_iter() {
+        if *visits == 0 {
+            continue;
+        }
+
+        let value = match max_visits {
+            1 => 0.0,
+            _ => (*visits - 1) as f32 / (max_visits - 1) as f32,
+        };
+        draw_rectangle(
+            x as f32,
+            y as f32,
+            1.0,
+            1.0,
+            Color::new(1.0, 1.0 - value, 0.0, 0.5),
+        );
+    }
+}
+
+/// draws all positions the walker may no longer enter as translucent overlay
+pub fn draw_locked_positions(walker: &CuteWalker) {
+    draw_bool_grid(
+        &walker.locked_positions,
+        &Color::new(0.0, 0.4, 1.0, 0.3),
+        &false,
+    );
+}
+
+/// draws the shift cost of all blocks within radius around the walker, from green (cheapest) to
+/// red (most expensive)
+pub fn draw_cost_field(walker: &CuteWalker, map: &Map, config: &GenerationConfig, radius: usize) {
+    let goal = match walker.goal.as_ref() {
+        Some(goal) => goal,
+        None => return,
+    };
+
+    let x_range =
+        walker.pos.x.saturating_sub(radius)..=usize::min(walker.pos.x + radius, map.width - 1);
+    let y_range =
+        walker.pos.y.saturating_sub(radius)..=usize::min(walker.pos.y + radius, map.height - 1);
+    let costs: Vec<(usize, usize, f32)> = x_range
+        .flat_map(|x| y_range.clone().map(move |y| (x, y)))
+        .map(|(x, y)| {
+            (
+                x,
+                y,
+                walker.shift_cost(&Position::new(x, y), goal, map, config),
+            )
+        })
+        .collect();
+
+    let min_cost = costs
+        .iter()
+        .map(|(_, _, cost)| *cost)
+        .fold(f32::INFINITY, f32::min);
+    let max_cost = costs
+        .iter()
+        .map(|(_, _, cost)| *cost)
+        .fold(f32::NEG_INFINITY, f32::max);
+    let cost_range = (max_cost - min_cost).max(f32::EPSILON);
+
+    for (x, y, cost) in costs {
+        let value = (cost - min_cost) / cost_range;
+        draw_rectangle(
+            x as f32,
+            y as f32,
+            1.0,
+            1.0,
+            Color::new(value, 1.0 - value, 0.0, 0.4),
+        );
+    }
+}
+
+/// Renders the map grid into a png file with one pixel per block. Transparent blocks are drawn
+/// on a white background, so the result looks like the editor view.
+/// draws added cells green, removed cells red and changed cells yellow
+pub fn draw_map_diff(diff: &MapDiff) {
+    draw_bool_grid(&diff.added, &Color::new(0.0, 1.0, 0.0, 0.6), &false);
+    draw_bool_grid(&diff.removed, &Color::new(1.0, 0.0, 0.0, 0.6), &false);
+    draw_bool_grid(&diff.changed, &Color::new(1.0, 1.0, 0.0, 0.6), &false);
+}
+
+pub fn export_map_png(map: &Map, path: &Path) {
+    let mut image = Image::gen_image_color(map.width as u16, map.height as u16, colors::WHITE);
+
+    for ((x, y), value) in map.grid.indexed_iter() {
+        let mut color = block_to_color(value, &map.markers[[x, y]]);
+        if color.a == 0.0 {
+            continue;
+        }
+        color.a = 1.0;
+
+        // export_png flips the image vertically, so flip it here aswell
+        image.set_pixel(x as u32, (map.height - y - 1) as u32, color);
+    }
+
+    image.export_png(&path.to_string_lossy());
+}
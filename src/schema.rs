@@ -0,0 +1,52 @@
+use schemars::schema_for;
+use serde_json::Value;
+
+use crate::{
+    config::{GenerationConfig, MapConfig},
+    export::MapProvenance,
+};
+
+/// json documents that external tools may read or write, e.g. to build preset editors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaKind {
+    /// generation preset, see GenerationConfig
+    GenerationConfig,
+
+    /// map preset, see MapConfig
+    MapConfig,
+
+    /// provenance sidecar that is written next to exported maps, see MapProvenance
+    Sidecar,
+}
+
+impl SchemaKind {
+    pub const ALL: [SchemaKind; 3] = [
+        SchemaKind::GenerationConfig,
+        SchemaKind::MapConfig,
+        SchemaKind::Sidecar,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SchemaKind::GenerationConfig => "gen_config",
+            SchemaKind::MapConfig => "map_config",
+            SchemaKind::Sidecar => "sidecar",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<SchemaKind> {
+        SchemaKind::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+/// JSON Schema of the document, generated from its serde types. Field descriptions are taken
+/// from the doc comments and fields with defaults are not required.
+pub fn json_schema(kind: SchemaKind) -> Value {
+    let schema = match kind {
+        SchemaKind::GenerationConfig => schema_for!(GenerationConfig),
+        SchemaKind::MapConfig => schema_for!(MapConfig),
+        SchemaKind::Sidecar => schema_for!(MapProvenance),
+    };
+
+    serde_json::to_value(schema).expect("failed to serialize schema")
+}
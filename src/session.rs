@@ -0,0 +1,79 @@
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{GenerationConfig, MapConfig};
+
+/// max amount of recently generated seeds that are remembered
+pub const MAX_RECENT_SEEDS: usize = 10;
+
+/// editor state that is restored when the editor is started again
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct EditorSession {
+    pub gen_config: GenerationConfig,
+    pub map_config: MapConfig,
+
+    pub seed_u64: u64,
+    pub seed_str: String,
+    pub fixed_seed: bool,
+
+    /// most recent seed first
+    pub recent_seeds: Vec<u64>,
+
+    pub zoom: f32,
+    pub offset: (f32, f32),
+
+    pub window_size: (f32, f32),
+    pub edit_gen_config: bool,
+    pub edit_map_config: bool,
+    pub ddnet_coordinates: bool,
+
+    pub visualize_debug_layers: HashMap<String, bool>,
+}
+
+impl Default for EditorSession {
+    fn default() -> EditorSession {
+        EditorSession {
+            gen_config: GenerationConfig::get_initial_gen_config(),
+            map_config: MapConfig::get_initial_config(),
+            seed_u64: 0,
+            seed_str: String::new(),
+            fixed_seed: false,
+            recent_seeds: Vec::new(),
+            zoom: 1.0,
+            offset: (0.0, 0.0),
+            window_size: (800.0, 600.0),
+            edit_gen_config: false,
+            edit_map_config: false,
+            ddnet_coordinates: false,
+            visualize_debug_layers: HashMap::new(),
+        }
+    }
+}
+
+impl EditorSession {
+    /// location of the persisted session in the users config directory
+    pub fn get_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("gores-mapgen").join("session.json"))
+    }
+
+    pub fn load(path: &PathBuf) -> Result<EditorSession, &'static str> {
+        let data = fs::read_to_string(path).map_err(|_| "failed to read session")?;
+        serde_json::from_str(&data).map_err(|_| "failed to parse session")
+    }
+
+    pub fn save(&self, path: &PathBuf) -> Result<(), &'static str> {
+        let data = serde_json::to_string_pretty(self).map_err(|_| "failed to serialize session")?;
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|_| "failed to create session directory")?;
+        }
+        fs::write(path, data).map_err(|_| "failed to write session")
+    }
+
+    /// loads the persisted session, if there is a valid one
+    pub fn load_stored() -> Option<EditorSession> {
+        EditorSession::get_path().and_then(|path| EditorSession::load(&path).ok())
+    }
+}
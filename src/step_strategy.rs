@@ -0,0 +1,223 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::GenerationConfig,
+    map::Map,
+    position::{Position, ShiftDirection},
+    random::Random,
+    walker::CuteWalker,
+};
+
+/// shift selected by a step strategy
+pub struct ShiftChoice {
+    pub direction: ShiftDirection,
+
+    /// whether the last shift direction was re-used due to momentum
+    pub momentum: bool,
+}
+
+impl ShiftChoice {
+    fn new(direction: ShiftDirection) -> ShiftChoice {
+        ShiftChoice {
+            direction,
+            momentum: false,
+        }
+    }
+}
+
+/// samples a shift from the rated shifts, penalizing turns and reversals relative to the last
+/// shift by the configured direction change costs
+fn sample_rated_shift(
+    walker: &CuteWalker,
+    shifts: &[ShiftDirection; 4],
+    config: &GenerationConfig,
+    rnd: &mut Random,
+) -> ShiftDirection {
+    let last_shift = match walker.last_shift {
+        Some(last_shift) if config.turn_cost > 0.0 || config.reverse_cost > 0.0 => last_shift,
+        _ => return rnd.sample_shift(shifts),
+    };
+
+    let scales = shifts.map(|shift| {
+        if shift == last_shift {
+            1.0
+        } else if shift == last_shift.opposite() {
+            1.0 - config.reverse_cost
+        } else {
+            1.0 - config.turn_cost
+        }
+    });
+
+    rnd.sample_shift_scaled(shifts, &scales)
+}
+
+/// decides in which direction the walker moves next. The walker takes care of everything else
+/// like locking, kernels and pulses, so strategies can be swapped without touching its state.
+pub trait StepStrategy {
+    /// select the shift for the current step. Shifts are ordered from best to worst with
+    /// respect to the current goal.
+    fn choose_shift(
+        &self,
+        walker: &CuteWalker,
+        shifts: &[ShiftDirection; 4],
+        map: &Map,
+        config: &GenerationConfig,
+        rnd: &mut Random,
+    ) -> ShiftChoice;
+
+    /// select another shift, if the previously selected target position is locked
+    fn resample_shift(&self, shifts: &[ShiftDirection; 4], rnd: &mut Random) -> ShiftDirection {
+        rnd.sample_shift(shifts)
+    }
+}
+
+/// available step strategies, selectable via GenerationConfig
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default, JsonSchema)]
+pub enum StepStrategyKind {
+    /// sample from the rated shifts using the shift weights
+    RatedGreedy,
+
+    /// like RatedGreedy, but re-use the last direction based on the momentum config
+    #[default]
+    Momentum,
+
+    /// like Momentum, but with strategy_noise probability any valid direction is picked
+    NoiseBiased,
+
+    /// like Momentum, but avoid directions that run into locked positions within
+    /// strategy_lookahead steps
+    Lookahead,
+}
+
+impl StepStrategyKind {
+    pub const ALL: [StepStrategyKind; 4] = [
+        StepStrategyKind::RatedGreedy,
+        StepStrategyKind::Momentum,
+        StepStrategyKind::NoiseBiased,
+        StepStrategyKind::Lookahead,
+    ];
+
+    pub fn get_strategy(&self) -> &'static dyn StepStrategy {
+        match self {
+            StepStrategyKind::RatedGreedy => &RatedGreedy,
+            StepStrategyKind::Momentum => &Momentum,
+            StepStrategyKind::NoiseBiased => &NoiseBiased,
+            StepStrategyKind::Lookahead => &Lookahead,
+        }
+    }
+}
+
+pub struct RatedGreedy;
+
+impl StepStrategy for RatedGreedy {
+    fn choose_shift(
+        &self,
+        walker: &CuteWalker,
+        shifts: &[ShiftDirection; 4],
+        _map: &Map,
+        config: &GenerationConfig,
+        rnd: &mut Random,
+    ) -> ShiftChoice {
+        ShiftChoice::new(sample_rated_shift(walker, shifts, config, rnd))
+    }
+}
+
+pub struct Momentum;
+
+impl StepStrategy for Momentum {
+    fn choose_shift(
+        &self,
+        walker: &CuteWalker,
+        shifts: &[ShiftDirection; 4],
+        _map: &Map,
+        config: &GenerationConfig,
+        rnd: &mut Random,
+    ) -> ShiftChoice {
+        let mut choice = ShiftChoice::new(sample_rated_shift(walker, shifts, config, rnd));
+
+        // re-use last shift direction with certain probability
+        if let Some(last_shift) = walker.last_shift {
+            if rnd.with_probability(config.momentum_prob_at(walker.straight_steps)) {
+                choice.direction = last_shift;
+                choice.momentum = true;
+            }
+        }
+
+        choice
+    }
+}
+
+pub struct NoiseBiased;
+
+impl StepStrategy for NoiseBiased {
+    fn choose_shift(
+        &self,
+        walker: &CuteWalker,
+        shifts: &[ShiftDirection; 4],
+        map: &Map,
+        config: &GenerationConfig,
+        rnd: &mut Random,
+    ) -> ShiftChoice {
+        let choice = Momentum.choose_shift(walker, shifts, map, config, rnd);
+
+        if rnd.with_probability(config.strategy_noise) {
+            let valid_shifts: Vec<ShiftDirection> = shifts
+                .iter()
+                .filter(|shift| walker.pos.clone().shift_in_direction(shift, map).is_ok())
+                .cloned()
+                .collect();
+
+            if !valid_shifts.is_empty() {
+                return ShiftChoice::new(*rnd.pick_element(&valid_shifts));
+            }
+        }
+
+        choice
+    }
+}
+
+pub struct Lookahead;
+
+impl Lookahead {
+    /// checks whether walking straight in a direction runs into locked or no-go positions
+    fn is_blocked(walker: &CuteWalker, shift: &ShiftDirection, map: &Map, steps: usize) -> bool {
+        let mut pos: Position = walker.pos.clone();
+        for _ in 0..steps {
+            if pos.shift_in_direction(shift, map).is_err()
+                || walker.locked_positions[pos.as_index()]
+                || map.no_go[pos.as_index()]
+            {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+impl StepStrategy for Lookahead {
+    fn choose_shift(
+        &self,
+        walker: &CuteWalker,
+        shifts: &[ShiftDirection; 4],
+        map: &Map,
+        config: &GenerationConfig,
+        rnd: &mut Random,
+    ) -> ShiftChoice {
+        let choice = Momentum.choose_shift(walker, shifts, map, config, rnd);
+
+        if !Lookahead::is_blocked(walker, &choice.direction, map, config.strategy_lookahead) {
+            return choice;
+        }
+
+        // use the best rated shift that doesnt run into locked positions
+        match shifts
+            .iter()
+            .find(|shift| !Lookahead::is_blocked(walker, shift, map, config.strategy_lookahead))
+        {
+            Some(shift) => ShiftChoice::new(*shift),
+            None => choice,
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{GenerationConfig, MapConfig},
+    generator::Generator,
+    position::ShiftDirection,
+    random::{Random, Seed},
+};
+
+/// outcome of a single sample of the rng
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TracedSample {
+    /// sampled shift of the walker
+    Shift(ShiftDirection),
+
+    /// inner kernel size of a kernel mutation
+    InnerKernelSize(usize),
+
+    /// outer kernel margin of a kernel mutation
+    OuterKernelMargin(usize),
+
+    /// kernel circularity of a kernel mutation
+    Circularity(f32),
+
+    /// value of a range or index of a weighted pick
+    Index(usize),
+
+    Value(u64),
+
+    /// whether a probability check passed
+    Chance(bool),
+
+    Fraction(f32),
+}
+
+impl TracedSample {
+    pub fn as_shift(&self) -> Option<ShiftDirection> {
+        match self {
+            TracedSample::Shift(shift) => Some(*shift),
+            _ => None,
+        }
+    }
+
+    pub fn as_index(&self) -> Option<usize> {
+        match self {
+            TracedSample::Index(index) => Some(*index),
+            _ => None,
+        }
+    }
+}
+
+/// Outcomes of all samples of a generation in order, e.g. every sampled shift and kernel
+/// mutation. A replay returns the traced outcomes instead of drawing from the rng, so a
+/// generation can be reproduced step by step even after the rng implementation changed.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GenerationTrace {
+    pub samples: Vec<TracedSample>,
+}
+
+impl GenerationTrace {
+    /// generator that traces all of its samples, see GenerationTrace::from_generator
+    pub fn recording_generator(
+        gen_config: &GenerationConfig,
+        map_config: &MapConfig,
+        seed: Seed,
+    ) -> Generator {
+        let mut rnd = Random::new(seed, gen_config);
+        rnd.enable_trace();
+        Generator::with_random(gen_config, map_config, rnd)
+    }
+
+    /// trace of a generator created by GenerationTrace::recording_generator
+    pub fn from_generator(gen: &Generator) -> Option<GenerationTrace> {
+        gen.rnd.get_trace().map(|samples| GenerationTrace {
+            samples: samples.to_vec(),
+        })
+    }
+
+    /// Generator that replays this trace, it has to be stepped with the configs of the traced
+    /// generation. Once a sample doesn't match the trace, the generator continues with the rng,
+    /// see Random::trace_divergence.
+    pub fn replay_generator(
+        &self,
+        gen_config: &GenerationConfig,
+        map_config: &MapConfig,
+        seed: Seed,
+    ) -> Generator {
+        let mut rnd = Random::new(seed, gen_config);
+        rnd.replay_trace(&self.samples);
+        Generator::with_random(gen_config, map_config, rnd)
+    }
+}
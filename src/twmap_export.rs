@@ -1,13 +1,43 @@
+use crate::config::Theme;
 use crate::map::{BlockTypeTW, Map};
 use crate::position::Position;
 use ndarray::{Array2};
 use rust_embed::RustEmbed;
+use std::fmt;
+use std::fs;
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 use twmap::{
     automapper::{self, Automapper},
-    GameLayer, GameTile, Layer, Tile, TileFlags, TilemapLayer, TilesLayer, TwMap,
+    Color, CompressedData, ExternalImage, GameLayer, GameTile, Group, Image, Layer, Tele,
+    TeleLayer, Tile, TileFlags, TilemapLayer, TilesLayer, TwMap, Version,
 };
 
+/// ddnet mapres images that are used for the tile layers and the doodad layer, if no theme is set
+const FREEZE_TILESET: &str = "ddmax_freeze";
+const HOOKABLE_TILESET: &str = "ddnet_walls";
+const DOODAD_IMAGE: &str = "grass_doodads";
+
+/// color of the tile layers
+const FREEZE_COLOR: Color = Color {
+    r: 0,
+    g: 0,
+    b: 29,
+    a: 139,
+};
+const HOOKABLE_COLOR: Color = Color {
+    r: 23,
+    g: 33,
+    b: 47,
+    a: 255,
+};
+
+/// name of the group that contains all tile layers
+const TILES_GROUP: &str = "Tiles";
+
+/// size of ddnet mapres images
+const MAPRES_SIZE: u32 = 1024;
+
 #[derive(RustEmbed)]
 #[folder = "automapper/"]
 pub struct AutoMapperConfigs;
@@ -22,79 +52,283 @@ impl AutoMapperConfigs {
     }
 }
 
+/// size and duration of a map export
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExportStats {
+    pub duration: Duration,
+
+    /// size of the written map file in bytes
+    pub file_size: u64,
+
+    /// tile layers that were dropped, because they didnt contain any tiles
+    pub removed_layers: usize,
+
+    /// images that were dropped, because no layer used them
+    pub removed_images: usize,
+}
+
+impl fmt::Display for ExportStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.1} KiB in {} ms",
+            self.file_size as f64 / 1024.0,
+            self.duration.as_millis()
+        )
+    }
+}
+
 pub struct TwExport;
 
 impl TwExport {
     pub fn get_automapper_config(rule_name: String, layer: &TilesLayer) -> automapper::Config {
         let config_index = layer.automapper_config.config.unwrap();
         let automapper = AutoMapperConfigs::get_config(rule_name);
+        // other tilesets may have less configs than the template tileset
         let automapper_config = automapper
             .configs
             .get(config_index as usize)
+            .or(automapper.configs.first())
             .expect("coulnt fetch automapper config via index");
 
         automapper_config.clone()
     }
 
+    /// ddnet map without any tiles, consisting of the game layer and an empty group for the tile
+    /// layers. The map is built from scratch, so exporting doesn't depend on a template file.
+    /// Unlike the old template it has no background quad group, see CHANGELOG.md.
+    pub fn empty_map(map: &Map) -> TwMap {
+        let mut tw_map = TwMap::empty(Version::DDNet06);
+
+        let mut physics_group = Group::physics();
+        physics_group.layers.push(Layer::Game(GameLayer {
+            tiles: CompressedData::Loaded(Array2::<GameTile>::from_elem(
+                (map.height, map.width),
+                GameTile::new(0, TileFlags::empty()),
+            )),
+        }));
+        tw_map.groups.push(physics_group);
+
+        tw_map.groups.push(Group {
+            name: TILES_GROUP.to_string(),
+            ..Group::default()
+        });
+
+        tw_map
+    }
+
+    /// adds a tele layer with all tele tiles of the map to the physics group
+    fn add_tele_layer(tw_map: &mut TwMap, map: &Map) {
+        let tiles =
+            Array2::from_shape_fn((map.height, map.width), |(y, x)| match map.tele[[x, y]] {
+                Some(tile) => Tele {
+                    number: tile.number,
+                    id: tile.tele_type.to_tw_tele_id(),
+                },
+                None => Tele { number: 0, id: 0 },
+            });
+
+        tw_map
+            .physics_group_mut()
+            .layers
+            .push(Layer::Tele(TeleLayer {
+                tiles: CompressedData::Loaded(tiles),
+            }));
+    }
+
+    /// group that contains all tile layers, see TwExport::empty_map
+    fn tiles_group(tw_map: &mut TwMap) -> &mut Group {
+        tw_map
+            .groups
+            .iter_mut()
+            .find(|group| group.name == TILES_GROUP)
+            .expect("map has no tiles group")
+    }
+
+    /// index of the external ddnet mapres image with the given name, which is added if required
+    fn image_index(tw_map: &mut TwMap, name: &str) -> u16 {
+        let index = match tw_map.images.iter().position(|image| image.name() == name) {
+            Some(index) => index,
+            None => {
+                tw_map.images.push(Image::External(ExternalImage {
+                    name: name.to_string(),
+                    width: MAPRES_SIZE,
+                    height: MAPRES_SIZE,
+                }));
+                tw_map.images.len() - 1
+            }
+        };
+
+        index as u16
+    }
+
+    /// adds an automapped tile layer with all blocks of the given type to the tiles group
     pub fn process_layer(
         tw_map: &mut TwMap,
         map: &Map,
-        layer_index: &usize,
         layer_name: &str,
         layer_type: &BlockTypeTW,
+        tileset: &str,
+        color: Color,
     ) {
-        let tile_group = tw_map.groups.get_mut(2).unwrap();
-        assert_eq!(tile_group.name, "Tiles");
-
-        if let Some(Layer::Tiles(layer)) = tile_group.layers.get_mut(*layer_index) {
-            assert_eq!(layer.name, layer_name);
-
-            let image_name = tw_map.images[layer.image.unwrap() as usize].name();
-            let automapper_config = TwExport::get_automapper_config(image_name.clone(), layer);
-
-            let tiles = layer.tiles_mut().unwrap_mut();
-            *tiles = Array2::<Tile>::default((map.height, map.width));
-
-            for ((x, y), block_type) in map.grid.indexed_iter() {
-                let block_type = block_type.to_tw_block_type();
-                let mut set_block: bool = *layer_type == block_type;
-
-                // custom rule for freeze
-                if layer_type == &BlockTypeTW::Freeze && block_type == BlockTypeTW::Hookable {
-                    let shifts = &[(-1, 0), (0, -1), (1, 0), (0, 1)];
-                    for shift in shifts {
-                        let neighbor_type = Position::new(x, y)
-                            .shifted_by(shift.0, shift.1)
-                            .ok()
-                            .and_then(|pos| map.grid.get(pos.as_index()));
-
-                        if neighbor_type.is_some_and(|t| t.is_freeze()) {
-                            set_block = true;
-                            break;
-                        }
+        let mut layer = TilesLayer::new((map.height, map.width));
+        layer.name = layer_name.to_string();
+        layer.color = color;
+        layer.image = Some(TwExport::image_index(tw_map, tileset));
+        layer.automapper_config.config = Some(0);
+        layer.automapper_config.automatic = true;
+
+        let automapper_config = TwExport::get_automapper_config(tileset.to_string(), &layer);
+
+        let tiles = layer.tiles_mut().unwrap_mut();
+        for ((x, y), block_type) in map.grid.indexed_iter() {
+            let block_type = block_type.to_tw_block_type();
+            let mut set_block: bool = *layer_type == block_type;
+
+            // custom rule for freeze
+            if layer_type == &BlockTypeTW::Freeze && block_type == BlockTypeTW::Hookable {
+                let shifts = &[(-1, 0), (0, -1), (1, 0), (0, 1)];
+                for shift in shifts {
+                    let neighbor_type = Position::new(x, y)
+                        .shifted_by(shift.0, shift.1)
+                        .ok()
+                        .and_then(|pos| map.grid.get(pos.as_index()));
+
+                    if neighbor_type.is_some_and(|t| t.is_freeze()) {
+                        set_block = true;
+                        break;
                     }
                 }
+            }
 
-                if set_block {
-                    tiles[[y, x]] = Tile::new(1, TileFlags::empty())
-                }
+            if set_block {
+                tiles[[y, x]] = Tile::new(1, TileFlags::empty())
             }
+        }
 
-            automapper_config.run(3777777777, tiles) // thanks Tater for the epic **random** seed
-        } else {
-            panic!(
-                "coulnt get layer at index {:} ({:})",
-                layer_index, layer_name
-            );
-        };
+        // the automapper only takes 32 bit seeds
+        automapper_config.run(map.decoration_seed as u32, tiles);
+
+        TwExport::tiles_group(tw_map)
+            .layers
+            .push(Layer::Tiles(layer));
     }
 
-    pub fn export(map: &Map, path: &PathBuf) {
-        let mut tw_map = TwMap::parse_file("automap_test.map").expect("parsing failed");
-        tw_map.load().expect("loading failed");
+    /// adds a decoration layer in front of all other tile layers, if the map has any doodads
+    pub fn add_doodad_layer(tw_map: &mut TwMap, map: &Map, doodad_image: &str) {
+        if map.doodads.iter().all(|tile| *tile == 0) {
+            return;
+        }
+
+        let mut layer = TilesLayer::new((map.height, map.width));
+        layer.name = "Doodads".to_string();
+        layer.image = Some(TwExport::image_index(tw_map, doodad_image));
+
+        let tiles = layer.tiles_mut().unwrap_mut();
+        for ((x, y), tile) in map.doodads.indexed_iter() {
+            if *tile != 0 {
+                tiles[[y, x]] = Tile::new(*tile, TileFlags::empty());
+            }
+        }
+
+        TwExport::tiles_group(tw_map)
+            .layers
+            .push(Layer::Tiles(layer));
+    }
+
+    /// removes tile layers without any tiles from the tile group, as they would still be written
+    /// to the map file with their full dimensions
+    pub fn remove_empty_layers(tw_map: &mut TwMap) -> usize {
+        let tile_group = TwExport::tiles_group(tw_map);
+
+        let layer_count = tile_group.layers.len();
+        tile_group.layers.retain(|layer| match layer {
+            Layer::Tiles(layer) => layer.tiles().unwrap_ref().iter().any(|tile| tile.id != 0),
+            _ => true,
+        });
+
+        layer_count - tile_group.layers.len()
+    }
+
+    /// removes all images that arent used by any layer, e.g. the image of a removed empty layer.
+    /// Returns the amount of removed images.
+    pub fn remove_unused_images(tw_map: &mut TwMap) -> usize {
+        let mut used = vec![false; tw_map.images.len()];
+        for layer in tw_map.groups.iter().flat_map(|group| group.layers.iter()) {
+            let image = match layer {
+                Layer::Tiles(layer) => layer.image,
+                Layer::Quads(layer) => layer.image,
+                _ => None,
+            };
+            if let Some(image) = image {
+                used[image as usize] = true;
+            }
+        }
+
+        // new index of each image after removing the unused ones
+        let mut new_indices = Vec::with_capacity(used.len());
+        let mut next_index: u16 = 0;
+        for is_used in used.iter() {
+            new_indices.push(next_index);
+            if *is_used {
+                next_index += 1;
+            }
+        }
+
+        let removed_images = used.len() - next_index as usize;
+        if removed_images == 0 {
+            return 0;
+        }
 
-        TwExport::process_layer(&mut tw_map, map, &0, "Freeze", &BlockTypeTW::Freeze);
-        TwExport::process_layer(&mut tw_map, map, &1, "Hookable", &BlockTypeTW::Hookable);
+        let mut used = used.into_iter();
+        tw_map.images.retain(|_| used.next().unwrap());
+
+        for layer in tw_map
+            .groups
+            .iter_mut()
+            .flat_map(|group| group.layers.iter_mut())
+        {
+            let image = match layer {
+                Layer::Tiles(layer) => &mut layer.image,
+                Layer::Quads(layer) => &mut layer.image,
+                _ => continue,
+            };
+            *image = image.map(|index| new_indices[index as usize]);
+        }
+
+        removed_images
+    }
+
+    pub fn export(map: &Map, path: &PathBuf, theme: Option<&Theme>) -> ExportStats {
+        let start = Instant::now();
+
+        let mut tw_map = TwExport::empty_map(map);
+
+        if !map.name.is_empty() {
+            tw_map.info.credits = map.name.clone();
+        }
+
+        TwExport::process_layer(
+            &mut tw_map,
+            map,
+            "Freeze",
+            &BlockTypeTW::Freeze,
+            theme.map_or(FREEZE_TILESET, |theme| theme.freeze_tileset.as_str()),
+            FREEZE_COLOR,
+        );
+        TwExport::process_layer(
+            &mut tw_map,
+            map,
+            "Hookable",
+            &BlockTypeTW::Hookable,
+            theme.map_or(HOOKABLE_TILESET, |theme| theme.hookable_tileset.as_str()),
+            HOOKABLE_COLOR,
+        );
+        TwExport::add_doodad_layer(
+            &mut tw_map,
+            map,
+            theme.map_or(DOODAD_IMAGE, |theme| theme.doodad_image.as_str()),
+        );
 
         // get game layer
         let game_layer = tw_map
@@ -103,18 +337,31 @@ impl TwExport {
             .tiles_mut()
             .unwrap_mut();
 
-        *game_layer = Array2::<GameTile>::from_elem(
-            (map.height, map.width),
-            GameTile::new(0, TileFlags::empty()),
-        );
-
         // modify game layer
         for ((x, y), value) in map.grid.indexed_iter() {
-            game_layer[[y, x]] = GameTile::new(value.to_tw_game_id(), TileFlags::empty())
+            let id = map.markers[[x, y]]
+                .and_then(|marker| marker.to_tw_game_id())
+                .unwrap_or(value.to_tw_game_id());
+            game_layer[[y, x]] = GameTile::new(id, TileFlags::empty())
         }
 
+        // ddnet only expects a tele layer on maps that use teleporters
+        if map.tele.iter().any(|tile| tile.is_some()) {
+            TwExport::add_tele_layer(&mut tw_map, map);
+        }
+
+        let removed_layers = TwExport::remove_empty_layers(&mut tw_map);
+        let removed_images = TwExport::remove_unused_images(&mut tw_map);
+
         // save map
         println!("exporting map to {:?}", &path);
         tw_map.save_file(path).expect("failed to write map file");
+
+        ExportStats {
+            duration: start.elapsed(),
+            file_size: fs::metadata(path).map_or(0, |metadata| metadata.len()),
+            removed_layers,
+            removed_images,
+        }
     }
 }
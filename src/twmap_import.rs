@@ -0,0 +1,151 @@
+use crate::config::{MapConfig, MAP_CONFIG_VERSION};
+use crate::map::{BlockType, Map, Marker};
+use crate::position::Position;
+use ndarray::Array2;
+use std::collections::VecDeque;
+use std::path::Path;
+use twmap::{GameLayer, GameTile, TilemapLayer, TwMap};
+
+/// tw game layer ids that are relevant for tracing a path
+const TW_HOOKABLE: u8 = 1;
+const TW_UNHOOKABLE: u8 = 3;
+const TW_FREEZE: u8 = 9;
+const TW_DEEP_FREEZE: u8 = 12;
+const TW_START: u8 = 33;
+const TW_FINISH: u8 = 34;
+const TW_SPAWNS: [u8; 3] = [192, 193, 194];
+
+/// default amount of path steps between two imported waypoints
+pub const DEFAULT_WAYPOINT_DIST: usize = 50;
+
+pub struct TwImport;
+
+impl TwImport {
+    /// loads the game layer of an existing .map file
+    pub fn load_game_layer(path: &Path) -> Result<Array2<GameTile>, String> {
+        let mut tw_map = TwMap::parse_file(path).map_err(|e| e.to_string())?;
+        tw_map.load().map_err(|e| e.to_string())?;
+
+        let game_layer = tw_map
+            .find_physics_layer::<GameLayer>()
+            .ok_or("map has no game layer")?;
+
+        Ok(game_layer.tiles().unwrap_ref().clone())
+    }
+
+    /// finds the shortest walkable path from spawn (or start) to the closest finish tile.
+    /// tiles are indexed as [y, x], the returned positions use the generators [x, y] order.
+    pub fn trace_path(tiles: &Array2<GameTile>) -> Result<Vec<Position>, &'static str> {
+        let (height, width) = tiles.dim();
+        let is_passable = |tile: &GameTile| tile.id != TW_HOOKABLE && tile.id != TW_UNHOOKABLE;
+
+        let find_tile = |ids: &[u8]| {
+            tiles
+                .indexed_iter()
+                .find(|(_, tile)| ids.contains(&tile.id))
+                .map(|((y, x), _)| (x, y))
+        };
+        let source = find_tile(&TW_SPAWNS)
+            .or_else(|| find_tile(&[TW_START]))
+            .ok_or("map has neither spawn nor start tiles")?;
+
+        // breadth first search, storing the predecessor of each visited tile
+        let mut previous: Array2<Option<(usize, usize)>> = Array2::from_elem((width, height), None);
+        let mut queue = VecDeque::from([source]);
+        previous[[source.0, source.1]] = Some(source);
+
+        let mut target = None;
+        while let Some((x, y)) = queue.pop_front() {
+            if tiles[[y, x]].id == TW_FINISH {
+                target = Some((x, y));
+                break;
+            }
+
+            let neighbors = [
+                (x.wrapping_sub(1), y),
+                (x + 1, y),
+                (x, y.wrapping_sub(1)),
+                (x, y + 1),
+            ];
+            for (nx, ny) in neighbors {
+                if nx >= width || ny >= height {
+                    continue;
+                }
+                if previous[[nx, ny]].is_none() && is_passable(&tiles[[ny, nx]]) {
+                    previous[[nx, ny]] = Some((x, y));
+                    queue.push_back((nx, ny));
+                }
+            }
+        }
+
+        let mut current = target.ok_or("no path from spawn to finish found")?;
+        let mut path = vec![Position::new(current.0, current.1)];
+        while current != source {
+            current = previous[[current.0, current.1]].unwrap();
+            path.push(Position::new(current.0, current.1));
+        }
+        path.reverse();
+
+        Ok(path)
+    }
+
+    /// converts a game layer into a map, so the analysis passes can run on existing maps.
+    /// Unhookable blocks become hookable and deep freeze becomes freeze, as the generator has no
+    /// separate block types for them.
+    pub fn game_layer_to_map(tiles: &Array2<GameTile>) -> Map {
+        let (height, width) = tiles.dim();
+        let mut map = Map::new(width, height, BlockType::Empty);
+        for ((y, x), tile) in tiles.indexed_iter() {
+            map.grid[[x, y]] = match tile.id {
+                TW_HOOKABLE | TW_UNHOOKABLE => BlockType::Hookable,
+                TW_FREEZE | TW_DEEP_FREEZE => BlockType::Freeze,
+                _ => BlockType::Empty,
+            };
+            map.markers[[x, y]] = match tile.id {
+                id if TW_SPAWNS.contains(&id) => Some(Marker::Spawn),
+                TW_START => Some(Marker::Start),
+                TW_FINISH => Some(Marker::Finish),
+                _ => None,
+            };
+        }
+
+        map
+    }
+
+    /// reduces a traced path to waypoints that are roughly waypoint_dist steps apart
+    pub fn path_to_waypoints(path: &[Position], waypoint_dist: usize) -> Vec<Position> {
+        let mut waypoints: Vec<Position> =
+            path.iter().step_by(waypoint_dist.max(1)).cloned().collect();
+
+        if let Some(last) = path.last() {
+            if waypoints.last() != Some(last) {
+                waypoints.push(last.clone());
+            }
+        }
+
+        waypoints
+    }
+
+    /// traces the path of an existing .map and converts it into a map config with the same
+    /// size, so its layout can be combined with any generation preset
+    pub fn trace_map_config(
+        path: &Path,
+        name: &str,
+        waypoint_dist: usize,
+    ) -> Result<MapConfig, String> {
+        let tiles = TwImport::load_game_layer(path)?;
+        let (height, width) = tiles.dim();
+        let traced_path = TwImport::trace_path(&tiles)?;
+
+        Ok(MapConfig {
+            name: name.to_string(),
+            version: MAP_CONFIG_VERSION.to_string(),
+            waypoints: TwImport::path_to_waypoints(&traced_path, waypoint_dist),
+            width,
+            height,
+            no_go_zones: Vec::new(),
+            start_room_size: None,
+            finish_room_size: None,
+        })
+    }
+}
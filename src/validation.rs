@@ -0,0 +1,233 @@
+use std::collections::VecDeque;
+
+use ndarray::Array2;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{
+    map::{Map, Marker},
+    position::Position,
+};
+
+/// DDNet constraint that a map violates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ViolationKind {
+    MissingSpawn,
+
+    /// spawn is directly below a solid block or touches freeze
+    BlockedSpawn,
+
+    MissingStartLine,
+
+    /// start line is split into multiple lines
+    MultipleStartLines,
+
+    /// start line doesnt border the area around the spawn, so leaving the spawn doesnt start
+    /// the race
+    StartLineNotAtSpawn,
+
+    MissingFinishLine,
+
+    /// finish can be reached from spawn without crossing the start line
+    FinishBypassesStart,
+
+    /// finish can't be reached from spawn at all
+    UnreachableFinish,
+}
+
+impl ViolationKind {
+    pub fn description(&self) -> &'static str {
+        match self {
+            ViolationKind::MissingSpawn => "map has no spawn",
+            ViolationKind::BlockedSpawn => "spawn is below a solid block or touches freeze",
+            ViolationKind::MissingStartLine => "map has no start line",
+            ViolationKind::MultipleStartLines => "map has more than one start line",
+            ViolationKind::StartLineNotAtSpawn => "start line doesnt border the spawn area",
+            ViolationKind::MissingFinishLine => "map has no finish line",
+            ViolationKind::FinishBypassesStart => "finish is reachable without crossing start",
+            ViolationKind::UnreachableFinish => "finish is not reachable from spawn",
+        }
+    }
+}
+
+/// a single violated constraint
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct Violation {
+    pub kind: ViolationKind,
+
+    /// position of the offending block, if there is one
+    pub pos: Option<Position>,
+}
+
+impl Violation {
+    fn new(kind: ViolationKind, pos: Option<Position>) -> Violation {
+        Violation { kind, pos }
+    }
+}
+
+/// 4-connected neighbors of a position that are within the map
+fn neighbors(map: &Map, pos: &Position) -> Vec<Position> {
+    [(-1, 0), (1, 0), (0, -1), (0, 1)]
+        .iter()
+        .filter_map(|(dx, dy)| pos.shifted_by(*dx, *dy).ok())
+        .filter(|neighbor| map.pos_in_bounds(neighbor))
+        .collect()
+}
+
+/// first position of every 8-connected area of the given marker
+fn find_components(map: &Map, marker: Marker) -> Vec<Position> {
+    let mut visited = Array2::from_elem((map.width, map.height), false);
+    let mut components = Vec::new();
+
+    for ((x, y), block_marker) in map.markers.indexed_iter() {
+        if *block_marker != Some(marker) || visited[[x, y]] {
+            continue;
+        }
+
+        components.push(Position::new(x, y));
+        visited[[x, y]] = true;
+        let mut queue = VecDeque::from([Position::new(x, y)]);
+        while let Some(pos) = queue.pop_front() {
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    let neighbor = match pos.shifted_by(dx, dy) {
+                        Ok(neighbor) if map.pos_in_bounds(&neighbor) => neighbor,
+                        _ => continue,
+                    };
+                    if map.markers[neighbor.as_index()] == Some(marker)
+                        && !visited[neighbor.as_index()]
+                    {
+                        visited[neighbor.as_index()] = true;
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+        }
+    }
+
+    components
+}
+
+/// all blocks that can be reached from the spawns, optionally without crossing the start line.
+/// Freeze is passable, as frozen tees still move.
+fn spawn_area(map: &Map, spawns: &[Position], cross_start: bool) -> Array2<bool> {
+    let mut area = Array2::from_elem((map.width, map.height), false);
+    let mut queue = VecDeque::new();
+    for spawn in spawns {
+        area[spawn.as_index()] = true;
+        queue.push_back(spawn.clone());
+    }
+
+    while let Some(pos) = queue.pop_front() {
+        for neighbor in neighbors(map, &pos) {
+            let block = &map.grid[neighbor.as_index()];
+            let passable = !block.is_solid()
+                && (cross_start || map.markers[neighbor.as_index()] != Some(Marker::Start));
+            if !area[neighbor.as_index()] && passable {
+                area[neighbor.as_index()] = true;
+                queue.push_back(neighbor);
+            }
+        }
+    }
+
+    area
+}
+
+/// checks DDNet specific constraints of a map before exporting it. Returns all violations, an
+/// empty vec means the map is valid.
+pub fn validate_map(map: &Map) -> Vec<Violation> {
+    let mut violations = Vec::new();
+
+    let spawns: Vec<Position> = map
+        .markers
+        .indexed_iter()
+        .filter(|(_, marker)| **marker == Some(Marker::Spawn))
+        .map(|((x, y), _)| Position::new(x, y))
+        .collect();
+    if spawns.is_empty() {
+        violations.push(Violation::new(ViolationKind::MissingSpawn, None));
+    }
+
+    for spawn in spawns.iter() {
+        let under_solid = spawn
+            .shifted_by(0, -1)
+            .is_ok_and(|above| map.grid[above.as_index()].is_solid());
+        let touches_freeze = neighbors(map, spawn)
+            .iter()
+            .any(|neighbor| map.grid[neighbor.as_index()].is_freeze());
+        if under_solid || touches_freeze {
+            violations.push(Violation::new(
+                ViolationKind::BlockedSpawn,
+                Some(spawn.clone()),
+            ));
+        }
+    }
+
+    let start_lines = find_components(map, Marker::Start);
+    match start_lines.len() {
+        0 => violations.push(Violation::new(ViolationKind::MissingStartLine, None)),
+        1 => {}
+        _ => violations.extend(
+            start_lines[1..]
+                .iter()
+                .map(|pos| Violation::new(ViolationKind::MultipleStartLines, Some(pos.clone()))),
+        ),
+    }
+
+    let finish_exists = map
+        .markers
+        .iter()
+        .any(|marker| *marker == Some(Marker::Finish));
+    if !finish_exists {
+        violations.push(Violation::new(ViolationKind::MissingFinishLine, None));
+    }
+
+    if spawns.is_empty() {
+        return violations;
+    }
+
+    if finish_exists {
+        let reachable = spawn_area(map, &spawns, true);
+        let finish_reachable = map
+            .markers
+            .indexed_iter()
+            .any(|(pos, marker)| *marker == Some(Marker::Finish) && reachable[pos]);
+        if !finish_reachable {
+            violations.push(Violation::new(ViolationKind::UnreachableFinish, None));
+        }
+    }
+
+    let area = spawn_area(map, &spawns, false);
+    let borders_start = |pos: &Position| {
+        neighbors(map, pos)
+            .iter()
+            .any(|neighbor| map.markers[neighbor.as_index()] == Some(Marker::Start))
+    };
+    let area_positions = area
+        .indexed_iter()
+        .filter(|(_, in_area)| **in_area)
+        .map(|((x, y), _)| Position::new(x, y));
+
+    let mut start_at_spawn = false;
+    let mut bypassed_finish = None;
+    for pos in area_positions {
+        if bypassed_finish.is_none() && map.markers[pos.as_index()] == Some(Marker::Finish) {
+            bypassed_finish = Some(pos.clone());
+        }
+        start_at_spawn |= borders_start(&pos);
+    }
+
+    if let Some(pos) = bypassed_finish {
+        violations.push(Violation::new(
+            ViolationKind::FinishBypassesStart,
+            Some(pos),
+        ));
+    }
+
+    if !start_lines.is_empty() && !start_at_spawn {
+        violations.push(Violation::new(ViolationKind::StartLineNotAtSpawn, None));
+    }
+
+    violations
+}
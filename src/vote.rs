@@ -0,0 +1,65 @@
+use crate::config::{GenerationConfig, MapConfig, MapSize};
+use std::collections::HashMap;
+
+/// name of the map file (without extension) of a named map slot
+pub fn slot_map_name(slot: usize) -> String {
+    format!("random_map{}", slot)
+}
+
+/// map requested by a player vote, e.g. `generate hardV2 large`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapVote {
+    /// name of the generation preset
+    pub gen_config: String,
+
+    pub size: MapSize,
+
+    /// named map slot the map is generated into, see slot_map_name
+    pub slot: Option<usize>,
+}
+
+impl MapVote {
+    /// parses a vote command of the form `generate <preset> [small|medium|large] [slot<n>]`.
+    /// Preset names are matched case insensitive, the size defaults to medium.
+    pub fn parse(
+        command: &str,
+        gen_configs: &HashMap<String, GenerationConfig>,
+    ) -> Result<MapVote, &'static str> {
+        let mut args = command.split_whitespace();
+
+        if args.next() != Some("generate") {
+            return Err("vote must start with 'generate'");
+        }
+
+        let preset = args.next().ok_or("missing preset")?;
+        let gen_config = gen_configs
+            .keys()
+            .find(|name| name.eq_ignore_ascii_case(preset))
+            .ok_or("unknown preset")?
+            .clone();
+
+        let mut size = None;
+        let mut slot = None;
+        for arg in args {
+            match arg.strip_prefix("slot") {
+                Some(_) if slot.is_some() => return Err("too many arguments"),
+                Some(number) => {
+                    slot = Some(number.parse().map_err(|_| "invalid slot")?);
+                }
+                None if size.is_some() => return Err("too many arguments"),
+                None => size = Some(MapSize::from_name(arg).ok_or("unknown map size")?),
+            }
+        }
+
+        Ok(MapVote {
+            gen_config,
+            size: size.unwrap_or_default(),
+            slot,
+        })
+    }
+
+    /// map config of the voted size for the given layout
+    pub fn map_config(&self, layout: &MapConfig) -> MapConfig {
+        self.size.apply(layout)
+    }
+}
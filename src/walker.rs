@@ -1,16 +1,92 @@
 use std::fmt;
 
+use log::warn;
 use ndarray::{s, Array2};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     config::GenerationConfig,
     kernel::Kernel,
-    map::{BlockType, Map, Overwrite},
+    map::{BlockType, Map, Marker, Overwrite},
     position::{Position, ShiftDirection},
+    position_history::PositionHistory,
     random::Random,
+    step_strategy::StepStrategy,
 };
 
+/// amount of steps a forced platform is placed behind the walker
+const FORCED_PLATFORM_LOOKBACK: usize = 50;
+
+/// decisions of the walker that are recorded in its event log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WalkerEventKind {
+    /// walker moved to pos using the chosen shift
+    Shift {
+        direction: ShiftDirection,
+        pos: Position,
+    },
+
+    /// last shift direction was re-used due to momentum
+    Momentum { direction: ShiftDirection },
+
+    /// max straight run length was reached, walker was forced to turn
+    ForcedTurn { direction: ShiftDirection },
+
+    /// sampled shift was rejected, as its target position is locked
+    LockRejection { direction: ShiftDirection },
+
+    /// walker was stuck and re-entered a locked position with the given amount of visits
+    Revisit {
+        direction: ShiftDirection,
+        visits: u16,
+    },
+
+    /// walker got stuck and rewound the given amount of steps to pos
+    Backtrack { steps: usize, pos: Position },
+
+    /// walker kernels have been mutated
+    KernelMutation {
+        inner_size: usize,
+        inner_circularity: f32,
+        outer_size: usize,
+        outer_circularity: f32,
+    },
+
+    /// pulse pattern with given index was applied instead of the walker kernels
+    Pulse { pattern: usize },
+
+    /// kernels left an empty block at pos that borders a hookable block without freeze padding
+    MarginViolation {
+        pos: Position,
+        inner_size: usize,
+        inner_circularity: f32,
+        outer_size: usize,
+        outer_circularity: f32,
+        pulse: Option<usize>,
+    },
+
+    /// walker placed a platform at pos
+    Platform { pos: Position },
+
+    /// walker reached the waypoint with given index
+    WaypointReached { index: usize, pos: Position },
+
+    /// route was cut short, the waypoint with given index is the last one
+    Truncated { index: usize, pos: Position },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WalkerEvent {
+    /// walker step at which the event occurred
+    pub step: usize,
+
+    #[serde(flatten)]
+    pub kind: WalkerEventKind,
+}
+
 // this walker is indeed very cute
+#[derive(Clone)]
 pub struct CuteWalker {
     pub pos: Position,
     pub steps: usize,
@@ -23,23 +99,47 @@ pub struct CuteWalker {
     /// indicates whether walker has reached the last waypoint
     pub finished: bool,
 
+    /// current difficulty (0 to 1) of the difficulty schedule, applied to sampled kernels
+    pub difficulty: f32,
+
     /// keeps track of how many steps ago the last platorm has been placed
     pub steps_since_platform: usize,
 
     /// keeps track of the last shift direction
     pub last_shift: Option<ShiftDirection>,
 
-    /// counts how many steps the pulse constraints have been fulfilled
-    pub pulse_counter: usize,
+    /// counts how many consecutive steps were performed in the last shift direction
+    pub straight_steps: usize,
+
+    /// counts how many steps the pulse constraints have been fulfilled, for each pulse pattern
+    pub pulse_counters: Vec<usize>,
+
+    /// keeps track of the step of the last pulse, for each pulse pattern
+    pub last_pulse_steps: Vec<Option<usize>>,
 
     /// keeps track on which positions can no longer be visited
     pub locked_positions: Array2<bool>,
 
     /// keeps track of all positions the walker has visited so far
-    pub position_history: Vec<Position>,
+    pub position_history: PositionHistory,
+
+    /// how often the walker has visited each block, used for the revisit cost of shifts
+    pub visit_counts: Array2<u16>,
 
     /// keeps track of current position locking step,
     pub locked_position_step: usize,
+
+    /// number of blocks that have been carved empty so far
+    pub carved_cells: usize,
+
+    /// number of times the walker has rewound its path
+    pub backtracks: usize,
+
+    /// shift that led into the dead end of the last backtrack, avoided in the next step
+    pub backtrack_avoid: Option<ShiftDirection>,
+
+    /// structured log of all walker decisions, only recorded if enabled
+    pub event_log: Option<Vec<WalkerEvent>>,
 }
 
 const NUM_SHIFT_SAMPLE_RETRIES: usize = 25;
@@ -57,10 +157,13 @@ impl fmt::Debug for CuteWalker {
             .field("finished", &self.finished)
             .field("steps_since_platform", &self.steps_since_platform)
             .field("last_shift", &self.last_shift)
-            .field("pulse_counter", &self.pulse_counter)
+            .field("straight_steps", &self.straight_steps)
+            .field("pulse_counters", &self.pulse_counters)
             // .field("locked_positions", &self.locked_positions)
             // .field("position_history", &self.position_history)
             .field("locked_position_step", &self.locked_position_step)
+            .field("carved_cells", &self.carved_cells)
+            .field("backtracks", &self.backtracks)
             .finish()
     }
 }
@@ -82,12 +185,36 @@ impl CuteWalker {
             goal_index: 0,
             waypoints,
             finished: false,
+            difficulty: 0.0,
             steps_since_platform: 0,
             last_shift: None,
-            pulse_counter: 0,
+            straight_steps: 0,
+            pulse_counters: Vec::new(),
+            last_pulse_steps: Vec::new(),
             locked_positions: Array2::from_elem((map.width, map.height), false),
             locked_position_step: 0,
-            position_history: Vec::new(),
+            carved_cells: 0,
+            backtracks: 0,
+            backtrack_avoid: None,
+            position_history: PositionHistory::new(),
+            visit_counts: Array2::zeros((map.width, map.height)),
+            event_log: None,
+        }
+    }
+
+    /// start recording walker decisions into the event log
+    pub fn enable_event_log(&mut self) {
+        if self.event_log.is_none() {
+            self.event_log = Some(Vec::new());
+        }
+    }
+
+    fn log_event(&mut self, kind: WalkerEventKind) {
+        if let Some(event_log) = self.event_log.as_mut() {
+            event_log.push(WalkerEvent {
+                step: self.steps,
+                kind,
+            });
         }
     }
 
@@ -97,7 +224,48 @@ impl CuteWalker {
             .map(|goal| goal.distance_squared(&self.pos) <= *waypoint_reached_dist)
     }
 
+    /// checks whether the walker has used up its step or path length budget
+    pub fn is_budget_exceeded(&self, gen_config: &GenerationConfig) -> bool {
+        (gen_config.max_walker_steps > 0 && self.steps >= gen_config.max_walker_steps)
+            || (gen_config.max_carved_cells > 0 && self.carved_cells >= gen_config.max_carved_cells)
+    }
+
+    /// stop walking at the current position, even if there are waypoints left
+    pub fn finish(&mut self) {
+        self.finished = true;
+        self.goal = None;
+    }
+
+    /// cuts the route short at the remaining waypoint that is closest to the walker, which
+    /// becomes the new goal and the last waypoint
+    pub fn truncate_route(&mut self) {
+        if self.finished {
+            return;
+        }
+
+        let Some(index) = (self.goal_index..self.waypoints.len())
+            .min_by_key(|index| self.waypoints[*index].distance_squared(&self.pos))
+        else {
+            return;
+        };
+
+        self.waypoints.truncate(index + 1);
+        self.goal_index = index;
+        self.goal = Some(self.waypoints[index].clone());
+        self.log_event(WalkerEventKind::Truncated {
+            index,
+            pos: self.waypoints[index].clone(),
+        });
+    }
+
     pub fn next_waypoint(&mut self) {
+        if let Some(goal) = self.goal.clone() {
+            self.log_event(WalkerEventKind::WaypointReached {
+                index: self.goal_index,
+                pos: goal,
+            });
+        }
+
         if let Some(next_goal) = self.waypoints.get(self.goal_index + 1) {
             self.goal_index += 1;
             self.goal = Some(next_goal.clone());
@@ -107,6 +275,26 @@ impl CuteWalker {
         }
     }
 
+    /// locks all solid blocks within the area (inclusive), so the walker routes around stamped
+    /// structures instead of walking through them. Parts outside of the map are ignored.
+    pub fn lock_solid_blocks(&mut self, map: &Map, top_left: &Position, bot_right: &Position) {
+        if top_left.x >= map.width || top_left.y >= map.height {
+            return;
+        }
+
+        let x_end = usize::min(bot_right.x, map.width - 1);
+        let y_end = usize::min(bot_right.y, map.height - 1);
+        let area = s![top_left.x..=x_end, top_left.y..=y_end];
+        for (lock_status, block_type) in self
+            .locked_positions
+            .slice_mut(area)
+            .iter_mut()
+            .zip(map.grid.slice(area).iter())
+        {
+            *lock_status |= block_type.is_solid();
+        }
+    }
+
     pub fn check_platform_at_walker(
         &mut self,
         map: &mut Map,
@@ -127,7 +315,13 @@ impl CuteWalker {
             // return Ok(());
 
             // try to place floor platform
-            let mut pos = self.position_history[self.steps.saturating_sub(50)].clone();
+            let mut pos = match self
+                .position_history
+                .get(self.steps.saturating_sub(FORCED_PLATFORM_LOOKBACK))
+            {
+                Some(pos) => pos.clone(),
+                None => return Ok(()),
+            };
             let mut reached_floor = false;
             while !reached_floor {
                 if pos.shift_in_direction(&ShiftDirection::Down, map).is_err() {
@@ -152,14 +346,19 @@ impl CuteWalker {
                     &BlockType::Empty,
                 )?
             {
+                let top_left = pos.shifted_by(-platform_width, -2)?;
+                let bot_right = pos.shifted_by(platform_width, -1)?;
                 map.set_area(
-                    &pos.shifted_by(-platform_width, -2)?,
-                    &pos.shifted_by(platform_width, -1)?,
+                    &top_left,
+                    &bot_right,
                     &BlockType::Platform,
                     &Overwrite::ReplaceNonSolid,
                 );
+                map.protect_area(&top_left, &bot_right);
+                self.lock_solid_blocks(map, &top_left, &bot_right);
 
                 self.steps_since_platform = 0;
+                self.log_event(WalkerEventKind::Platform { pos });
             }
 
             return Ok(());
@@ -172,42 +371,141 @@ impl CuteWalker {
             &BlockType::Empty,
         )?;
         if area_empty {
+            let top_left = self.pos.shifted_by(-1, 0)?;
+            let bot_right = self.pos.shifted_by(1, 0)?;
             map.set_area(
-                &self.pos.shifted_by(-1, 0)?,
-                &self.pos.shifted_by(1, 0)?,
+                &top_left,
+                &bot_right,
                 &BlockType::Platform,
                 &Overwrite::ReplaceEmptyOnly,
             );
+            map.protect_area(&top_left, &bot_right);
+            self.lock_solid_blocks(map, &top_left, &bot_right);
             self.steps_since_platform = 0;
+            self.log_event(WalkerEventKind::Platform {
+                pos: self.pos.clone(),
+            });
         }
 
         Ok(())
     }
 
+    /// cost of shifting to pos, lower is better. Combines the distance to the goal with the
+    /// revisit, border and parallel costs of the config.
+    pub fn shift_cost(
+        &self,
+        pos: &Position,
+        goal: &Position,
+        map: &Map,
+        config: &GenerationConfig,
+    ) -> f32 {
+        let edge_distance = usize::min(
+            usize::min(pos.x, pos.y),
+            usize::min(map.width - 1 - pos.x, map.height - 1 - pos.y),
+        );
+        let border_blocks = config.border_cost_range.saturating_sub(edge_distance);
+
+        config.goal_distance_cost * pos.distance(goal)
+            + config.revisit_cost * self.visit_counts[pos.as_index()] as f32
+            + config.border_cost * border_blocks as f32
+            + match config.parallel_cost > 0.0 {
+                true => config.parallel_cost * self.parallel_sides(pos, map) as f32,
+                false => 0.0,
+            }
+    }
+
+    /// amount of sides (0 to 2) of pos that have a visited block perpendicular to the shift
+    /// from the walker position and closer than twice the outer kernel size. These belong to an
+    /// earlier segment that would run parallel to the new one with a wall thinner than a
+    /// corridor in between, as the walkers own trail is always behind the shift.
+    pub fn parallel_sides(&self, pos: &Position, map: &Map) -> usize {
+        let horizontal_shift = pos.y == self.pos.y;
+        let side_visited = |sign: i32| {
+            (1..2 * self.outer_kernel.size as i32).any(|offset| {
+                let side_pos = match horizontal_shift {
+                    true => pos.shifted_by(0, sign * offset),
+                    false => pos.shifted_by(sign * offset, 0),
+                };
+                side_pos.is_ok_and(|side_pos| {
+                    map.pos_in_bounds(&side_pos) && self.visit_counts[side_pos.as_index()] > 0
+                })
+            })
+        };
+
+        [-1, 1]
+            .into_iter()
+            .filter(|sign| side_visited(*sign))
+            .count()
+    }
+
+    /// best rated shift whose target has the fewest visits, ignoring locked positions. Only
+    /// targets with less than max_revisits visits are considered, so this is disabled for 0.
+    fn least_visited_shift(
+        &self,
+        shifts: &[ShiftDirection; 4],
+        map: &Map,
+        config: &GenerationConfig,
+    ) -> Option<ShiftDirection> {
+        let mut best: Option<(ShiftDirection, u16)> = None;
+        for shift in shifts {
+            let mut target_pos = self.pos.clone();
+            if target_pos.shift_in_direction(shift, map).is_err()
+                || map.no_go[target_pos.as_index()]
+                || self.backtrack_avoid == Some(*shift)
+            {
+                continue;
+            }
+
+            let visits = self.visit_counts[target_pos.as_index()];
+            if (visits as usize) >= config.max_revisits {
+                continue;
+            }
+
+            // shifts are ordered from best to worst, so only replace on strictly fewer visits
+            match best {
+                Some((_, best_visits)) if best_visits <= visits => {}
+                _ => best = Some((*shift, visits)),
+            }
+        }
+
+        best.map(|(shift, _)| shift)
+    }
+
     pub fn probabilistic_step(
         &mut self,
         map: &mut Map,
         gen_config: &GenerationConfig,
         rnd: &mut Random,
+        strategy: &dyn StepStrategy,
     ) -> Result<(), &'static str> {
         if self.finished {
             return Err("Walker is finished");
         }
 
-        // save position to history before its updated
-        self.position_history.push(self.pos.clone());
+        // save position to history before its updated, the walker never looks back further
+        // than the lock delay or a forced platform
+        self.position_history.push(
+            self.pos.clone(),
+            usize::max(
+                gen_config.pos_lock_max_delay + 2,
+                FORCED_PLATFORM_LOOKBACK + 1,
+            ),
+        )?;
+        let visits = &mut self.visit_counts[self.pos.as_index()];
+        *visits = visits.saturating_add(1);
 
         // sample next shift
         let goal = self.goal.as_ref().ok_or("Error: Goal is None")?;
-        let shifts = self.pos.get_rated_shifts(goal, map);
-
-        let mut current_shift = rnd.sample_shift(&shifts);
-
-        // Momentum: re-use last shift direction with certain probability
-        if let Some(last_shift) = self.last_shift {
-            if rnd.with_probability(gen_config.momentum_prob) {
-                current_shift = last_shift;
-            }
+        let shifts = self
+            .pos
+            .get_rated_shifts(map, |pos| self.shift_cost(pos, goal, map, gen_config));
+
+        let choice = strategy.choose_shift(self, &shifts, map, gen_config, rnd);
+        let mut current_shift = choice.direction;
+        if choice.momentum {
+            self.log_event(WalkerEventKind::Momentum {
+                direction: current_shift,
+            });
         }
 
         let mut current_target_pos = self.pos.clone();
@@ -216,18 +514,66 @@ impl CuteWalker {
         // if target pos is locked, re-sample until a valid one is found
         let mut invalid = false;
         for _ in 0..NUM_SHIFT_SAMPLE_RETRIES {
-            invalid = self.locked_positions[current_target_pos.as_index()];
+            invalid = self.locked_positions[current_target_pos.as_index()]
+                || map.no_go[current_target_pos.as_index()]
+                || self.backtrack_avoid == Some(current_shift);
 
             if invalid {
-                current_shift = rnd.sample_shift(&shifts);
+                self.log_event(WalkerEventKind::LockRejection {
+                    direction: current_shift,
+                });
+                current_shift = strategy.resample_shift(&shifts, rnd);
                 current_target_pos = self.pos.clone();
                 current_target_pos.shift_in_direction(&current_shift, map)?;
             }
         }
 
+        // soft lock, re-enter the least visited locked position instead of getting stuck
+        if invalid {
+            if let Some(shift) = self.least_visited_shift(&shifts, map, gen_config) {
+                let mut target_pos = self.pos.clone();
+                target_pos.shift_in_direction(&shift, map)?;
+                self.log_event(WalkerEventKind::Revisit {
+                    direction: shift,
+                    visits: self.visit_counts[target_pos.as_index()],
+                });
+                current_shift = shift;
+                invalid = false;
+            }
+        }
+
         if invalid {
+            if self.backtracks < gen_config.max_backtracks {
+                return self.backtrack(gen_config.backtrack_steps);
+            }
+
             return Err("number of shift sample retries exceeded, walker stuck?");
         }
+        self.backtrack_avoid = None;
+
+        // force a turn if the max straight run length has been reached
+        if gen_config.max_straight_steps > 0
+            && self.straight_steps >= gen_config.max_straight_steps
+            && self.last_shift == Some(current_shift)
+        {
+            // use the best rated shift that turns (not reverses) and isnt locked, otherwise keep
+            // going straight
+            let turn = shifts.iter().find(|shift| {
+                let mut target_pos = self.pos.clone();
+                **shift != current_shift
+                    && **shift != current_shift.opposite()
+                    && target_pos.shift_in_direction(shift, map).is_ok()
+                    && !self.locked_positions[target_pos.as_index()]
+                    && !map.no_go[target_pos.as_index()]
+            });
+
+            if let Some(turn) = turn {
+                current_shift = *turn;
+                self.log_event(WalkerEventKind::ForcedTurn {
+                    direction: current_shift,
+                });
+            }
+        }
 
         // determine if direction changed from last shift
         let same_dir = match self.last_shift {
@@ -242,42 +588,220 @@ impl CuteWalker {
         // lock old position
         self.lock_previous_location(map, gen_config, false)?;
 
-        // perform pulse if config constraints allows it
-        let perform_pulse = gen_config.enable_pulse
-            && ((same_dir && self.pulse_counter > gen_config.pulse_straight_delay)
-                || (!same_dir && self.pulse_counter > gen_config.pulse_corner_delay));
+        // perform the first pulse whose config constraints allow it
+        let pulse_patterns = gen_config.active_pulse_patterns();
+        self.pulse_counters.resize(pulse_patterns.len(), 0);
+        self.last_pulse_steps.resize(pulse_patterns.len(), None);
+
+        let pulse_index = (0..pulse_patterns.len()).find(|&index| {
+            let pattern = &pulse_patterns[index];
+            let counter = self.pulse_counters[index];
+            let cooled_down = match self.last_pulse_steps[index] {
+                Some(step) => self.steps - step >= pattern.cooldown,
+                None => true,
+            };
+
+            cooled_down
+                && ((same_dir && counter > pattern.straight_delay)
+                    || (!same_dir && counter > pattern.corner_delay))
+        });
 
         // apply kernels
-        if perform_pulse {
-            self.pulse_counter = 0; // reset pulse counter
-            map.apply_kernel(
-                &self.pos,
-                &Kernel::new(&self.inner_kernel.size + 4, 0.0),
-                BlockType::Freeze,
-            )?;
-            map.apply_kernel(
-                &self.pos,
-                &Kernel::new(&self.inner_kernel.size + 2, 0.0),
+        let freeze_style = gen_config.freeze_style;
+
+        // size of the area that is affected by this kernel application
+        let applied_size = match pulse_index {
+            Some(pulse_index) => pulse_patterns[pulse_index]
+                .rings
+                .iter()
+                .map(|ring| self.inner_kernel.size + ring.size_delta)
+                .fold(self.outer_kernel.size, usize::max),
+            None => self.outer_kernel.size + freeze_style.outer_kernel_growth(),
+        };
+        let known_violations = match gen_config.check_kernel_margin {
+            true => self.find_margin_violations(map, applied_size),
+            false => Vec::new(),
+        };
+
+        if let Some(pulse_index) = pulse_index {
+            self.pulse_counters[pulse_index] = 0; // reset pulse counter
+            self.last_pulse_steps[pulse_index] = Some(self.steps);
+            self.log_event(WalkerEventKind::Pulse {
+                pattern: pulse_index,
+            });
+
+            for ring in pulse_patterns[pulse_index].rings.iter() {
+                let replaced_blocks = map.apply_kernel(
+                    &self.pos,
+                    &Kernel::new(self.inner_kernel.size + ring.size_delta, ring.circularity),
+                    ring.block_type.clone(),
+                    None,
+                )?;
+                if ring.block_type.is_empty() {
+                    self.carved_cells += replaced_blocks;
+                }
+            }
+        } else {
+            let grown_kernel;
+            let outer_kernel = match freeze_style.outer_kernel_growth() {
+                0 => &self.outer_kernel,
+                growth => {
+                    grown_kernel = Kernel::new(
+                        self.outer_kernel.size + growth,
+                        self.outer_kernel.circularity,
+                    );
+                    &grown_kernel
+                }
+            };
+            map.apply_kernel_masked(&self.pos, outer_kernel, BlockType::Freeze, None, |pos| {
+                !freeze_style.is_perforated(pos)
+            })?;
+
+            let (reserved_start, reserved_end) = gen_config.get_reserved_steps();
+            let reserved =
+                gen_config.fade_reserved && (reserved_start..reserved_end).contains(&self.steps);
+            let profile = gen_config.get_corridor_profile(self.goal_index.saturating_sub(1));
+            let inner_pos = match profile.inner_kernel_offset(
+                self.inner_kernel.size,
+                self.outer_kernel.size,
+                gen_config.corridor_profile_strength,
+            ) {
+                0 => self.pos.clone(),
+                offset => self.pos.shifted_by(0, offset).unwrap_or(self.pos.clone()),
+            };
+            self.carved_cells += map.apply_kernel(
+                &inner_pos,
+                &self.inner_kernel,
                 BlockType::Empty,
+                reserved.then_some(Marker::Reserved),
             )?;
-        } else {
-            map.apply_kernel(&self.pos, &self.outer_kernel, BlockType::Freeze)?;
+        };
+
+        if gen_config.check_kernel_margin {
+            // only report violations that were introduced by this kernel application
+            let new_violation = self
+                .find_margin_violations(map, applied_size)
+                .into_iter()
+                .find(|pos| !known_violations.contains(pos));
+            if let Some(pos) = new_violation {
+                warn!(
+                    "freeze padding violated at {:?} in step {} (walker at {:?}, inner kernel {} / {}, outer kernel {} / {}, pulse {:?})",
+                    pos,
+                    self.steps,
+                    self.pos,
+                    self.inner_kernel.size,
+                    self.inner_kernel.circularity,
+                    self.outer_kernel.size,
+                    self.outer_kernel.circularity,
+                    pulse_index
+                );
+                self.log_event(WalkerEventKind::MarginViolation {
+                    pos,
+                    inner_size: self.inner_kernel.size,
+                    inner_circularity: self.inner_kernel.circularity,
+                    outer_size: self.outer_kernel.size,
+                    outer_circularity: self.outer_kernel.circularity,
+                    pulse: pulse_index,
+                });
+            }
+        }
 
-            let empty = if self.steps < gen_config.fade_steps {
-                BlockType::EmptyReserved
+        for (pattern, counter) in pulse_patterns.iter().zip(self.pulse_counters.iter_mut()) {
+            if same_dir && self.inner_kernel.size <= pattern.max_kernel_size {
+                *counter += 1;
             } else {
-                BlockType::Empty
+                *counter = 0;
             };
-            map.apply_kernel(&self.pos, &self.inner_kernel, empty)?;
-        };
+        }
+
+        self.log_event(WalkerEventKind::Shift {
+            direction: current_shift,
+            pos: self.pos.clone(),
+        });
+
+        self.straight_steps = if same_dir { self.straight_steps + 1 } else { 1 };
+        self.last_shift = Some(current_shift.clone());
+
+        Ok(())
+    }
+
+    /// returns all empty blocks around the walker that border a hookable block, i.e. where the
+    /// freeze padding is missing. Only the area that kernels of the given size can touch is
+    /// checked.
+    fn find_margin_violations(&self, map: &Map, kernel_size: usize) -> Vec<Position> {
+        let mut violations = Vec::new();
+        let radius = kernel_size / 2 + 1;
+        let min_x = self.pos.x.saturating_sub(radius);
+        let min_y = self.pos.y.saturating_sub(radius);
+        let max_x = usize::min(self.pos.x + radius, map.width - 1);
+        let max_y = usize::min(self.pos.y + radius, map.height - 1);
+
+        for x in min_x..=max_x {
+            for y in min_y..=max_y {
+                if map.grid[[x, y]] != BlockType::Empty {
+                    continue;
+                }
+
+                let pos = Position::new(x, y);
+                let unpadded = [(-1, 0), (1, 0), (0, -1), (0, 1)].iter().any(|shift| {
+                    pos.shifted_by(shift.0, shift.1)
+                        .ok()
+                        .and_then(|neighbor| map.grid.get(neighbor.as_index()))
+                        .is_some_and(|block| *block == BlockType::Hookable)
+                });
+                if unpadded {
+                    violations.push(pos);
+                }
+            }
+        }
 
-        if same_dir && self.inner_kernel.size <= gen_config.pulse_max_kernel_size {
-            self.pulse_counter += 1;
+        violations
+    }
+
+    /// rewinds the walker up to the given amount of steps along its position history, so it can
+    /// pick a different branch. The map is not restored and locked positions can't be rewound.
+    fn backtrack(&mut self, steps: usize) -> Result<(), &'static str> {
+        // position history already contains the current position at index self.steps
+        let target_step = self
+            .steps
+            .saturating_sub(steps)
+            .max(self.locked_position_step + 1);
+        if target_step >= self.steps {
+            return Err("walker stuck, cannot backtrack into locked positions");
+        }
+
+        let target_pos = self
+            .position_history
+            .get(target_step)
+            .ok_or("backtrack target no longer in position history")?
+            .clone();
+        let next_pos = self
+            .position_history
+            .get(target_step + 1)
+            .ok_or("backtrack target no longer in position history")?;
+        self.backtrack_avoid = if next_pos.x < target_pos.x {
+            Some(ShiftDirection::Left)
+        } else if next_pos.x > target_pos.x {
+            Some(ShiftDirection::Right)
+        } else if next_pos.y < target_pos.y {
+            Some(ShiftDirection::Up)
         } else {
-            self.pulse_counter = 0;
+            Some(ShiftDirection::Down)
         };
 
-        self.last_shift = Some(current_shift.clone());
+        self.log_event(WalkerEventKind::Backtrack {
+            steps: self.steps - target_step,
+            pos: target_pos.clone(),
+        });
+
+        self.position_history.truncate(target_step);
+        self.steps = target_step;
+        self.pos = target_pos;
+        self.backtracks += 1;
+        self.last_shift = None;
+        self.straight_steps = 0;
+        self.pulse_counters.clear();
+        self.last_pulse_steps.clear();
 
         Ok(())
     }
@@ -301,6 +825,16 @@ impl CuteWalker {
         self.outer_kernel = Kernel::new(kernel_size + 2, 0.0);
     }
 
+    /// fades kernel size from max_size to min_size based on the progress (0 to 1) within the
+    /// current waypoint segment
+    pub fn set_segment_fade_kernel(&mut self, progress: f32, min_size: usize, max_size: usize) {
+        let progress = progress.clamp(0.0, 1.0);
+        let kernel_size_f = max_size as f32 + (min_size as f32 - max_size as f32) * progress;
+        let kernel_size = kernel_size_f.round() as usize;
+        self.inner_kernel = Kernel::new(kernel_size, 0.0);
+        self.outer_kernel = Kernel::new(kernel_size + 2, 0.0);
+    }
+
     pub fn mutate_kernel(&mut self, config: &GenerationConfig, rnd: &mut Random) {
         let mut inner_size = self.inner_kernel.size;
         let mut inner_circ = self.inner_kernel.circularity;
@@ -308,16 +842,20 @@ impl CuteWalker {
         let mut outer_circ = self.outer_kernel.circularity;
         let mut outer_margin = outer_size - inner_size;
         let mut modified = false;
+        let (kernel_shrink, freeze_growth) = config.get_kernel_schedule(self.difficulty);
 
         if rnd.with_probability(config.inner_size_mut_prob) {
-            inner_size = rnd.sample_inner_kernel_size();
+            inner_size = rnd
+                .sample_inner_kernel_size()
+                .saturating_sub(kernel_shrink)
+                .max(1);
             modified = true;
         } else {
             rnd.skip_n(2); // for some reason sampling requires two values?
         }
 
         if rnd.with_probability(config.outer_size_mut_prob) {
-            outer_margin = rnd.sample_outer_kernel_margin();
+            outer_margin = rnd.sample_outer_kernel_margin() + freeze_growth;
             modified = true;
         } else {
             rnd.skip_n(2);
@@ -353,6 +891,12 @@ impl CuteWalker {
         if modified {
             self.inner_kernel = Kernel::new(inner_size, inner_circ);
             self.outer_kernel = Kernel::new(outer_size, outer_circ);
+            self.log_event(WalkerEventKind::KernelMutation {
+                inner_size,
+                inner_circularity: inner_circ,
+                outer_size,
+                outer_circularity: outer_circ,
+            });
         }
     }
 
@@ -367,14 +911,18 @@ impl CuteWalker {
                 return Ok(()); // history not long enough yet to lock another step
             }
 
-            // get position of the next step to lock
-            let next_lock_pos = &self.position_history[self.locked_position_step + 1];
-
             // check if locking lacks too far behind -> walker most likely stuck
             if self.steps - self.locked_position_step > gen_config.pos_lock_max_delay {
                 return Err("pos_lock_max_delay exceeded, walker stuck");
             }
 
+            // get position of the next step to lock
+            let next_lock_pos = self
+                .position_history
+                .get(self.locked_position_step + 1)
+                .ok_or("position to lock no longer in position history")?
+                .clone();
+
             // check if walker is far enough to lock next position
             if !ignore_distance && next_lock_pos.distance(&self.pos) < gen_config.pos_lock_max_dist
             {
@@ -406,3 +954,82 @@ impl CuteWalker {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::MapConfig, generator::Generator, random::Seed};
+
+    /// generator with a lock delay below the forced platform lookback, stepped a few hundred
+    /// times or until the walker gets stuck
+    fn stepped_generator(full_history: bool) -> Generator {
+        let mut gen_config = GenerationConfig::get_all_configs()["hardV2"].clone();
+        gen_config.pos_lock_max_delay = 45;
+        gen_config.pos_lock_max_dist = 12.0;
+        let map_config = &MapConfig::get_all_configs()["small_s"];
+
+        let mut gen = Generator::new(&gen_config, map_config, Seed::from_u64(1));
+        if full_history {
+            gen.walker.position_history.enable_full_history();
+        }
+        for _ in 0..300 {
+            if gen.step(&gen_config).is_err() {
+                break;
+            }
+        }
+
+        gen
+    }
+
+    #[test]
+    fn small_lock_delay_keeps_platform_lookback() {
+        let gen = stepped_generator(false);
+        assert!(gen.walker.steps > FORCED_PLATFORM_LOOKBACK);
+
+        let full = stepped_generator(true);
+        assert_eq!(gen.walker.steps, full.walker.steps);
+        for step in gen.walker.steps - FORCED_PLATFORM_LOOKBACK..=gen.walker.steps {
+            assert!(gen.walker.position_history.get(step).is_some());
+            assert_eq!(
+                gen.walker.position_history.get(step),
+                full.walker.position_history.get(step)
+            );
+        }
+        assert_eq!(
+            Generator::fingerprint_map(&gen.map),
+            Generator::fingerprint_map(&full.map)
+        );
+    }
+
+    #[test]
+    fn forced_turns_never_reverse() {
+        let mut gen_config = GenerationConfig::get_all_configs()["hardV2"].clone();
+        gen_config.max_straight_steps = 2;
+        gen_config.momentum_prob = 1.0;
+        let map_config = &MapConfig::get_all_configs()["small_s"];
+
+        let mut gen = Generator::new(&gen_config, map_config, Seed::from_u64(1));
+        gen.walker.enable_event_log();
+        for _ in 0..500 {
+            if gen.step(&gen_config).is_err() {
+                break;
+            }
+        }
+
+        let mut last_shift = None;
+        let mut forced_turns = 0;
+        for event in gen.walker.event_log.as_ref().unwrap() {
+            match event.kind {
+                WalkerEventKind::ForcedTurn { direction } => {
+                    let last_shift = last_shift.unwrap();
+                    assert_ne!(direction, last_shift);
+                    assert_ne!(direction, ShiftDirection::opposite(&last_shift));
+                    forced_turns += 1;
+                }
+                WalkerEventKind::Shift { direction, .. } => last_shift = Some(direction),
+                _ => {}
+            }
+        }
+        assert!(forced_turns > 0);
+    }
+}